@@ -34,9 +34,12 @@ async fn init_dev() -> Result<(), Error> {
         .context("Could not get dev database settings")?;
     let conn_str = settings.connection_string();
     let root_db = new_db_pool(&conn_str).await?;
-    let paths = get_sql_files("1").await?;
-    for path in paths {
-        exec_file(&root_db, &path).await?;
+    // User files start with '1', and then with '2' once the '1x' are taken.
+    for prefix in ["1", "2"] {
+        let paths = get_sql_files(prefix).await?;
+        for path in paths {
+            exec_file(&root_db, &path).await?;
+        }
     }
     Ok(())
 }
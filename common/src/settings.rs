@@ -3,6 +3,7 @@ use serde_aux::field_attributes::deserialize_number_from_string;
 use serde_with::{serde_as, DisplayFromStr};
 use sqlx::postgres::PgConnectOptions;
 use sqlx::postgres::PgSslMode;
+use std::collections::HashMap;
 use std::fmt;
 use std::path::PathBuf;
 
@@ -15,6 +16,8 @@ pub struct ApplicationSettings {
     #[serde(deserialize_with = "deserialize_number_from_string")]
     pub http: u16,
     pub base_url: String,
+    /// Password the email service authenticates its webhook calls with.
+    pub webhook_secret: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -27,6 +30,9 @@ pub struct DatabaseSettings {
     pub database_name: String,
     pub require_ssl: bool,
     pub connection_timeout: u64,
+    /// Key of the hashes kept of erased addresses, which recognize an address
+    /// without revealing it.
+    pub erasure_key: String,
 }
 
 impl DatabaseSettings {
@@ -56,11 +62,139 @@ impl DatabaseSettings {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EmailClientSettings {
+    /// The kind of service sending the emails.
+    #[serde(default)]
+    pub provider: EmailProvider,
     /// URL of the Email Service the client connects to.
     pub server_url: String,
     pub sender_email: String,
     pub authorization_token: String,
     pub timeout: u64,
+    /// Size, in bytes, above which emails are refused, attachments included.
+    pub max_size: usize,
+    /// Maximum number of emails in a batch request, as accepted by the service.
+    pub batch_size: usize,
+    pub retry: RetrySettings,
+    pub circuit_breaker: CircuitBreakerSettings,
+    /// The SMTP relay, required by the `smtp` provider.
+    #[serde(default)]
+    pub smtp: Option<SmtpSettings>,
+    /// Backup providers, used when this one fails.
+    #[serde(default)]
+    pub failover: Option<FailoverSettings>,
+}
+
+impl EmailClientSettings {
+    /// A name for the provider, in traces.
+    pub fn name(&self) -> String {
+        match (&self.provider, &self.smtp) {
+            (EmailProvider::Smtp, Some(smtp)) => format!("smtp:{}:{}", smtp.host, smtp.port),
+            _ => format!("http:{}", self.server_url),
+        }
+    }
+}
+
+/// Providers tried in order, after the primary one, when a call fails in a
+/// way another provider may not.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FailoverSettings {
+    /// Seconds between health probes of the providers preferred to the one in
+    /// use.
+    pub probe_interval: u64,
+    /// The backup providers, whose own failover settings are ignored.
+    pub providers: Vec<EmailClientSettings>,
+}
+
+/// Retries of the calls to the email service which fail transiently: server
+/// errors, throttling, or connection failures.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetrySettings {
+    /// Attempts for one call, the first one included.
+    pub max_attempts: u32,
+    /// Milliseconds before the first retry, doubled for each retry, with jitter.
+    pub initial_backoff: u64,
+    /// Upper bound, in milliseconds, on the delay between two attempts,
+    /// including delays asked for with `Retry-After`.
+    pub max_backoff: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CircuitBreakerSettings {
+    /// Consecutive failed attempts after which calls fail fast.
+    pub failure_threshold: u32,
+    /// Seconds during which calls fail fast, before a trial call is let through,
+    /// and given to the trial call to finish, before another one is let through.
+    pub reset_timeout: u64,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EmailProvider {
+    /// A Postmark-style HTTP JSON API, at `server_url`.
+    #[default]
+    Http,
+    /// An SMTP relay.
+    Smtp,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SmtpSettings {
+    pub host: String,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub port: u16,
+    pub tls: SmtpTls,
+    /// Credentials for AUTH, if the relay requires them.
+    pub username: Option<String>,
+    pub password: Option<String>,
+    /// Maximum number of connections kept open to the relay.
+    pub pool_size: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SmtpTls {
+    /// Plaintext, only for trusted local relays.
+    None,
+    /// A plaintext connection, upgraded with STARTTLS, usually on port 587.
+    Starttls,
+    /// Implicit TLS, usually on port 465.
+    Tls,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TemplateSettings {
+    /// Directory holding the email templates, with one sub directory per template.
+    pub directory: PathBuf,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SanitizerSettings {
+    /// Tags kept in newsletters. Other tags are removed, but their content is kept.
+    pub tags: Vec<String>,
+    /// Attributes kept on every tag.
+    pub generic_attributes: Vec<String>,
+    /// Attributes kept on specific tags.
+    pub tag_attributes: HashMap<String, Vec<String>>,
+    /// Schemes of the URLs kept in links. Relative URLs are rewritten against
+    /// the base url, and other URLs are removed.
+    pub url_schemes: Vec<String>,
+    /// Share of the content, between 0 and 1, the sanitizer may remove before
+    /// the newsletter is refused.
+    pub max_removed: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NewsletterSettings {
+    /// Seconds between two checks for scheduled issues which are due.
+    pub scheduler_interval: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutboxSettings {
+    /// Seconds between two runs of the relay of the outbox.
+    pub relay_interval: u64,
+    /// Most emails sent by a run of the relay.
+    pub batch_size: i64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -80,6 +214,10 @@ pub struct Settings {
     pub application: ApplicationSettings,
     pub database: DatabaseSettings,
     pub email_client: EmailClientSettings,
+    pub templates: TemplateSettings,
+    pub sanitizer: SanitizerSettings,
+    pub newsletter: NewsletterSettings,
+    pub outbox: OutboxSettings,
     pub tracing: TracingSettings,
     pub mode: String,
 }
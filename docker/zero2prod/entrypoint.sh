@@ -11,7 +11,7 @@ echo "------"
 
 _main() {
 	if [ "$1" = 'run' ]; then
-    /srv/zero2prod/bin/zero2prod --config-dir /srv/zero2prod/etc/zero2prod --setting "application.static_dir='/srv/zero2prod/var/http'" run
+    /srv/zero2prod/bin/zero2prod --config-dir /srv/zero2prod/etc/zero2prod --setting "application.static_dir='/srv/zero2prod/var/http'" --setting "templates.directory='/srv/zero2prod/share/templates/email'" run
   fi
 }
 
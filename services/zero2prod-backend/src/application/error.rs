@@ -2,7 +2,9 @@ use common::err_context::ErrorContext;
 use std::fmt;
 
 use super::listener::Error as ListenerError;
-use crate::domain::ports::secondary::{AuthenticationError, EmailError, SubscriptionError};
+use crate::domain::ports::secondary::{
+    AuthenticationError, EmailError, SanitizerError, SubscriptionError, TemplateError,
+};
 use crate::services::postgres::Error as PostgresError;
 
 #[derive(Debug)]
@@ -27,6 +29,14 @@ pub enum Error {
         context: String,
         source: EmailError,
     },
+    Template {
+        context: String,
+        source: TemplateError,
+    },
+    Sanitizer {
+        context: String,
+        source: SanitizerError,
+    },
     Server {
         context: String,
         source: hyper::Error,
@@ -55,6 +65,12 @@ impl fmt::Display for Error {
             Error::Email { context, source } => {
                 write!(fmt, "Email Error: {context} | {source}")
             }
+            Error::Template { context, source } => {
+                write!(fmt, "Template Error: {context} | {source}")
+            }
+            Error::Sanitizer { context, source } => {
+                write!(fmt, "Sanitizer Error: {context} | {source}")
+            }
             Error::Server { context, source } => {
                 write!(fmt, "Application Server Error: {context} | {source}")
             }
@@ -103,6 +119,24 @@ impl From<ErrorContext<EmailError>> for Error {
     }
 }
 
+impl From<ErrorContext<TemplateError>> for Error {
+    fn from(err: ErrorContext<TemplateError>) -> Self {
+        Error::Template {
+            context: err.0,
+            source: err.1,
+        }
+    }
+}
+
+impl From<ErrorContext<SanitizerError>> for Error {
+    fn from(err: ErrorContext<SanitizerError>) -> Self {
+        Error::Sanitizer {
+            context: err.0,
+            source: err.1,
+        }
+    }
+}
+
 impl From<ErrorContext<ListenerError>> for Error {
     fn from(err: ErrorContext<ListenerError>) -> Self {
         Error::Listener {
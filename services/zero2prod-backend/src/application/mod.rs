@@ -1,26 +1,45 @@
 mod error;
 mod listener;
 pub mod opts;
+pub mod outbox;
+pub mod scheduler;
 pub mod server;
+pub mod subscribers_csv;
 
 pub use self::error::Error;
 
 use axum::routing::Router;
 use common::err_context::ErrorContextExt;
-use common::settings::{ApplicationSettings, DatabaseSettings, EmailClientSettings, Settings};
+use common::settings::{
+    ApplicationSettings, DatabaseSettings, EmailClientSettings, EmailProvider, NewsletterSettings,
+    OutboxSettings, SanitizerSettings, Settings, TemplateSettings,
+};
 use secrecy::Secret;
 use std::net::TcpListener;
 use std::sync::Arc;
+use std::time::Duration;
 
 use self::listener::listen_with_host_port;
-use crate::domain::ports::secondary::{AuthenticationStorage, EmailService, SubscriptionStorage};
+use self::outbox::OutboxRelay;
+use self::scheduler::Scheduler;
+use crate::domain::ports::secondary::{
+    AuthenticationStorage, EmailService, HtmlSanitizer, IssueStorage, SubscriptionStorage,
+    TemplateEngine,
+};
 use crate::services::email::EmailClient;
+use crate::services::failover::{FailoverEmailService, Provider};
 use crate::services::postgres::PostgresStorage;
+use crate::services::sanitizer::AmmoniaSanitizer;
+use crate::services::smtp::SmtpEmailClient;
+use crate::services::suppression::SuppressingEmailService;
+use crate::services::templates::MiniJinjaTemplates;
 
 pub struct Application {
     http: u16,
     app: Router,
     server: server::AppServer,
+    scheduler: Scheduler,
+    relay: OutboxRelay,
 }
 
 impl Application {
@@ -33,11 +52,18 @@ impl Application {
 pub struct ApplicationBuilder {
     pub authentication: Option<Arc<dyn AuthenticationStorage + Send + Sync>>,
     pub subscription: Option<Arc<dyn SubscriptionStorage + Send + Sync>>,
+    pub issues: Option<Arc<dyn IssueStorage + Send + Sync>>,
     pub email: Option<Arc<dyn EmailService + Send + Sync>>,
+    pub templates: Option<Arc<dyn TemplateEngine + Send + Sync>>,
+    pub sanitizer: Option<Arc<dyn HtmlSanitizer + Send + Sync>>,
+    pub scheduler_interval: Option<Duration>,
+    pub relay_interval: Option<Duration>,
+    pub relay_batch_size: Option<i64>,
     pub listener: Option<TcpListener>,
     pub http: Option<u16>,
     pub url: Option<String>,
     pub secret: Option<Secret<String>>,
+    pub webhook_secret: Option<Secret<String>>,
 }
 
 impl ApplicationBuilder {
@@ -46,20 +72,31 @@ impl ApplicationBuilder {
             application,
             database,
             email_client,
+            templates,
+            sanitizer,
+            newsletter,
+            outbox,
             tracing: _,
             mode: _,
         } = settings;
         let builder = Self::default()
             .authentication(database.clone())
             .await?
-            .subscription(database)
+            .subscription(database.clone())
+            .await?
+            .issues(database)
             .await?
             .email(email_client)
             .await?
+            .templates(templates)?
+            .sanitizer(sanitizer, &application.base_url)?
+            .scheduler(newsletter)
+            .outbox(outbox)
             .listener(application.clone())?
             .http(application.http)
             .url(application.base_url)
-            .secret("Secret".to_string());
+            .secret("Secret".to_string())
+            .webhook_secret(application.webhook_secret);
 
         Ok(builder)
     }
@@ -84,16 +121,70 @@ impl ApplicationBuilder {
         Ok(self)
     }
 
-    pub async fn email(mut self, settings: EmailClientSettings) -> Result<Self, Error> {
-        let email = Arc::new(
-            EmailClient::new(settings)
+    pub async fn issues(mut self, settings: DatabaseSettings) -> Result<Self, Error> {
+        let storage = Arc::new(
+            PostgresStorage::new(settings)
                 .await
-                .context("Establishing an email service connection")?,
+                .context("Establishing a database connection")?,
         );
+        self.issues = Some(storage);
+        Ok(self)
+    }
+
+    /// Sets up the email service selected by the provider of the settings,
+    /// behind a failover if backup providers are set.
+    pub async fn email(mut self, settings: EmailClientSettings) -> Result<Self, Error> {
+        let email = match settings.failover.clone() {
+            None => email_service(settings).await?,
+            Some(failover) => {
+                let mut providers = vec![Provider {
+                    name: settings.name(),
+                    service: email_service(settings).await?,
+                }];
+                for settings in failover.providers {
+                    providers.push(Provider {
+                        name: settings.name(),
+                        service: email_service(settings).await?,
+                    });
+                }
+                Arc::new(FailoverEmailService::new(
+                    providers,
+                    Duration::from_secs(failover.probe_interval),
+                ))
+            }
+        };
         self.email = Some(email);
         Ok(self)
     }
 
+    #[allow(clippy::result_large_err)]
+    pub fn templates(mut self, settings: TemplateSettings) -> Result<Self, Error> {
+        let templates =
+            Arc::new(MiniJinjaTemplates::new(settings).context("Loading email templates")?);
+        self.templates = Some(templates);
+        Ok(self)
+    }
+
+    #[allow(clippy::result_large_err)]
+    pub fn sanitizer(mut self, settings: SanitizerSettings, base_url: &str) -> Result<Self, Error> {
+        let sanitizer = Arc::new(
+            AmmoniaSanitizer::new(settings, base_url).context("Loading the sanitizer policy")?,
+        );
+        self.sanitizer = Some(sanitizer);
+        Ok(self)
+    }
+
+    pub fn scheduler(mut self, settings: NewsletterSettings) -> Self {
+        self.scheduler_interval = Some(Duration::from_secs(settings.scheduler_interval));
+        self
+    }
+
+    pub fn outbox(mut self, settings: OutboxSettings) -> Self {
+        self.relay_interval = Some(Duration::from_secs(settings.relay_interval));
+        self.relay_batch_size = Some(settings.batch_size);
+        self
+    }
+
     #[allow(clippy::result_large_err)]
     pub fn listener(mut self, settings: ApplicationSettings) -> Result<Self, Error> {
         let listener =
@@ -121,31 +212,64 @@ impl ApplicationBuilder {
         self
     }
 
+    pub fn webhook_secret(mut self, secret: String) -> Self {
+        self.webhook_secret = Some(Secret::new(secret));
+        self
+    }
+
     pub fn build(self) -> Application {
         let ApplicationBuilder {
             authentication,
             subscription,
+            issues,
             email,
+            templates,
+            sanitizer,
+            scheduler_interval,
+            relay_interval,
+            relay_batch_size,
             listener,
             http,
             url,
             secret,
+            webhook_secret,
         } = self;
         let listener = listener.expect("listener");
+        let subscription = subscription.expect("subscription");
+        // Suppressed addresses are enforced for every email sent.
+        let email = Arc::new(SuppressingEmailService::new(
+            email.expect("email"),
+            subscription.clone(),
+        ));
         let state = server::AppState {
             authentication: authentication.expect("authentication"),
-            subscription: subscription.expect("subscription"),
-            email: email.expect("email"),
+            subscription,
+            issues: issues.expect("issues"),
+            email,
+            templates: templates.expect("templates"),
+            sanitizer: sanitizer.expect("sanitizer"),
             base_url: server::ApplicationBaseUrl(url.expect("url")),
             secret: secret.expect("secret"),
+            webhook_secret: webhook_secret.expect("webhook secret"),
         };
 
+        let scheduler = Scheduler {
+            state: state.clone(),
+            interval: scheduler_interval.expect("scheduler interval"),
+        };
+        let relay = OutboxRelay {
+            state: state.clone(),
+            interval: relay_interval.expect("relay interval"),
+            batch_size: relay_batch_size.expect("relay batch size"),
+        };
         let (app, server) = server::new(listener, state);
 
         Application {
             http: http.expect("http"),
             app,
             server,
+            scheduler,
+            relay,
         }
     }
 }
@@ -156,10 +280,35 @@ impl Application {
     }
 
     pub async fn run_until_stopped(self) -> Result<(), Error> {
-        self.server
+        let scheduler = tokio::spawn(self.scheduler.run());
+        let relay = tokio::spawn(self.relay.run());
+        let served = self
+            .server
             .serve(self.app.into_make_service())
             .await
-            .context("server execution error")?;
+            .context("server execution error");
+        scheduler.abort();
+        relay.abort();
+        served?;
         Ok(())
     }
 }
+
+/// Sets up the email service selected by the provider of the settings.
+async fn email_service(
+    settings: EmailClientSettings,
+) -> Result<Arc<dyn EmailService + Send + Sync>, Error> {
+    let email: Arc<dyn EmailService + Send + Sync> = match settings.provider {
+        EmailProvider::Http => Arc::new(
+            EmailClient::new(settings)
+                .await
+                .context("Establishing an email service connection")?,
+        ),
+        EmailProvider::Smtp => Arc::new(
+            SmtpEmailClient::new(settings)
+                .await
+                .context("Establishing an SMTP relay connection")?,
+        ),
+    };
+    Ok(email)
+}
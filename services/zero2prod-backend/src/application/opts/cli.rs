@@ -39,6 +39,33 @@ pub enum Command {
     Run,
     /// Prints zero2prod configuration
     Config,
+    /// Prints the OpenAPI document of the REST API
+    Openapi,
+    /// Imports or exports subscriptions as CSV, directly with the database
+    Subscribers {
+        #[clap(subcommand)]
+        cmd: SubscribersCommand,
+    },
+}
+
+#[derive(Debug, Clone, clap::Parser)]
+pub enum SubscribersCommand {
+    /// Imports subscriptions from a CSV file, and prints a report
+    Import {
+        /// Path to the CSV file
+        #[arg(value_parser = clap::value_parser!(PathBuf), short = 'f', long = "file")]
+        file: PathBuf,
+
+        /// Import lines without a status as pending, and send them a confirmation email
+        #[arg(long = "pending")]
+        pending: bool,
+    },
+    /// Exports all subscriptions as CSV
+    Export {
+        /// Path to the CSV file. If no path is provided, prints to stdout.
+        #[arg(value_parser = clap::value_parser!(PathBuf), short = 'f', long = "file")]
+        file: Option<PathBuf>,
+    },
 }
 
 impl TryInto<settings::Settings> for Opts {
@@ -47,7 +74,16 @@ impl TryInto<settings::Settings> for Opts {
     fn try_into(self) -> Result<settings::Settings, Self::Error> {
         config::merge_configuration(
             self.config_dir.as_ref(),
-            &["service", "database", "email", "tracing"],
+            &[
+                "service",
+                "database",
+                "email",
+                "templates",
+                "sanitizer",
+                "newsletter",
+                "outbox",
+                "tracing",
+            ],
             self.run_mode.as_deref(),
             "ZERO2PROD",
             self.settings.clone(),
@@ -2,4 +2,4 @@ pub mod cli;
 mod error;
 
 pub use self::error::Error;
-pub use cli::{Command, Opts};
+pub use cli::{Command, Opts, SubscribersCommand};
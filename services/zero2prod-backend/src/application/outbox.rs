@@ -0,0 +1,219 @@
+use chrono::Utc;
+use common::err_context::ErrorContextExt;
+use std::time::Duration;
+
+use crate::application::server::routes::Error;
+use crate::application::server::AppState;
+use crate::domain::{OutboxEmail, OUTBOX_LEASE};
+
+/// Attempts to send an email before it is given up.
+const MAX_ATTEMPTS: i32 = 10;
+/// Longest wait before an email is sent again.
+const MAX_BACKOFF: chrono::Duration = chrono::Duration::hours(1);
+
+/// Sends the emails of the outbox at a regular interval. Emails are written to
+/// the outbox with the change they are about, in the same transaction, so that
+/// a change is never committed without its email. Their sender tries to send
+/// them right away, and the relay sends those which are still there once their
+/// lease has expired. An email is thus sent at least once.
+pub struct OutboxRelay {
+    pub state: AppState,
+    pub interval: Duration,
+    pub batch_size: i64,
+}
+
+impl OutboxRelay {
+    pub async fn run(self) {
+        let mut interval = tokio::time::interval(self.interval);
+        loop {
+            interval.tick().await;
+            if let Err(err) = relay_outbox(&self.state, self.batch_size).await {
+                tracing::error!("Could not relay the outbox: {err}");
+            }
+        }
+    }
+}
+
+/// Claims up to `batch_size` emails of the outbox which are available, sends
+/// them, and returns how many were sent.
+pub async fn relay_outbox(state: &AppState, batch_size: i64) -> Result<usize, Error> {
+    let now = Utc::now();
+    let emails = state
+        .subscription
+        .claim_outbox_emails(&now, &(now + OUTBOX_LEASE), batch_size)
+        .await
+        .context("Could not claim the emails of the outbox")?;
+
+    let mut sent = 0;
+    for email in emails {
+        if deliver(state, &email).await {
+            sent += 1;
+        }
+    }
+    Ok(sent)
+}
+
+/// Sends an email of the outbox, and records the outcome. Failed emails are
+/// sent again later, with an exponential backoff, unless the failure is
+/// permanent or they failed too often. Returns true if the email was sent.
+pub(crate) async fn deliver(state: &AppState, outbox: &OutboxEmail) -> bool {
+    match state.email.send_email(outbox.email.clone()).await {
+        Ok(()) => {
+            if let Err(err) = state.subscription.complete_outbox_email(&outbox.id).await {
+                tracing::error!(
+                    "Could not complete email {} of the outbox: {err}",
+                    outbox.id
+                );
+            }
+            true
+        }
+        Err(err) => {
+            let attempts = outbox.attempts + 1;
+            let retry_at = if err.is_permanent() || attempts >= MAX_ATTEMPTS {
+                tracing::error!("Giving up email {} of the outbox: {err}", outbox.id);
+                None
+            } else {
+                tracing::warn!("Could not send email {} of the outbox: {err}", outbox.id);
+                Some(Utc::now() + backoff(attempts))
+            };
+            if let Err(err) = state
+                .subscription
+                .fail_outbox_email(&outbox.id, &err.to_string(), retry_at)
+                .await
+            {
+                tracing::error!("Could not fail email {} of the outbox: {err}", outbox.id);
+            }
+            false
+        }
+    }
+}
+
+/// Wait before the next attempt, after the given number of failed attempts.
+fn backoff(attempts: i32) -> chrono::Duration {
+    let exponent = attempts.clamp(1, 8) - 1;
+    std::cmp::min(chrono::Duration::seconds(30 << exponent), MAX_BACKOFF)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::DateTime;
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::application::server::TestStateBuilder;
+    use crate::{
+        domain::ports::secondary::{Email, EmailError, MockEmailService, MockSubscriptionStorage},
+        domain::SubscriberEmail,
+    };
+
+    fn outbox_email(attempts: i32) -> OutboxEmail {
+        OutboxEmail {
+            attempts,
+            ..OutboxEmail::new(Email::new(
+                SubscriberEmail::parse("alice@acme.inc").unwrap(),
+                "Welcome".to_string(),
+                "<p>Welcome</p>".to_string(),
+                "Welcome".to_string(),
+            ))
+        }
+    }
+
+    fn state(subscription_mock: MockSubscriptionStorage, email_mock: MockEmailService) -> AppState {
+        TestStateBuilder::new()
+            .subscription(subscription_mock)
+            .email(email_mock)
+            .build()
+    }
+
+    #[tokio::test]
+    async fn relay_should_send_and_complete_the_claimed_emails() {
+        let email = outbox_email(0);
+        let id = email.id;
+        let mut subscription_mock = MockSubscriptionStorage::new();
+        subscription_mock
+            .expect_claim_outbox_emails()
+            .withf(|now, lease_until, limit| *lease_until == *now + OUTBOX_LEASE && *limit == 10)
+            .return_once(move |_, _, _| Ok(vec![email]));
+        subscription_mock
+            .expect_complete_outbox_email()
+            .withf(move |completed: &Uuid| completed == &id)
+            .times(1)
+            .return_once(|_| Ok(()));
+        subscription_mock.expect_fail_outbox_email().never();
+        let mut email_mock = MockEmailService::new();
+        email_mock
+            .expect_send_email()
+            .withf(|email| email.to.as_ref() == "alice@acme.inc")
+            .times(1)
+            .return_once(|_| Ok(()));
+
+        let sent = relay_outbox(&state(subscription_mock, email_mock), 10)
+            .await
+            .expect("relayed");
+
+        assert_eq!(sent, 1);
+    }
+
+    #[tokio::test]
+    async fn relay_should_retry_emails_which_failed_transiently() {
+        let email = outbox_email(2);
+        let mut subscription_mock = MockSubscriptionStorage::new();
+        subscription_mock
+            .expect_claim_outbox_emails()
+            .return_once(move |_, _, _| Ok(vec![email]));
+        subscription_mock.expect_complete_outbox_email().never();
+        subscription_mock
+            .expect_fail_outbox_email()
+            .withf(|_, error: &str, retry_at: &Option<DateTime<Utc>>| {
+                error.contains("Unavailable")
+                    && retry_at.is_some_and(|at| at > Utc::now() + chrono::Duration::minutes(1))
+            })
+            .times(1)
+            .return_once(|_, _, _| Ok(()));
+        let mut email_mock = MockEmailService::new();
+        email_mock.expect_send_email().return_once(|_| {
+            Err(EmailError::Unavailable {
+                context: "Postmark is down".to_string(),
+            })
+        });
+
+        let sent = relay_outbox(&state(subscription_mock, email_mock), 10)
+            .await
+            .expect("relayed");
+
+        assert_eq!(sent, 0);
+    }
+
+    #[tokio::test]
+    async fn relay_should_give_up_emails_which_failed_permanently() {
+        let email = outbox_email(0);
+        let mut subscription_mock = MockSubscriptionStorage::new();
+        subscription_mock
+            .expect_claim_outbox_emails()
+            .return_once(move |_, _, _| Ok(vec![email]));
+        subscription_mock
+            .expect_fail_outbox_email()
+            .withf(|_, _, retry_at: &Option<DateTime<Utc>>| retry_at.is_none())
+            .times(1)
+            .return_once(|_, _, _| Ok(()));
+        let mut email_mock = MockEmailService::new();
+        email_mock.expect_send_email().return_once(|_| {
+            Err(EmailError::Suppressed {
+                context: "alice@acme.inc is suppressed".to_string(),
+            })
+        });
+
+        let sent = relay_outbox(&state(subscription_mock, email_mock), 10)
+            .await
+            .expect("relayed");
+
+        assert_eq!(sent, 0);
+    }
+
+    #[test]
+    fn backoff_should_double_up_to_an_hour() {
+        assert_eq!(backoff(1), chrono::Duration::seconds(30));
+        assert_eq!(backoff(2), chrono::Duration::seconds(60));
+        assert_eq!(backoff(9), MAX_BACKOFF);
+    }
+}
@@ -0,0 +1,365 @@
+use chrono::Utc;
+use common::err_context::ErrorContextExt;
+use std::time::Duration;
+
+use crate::application::server::routes::lists::resolve_lists;
+use crate::application::server::routes::newsletter::{render_digest, send_newsletter, Newsletter};
+use crate::application::server::routes::Error;
+use crate::application::server::AppState;
+use crate::domain::{Digest, Issue, IssueStatus};
+
+/// How long a newsletter waits for the digest of a weekly subscriber.
+const DIGEST_PERIOD: chrono::Duration = chrono::Duration::days(7);
+/// How long an issue may be sending before it is deemed stalled, its
+/// scheduler having stopped before recording the outcome.
+const SENDING_TIMEOUT: chrono::Duration = chrono::Duration::hours(6);
+
+/// Checks the scheduled issues at a regular interval, and publishes those
+/// which are due, then sends the digests which are due.
+pub struct Scheduler {
+    pub state: AppState,
+    pub interval: Duration,
+}
+
+impl Scheduler {
+    pub async fn run(self) {
+        let mut interval = tokio::time::interval(self.interval);
+        loop {
+            interval.tick().await;
+            if let Err(err) = publish_due_issues(&self.state).await {
+                tracing::error!("Could not publish scheduled issues: {err}");
+            }
+            if let Err(err) = send_due_digests(&self.state).await {
+                tracing::error!("Could not send digests: {err}");
+            }
+        }
+    }
+}
+
+/// Publishes the issues which are due, and returns how many were sent.
+/// Issues are claimed before being sent, so they can no longer be modified
+/// or cancelled. Issues stalled in their sending fail, rather than being sent
+/// again, as some subscribers may have received them already.
+pub async fn publish_due_issues(state: &AppState) -> Result<usize, Error> {
+    let now = Utc::now();
+    let stalled = state
+        .issues
+        .fail_stalled_issues(&(now - SENDING_TIMEOUT))
+        .await
+        .context("Could not fail stalled issues")?;
+    for id in stalled {
+        tracing::error!("Issue {id} stalled while being sent, and failed");
+    }
+
+    let issues = state
+        .issues
+        .claim_due_issues(&now)
+        .await
+        .context("Could not claim due issues")?;
+
+    let mut sent = 0;
+    for issue in issues {
+        let status = match publish(state, &issue).await {
+            Ok(()) => {
+                sent += 1;
+                IssueStatus::Sent
+            }
+            Err(err) => {
+                tracing::error!("Could not publish issue {}: {err}", issue.id);
+                IssueStatus::Failed
+            }
+        };
+        // The other claimed issues are still published. This one fails once
+        // it has stalled.
+        if let Err(err) = state.issues.set_issue_status(&issue.id, &status).await {
+            tracing::error!("Could not set the status of issue {}: {err}", issue.id);
+        }
+    }
+    Ok(sent)
+}
+
+async fn publish(state: &AppState, issue: &Issue) -> Result<(), Error> {
+    let lists = resolve_lists(state, &issue.lists).await?;
+    let newsletter =
+        Newsletter::prepare(state, &issue.title, &issue.content, issue.force)?.for_issue(issue);
+    send_newsletter(state, &newsletter, issue.author_id, &lists, &issue.segment).await
+}
+
+/// Sends their digest to the weekly subscribers whose oldest queued newsletter
+/// has waited for a week, and returns how many were sent. A digest which could
+/// not be sent stays queued, and is tried again on the next run.
+pub async fn send_due_digests(state: &AppState) -> Result<usize, Error> {
+    let digests = state
+        .subscription
+        .get_due_digests(&(Utc::now() - DIGEST_PERIOD))
+        .await
+        .context("Could not get due digests")?;
+
+    let mut sent = 0;
+    for digest in digests {
+        match send_digest(state, &digest).await {
+            Ok(()) => sent += 1,
+            Err(err) => tracing::error!(
+                "Could not send the digest of subscriber {}: {err}",
+                digest.subscriber_id
+            ),
+        }
+    }
+    Ok(sent)
+}
+
+async fn send_digest(state: &AppState, digest: &Digest) -> Result<(), Error> {
+    let email = render_digest(state, digest)?;
+    state
+        .email
+        .send_email(email)
+        .await
+        .context("Could not send digest email")?;
+    let issues = digest
+        .issues
+        .iter()
+        .map(|issue| issue.id)
+        .collect::<Vec<_>>();
+    state
+        .subscription
+        .complete_digest(&digest.subscriber_id, &issues)
+        .await
+        .context("Could not complete digest")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use mockall::predicate::*;
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::application::server::TestStateBuilder;
+    use crate::{
+        domain::ports::secondary::{
+            IssueError, MockEmailService, MockHtmlSanitizer, MockIssueStorage,
+            MockSubscriptionStorage,
+        },
+        domain::{
+            ArchivedIssueSummary, ConfirmedSubscriber, Content, Delivery, DeliveryFrequency,
+            DeliveryStatus, IssueRequest, MailingList, Segment, SubscriberEmail, SubscriberName,
+        },
+        services::templates::repository_templates,
+    };
+
+    #[tokio::test]
+    async fn due_issues_should_be_sent_and_marked_sent() {
+        let issue = Issue::new(
+            IssueRequest {
+                title: "News".to_string(),
+                content: Content::Markdown {
+                    markdown: "Some *news*".to_string(),
+                },
+                scheduled_at: Some(Utc::now()),
+                force: false,
+                lists: vec!["rust".to_string()],
+                segment: Segment::default(),
+                tracking: false,
+            },
+            Uuid::new_v4(),
+        );
+        let id = issue.id;
+
+        let mut issues_mock = MockIssueStorage::new();
+        issues_mock
+            .expect_fail_stalled_issues()
+            .withf(|before| *before <= Utc::now() - SENDING_TIMEOUT)
+            .return_once(|_| Ok(Vec::new()));
+        issues_mock
+            .expect_claim_due_issues()
+            .return_once(move |_| Ok(vec![issue]));
+        issues_mock
+            .expect_set_issue_status()
+            .with(eq(id), eq(IssueStatus::Sent))
+            .times(1)
+            .return_once(|_, _| Ok(()));
+        issues_mock
+            .expect_archive_issue()
+            .times(1)
+            .return_once(|_| Ok(()));
+        // Each recipient is queued, then sent with the id of its message.
+        issues_mock
+            .expect_record_deliveries()
+            .withf(move |deliveries: &[Delivery]| {
+                deliveries.len() == 1
+                    && deliveries[0].issue_id == id
+                    && deliveries[0].status == DeliveryStatus::Queued
+            })
+            .times(1)
+            .returning(|_| Ok(()));
+        issues_mock
+            .expect_record_deliveries()
+            .withf(|deliveries: &[Delivery]| {
+                deliveries.len() == 1
+                    && deliveries[0].status == DeliveryStatus::Sent
+                    && deliveries[0].message_id.as_deref() == Some("b7bc2f4a")
+            })
+            .times(1)
+            .returning(|_| Ok(()));
+        let list = MailingList {
+            id: Uuid::new_v4(),
+            slug: "rust".to_string(),
+            name: "Rust".to_string(),
+            tracking: true,
+            created_at: Utc::now(),
+        };
+        let list_id = list.id;
+        let mut subscription_mock = MockSubscriptionStorage::new();
+        subscription_mock
+            .expect_get_list()
+            .with(eq("rust"))
+            .return_once(move |_| Ok(Some(list)));
+        subscription_mock
+            .expect_get_confirmed_subscribers_email()
+            .withf(move |lists: &[Uuid], _: &Segment| lists == [list_id])
+            .return_once(move |_, _| {
+                Ok(vec![ConfirmedSubscriber {
+                    id: Uuid::new_v4(),
+                    email: SubscriberEmail::parse("alice@acme.inc").unwrap(),
+                    username: SubscriberName::parse("alice".to_string()).unwrap(),
+                    list_id,
+                    frequency: DeliveryFrequency::Immediate,
+                    attributes: serde_json::json!({}),
+                }])
+            });
+        let mut email_mock = MockEmailService::new();
+        email_mock
+            .expect_send_batch()
+            .withf(|emails| emails.len() == 1)
+            .times(1)
+            .return_once(|emails| {
+                emails
+                    .iter()
+                    .map(|_| Ok(Some("b7bc2f4a".to_string())))
+                    .collect()
+            });
+        let mut sanitizer_mock = MockHtmlSanitizer::new();
+        sanitizer_mock
+            .expect_sanitize()
+            .returning(|html, _| Ok(html.to_string()));
+
+        let state = TestStateBuilder::new()
+            .subscription(subscription_mock)
+            .issues(issues_mock)
+            .email(email_mock)
+            .templates(repository_templates())
+            .sanitizer(sanitizer_mock)
+            .build();
+
+        let sent = publish_due_issues(&state).await.expect("published");
+
+        assert_eq!(sent, 1);
+    }
+
+    #[tokio::test]
+    async fn claimed_issues_should_all_get_a_status_even_if_one_can_not_be_set() {
+        // In this test, the issues fail, as their list no longer exists, and
+        // the status of the first one can not be set.
+        let issues = (0..2)
+            .map(|_| {
+                Issue::new(
+                    IssueRequest {
+                        title: "News".to_string(),
+                        content: Content::Markdown {
+                            markdown: "Some *news*".to_string(),
+                        },
+                        scheduled_at: Some(Utc::now()),
+                        force: false,
+                        lists: vec!["gone".to_string()],
+                        segment: Segment::default(),
+                        tracking: false,
+                    },
+                    Uuid::new_v4(),
+                )
+            })
+            .collect::<Vec<_>>();
+        let (first, second) = (issues[0].id, issues[1].id);
+
+        let mut issues_mock = MockIssueStorage::new();
+        issues_mock
+            .expect_fail_stalled_issues()
+            .return_once(|_| Ok(Vec::new()));
+        issues_mock
+            .expect_claim_due_issues()
+            .return_once(move |_| Ok(issues));
+        issues_mock
+            .expect_set_issue_status()
+            .with(eq(first), eq(IssueStatus::Failed))
+            .times(1)
+            .return_once(|_, _| {
+                Err(IssueError::Connection {
+                    context: "Could not set the status".to_string(),
+                    source: "timed out".to_string(),
+                })
+            });
+        issues_mock
+            .expect_set_issue_status()
+            .with(eq(second), eq(IssueStatus::Failed))
+            .times(1)
+            .return_once(|_, _| Ok(()));
+        let mut subscription_mock = MockSubscriptionStorage::new();
+        subscription_mock.expect_get_list().returning(|_| Ok(None));
+
+        let state = TestStateBuilder::new()
+            .subscription(subscription_mock)
+            .issues(issues_mock)
+            .templates(repository_templates())
+            .build();
+
+        let sent = publish_due_issues(&state).await.expect("published");
+
+        assert_eq!(sent, 0);
+    }
+
+    #[tokio::test]
+    async fn due_digests_should_be_sent_and_completed() {
+        let subscriber_id = Uuid::new_v4();
+        let issue_id = Uuid::new_v4();
+        let digest = Digest {
+            subscriber_id,
+            email: SubscriberEmail::parse("alice@acme.inc").unwrap(),
+            issues: vec![ArchivedIssueSummary {
+                id: issue_id,
+                slug: "news-0f1e2d3c".to_string(),
+                title: "News".to_string(),
+                published_at: Utc::now(),
+            }],
+        };
+
+        let mut subscription_mock = MockSubscriptionStorage::new();
+        subscription_mock
+            .expect_get_due_digests()
+            .withf(|before| *before <= Utc::now() - DIGEST_PERIOD)
+            .return_once(move |_| Ok(vec![digest]));
+        subscription_mock
+            .expect_complete_digest()
+            .withf(move |id: &Uuid, issues: &[Uuid]| id == &subscriber_id && issues == [issue_id])
+            .times(1)
+            .return_once(|_, _| Ok(()));
+        let mut email_mock = MockEmailService::new();
+        email_mock
+            .expect_send_email()
+            .withf(|email| {
+                email
+                    .text_content
+                    .contains("http://127.0.0.1/issues/news-0f1e2d3c")
+            })
+            .times(1)
+            .return_once(|_| Ok(()));
+
+        let state = TestStateBuilder::new()
+            .subscription(subscription_mock)
+            .email(email_mock)
+            .templates(repository_templates())
+            .build();
+
+        let sent = send_due_digests(&state).await.expect("sent");
+
+        assert_eq!(sent, 1);
+    }
+}
@@ -6,12 +6,10 @@ pub async fn error(resp: Response) -> Response {
 
     // -- Get the eventual response error.
     let error = resp.extensions().get::<Error>();
-    let code_and_json = error.map(|err| err.standardize());
+    let problem = error.map(|err| err.standardize());
 
     // -- If client error, build the new reponse.
-    let error_resp = code_and_json
-        .as_ref()
-        .map(|(status_code, value)| (*status_code, value.clone()).into_response());
+    let error_resp = problem.map(|problem| problem.into_response());
 
     error_resp.unwrap_or(resp)
 }
@@ -16,8 +16,19 @@ use tower_http::{cors::CorsLayer, trace::TraceLayer};
 
 use self::middleware::resolve_context::resolve_context;
 use self::middleware::response_map::error;
-use crate::domain::ports::secondary::{AuthenticationStorage, EmailService, SubscriptionStorage};
+use crate::domain::ports::secondary::{
+    AuthenticationStorage, EmailService, HtmlSanitizer, IssueStorage, SubscriptionStorage,
+    TemplateEngine,
+};
 use crate::utils::tracing::make_span;
+#[cfg(test)]
+use {
+    crate::domain::ports::secondary::{
+        MockAuthenticationStorage, MockEmailService, MockHtmlSanitizer, MockIssueStorage,
+        MockSubscriptionStorage, MockTemplateEngine,
+    },
+    uuid::Uuid,
+};
 
 pub fn new(listener: TcpListener, state: AppState) -> (Router, Server<DefaultAcceptor>) {
     // FIXME Hardcoded origin
@@ -42,15 +53,114 @@ pub fn new(listener: TcpListener, state: AppState) -> (Router, Server<DefaultAcc
 
 pub type DynAuthentication = Arc<dyn AuthenticationStorage + Send + Sync>;
 pub type DynSubscription = Arc<dyn SubscriptionStorage + Send + Sync>;
+pub type DynIssues = Arc<dyn IssueStorage + Send + Sync>;
 pub type DynEmail = Arc<dyn EmailService + Send + Sync>;
+pub type DynTemplates = Arc<dyn TemplateEngine + Send + Sync>;
+pub type DynSanitizer = Arc<dyn HtmlSanitizer + Send + Sync>;
 
 #[derive(Clone)]
 pub struct AppState {
     pub subscription: DynSubscription,
     pub authentication: DynAuthentication,
+    pub issues: DynIssues,
     pub email: DynEmail,
+    pub templates: DynTemplates,
+    pub sanitizer: DynSanitizer,
     pub base_url: ApplicationBaseUrl,
     pub secret: Secret<String>,
+    pub webhook_secret: Secret<String>,
+}
+
+/// Builds the state of the tests. Every port is a mock which expects nothing,
+/// unless it is set, and the base url and secrets are fixed.
+#[cfg(test)]
+pub(crate) struct TestStateBuilder {
+    state: AppState,
+}
+
+#[cfg(test)]
+impl TestStateBuilder {
+    pub(crate) fn new() -> Self {
+        TestStateBuilder {
+            state: AppState {
+                subscription: Arc::new(MockSubscriptionStorage::new()),
+                authentication: Arc::new(MockAuthenticationStorage::new()),
+                issues: Arc::new(MockIssueStorage::new()),
+                email: Arc::new(MockEmailService::new()),
+                templates: Arc::new(MockTemplateEngine::new()),
+                sanitizer: Arc::new(MockHtmlSanitizer::new()),
+                base_url: ApplicationBaseUrl("http://127.0.0.1".to_string()),
+                secret: Secret::new("secret".to_string()),
+                webhook_secret: Secret::new("webhook-secret".to_string()),
+            },
+        }
+    }
+
+    pub(crate) fn subscription(
+        mut self,
+        subscription: impl SubscriptionStorage + Send + Sync + 'static,
+    ) -> Self {
+        self.state.subscription = Arc::new(subscription);
+        self
+    }
+
+    pub(crate) fn authentication(
+        mut self,
+        authentication: impl AuthenticationStorage + Send + Sync + 'static,
+    ) -> Self {
+        self.state.authentication = Arc::new(authentication);
+        self
+    }
+
+    /// Authenticates the user identified by user_id.
+    pub(crate) fn user(self, user_id: Uuid) -> Self {
+        let mut authentication_mock = MockAuthenticationStorage::new();
+        authentication_mock
+            .expect_id_exists()
+            .withf(move |id: &Uuid| id == &user_id)
+            .return_const(Ok(true));
+        self.authentication(authentication_mock)
+    }
+
+    pub(crate) fn issues(mut self, issues: impl IssueStorage + Send + Sync + 'static) -> Self {
+        self.state.issues = Arc::new(issues);
+        self
+    }
+
+    pub(crate) fn email(mut self, email: impl EmailService + 'static) -> Self {
+        self.state.email = Arc::new(email);
+        self
+    }
+
+    pub(crate) fn templates(
+        mut self,
+        templates: impl TemplateEngine + Send + Sync + 'static,
+    ) -> Self {
+        self.state.templates = Arc::new(templates);
+        self
+    }
+
+    pub(crate) fn sanitizer(
+        mut self,
+        sanitizer: impl HtmlSanitizer + Send + Sync + 'static,
+    ) -> Self {
+        self.state.sanitizer = Arc::new(sanitizer);
+        self
+    }
+
+    pub(crate) fn base_url(mut self, base_url: ApplicationBaseUrl) -> Self {
+        self.state.base_url = base_url;
+        self
+    }
+
+    pub(crate) fn webhook_secret(mut self, webhook_secret: Secret<String>) -> Self {
+        self.state.webhook_secret = webhook_secret;
+        self
+    }
+
+    pub(crate) fn build(self) -> AppState {
+        self.state
+    }
 }
 
 pub type AppServer = Server<DefaultAcceptor>;
@@ -0,0 +1,246 @@
+use axum::extract::{Json, Path, Query, State};
+use axum::response::IntoResponse;
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use super::{Error, Problem};
+
+use crate::application::server::AppState;
+use crate::domain::{
+    ArchiveCursor, ArchiveFilter, ArchivedIssue, ArchivedIssueSummary, FieldError,
+};
+use common::err_context::ErrorContextExt;
+
+/// Number of issues returned in a page, when the request does not specify it.
+const DEFAULT_LIMIT: i64 = 20;
+/// Upper bound on the number of issues returned in a page.
+const MAX_LIMIT: i64 = 100;
+
+/// GET handler for the archive of published newsletters
+/// The response contains a page of issues, the most recent first, and, if
+/// there are more, a cursor to use in the next request.
+#[utoipa::path(
+    get,
+    path = "/issues",
+    tag = "archive",
+    params(ArchiveQuery),
+    responses(
+        (status = 200, description = "A page of published issues", body = ArchiveResp),
+        (status = 400, description = "Invalid cursor or limit", body = Problem, content_type = "application/problem+json"),
+        (status = 500, description = "Internal error", body = Problem, content_type = "application/problem+json"),
+    )
+)]
+#[allow(clippy::unused_async)]
+#[tracing::instrument(
+    name = "Listing the archive"
+    skip(state),
+    fields(
+        request_id = %Uuid::new_v4(),
+    )
+)]
+pub async fn list_archived_issues(
+    State(state): State<AppState>,
+    Query(query): Query<ArchiveQuery>,
+) -> Result<impl IntoResponse, Error> {
+    let mut filter = ArchiveFilter::try_from(query).context("Invalid archive query")?;
+    let limit = filter.limit;
+    // Fetch one more issue than requested, to know if there is a next page.
+    filter.limit += 1;
+
+    let mut issues = state
+        .issues
+        .list_archived_issues(&filter)
+        .await
+        .context("Could not list archived issues")?;
+
+    let next_cursor = if issues.len() as i64 > limit {
+        issues.truncate(limit as usize);
+        issues.last().map(|issue| {
+            ArchiveCursor {
+                published_at: issue.published_at,
+                id: issue.id,
+            }
+            .encode()
+        })
+    } else {
+        None
+    };
+
+    Ok::<_, Error>(Json(ArchiveResp {
+        issues,
+        next_cursor,
+    }))
+}
+
+/// GET handler for a published newsletter
+#[utoipa::path(
+    get,
+    path = "/issues/{slug}",
+    tag = "archive",
+    params(("slug" = String, Path, description = "Issue slug")),
+    responses(
+        (status = 200, description = "The published issue", body = ArchivedIssue),
+        (status = 404, description = "Unknown issue", body = Problem, content_type = "application/problem+json"),
+        (status = 500, description = "Internal error", body = Problem, content_type = "application/problem+json"),
+    )
+)]
+#[allow(clippy::unused_async)]
+#[tracing::instrument(
+    name = "Fetching an archived issue"
+    skip(state),
+    fields(
+        request_id = %Uuid::new_v4(),
+    )
+)]
+pub async fn get_archived_issue(
+    State(state): State<AppState>,
+    Path(slug): Path<String>,
+) -> Result<impl IntoResponse, Error> {
+    let issue = state
+        .issues
+        .get_archived_issue(&slug)
+        .await
+        .context("Could not get archived issue")?
+        .ok_or_else(|| Error::MissingIssue {
+            context: format!("No published issue {slug}"),
+        })?;
+
+    Ok::<_, Error>(Json(issue))
+}
+
+/// Query string of the archive listing.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ArchiveQuery {
+    /// Cursor returned with the previous page.
+    pub cursor: Option<String>,
+    /// Maximum number of issues in the page (1 to 100, default 20).
+    pub limit: Option<i64>,
+}
+
+impl TryFrom<ArchiveQuery> for ArchiveFilter {
+    type Error = Vec<FieldError>;
+
+    fn try_from(query: ArchiveQuery) -> Result<Self, Self::Error> {
+        let ArchiveQuery { cursor, limit } = query;
+
+        let mut errors = Vec::new();
+
+        let after = cursor
+            .map(ArchiveCursor::decode)
+            .transpose()
+            .unwrap_or_else(|err| {
+                errors.push(FieldError::new("cursor", err));
+                None
+            });
+
+        let limit = limit.unwrap_or(DEFAULT_LIMIT);
+        if !(1..=MAX_LIMIT).contains(&limit) {
+            errors.push(FieldError::new(
+                "limit",
+                format!("{limit} is not between 1 and {MAX_LIMIT}."),
+            ));
+        }
+
+        if errors.is_empty() {
+            Ok(ArchiveFilter { after, limit })
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+/// A page of published issues. `next_cursor` is absent on the last page.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ArchiveResp {
+    pub issues: Vec<ArchivedIssueSummary>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        body::Body,
+        http::{Request, StatusCode},
+        middleware::map_response,
+        routing::{get, Router},
+    };
+    use chrono::Utc;
+    use hyper::body::to_bytes;
+    use speculoos::prelude::*;
+    use tower::ServiceExt;
+
+    use crate::{
+        application::server::middleware::response_map::error,
+        domain::ports::secondary::MockIssueStorage,
+    };
+
+    use super::*;
+    use crate::application::server::TestStateBuilder;
+
+    /// This is a helper function to build an App with axum.
+    fn archive_route(issues_mock: MockIssueStorage) -> Router {
+        let state = TestStateBuilder::new().issues(issues_mock).build();
+        Router::new()
+            .route("/api/issues", get(list_archived_issues))
+            .route("/api/issues/:slug", get(get_archived_issue))
+            .layer(map_response(error))
+            .with_state(state)
+    }
+
+    fn summary(slug: &str) -> ArchivedIssueSummary {
+        ArchivedIssueSummary {
+            id: Uuid::new_v4(),
+            slug: slug.to_string(),
+            title: "News".to_string(),
+            published_at: Utc::now(),
+        }
+    }
+
+    #[tokio::test]
+    async fn archive_should_return_a_cursor_when_there_are_more_issues() {
+        let mut issues_mock = MockIssueStorage::new();
+        issues_mock
+            .expect_list_archived_issues()
+            .withf(|filter: &ArchiveFilter| filter.limit == 3 && filter.after.is_none())
+            .return_once(|_| Ok(vec![summary("c"), summary("b"), summary("a")]));
+
+        let response = archive_route(issues_mock)
+            .oneshot(
+                Request::builder()
+                    .uri("/api/issues?limit=2")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .expect("response");
+
+        assert_that(&response.status()).is_equal_to(StatusCode::OK);
+        let body = to_bytes(response.into_body()).await.unwrap();
+        let resp: ArchiveResp = serde_json::from_slice(&body).unwrap();
+        assert_eq!(resp.issues.len(), 2);
+        assert_that(&resp.next_cursor).is_some();
+    }
+
+    #[tokio::test]
+    async fn unknown_archived_issue_should_return_404() {
+        let mut issues_mock = MockIssueStorage::new();
+        issues_mock
+            .expect_get_archived_issue()
+            .return_once(|_| Ok(None));
+
+        let response = archive_route(issues_mock)
+            .oneshot(
+                Request::builder()
+                    .uri("/api/issues/unknown")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .expect("response");
+
+        assert_that(&response.status()).is_equal_to(StatusCode::NOT_FOUND);
+    }
+}
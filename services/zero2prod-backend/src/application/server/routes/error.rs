@@ -1,17 +1,23 @@
 use axum::extract::Json;
-use axum::http::status::StatusCode;
+use axum::http::{header, status::StatusCode};
 use axum::response::{IntoResponse, Response};
-use serde_json::Value;
-
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::fmt;
+use utoipa::ToSchema;
 
 use crate::application::server::context::Error as ContextError;
 use crate::application::server::middleware::resolve_context::Error as ContextResolutionError;
+use crate::application::subscribers_csv::Error as CsvError;
+use crate::authentication::basic::Error as BasicError;
 use crate::authentication::password::Error as PasswordError;
 use crate::domain::ports::secondary::AuthenticationError;
 use crate::domain::ports::secondary::EmailError;
+use crate::domain::ports::secondary::IssueError;
+use crate::domain::ports::secondary::SanitizerError;
 use crate::domain::ports::secondary::SubscriptionError;
+use crate::domain::ports::secondary::TemplateError;
+use crate::domain::ports::secondary::{UNIQUE_EMAIL, UNIQUE_USERNAME};
+use crate::domain::FieldError;
 use common::err_context::ErrorContext;
 
 #[derive(Debug, Serialize)]
@@ -32,6 +38,10 @@ pub enum Error {
         context: String,
         source: ContextResolutionError,
     },
+    BasicAuthentication {
+        context: String,
+        source: BasicError,
+    },
     DuplicateEmail {
         context: String,
     },
@@ -43,19 +53,58 @@ pub enum Error {
     },
     InvalidRequest {
         context: String,
-        source: String,
+        source: Vec<FieldError>,
     },
     MissingToken {
         context: String,
     },
+    InvalidToken {
+        context: String,
+    },
+    MissingSubscription {
+        context: String,
+    },
+    MissingIssue {
+        context: String,
+    },
+    IssueLocked {
+        context: String,
+    },
+    MissingList {
+        context: String,
+    },
+    MissingSuppression {
+        context: String,
+    },
+    DuplicateList {
+        context: String,
+    },
+    EmptySegment {
+        context: String,
+    },
+    UnresolvedPlaceholder {
+        context: String,
+    },
     Data {
         context: String,
         source: SubscriptionError,
     },
+    Issue {
+        context: String,
+        source: IssueError,
+    },
     Email {
         context: String,
         source: EmailError,
     },
+    Template {
+        context: String,
+        source: TemplateError,
+    },
+    Sanitizer {
+        context: String,
+        source: SanitizerError,
+    },
 }
 
 impl fmt::Display for Error {
@@ -73,6 +122,9 @@ impl fmt::Display for Error {
             Error::ContextResolution { context, source } => {
                 write!(fmt, "Context: {context} {source}")
             }
+            Error::BasicAuthentication { context, source } => {
+                write!(fmt, "Basic Authentication: {context} {source}")
+            }
             Error::DuplicateEmail { context } => {
                 write!(fmt, "Duplicate email: {context} ")
             }
@@ -83,17 +135,58 @@ impl fmt::Display for Error {
                 write!(fmt, "Weak password: {context} ")
             }
             Error::InvalidRequest { context, source } => {
-                write!(fmt, "Invalid Request: {context} {source}")
+                let fields = source
+                    .iter()
+                    .map(|err| err.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                write!(fmt, "Invalid Request: {context} {fields}")
             }
             Error::MissingToken { context } => {
                 write!(fmt, "Missing Token: {context} ")
             }
+            Error::InvalidToken { context } => {
+                write!(fmt, "Invalid Token: {context} ")
+            }
+            Error::MissingSubscription { context } => {
+                write!(fmt, "Missing Subscription: {context} ")
+            }
+            Error::MissingIssue { context } => {
+                write!(fmt, "Missing Issue: {context} ")
+            }
+            Error::IssueLocked { context } => {
+                write!(fmt, "Issue Locked: {context} ")
+            }
+            Error::MissingList { context } => {
+                write!(fmt, "Missing List: {context} ")
+            }
+            Error::MissingSuppression { context } => {
+                write!(fmt, "Missing Suppression: {context} ")
+            }
+            Error::DuplicateList { context } => {
+                write!(fmt, "Duplicate List: {context} ")
+            }
+            Error::EmptySegment { context } => {
+                write!(fmt, "Empty Segment: {context} ")
+            }
+            Error::UnresolvedPlaceholder { context } => {
+                write!(fmt, "Unresolved Placeholder: {context} ")
+            }
             Error::Data { context, source } => {
                 write!(fmt, "Data: {context} {source}")
             }
+            Error::Issue { context, source } => {
+                write!(fmt, "Issue: {context} {source}")
+            }
             Error::Email { context, source } => {
                 write!(fmt, "Email: {context} {source}")
             }
+            Error::Template { context, source } => {
+                write!(fmt, "Template: {context} {source}")
+            }
+            Error::Sanitizer { context, source } => {
+                write!(fmt, "Sanitizer: {context} {source}")
+            }
         }
     }
 }
@@ -124,17 +217,40 @@ impl From<ErrorContext<ContextResolutionError>> for Error {
     }
 }
 
-impl From<ErrorContext<AuthenticationError>> for Error {
-    fn from(err: ErrorContext<AuthenticationError>) -> Self {
-        Error::AuthenticationService {
+impl From<ErrorContext<BasicError>> for Error {
+    fn from(err: ErrorContext<BasicError>) -> Self {
+        Error::BasicAuthentication {
             context: err.0,
             source: err.1,
         }
     }
 }
 
-impl From<ErrorContext<String>> for Error {
-    fn from(err: ErrorContext<String>) -> Self {
+impl From<ErrorContext<AuthenticationError>> for Error {
+    fn from(err: ErrorContext<AuthenticationError>) -> Self {
+        match err.1 {
+            AuthenticationError::UniqueViolation {
+                context,
+                constraint,
+            } if constraint == UNIQUE_EMAIL => Error::DuplicateEmail {
+                context: format!("{}: {context}", err.0),
+            },
+            AuthenticationError::UniqueViolation {
+                context,
+                constraint,
+            } if constraint == UNIQUE_USERNAME => Error::DuplicateUsername {
+                context: format!("{}: {context}", err.0),
+            },
+            source => Error::AuthenticationService {
+                context: err.0,
+                source,
+            },
+        }
+    }
+}
+
+impl From<ErrorContext<Vec<FieldError>>> for Error {
+    fn from(err: ErrorContext<Vec<FieldError>>) -> Self {
         Error::InvalidRequest {
             context: err.0,
             source: err.1,
@@ -151,6 +267,30 @@ impl From<ErrorContext<SubscriptionError>> for Error {
     }
 }
 
+impl From<ErrorContext<IssueError>> for Error {
+    fn from(err: ErrorContext<IssueError>) -> Self {
+        Error::Issue {
+            context: err.0,
+            source: err.1,
+        }
+    }
+}
+
+impl From<ErrorContext<CsvError>> for Error {
+    fn from(err: ErrorContext<CsvError>) -> Self {
+        match err.1 {
+            CsvError::Storage { context, source } => Error::Data {
+                context: format!("{}: {context}", err.0),
+                source,
+            },
+            other => Error::InvalidRequest {
+                context: err.0,
+                source: vec![FieldError::new("file", other.to_string())],
+            },
+        }
+    }
+}
+
 impl From<ErrorContext<EmailError>> for Error {
     fn from(err: ErrorContext<EmailError>) -> Self {
         Error::Email {
@@ -160,97 +300,309 @@ impl From<ErrorContext<EmailError>> for Error {
     }
 }
 
+impl From<ErrorContext<TemplateError>> for Error {
+    fn from(err: ErrorContext<TemplateError>) -> Self {
+        Error::Template {
+            context: err.0,
+            source: err.1,
+        }
+    }
+}
+
+impl From<ErrorContext<SanitizerError>> for Error {
+    fn from(err: ErrorContext<SanitizerError>) -> Self {
+        Error::Sanitizer {
+            context: err.0,
+            source: err.1,
+        }
+    }
+}
+
 impl Error {
-    pub fn standardize(&self) -> (StatusCode, Json<Value>) {
+    /// Returns the stable, machine readable code identifying this error.
+    /// The mapping from variants to codes and HTTP status codes is documented
+    /// in `documentation/error-handling.md`.
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            Error::AuthenticationService { source, .. } if source.is_retryable() => {
+                ErrorCode::StorageUnavailable
+            }
+            Error::AuthenticationService { .. } => ErrorCode::AuthInternalError,
+            Error::Credentials { .. } => ErrorCode::AuthInvalidCredentials,
+            Error::Context { source, .. } => match source {
+                ContextError::ContextNotFound | ContextError::TokenNotFound => {
+                    ErrorCode::AuthMissingCredentials
+                }
+                ContextError::InvalidCredentials { .. } | ContextError::InvalidUserId { .. } => {
+                    ErrorCode::AuthInvalidToken
+                }
+            },
+            Error::ContextResolution { source, .. } => match source {
+                ContextResolutionError::TokenNotFound => ErrorCode::AuthMissingCredentials,
+                ContextResolutionError::InvalidCredentials { .. }
+                | ContextResolutionError::InvalidUserId { .. } => ErrorCode::AuthInvalidToken,
+            },
+            Error::BasicAuthentication { source, .. } => match source {
+                BasicError::MissingHeader { .. } => ErrorCode::AuthMissingCredentials,
+                _ => ErrorCode::AuthInvalidCredentials,
+            },
+            Error::DuplicateEmail { .. } => ErrorCode::AuthDuplicateEmail,
+            Error::DuplicateUsername { .. } => ErrorCode::AuthDuplicateUsername,
+            Error::WeakPassword { .. } => ErrorCode::AuthWeakPassword,
+            Error::InvalidRequest { .. } => ErrorCode::RequestInvalid,
+            Error::MissingToken { .. } => ErrorCode::SubscriptionTokenNotFound,
+            Error::InvalidToken { .. } => ErrorCode::AuthInvalidToken,
+            Error::MissingSubscription { .. } => ErrorCode::SubscriptionNotFound,
+            Error::MissingIssue { .. } => ErrorCode::IssueNotFound,
+            Error::IssueLocked { .. } => ErrorCode::IssueLocked,
+            Error::MissingList { .. } => ErrorCode::ListNotFound,
+            Error::MissingSuppression { .. } => ErrorCode::SuppressionNotFound,
+            Error::DuplicateList { .. } => ErrorCode::ListDuplicateSlug,
+            Error::EmptySegment { .. } => ErrorCode::NewsletterEmptySegment,
+            Error::UnresolvedPlaceholder { .. } => ErrorCode::NewsletterUnresolvedPlaceholder,
+            Error::Data { source, .. } if source.is_retryable() => ErrorCode::StorageUnavailable,
+            Error::Data { .. } | Error::Issue { .. } => ErrorCode::StorageInternalError,
+            Error::Email { source, .. } => match source {
+                EmailError::TooLarge { .. } => ErrorCode::EmailTooLarge,
+                EmailError::Suppressed { .. } => ErrorCode::EmailSuppressed,
+                EmailError::Unavailable { .. } | EmailError::CircuitOpen { .. } => {
+                    ErrorCode::EmailUnavailable
+                }
+                _ => ErrorCode::EmailDeliveryFailed,
+            },
+            Error::Template { .. } => ErrorCode::EmailTemplateFailed,
+            Error::Sanitizer { source, .. } => match source {
+                SanitizerError::Policy { .. } => ErrorCode::NewsletterSanitizerFailed,
+                SanitizerError::Rejected { .. } => ErrorCode::NewsletterContentRejected,
+            },
+        }
+    }
+
+    /// Builds the RFC 7807 problem details reported to the client.
+    pub fn standardize(&self) -> Problem {
+        let code = self.code();
+        let detail = match self {
+            Error::AuthenticationService { context, .. }
+            | Error::Credentials { context, .. }
+            | Error::Context { context, .. }
+            | Error::ContextResolution { context, .. }
+            | Error::BasicAuthentication { context, .. }
+            | Error::DuplicateEmail { context }
+            | Error::DuplicateUsername { context }
+            | Error::WeakPassword { context }
+            | Error::InvalidRequest { context, .. }
+            | Error::MissingToken { context }
+            | Error::InvalidToken { context }
+            | Error::MissingSubscription { context }
+            | Error::MissingIssue { context }
+            | Error::IssueLocked { context }
+            | Error::MissingList { context }
+            | Error::MissingSuppression { context }
+            | Error::DuplicateList { context }
+            | Error::EmptySegment { context }
+            | Error::UnresolvedPlaceholder { context }
+            | Error::Data { context, .. }
+            | Error::Issue { context, .. }
+            | Error::Email { context, .. }
+            | Error::Template { context, .. }
+            | Error::Sanitizer { context, .. } => context.clone(),
+        };
+        let errors = match self {
+            Error::InvalidRequest { source, .. } => source.clone(),
+            _ => Vec::new(),
+        };
+        Problem {
+            problem_type: format!("urn:zero2prod:problem:{}", code.as_str()),
+            title: code.title().to_string(),
+            status: code.status().as_u16(),
+            detail,
+            code,
+            errors,
+        }
+    }
+}
+
+/// Closed set of error codes returned to the client. The serialized values
+/// are part of the API, and must not change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub enum ErrorCode {
+    #[serde(rename = "auth/internal_error")]
+    AuthInternalError,
+    #[serde(rename = "auth/invalid_credentials")]
+    AuthInvalidCredentials,
+    #[serde(rename = "auth/missing_credentials")]
+    AuthMissingCredentials,
+    #[serde(rename = "auth/invalid_token")]
+    AuthInvalidToken,
+    #[serde(rename = "auth/duplicate_email")]
+    AuthDuplicateEmail,
+    #[serde(rename = "auth/duplicate_username")]
+    AuthDuplicateUsername,
+    #[serde(rename = "auth/weak_password")]
+    AuthWeakPassword,
+    #[serde(rename = "request/invalid")]
+    RequestInvalid,
+    #[serde(rename = "subscription/token_not_found")]
+    SubscriptionTokenNotFound,
+    #[serde(rename = "subscription/not_found")]
+    SubscriptionNotFound,
+    #[serde(rename = "issue/not_found")]
+    IssueNotFound,
+    #[serde(rename = "issue/locked")]
+    IssueLocked,
+    #[serde(rename = "list/not_found")]
+    ListNotFound,
+    #[serde(rename = "list/duplicate_slug")]
+    ListDuplicateSlug,
+    #[serde(rename = "suppression/not_found")]
+    SuppressionNotFound,
+    #[serde(rename = "storage/internal_error")]
+    StorageInternalError,
+    #[serde(rename = "storage/unavailable")]
+    StorageUnavailable,
+    #[serde(rename = "email/delivery_failed")]
+    EmailDeliveryFailed,
+    #[serde(rename = "email/too_large")]
+    EmailTooLarge,
+    #[serde(rename = "email/unavailable")]
+    EmailUnavailable,
+    #[serde(rename = "email/suppressed")]
+    EmailSuppressed,
+    #[serde(rename = "email/template_failed")]
+    EmailTemplateFailed,
+    #[serde(rename = "newsletter/content_rejected")]
+    NewsletterContentRejected,
+    #[serde(rename = "newsletter/sanitizer_failed")]
+    NewsletterSanitizerFailed,
+    #[serde(rename = "newsletter/empty_segment")]
+    NewsletterEmptySegment,
+    #[serde(rename = "newsletter/unresolved_placeholder")]
+    NewsletterUnresolvedPlaceholder,
+}
+
+impl ErrorCode {
+    pub fn as_str(&self) -> &'static str {
         match self {
-            Error::AuthenticationService { context, source: _ } => (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(serde_json::json!({
-                    "status": "fail",
-                    "message": context,
-                    "code": "auth/internal_error".to_string()
-                })),
-            ),
-            Error::Credentials { context, source: _ } => (
-                StatusCode::UNAUTHORIZED,
-                Json(serde_json::json!({
-                    "status": "fail",
-                    "message": context,
-                    "code": "auth/invalid_credentials".to_string()
-                })),
-            ),
-            Error::Context { context, source: _ } => (
-                StatusCode::UNAUTHORIZED,
-                Json(serde_json::json!({
-                    "status": "fail",
-                    "message": context,
-                    "code": "auth/missing_credentials".to_string()
-                })),
-            ),
-            Error::ContextResolution { context, source: _ } => (
-                StatusCode::UNAUTHORIZED,
-                Json(serde_json::json!({
-                    "status": "fail",
-                    "message": context,
-                    "code": "tbd".to_string()
-                })),
-            ),
-            Error::DuplicateEmail { context } => (
-                StatusCode::CONFLICT,
-                Json(serde_json::json!({
-                    "status": "fail",
-                    "message": context,
-                    "code": "auth/duplicate_email"
-                })),
-            ),
-            Error::DuplicateUsername { context } => (
-                StatusCode::CONFLICT,
-                Json(serde_json::json!({
-                    "status": "fail",
-                    "message": context,
-                    "code": "auth/duplicate_username"
-                })),
-            ),
-            Error::WeakPassword { context } => (
-                StatusCode::BAD_REQUEST,
-                Json(serde_json::json!({
-                    "status": "fail",
-                    "message": context,
-                    "code": "auth/weak_password"
-                })),
-            ),
-            Error::InvalidRequest { context, source: _ } => (
-                StatusCode::BAD_REQUEST,
-                Json(serde_json::json!({
-                    "status": "fail",
-                    "message": context,
-                    "code": "tbd"
-                })),
-            ),
-            Error::MissingToken { context } => (
-                StatusCode::UNAUTHORIZED,
-                Json(serde_json::json!({
-                    "status": "fail",
-                    "message": context,
-                    "code": "tbd"
-                })),
-            ),
-            Error::Data { context, source: _ } => (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(serde_json::json!({
-                    "status": "fail",
-                    "message": context,
-                    "code": "tbd"
-                })),
-            ),
-            Error::Email { context, source: _ } => (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(serde_json::json!({
-                    "status": "fail",
-                    "message": context,
-                    "code": "tbd"
-                })),
-            ),
+            ErrorCode::AuthInternalError => "auth/internal_error",
+            ErrorCode::AuthInvalidCredentials => "auth/invalid_credentials",
+            ErrorCode::AuthMissingCredentials => "auth/missing_credentials",
+            ErrorCode::AuthInvalidToken => "auth/invalid_token",
+            ErrorCode::AuthDuplicateEmail => "auth/duplicate_email",
+            ErrorCode::AuthDuplicateUsername => "auth/duplicate_username",
+            ErrorCode::AuthWeakPassword => "auth/weak_password",
+            ErrorCode::RequestInvalid => "request/invalid",
+            ErrorCode::SubscriptionTokenNotFound => "subscription/token_not_found",
+            ErrorCode::SubscriptionNotFound => "subscription/not_found",
+            ErrorCode::IssueNotFound => "issue/not_found",
+            ErrorCode::IssueLocked => "issue/locked",
+            ErrorCode::ListNotFound => "list/not_found",
+            ErrorCode::ListDuplicateSlug => "list/duplicate_slug",
+            ErrorCode::SuppressionNotFound => "suppression/not_found",
+            ErrorCode::StorageInternalError => "storage/internal_error",
+            ErrorCode::StorageUnavailable => "storage/unavailable",
+            ErrorCode::EmailDeliveryFailed => "email/delivery_failed",
+            ErrorCode::EmailTooLarge => "email/too_large",
+            ErrorCode::EmailUnavailable => "email/unavailable",
+            ErrorCode::EmailSuppressed => "email/suppressed",
+            ErrorCode::EmailTemplateFailed => "email/template_failed",
+            ErrorCode::NewsletterContentRejected => "newsletter/content_rejected",
+            ErrorCode::NewsletterSanitizerFailed => "newsletter/sanitizer_failed",
+            ErrorCode::NewsletterEmptySegment => "newsletter/empty_segment",
+            ErrorCode::NewsletterUnresolvedPlaceholder => "newsletter/unresolved_placeholder",
         }
     }
+
+    /// The HTTP status code associated with the error code.
+    pub fn status(&self) -> StatusCode {
+        match self {
+            ErrorCode::AuthInternalError => StatusCode::INTERNAL_SERVER_ERROR,
+            ErrorCode::AuthInvalidCredentials => StatusCode::UNAUTHORIZED,
+            ErrorCode::AuthMissingCredentials => StatusCode::UNAUTHORIZED,
+            ErrorCode::AuthInvalidToken => StatusCode::UNAUTHORIZED,
+            ErrorCode::AuthDuplicateEmail => StatusCode::CONFLICT,
+            ErrorCode::AuthDuplicateUsername => StatusCode::CONFLICT,
+            ErrorCode::AuthWeakPassword => StatusCode::BAD_REQUEST,
+            ErrorCode::RequestInvalid => StatusCode::BAD_REQUEST,
+            ErrorCode::SubscriptionTokenNotFound => StatusCode::NOT_FOUND,
+            ErrorCode::SubscriptionNotFound => StatusCode::NOT_FOUND,
+            ErrorCode::IssueNotFound => StatusCode::NOT_FOUND,
+            ErrorCode::IssueLocked => StatusCode::CONFLICT,
+            ErrorCode::ListNotFound => StatusCode::NOT_FOUND,
+            ErrorCode::ListDuplicateSlug => StatusCode::CONFLICT,
+            ErrorCode::SuppressionNotFound => StatusCode::NOT_FOUND,
+            ErrorCode::StorageInternalError => StatusCode::INTERNAL_SERVER_ERROR,
+            ErrorCode::StorageUnavailable => StatusCode::SERVICE_UNAVAILABLE,
+            ErrorCode::EmailDeliveryFailed => StatusCode::INTERNAL_SERVER_ERROR,
+            ErrorCode::EmailTooLarge => StatusCode::UNPROCESSABLE_ENTITY,
+            ErrorCode::EmailUnavailable => StatusCode::SERVICE_UNAVAILABLE,
+            ErrorCode::EmailSuppressed => StatusCode::UNPROCESSABLE_ENTITY,
+            ErrorCode::EmailTemplateFailed => StatusCode::INTERNAL_SERVER_ERROR,
+            ErrorCode::NewsletterContentRejected => StatusCode::UNPROCESSABLE_ENTITY,
+            ErrorCode::NewsletterSanitizerFailed => StatusCode::INTERNAL_SERVER_ERROR,
+            ErrorCode::NewsletterEmptySegment => StatusCode::UNPROCESSABLE_ENTITY,
+            ErrorCode::NewsletterUnresolvedPlaceholder => StatusCode::UNPROCESSABLE_ENTITY,
+        }
+    }
+
+    /// A short, human readable summary of the problem, which does not change
+    /// from occurrence to occurrence.
+    pub fn title(&self) -> &'static str {
+        match self {
+            ErrorCode::AuthInternalError => "Authentication service failure",
+            ErrorCode::AuthInvalidCredentials => "Invalid credentials",
+            ErrorCode::AuthMissingCredentials => "Missing credentials",
+            ErrorCode::AuthInvalidToken => "Invalid token",
+            ErrorCode::AuthDuplicateEmail => "Duplicate email",
+            ErrorCode::AuthDuplicateUsername => "Duplicate username",
+            ErrorCode::AuthWeakPassword => "Weak password",
+            ErrorCode::RequestInvalid => "Invalid request",
+            ErrorCode::SubscriptionTokenNotFound => "Subscription token not found",
+            ErrorCode::SubscriptionNotFound => "Subscription not found",
+            ErrorCode::IssueNotFound => "Issue not found",
+            ErrorCode::IssueLocked => "Issue locked",
+            ErrorCode::ListNotFound => "List not found",
+            ErrorCode::ListDuplicateSlug => "List slug already used",
+            ErrorCode::SuppressionNotFound => "Suppression not found",
+            ErrorCode::StorageInternalError => "Storage failure",
+            ErrorCode::StorageUnavailable => "Storage unavailable",
+            ErrorCode::EmailDeliveryFailed => "Email delivery failure",
+            ErrorCode::EmailTooLarge => "Email too large",
+            ErrorCode::EmailUnavailable => "Email service unavailable",
+            ErrorCode::EmailSuppressed => "Email address suppressed",
+            ErrorCode::EmailTemplateFailed => "Email template failure",
+            ErrorCode::NewsletterContentRejected => "Newsletter content rejected",
+            ErrorCode::NewsletterSanitizerFailed => "Newsletter sanitizer failure",
+            ErrorCode::NewsletterEmptySegment => "Newsletter segment without subscribers",
+            ErrorCode::NewsletterUnresolvedPlaceholder => "Newsletter placeholder without value",
+        }
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.write_str(self.as_str())
+    }
+}
+
+/// Problem Details for HTTP APIs (RFC 7807), extended with our error `code`
+/// and, for invalid requests, the list of fields which failed validation.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Problem {
+    #[serde(rename = "type")]
+    pub problem_type: String,
+    pub title: String,
+    pub status: u16,
+    pub detail: String,
+    pub code: ErrorCode,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<FieldError>,
+}
+
+pub const PROBLEM_JSON: &str = "application/problem+json";
+
+impl IntoResponse for Problem {
+    fn into_response(self) -> Response {
+        let status = StatusCode::from_u16(self.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+        (status, [(header::CONTENT_TYPE, PROBLEM_JSON)], Json(self)).into_response()
+    }
 }
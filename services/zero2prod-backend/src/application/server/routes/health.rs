@@ -1,12 +1,21 @@
 use axum::extract::Json;
 use axum::response::IntoResponse;
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
 /// GET handler for health requests by an application platform
 ///
 /// Intended for use in environments such as Amazon ECS or Kubernetes which want
 /// to validate that the HTTP service is available for traffic, by returning a
 /// 200 OK response with any content.
+#[utoipa::path(
+    get,
+    path = "/health",
+    tag = "health",
+    responses(
+        (status = 200, description = "The service is available", body = Zero2ProdHealthResp)
+    )
+)]
 #[allow(clippy::unused_async)]
 pub async fn health() -> impl IntoResponse {
     let resp = Zero2ProdHealthResp {
@@ -15,7 +24,7 @@ pub async fn health() -> impl IntoResponse {
     Json(resp)
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct Zero2ProdHealthResp {
     pub status: String,
 }
@@ -0,0 +1,994 @@
+use axum::extract::{Json, Path, Query, State};
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::Extension;
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use super::lists::resolve_lists;
+use super::newsletter::Newsletter;
+use super::{authorize, Error, Problem, StatusResp};
+
+use crate::application::server::middleware::resolve_context::Error as ContextResolutionError;
+use crate::application::server::{
+    context::{Context, Error as ContextError},
+    AppState,
+};
+use crate::domain::ports::secondary::Email;
+use crate::domain::{
+    Delivery, DeliveryFilter, DeliveryStatus, DeliverySummary, FieldError, Issue, IssueRequest,
+    IssueStats, SubscriberEmail,
+};
+use common::err_context::ErrorContextExt;
+
+/// POST handler for writing a new issue
+/// Without `scheduled_at`, the issue is a draft. Otherwise it is published by
+/// the scheduler at that time.
+#[utoipa::path(
+    post,
+    path = "/newsletter/issues",
+    tag = "newsletter",
+    request_body = IssueRequest,
+    security(("jwt" = [])),
+    responses(
+        (status = 201, description = "The issue", body = Issue),
+        (status = 400, description = "Invalid issue", body = Problem, content_type = "application/problem+json"),
+        (status = 401, description = "Missing or invalid JWT cookie", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Unknown list", body = Problem, content_type = "application/problem+json"),
+        (status = 422, description = "The sanitizer removed too much of the content", body = Problem, content_type = "application/problem+json"),
+        (status = 500, description = "Internal error", body = Problem, content_type = "application/problem+json"),
+    )
+)]
+#[allow(clippy::unused_async)]
+#[tracing::instrument(
+    name = "Creating an issue"
+    skip(state, context),
+    fields(
+        request_id = %Uuid::new_v4(),
+    )
+)]
+pub async fn create_issue(
+    Extension(context): Extension<Result<Context, ContextResolutionError>>,
+    State(state): State<AppState>,
+    Json(request): Json<IssueRequest>,
+) -> Result<impl IntoResponse, Error> {
+    let author_id = authorize(context)?;
+    check(&state, &request).await?;
+
+    let issue = Issue::new(request, author_id);
+    state
+        .issues
+        .create_issue(&issue)
+        .await
+        .context("Could not create issue")?;
+
+    Ok::<_, Error>((StatusCode::CREATED, Json(issue)))
+}
+
+/// GET handler for listing issues, the most recent first
+#[utoipa::path(
+    get,
+    path = "/newsletter/issues",
+    tag = "newsletter",
+    security(("jwt" = [])),
+    responses(
+        (status = 200, description = "All the issues", body = IssuesResp),
+        (status = 401, description = "Missing or invalid JWT cookie", body = Problem, content_type = "application/problem+json"),
+        (status = 500, description = "Internal error", body = Problem, content_type = "application/problem+json"),
+    )
+)]
+#[allow(clippy::unused_async)]
+#[tracing::instrument(
+    name = "Listing issues"
+    skip(state, context),
+    fields(
+        request_id = %Uuid::new_v4(),
+    )
+)]
+pub async fn list_issues(
+    Extension(context): Extension<Result<Context, ContextResolutionError>>,
+    State(state): State<AppState>,
+) -> Result<impl IntoResponse, Error> {
+    authorize(context)?;
+
+    let issues = state
+        .issues
+        .list_issues()
+        .await
+        .context("Could not list issues")?;
+
+    Ok::<_, Error>(Json(IssuesResp { issues }))
+}
+
+/// GET handler for a single issue
+#[utoipa::path(
+    get,
+    path = "/newsletter/issues/{id}",
+    tag = "newsletter",
+    params(("id" = Uuid, Path, description = "Issue id")),
+    security(("jwt" = [])),
+    responses(
+        (status = 200, description = "The issue", body = Issue),
+        (status = 401, description = "Missing or invalid JWT cookie", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Unknown issue", body = Problem, content_type = "application/problem+json"),
+        (status = 500, description = "Internal error", body = Problem, content_type = "application/problem+json"),
+    )
+)]
+#[allow(clippy::unused_async)]
+#[tracing::instrument(
+    name = "Fetching an issue"
+    skip(state, context),
+    fields(
+        request_id = %Uuid::new_v4(),
+    )
+)]
+pub async fn get_issue(
+    Extension(context): Extension<Result<Context, ContextResolutionError>>,
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<impl IntoResponse, Error> {
+    authorize(context)?;
+
+    let issue = fetch_issue(&state, &id).await?;
+
+    Ok::<_, Error>(Json(issue))
+}
+
+/// GET handler for the engagement with an issue
+/// Only the recipients of issues sent with `tracking`, whose list does not
+/// turn tracking off, are counted.
+#[utoipa::path(
+    get,
+    path = "/newsletter/issues/{id}/stats",
+    tag = "newsletter",
+    params(("id" = Uuid, Path, description = "Issue id")),
+    security(("jwt" = [])),
+    responses(
+        (status = 200, description = "Opens, clicks, and bounces of the issue", body = IssueStats),
+        (status = 401, description = "Missing or invalid JWT cookie", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Unknown issue", body = Problem, content_type = "application/problem+json"),
+        (status = 500, description = "Internal error", body = Problem, content_type = "application/problem+json"),
+    )
+)]
+#[allow(clippy::unused_async)]
+#[tracing::instrument(
+    name = "Computing the stats of an issue"
+    skip(state, context),
+    fields(
+        request_id = %Uuid::new_v4(),
+    )
+)]
+pub async fn get_issue_stats(
+    Extension(context): Extension<Result<Context, ContextResolutionError>>,
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<impl IntoResponse, Error> {
+    authorize(context)?;
+
+    fetch_issue(&state, &id).await?;
+    let stats = state
+        .issues
+        .get_issue_stats(&id)
+        .await
+        .context("Could not compute issue stats")?;
+
+    Ok::<_, Error>(Json(stats))
+}
+
+/// GET handler for the deliveries of an issue to its recipients
+/// Deliveries are recorded for the issues published by the scheduler, to the
+/// subscribers who get them immediately.
+#[utoipa::path(
+    get,
+    path = "/newsletter/issues/{id}/deliveries",
+    tag = "newsletter",
+    params(("id" = Uuid, Path, description = "Issue id"), DeliveriesQuery),
+    security(("jwt" = [])),
+    responses(
+        (status = 200, description = "The deliveries of the issue, by email", body = DeliveriesResp),
+        (status = 400, description = "Invalid status", body = Problem, content_type = "application/problem+json"),
+        (status = 401, description = "Missing or invalid JWT cookie", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Unknown issue", body = Problem, content_type = "application/problem+json"),
+        (status = 500, description = "Internal error", body = Problem, content_type = "application/problem+json"),
+    )
+)]
+#[allow(clippy::unused_async)]
+#[tracing::instrument(
+    name = "Listing the deliveries of an issue"
+    skip(state, context),
+    fields(
+        request_id = %Uuid::new_v4(),
+    )
+)]
+pub async fn list_deliveries(
+    Extension(context): Extension<Result<Context, ContextResolutionError>>,
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Query(query): Query<DeliveriesQuery>,
+) -> Result<impl IntoResponse, Error> {
+    authorize(context)?;
+
+    fetch_issue(&state, &id).await?;
+    let deliveries = state
+        .issues
+        .list_deliveries(&id, &DeliveryFilter::from(query))
+        .await
+        .context("Could not list issue deliveries")?;
+
+    Ok::<_, Error>(Json(DeliveriesResp { deliveries }))
+}
+
+/// GET handler for the number of deliveries of an issue in each state
+#[utoipa::path(
+    get,
+    path = "/newsletter/issues/{id}/deliveries/summary",
+    tag = "newsletter",
+    params(("id" = Uuid, Path, description = "Issue id")),
+    security(("jwt" = [])),
+    responses(
+        (status = 200, description = "Number of deliveries in each state", body = DeliverySummary),
+        (status = 401, description = "Missing or invalid JWT cookie", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Unknown issue", body = Problem, content_type = "application/problem+json"),
+        (status = 500, description = "Internal error", body = Problem, content_type = "application/problem+json"),
+    )
+)]
+#[allow(clippy::unused_async)]
+#[tracing::instrument(
+    name = "Summarizing the deliveries of an issue"
+    skip(state, context),
+    fields(
+        request_id = %Uuid::new_v4(),
+    )
+)]
+pub async fn get_delivery_summary(
+    Extension(context): Extension<Result<Context, ContextResolutionError>>,
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<impl IntoResponse, Error> {
+    authorize(context)?;
+
+    fetch_issue(&state, &id).await?;
+    let summary = state
+        .issues
+        .get_delivery_summary(&id)
+        .await
+        .context("Could not summarize issue deliveries")?;
+
+    Ok::<_, Error>(Json(summary))
+}
+
+/// PUT handler for rewriting an issue, or changing its schedule
+/// Issues can be rewritten until their sending starts.
+#[utoipa::path(
+    put,
+    path = "/newsletter/issues/{id}",
+    tag = "newsletter",
+    params(("id" = Uuid, Path, description = "Issue id")),
+    request_body = IssueRequest,
+    security(("jwt" = [])),
+    responses(
+        (status = 200, description = "The issue", body = Issue),
+        (status = 400, description = "Invalid issue", body = Problem, content_type = "application/problem+json"),
+        (status = 401, description = "Missing or invalid JWT cookie", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Unknown issue", body = Problem, content_type = "application/problem+json"),
+        (status = 409, description = "The sending of the issue has started", body = Problem, content_type = "application/problem+json"),
+        (status = 422, description = "The sanitizer removed too much of the content", body = Problem, content_type = "application/problem+json"),
+        (status = 500, description = "Internal error", body = Problem, content_type = "application/problem+json"),
+    )
+)]
+#[allow(clippy::unused_async)]
+#[tracing::instrument(
+    name = "Updating an issue"
+    skip(state, context),
+    fields(
+        request_id = %Uuid::new_v4(),
+    )
+)]
+pub async fn update_issue(
+    Extension(context): Extension<Result<Context, ContextResolutionError>>,
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Json(request): Json<IssueRequest>,
+) -> Result<impl IntoResponse, Error> {
+    authorize(context)?;
+    check(&state, &request).await?;
+
+    let issue = fetch_issue(&state, &id).await?.update(request);
+    if !state
+        .issues
+        .update_issue(&issue)
+        .await
+        .context("Could not update issue")?
+    {
+        return Err(locked(&id));
+    }
+
+    Ok::<_, Error>(Json(issue))
+}
+
+/// DELETE handler for an issue
+/// Issues can be deleted until their sending starts.
+#[utoipa::path(
+    delete,
+    path = "/newsletter/issues/{id}",
+    tag = "newsletter",
+    params(("id" = Uuid, Path, description = "Issue id")),
+    security(("jwt" = [])),
+    responses(
+        (status = 204, description = "Issue deleted"),
+        (status = 401, description = "Missing or invalid JWT cookie", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Unknown issue", body = Problem, content_type = "application/problem+json"),
+        (status = 409, description = "The sending of the issue has started", body = Problem, content_type = "application/problem+json"),
+        (status = 500, description = "Internal error", body = Problem, content_type = "application/problem+json"),
+    )
+)]
+#[allow(clippy::unused_async)]
+#[tracing::instrument(
+    name = "Deleting an issue"
+    skip(state, context),
+    fields(
+        request_id = %Uuid::new_v4(),
+    )
+)]
+pub async fn delete_issue(
+    Extension(context): Extension<Result<Context, ContextResolutionError>>,
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<impl IntoResponse, Error> {
+    authorize(context)?;
+
+    fetch_issue(&state, &id).await?;
+    if !state
+        .issues
+        .delete_issue(&id)
+        .await
+        .context("Could not delete issue")?
+    {
+        return Err(locked(&id));
+    }
+
+    Ok::<_, Error>(StatusCode::NO_CONTENT)
+}
+
+/// POST handler for cancelling the publication of a scheduled issue
+/// The issue becomes a draft again. This is possible until its sending starts.
+#[utoipa::path(
+    post,
+    path = "/newsletter/issues/{id}/cancel",
+    tag = "newsletter",
+    params(("id" = Uuid, Path, description = "Issue id")),
+    security(("jwt" = [])),
+    responses(
+        (status = 200, description = "The issue is a draft", body = StatusResp),
+        (status = 401, description = "Missing or invalid JWT cookie", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Unknown issue", body = Problem, content_type = "application/problem+json"),
+        (status = 409, description = "The issue is not scheduled", body = Problem, content_type = "application/problem+json"),
+        (status = 500, description = "Internal error", body = Problem, content_type = "application/problem+json"),
+    )
+)]
+#[allow(clippy::unused_async)]
+#[tracing::instrument(
+    name = "Cancelling an issue"
+    skip(state, context),
+    fields(
+        request_id = %Uuid::new_v4(),
+    )
+)]
+pub async fn cancel_issue(
+    Extension(context): Extension<Result<Context, ContextResolutionError>>,
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<impl IntoResponse, Error> {
+    authorize(context)?;
+
+    fetch_issue(&state, &id).await?;
+    if !state
+        .issues
+        .cancel_issue(&id)
+        .await
+        .context("Could not cancel issue")?
+    {
+        return Err(Error::IssueLocked {
+            context: format!("Issue {id} is not scheduled"),
+        });
+    }
+
+    Ok::<_, Error>(Json(StatusResp::success()))
+}
+
+/// GET handler for previewing an issue
+/// The email is rendered as subscribers get it, for the requesting admin.
+#[utoipa::path(
+    get,
+    path = "/newsletter/issues/{id}/preview",
+    tag = "newsletter",
+    params(("id" = Uuid, Path, description = "Issue id")),
+    security(("jwt" = [])),
+    responses(
+        (status = 200, description = "The email sent to subscribers", body = IssuePreview),
+        (status = 401, description = "Missing or invalid JWT cookie", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Unknown issue", body = Problem, content_type = "application/problem+json"),
+        (status = 422, description = "The sanitizer removed too much of the content", body = Problem, content_type = "application/problem+json"),
+        (status = 500, description = "Internal error", body = Problem, content_type = "application/problem+json"),
+    )
+)]
+#[allow(clippy::unused_async)]
+#[tracing::instrument(
+    name = "Previewing an issue"
+    skip(state, context),
+    fields(
+        request_id = %Uuid::new_v4(),
+    )
+)]
+pub async fn preview_issue(
+    Extension(context): Extension<Result<Context, ContextResolutionError>>,
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<impl IntoResponse, Error> {
+    let user_id = authorize(context)?;
+
+    let email = render_for_admin(&state, &id, &user_id).await?;
+
+    Ok::<_, Error>(Json(IssuePreview {
+        subject: email.subject,
+        html_content: email.html_content,
+        text_content: email.text_content,
+    }))
+}
+
+/// POST handler for sending an issue to the requesting admin only
+#[utoipa::path(
+    post,
+    path = "/newsletter/issues/{id}/test",
+    tag = "newsletter",
+    params(("id" = Uuid, Path, description = "Issue id")),
+    security(("jwt" = [])),
+    responses(
+        (status = 200, description = "The issue was sent to the requesting admin", body = StatusResp),
+        (status = 401, description = "Missing or invalid JWT cookie", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Unknown issue", body = Problem, content_type = "application/problem+json"),
+        (status = 422, description = "The sanitizer removed too much of the content", body = Problem, content_type = "application/problem+json"),
+        (status = 500, description = "Internal error", body = Problem, content_type = "application/problem+json"),
+    )
+)]
+#[allow(clippy::unused_async)]
+#[tracing::instrument(
+    name = "Test sending an issue"
+    skip(state, context),
+    fields(
+        request_id = %Uuid::new_v4(),
+    )
+)]
+pub async fn test_issue(
+    Extension(context): Extension<Result<Context, ContextResolutionError>>,
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<impl IntoResponse, Error> {
+    let user_id = authorize(context)?;
+
+    let email = render_for_admin(&state, &id, &user_id).await?;
+    state
+        .email
+        .send_email(email)
+        .await
+        .context("Could not send test email")?;
+
+    Ok::<_, Error>(Json(StatusResp::success()))
+}
+
+/// Validates the request, and, if it is scheduled, makes sure the scheduler
+/// will be able to publish it.
+async fn check(state: &AppState, request: &IssueRequest) -> Result<(), Error> {
+    request.validate().context("Invalid issue")?;
+    resolve_lists(state, &request.lists).await?;
+    if request.scheduled_at.is_some() {
+        Newsletter::prepare(state, &request.title, &request.content, request.force)?;
+    }
+    Ok(())
+}
+
+async fn fetch_issue(state: &AppState, id: &Uuid) -> Result<Issue, Error> {
+    state
+        .issues
+        .get_issue(id)
+        .await
+        .context("Could not get issue")?
+        .ok_or_else(|| Error::MissingIssue {
+            context: format!("No issue with id {id}"),
+        })
+}
+
+fn locked(id: &Uuid) -> Error {
+    Error::IssueLocked {
+        context: format!("The sending of issue {id} has started"),
+    }
+}
+
+/// Renders the issue as subscribers get it, for the admin identified by
+/// user_id. The unsubscribe link is not tied to any subscription, or list.
+async fn render_for_admin(state: &AppState, id: &Uuid, user_id: &Uuid) -> Result<Email, Error> {
+    let issue = fetch_issue(state, id).await?;
+
+    let email = state
+        .authentication
+        .get_email(user_id)
+        .await
+        .context("Could not get user email")?
+        .ok_or_else(|| Error::Context {
+            context: "Unknown user".to_string(),
+            source: ContextError::InvalidUserId {
+                context: format!("No user with id {user_id}"),
+            },
+        })?;
+    let to = SubscriberEmail::parse(email)
+        .map_err(|err| vec![FieldError::new("email", err)])
+        .context("Invalid user email")?;
+
+    let newsletter = Newsletter::prepare(state, &issue.title, &issue.content, issue.force)?;
+    newsletter.render_preview(state, &to)
+}
+
+/// All the issues, the most recent first.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct IssuesResp {
+    pub issues: Vec<Issue>,
+}
+
+/// Query string of the delivery listing.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DeliveriesQuery {
+    /// Only return deliveries in this state.
+    pub status: Option<DeliveryStatus>,
+    /// Only return deliveries whose email contains this string (case insensitive).
+    pub email: Option<String>,
+}
+
+impl From<DeliveriesQuery> for DeliveryFilter {
+    fn from(query: DeliveriesQuery) -> Self {
+        DeliveryFilter {
+            status: query.status,
+            email: query.email,
+        }
+    }
+}
+
+/// The deliveries of an issue, by email.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct DeliveriesResp {
+    pub deliveries: Vec<Delivery>,
+}
+
+/// The email, as sent to subscribers.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct IssuePreview {
+    pub subject: String,
+    pub html_content: String,
+    pub text_content: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        body::Body,
+        http::{header, Request},
+        middleware::{from_fn_with_state, map_response},
+        routing::{get, post, Router},
+    };
+    use chrono::{Duration, Utc};
+    use hyper::body::to_bytes;
+    use mockall::predicate::*;
+    use secrecy::Secret;
+    use speculoos::prelude::*;
+    use tower::ServiceExt;
+    use tower_cookies::CookieManagerLayer;
+
+    use crate::{
+        application::server::cookies::JWT,
+        application::server::{
+            middleware::resolve_context::resolve_context, middleware::response_map::error,
+        },
+        authentication::jwt::build_token,
+        domain::ports::secondary::{
+            MockAuthenticationStorage, MockEmailService, MockHtmlSanitizer, MockIssueStorage,
+            MockSubscriptionStorage,
+        },
+        domain::{Content, IssueStatus, MailingList, Segment},
+        services::templates::repository_templates,
+    };
+
+    use super::*;
+    use crate::application::server::TestStateBuilder;
+
+    /// This is a helper function to build an App with axum.
+    fn issues_route(state: AppState) -> Router {
+        Router::new()
+            .route("/api/issues", post(create_issue).get(list_issues))
+            .route(
+                "/api/issues/:id",
+                get(get_issue).put(update_issue).delete(delete_issue),
+            )
+            .route("/api/issues/:id/cancel", post(cancel_issue))
+            .route("/api/issues/:id/preview", get(preview_issue))
+            .route("/api/issues/:id/test", post(test_issue))
+            .route("/api/issues/:id/stats", get(get_issue_stats))
+            .route("/api/issues/:id/deliveries", get(list_deliveries))
+            .route(
+                "/api/issues/:id/deliveries/summary",
+                get(get_delivery_summary),
+            )
+            .layer(map_response(error))
+            .layer(from_fn_with_state(state.clone(), resolve_context))
+            .layer(CookieManagerLayer::new())
+            .with_state(state)
+    }
+
+    /// This is a helper function to build the state of an admin, identified by
+    /// user_id, with the given issue storage and email service.
+    fn state(
+        user_id: Uuid,
+        issues_mock: MockIssueStorage,
+        email_mock: MockEmailService,
+    ) -> AppState {
+        let mut authentication_mock = MockAuthenticationStorage::new();
+        authentication_mock
+            .expect_id_exists()
+            .withf(move |id: &Uuid| id == &user_id)
+            .return_const(Ok(true));
+        authentication_mock
+            .expect_get_email()
+            .withf(move |id: &Uuid| id == &user_id)
+            .return_const(Ok(Some("admin@acme.inc".to_string())));
+        let mut sanitizer_mock = MockHtmlSanitizer::new();
+        sanitizer_mock
+            .expect_sanitize()
+            .returning(|html, _| Ok(html.to_string()));
+        let mut subscription_mock = MockSubscriptionStorage::new();
+        subscription_mock.expect_get_list().returning(|key| {
+            Ok(Some(MailingList {
+                id: Uuid::new_v4(),
+                slug: key.to_string(),
+                name: "Newsletter".to_string(),
+                tracking: true,
+                created_at: Utc::now(),
+            }))
+        });
+        TestStateBuilder::new()
+            .authentication(authentication_mock)
+            .subscription(subscription_mock)
+            .issues(issues_mock)
+            .email(email_mock)
+            .templates(repository_templates())
+            .sanitizer(sanitizer_mock)
+            .build()
+    }
+
+    fn issue(author_id: Uuid, status: IssueStatus) -> Issue {
+        Issue {
+            status,
+            ..Issue::new(
+                IssueRequest {
+                    title: "News".to_string(),
+                    content: Content::Markdown {
+                        markdown: "Some *news*".to_string(),
+                    },
+                    scheduled_at: None,
+                    force: false,
+                    lists: Vec::new(),
+                    segment: Segment::default(),
+                    tracking: false,
+                },
+                author_id,
+            )
+        }
+    }
+
+    fn send_request(
+        method: &str,
+        uri: &str,
+        body: Option<serde_json::Value>,
+        user_id: Uuid,
+        secret: &Secret<String>,
+    ) -> Request<Body> {
+        let token = build_token(user_id, secret);
+        Request::builder()
+            .uri(uri)
+            .method(method)
+            .header(header::COOKIE, format!("{}={}", JWT, token))
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(body.map_or_else(Body::empty, |body| Body::from(body.to_string())))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn create_should_store_a_scheduled_issue() {
+        let user_id = Uuid::new_v4();
+        let mut issues_mock = MockIssueStorage::new();
+        issues_mock
+            .expect_create_issue()
+            .withf(move |issue: &Issue| {
+                issue.author_id == user_id && issue.status == IssueStatus::Scheduled
+            })
+            .times(1)
+            .return_once(|_| Ok(()));
+        let state = state(user_id, issues_mock, MockEmailService::new());
+
+        let body = serde_json::json!({
+            "title": "News",
+            "content": { "markdown": "Some *news*" },
+            "scheduled_at": Utc::now() + Duration::hours(1),
+        });
+        let response = issues_route(state.clone())
+            .oneshot(send_request(
+                "POST",
+                "/api/issues",
+                Some(body),
+                user_id,
+                &state.secret,
+            ))
+            .await
+            .expect("response");
+
+        assert_eq!(response.status(), StatusCode::CREATED);
+    }
+
+    #[tokio::test]
+    async fn update_should_be_refused_once_sending_started() {
+        let user_id = Uuid::new_v4();
+        let stored = issue(user_id, IssueStatus::Sending);
+        let id = stored.id;
+        let mut issues_mock = MockIssueStorage::new();
+        issues_mock
+            .expect_get_issue()
+            .with(eq(id))
+            .return_once(move |_| Ok(Some(stored)));
+        issues_mock.expect_update_issue().return_once(|_| Ok(false));
+        let state = state(user_id, issues_mock, MockEmailService::new());
+
+        let body = serde_json::json!({
+            "title": "News",
+            "content": { "markdown": "Other *news*" },
+        });
+        let response = issues_route(state.clone())
+            .oneshot(send_request(
+                "PUT",
+                &format!("/api/issues/{id}"),
+                Some(body),
+                user_id,
+                &state.secret,
+            ))
+            .await
+            .expect("response");
+
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+    }
+
+    #[tokio::test]
+    async fn cancel_should_turn_a_scheduled_issue_into_a_draft() {
+        let user_id = Uuid::new_v4();
+        let stored = issue(user_id, IssueStatus::Scheduled);
+        let id = stored.id;
+        let mut issues_mock = MockIssueStorage::new();
+        issues_mock
+            .expect_get_issue()
+            .return_once(move |_| Ok(Some(stored)));
+        issues_mock
+            .expect_cancel_issue()
+            .with(eq(id))
+            .times(1)
+            .return_once(|_| Ok(true));
+        let state = state(user_id, issues_mock, MockEmailService::new());
+
+        let response = issues_route(state.clone())
+            .oneshot(send_request(
+                "POST",
+                &format!("/api/issues/{id}/cancel"),
+                None,
+                user_id,
+                &state.secret,
+            ))
+            .await
+            .expect("response");
+
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn stats_should_report_the_engagement_with_the_issue() {
+        let user_id = Uuid::new_v4();
+        let stored = issue(user_id, IssueStatus::Sent);
+        let id = stored.id;
+        let mut issues_mock = MockIssueStorage::new();
+        issues_mock
+            .expect_get_issue()
+            .return_once(move |_| Ok(Some(stored)));
+        issues_mock
+            .expect_get_issue_stats()
+            .with(eq(id))
+            .times(1)
+            .return_once(move |_| {
+                Ok(IssueStats {
+                    issue_id: id,
+                    recipients: 4,
+                    unique_opens: 3,
+                    clicks: Vec::new(),
+                    bounces: 1,
+                    bounce_rate: 0.25,
+                })
+            });
+        let state = state(user_id, issues_mock, MockEmailService::new());
+
+        let response = issues_route(state.clone())
+            .oneshot(send_request(
+                "GET",
+                &format!("/api/issues/{id}/stats"),
+                None,
+                user_id,
+                &state.secret,
+            ))
+            .await
+            .expect("response");
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = to_bytes(response.into_body()).await.unwrap();
+        let stats: IssueStats = serde_json::from_slice(&body).unwrap();
+        assert_eq!(stats.unique_opens, 3);
+        assert_eq!(stats.bounce_rate, 0.25);
+    }
+
+    #[tokio::test]
+    async fn deliveries_should_be_filtered_by_status_and_email() {
+        let user_id = Uuid::new_v4();
+        let stored = issue(user_id, IssueStatus::Sent);
+        let id = stored.id;
+        let mut issues_mock = MockIssueStorage::new();
+        issues_mock
+            .expect_get_issue()
+            .return_once(move |_| Ok(Some(stored)));
+        issues_mock
+            .expect_list_deliveries()
+            .withf(move |issue_id: &Uuid, filter: &DeliveryFilter| {
+                issue_id == &id
+                    && filter.status == Some(DeliveryStatus::Failed)
+                    && filter.email.as_deref() == Some("alice")
+            })
+            .times(1)
+            .return_once(move |_, _| {
+                Ok(vec![Delivery {
+                    last_error: Some("Unavailable: down".to_string()),
+                    ..Delivery::new(
+                        id,
+                        Uuid::new_v4(),
+                        "alice@acme.inc".to_string(),
+                        DeliveryStatus::Failed,
+                    )
+                }])
+            });
+        let state = state(user_id, issues_mock, MockEmailService::new());
+
+        let response = issues_route(state.clone())
+            .oneshot(send_request(
+                "GET",
+                &format!("/api/issues/{id}/deliveries?status=failed&email=alice"),
+                None,
+                user_id,
+                &state.secret,
+            ))
+            .await
+            .expect("response");
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = to_bytes(response.into_body()).await.unwrap();
+        let resp: DeliveriesResp = serde_json::from_slice(&body).unwrap();
+        assert_eq!(resp.deliveries.len(), 1);
+        assert_that(&resp.deliveries[0].last_error.as_deref())
+            .is_equal_to(Some("Unavailable: down"));
+    }
+
+    #[tokio::test]
+    async fn delivery_summary_should_count_the_deliveries_by_state() {
+        let user_id = Uuid::new_v4();
+        let stored = issue(user_id, IssueStatus::Sent);
+        let id = stored.id;
+        let mut issues_mock = MockIssueStorage::new();
+        issues_mock
+            .expect_get_issue()
+            .return_once(move |_| Ok(Some(stored)));
+        issues_mock
+            .expect_get_delivery_summary()
+            .with(eq(id))
+            .times(1)
+            .return_once(move |_| {
+                Ok(DeliverySummary {
+                    issue_id: id,
+                    total: 5,
+                    sent: 3,
+                    failed: 1,
+                    bounced: 1,
+                    ..DeliverySummary::default()
+                })
+            });
+        let state = state(user_id, issues_mock, MockEmailService::new());
+
+        let response = issues_route(state.clone())
+            .oneshot(send_request(
+                "GET",
+                &format!("/api/issues/{id}/deliveries/summary"),
+                None,
+                user_id,
+                &state.secret,
+            ))
+            .await
+            .expect("response");
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = to_bytes(response.into_body()).await.unwrap();
+        let summary: DeliverySummary = serde_json::from_slice(&body).unwrap();
+        assert_eq!(summary.sent, 3);
+        assert_eq!(summary.bounced, 1);
+    }
+
+    #[tokio::test]
+    async fn preview_should_render_the_email() {
+        let user_id = Uuid::new_v4();
+        let stored = issue(user_id, IssueStatus::Draft);
+        let id = stored.id;
+        let mut issues_mock = MockIssueStorage::new();
+        issues_mock
+            .expect_get_issue()
+            .return_once(move |_| Ok(Some(stored)));
+        let mut email_mock = MockEmailService::new();
+        email_mock.expect_send_email().never();
+        let state = state(user_id, issues_mock, email_mock);
+
+        let response = issues_route(state.clone())
+            .oneshot(send_request(
+                "GET",
+                &format!("/api/issues/{id}/preview"),
+                None,
+                user_id,
+                &state.secret,
+            ))
+            .await
+            .expect("response");
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = to_bytes(response.into_body()).await.expect("body");
+        let preview: IssuePreview = serde_json::from_slice(&body).expect("json");
+        assert_eq!(preview.subject, "News");
+        assert_that(&preview.html_content).contains("<em>news</em>");
+        assert_that(&preview.text_content).contains("/subscriptions/unsubscribe?token=");
+    }
+
+    #[tokio::test]
+    async fn test_send_should_only_mail_the_admin() {
+        let user_id = Uuid::new_v4();
+        let stored = issue(user_id, IssueStatus::Draft);
+        let id = stored.id;
+        let mut issues_mock = MockIssueStorage::new();
+        issues_mock
+            .expect_get_issue()
+            .return_once(move |_| Ok(Some(stored)));
+        let mut email_mock = MockEmailService::new();
+        email_mock
+            .expect_send_email()
+            .withf(|email: &Email| email.to.as_ref() == "admin@acme.inc")
+            .times(1)
+            .return_once(|_| Ok(()));
+        let state = state(user_id, issues_mock, email_mock);
+
+        let response = issues_route(state.clone())
+            .oneshot(send_request(
+                "POST",
+                &format!("/api/issues/{id}/test"),
+                None,
+                user_id,
+                &state.secret,
+            ))
+            .await
+            .expect("response");
+
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+}
@@ -0,0 +1,184 @@
+use axum::extract::{Json, State};
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::Extension;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use super::{authorize, Error, Problem};
+
+use crate::application::server::middleware::resolve_context::Error as ContextResolutionError;
+use crate::application::server::{context::Context, AppState};
+use crate::domain::{MailingList, MailingListRequest, DEFAULT_LIST};
+use common::err_context::ErrorContextExt;
+
+/// GET handler for the mailing lists one can subscribe to
+#[utoipa::path(
+    get,
+    path = "/lists",
+    tag = "lists",
+    responses(
+        (status = 200, description = "All the lists", body = ListsResp),
+        (status = 500, description = "Internal error", body = Problem, content_type = "application/problem+json"),
+    )
+)]
+#[allow(clippy::unused_async)]
+#[tracing::instrument(
+    name = "Listing lists"
+    skip(state),
+    fields(
+        request_id = %Uuid::new_v4(),
+    )
+)]
+pub async fn list_lists(State(state): State<AppState>) -> Result<impl IntoResponse, Error> {
+    let lists = state
+        .subscription
+        .get_lists()
+        .await
+        .context("Could not list lists")?;
+
+    Ok::<_, Error>(Json(ListsResp { lists }))
+}
+
+/// POST handler for creating a mailing list
+#[utoipa::path(
+    post,
+    path = "/lists",
+    tag = "lists",
+    request_body = MailingListRequest,
+    security(("jwt" = [])),
+    responses(
+        (status = 201, description = "The list", body = MailingList),
+        (status = 400, description = "Invalid list", body = Problem, content_type = "application/problem+json"),
+        (status = 401, description = "Missing or invalid JWT cookie", body = Problem, content_type = "application/problem+json"),
+        (status = 409, description = "There is already a list with that slug", body = Problem, content_type = "application/problem+json"),
+        (status = 500, description = "Internal error", body = Problem, content_type = "application/problem+json"),
+    )
+)]
+#[allow(clippy::unused_async)]
+#[tracing::instrument(
+    name = "Creating a list"
+    skip(state, context),
+    fields(
+        request_id = %Uuid::new_v4(),
+    )
+)]
+pub async fn create_list(
+    Extension(context): Extension<Result<Context, ContextResolutionError>>,
+    State(state): State<AppState>,
+    Json(request): Json<MailingListRequest>,
+) -> Result<impl IntoResponse, Error> {
+    authorize(context)?;
+    request.validate().context("Invalid list")?;
+
+    let list = MailingList::new(request);
+    let created = state
+        .subscription
+        .create_list(&list)
+        .await
+        .context("Could not create list")?;
+    if !created {
+        return Err(Error::DuplicateList {
+            context: format!("There is already a list {}", list.slug),
+        });
+    }
+
+    Ok::<_, Error>((StatusCode::CREATED, Json(list)))
+}
+
+/// Returns the list identified by key, its id or its slug, or the default
+/// list if there is no key.
+pub(crate) async fn fetch_list(state: &AppState, key: Option<&str>) -> Result<MailingList, Error> {
+    let key = key.unwrap_or(DEFAULT_LIST);
+    state
+        .subscription
+        .get_list(key)
+        .await
+        .context("Could not get list")?
+        .ok_or_else(|| Error::MissingList {
+            context: format!("No list {key}"),
+        })
+}
+
+/// Returns the ids of the lists identified by keys, or that of the default
+/// list if there are no keys.
+pub(crate) async fn resolve_lists(state: &AppState, keys: &[String]) -> Result<Vec<Uuid>, Error> {
+    if keys.is_empty() {
+        return Ok(vec![fetch_list(state, None).await?.id]);
+    }
+    let mut ids = Vec::with_capacity(keys.len());
+    for key in keys {
+        ids.push(fetch_list(state, Some(key)).await?.id);
+    }
+    Ok(ids)
+}
+
+/// All the lists, in the order they were created.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ListsResp {
+    pub lists: Vec<MailingList>,
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        body::Body,
+        http::{header, Request},
+        middleware::{from_fn_with_state, map_response},
+        routing::{post, Router},
+    };
+
+    use tower::ServiceExt;
+    use tower_cookies::CookieManagerLayer;
+
+    use crate::{
+        application::server::cookies::JWT,
+        application::server::{
+            middleware::resolve_context::resolve_context, middleware::response_map::error,
+        },
+        authentication::jwt::build_token,
+        domain::ports::secondary::MockSubscriptionStorage,
+    };
+
+    use super::*;
+    use crate::application::server::TestStateBuilder;
+
+    /// This is a helper function to build an App with axum.
+    fn lists_route(state: AppState) -> Router {
+        Router::new()
+            .route("/api/lists", post(create_list).get(list_lists))
+            .layer(map_response(error))
+            .layer(from_fn_with_state(state.clone(), resolve_context))
+            .layer(CookieManagerLayer::new())
+            .with_state(state)
+    }
+
+    #[tokio::test]
+    async fn create_should_refuse_a_slug_already_used() {
+        let user_id = Uuid::new_v4();
+        let mut subscription_mock = MockSubscriptionStorage::new();
+        subscription_mock
+            .expect_create_list()
+            .withf(|list: &MailingList| list.slug == "rust")
+            .return_once(|_| Ok(false));
+        let state = TestStateBuilder::new()
+            .user(user_id)
+            .subscription(subscription_mock)
+            .build();
+
+        let token = build_token(user_id, &state.secret);
+        let request = Request::builder()
+            .uri("/api/lists")
+            .method("POST")
+            .header(header::COOKIE, format!("{}={}", JWT, token))
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(
+                serde_json::json!({ "slug": "rust", "name": "Rust" }).to_string(),
+            ))
+            .unwrap();
+        let response = lists_route(state).oneshot(request).await.expect("response");
+
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+    }
+}
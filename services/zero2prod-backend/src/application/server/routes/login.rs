@@ -3,9 +3,10 @@ use axum::response::IntoResponse;
 use secrecy::Secret;
 use serde::{Deserialize, Serialize};
 use tower_cookies::Cookies;
+use utoipa::ToSchema;
 use uuid::Uuid;
 
-use super::Error;
+use super::{Error, Problem, StatusResp};
 use crate::application::server::cookies;
 use crate::application::server::AppState;
 use crate::authentication::jwt::build_token;
@@ -18,6 +19,17 @@ use crate::domain::Credentials;
 /// - On success (valid credentials...) => { "status": "success" } + cookie
 /// - On error, an Error that will be handled by a layer on the way to the user
 /// We don't instrument the request for security purpose (including the username)
+#[utoipa::path(
+    post,
+    path = "/login",
+    tag = "authentication",
+    request_body = LoginRequest,
+    responses(
+        (status = 200, description = "Logged in, the JWT cookie is set", body = StatusResp),
+        (status = 401, description = "Invalid credentials", body = Problem, content_type = "application/problem+json"),
+        (status = 500, description = "Internal error", body = Problem, content_type = "application/problem+json"),
+    )
+)]
 #[allow(clippy::unused_async)]
 #[tracing::instrument(
     name = "User Login"
@@ -54,13 +66,11 @@ pub async fn login(
 
     cookies::set_token_cookie(&cookies, &token);
 
-    Ok::<_, Error>(Json(serde_json::json!({
-        "status": "success"
-    })))
+    Ok::<_, Error>(Json(StatusResp::success()))
 }
 
 /// This is the information sent by the user to login.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct LoginRequest {
     pub username: String,
     pub password: String,
@@ -79,15 +89,14 @@ mod tests {
     use hyper::{body::HttpBody, header::SET_COOKIE};
     use mockall::predicate::*;
     use secrecy::Secret;
-    use std::sync::Arc;
     use tower::ServiceExt;
     use tower_cookies::{Cookie, CookieManagerLayer};
 
     use crate::{
+        application::server::AppState,
         application::server::{
             middleware::resolve_context::resolve_context, middleware::response_map::error,
         },
-        application::server::{AppState, ApplicationBaseUrl},
         authentication::password::compute_password_hash,
         domain::ports::secondary::{
             MockAuthenticationStorage, MockEmailService, MockSubscriptionStorage,
@@ -95,11 +104,15 @@ mod tests {
     };
 
     use super::*;
+    use crate::application::server::TestStateBuilder;
 
     #[derive(Debug, Clone, Serialize, Deserialize)]
     pub struct FailedLoginResp {
-        pub status: String,
-        pub message: String,
+        #[serde(rename = "type")]
+        pub problem_type: String,
+        pub title: String,
+        pub status: u16,
+        pub detail: String,
         pub code: String,
     }
 
@@ -145,13 +158,11 @@ mod tests {
             .return_once(move |_| Ok(Some((id, password_hash))));
         let subscription_mock = MockSubscriptionStorage::new();
         let email_mock = MockEmailService::new();
-        let state = AppState {
-            authentication: Arc::new(authentication_mock),
-            subscription: Arc::new(subscription_mock),
-            email: Arc::new(email_mock),
-            base_url: ApplicationBaseUrl("http://127.0.0.1".to_string()),
-            secret: Secret::new("secret".to_string()),
-        };
+        let state = TestStateBuilder::new()
+            .authentication(authentication_mock)
+            .subscription(subscription_mock)
+            .email(email_mock)
+            .build();
 
         let app = login_route(state);
 
@@ -198,13 +209,11 @@ mod tests {
         let subscription_mock = MockSubscriptionStorage::new();
 
         let email_mock = MockEmailService::new();
-        let state = AppState {
-            authentication: Arc::new(authentication_mock),
-            subscription: Arc::new(subscription_mock),
-            email: Arc::new(email_mock),
-            base_url: ApplicationBaseUrl("http://127.0.0.1".to_string()),
-            secret: Secret::new("secret".to_string()),
-        };
+        let state = TestStateBuilder::new()
+            .authentication(authentication_mock)
+            .subscription(subscription_mock)
+            .email(email_mock)
+            .build();
 
         let app = login_route(state);
 
@@ -223,7 +232,7 @@ mod tests {
             data.extend(&chunk.unwrap());
         }
         let response: FailedLoginResp = serde_json::from_slice(&data).expect("json");
-        assert_eq!(response.status, "fail");
+        assert_eq!(response.status, 401);
         assert_eq!(response.code, "auth/invalid_credentials");
     }
 }
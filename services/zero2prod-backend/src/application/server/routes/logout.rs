@@ -3,10 +3,18 @@ use axum::response::IntoResponse;
 use tower_cookies::Cookies;
 use uuid::Uuid;
 
-use super::Error;
+use super::{Error, StatusResp};
 use crate::application::server::cookies;
 
 /// GET handler for user logout
+#[utoipa::path(
+    get,
+    path = "/logout",
+    tag = "authentication",
+    responses(
+        (status = 200, description = "Logged out, the JWT cookie is removed", body = StatusResp)
+    )
+)]
 #[allow(clippy::unused_async)]
 #[tracing::instrument(
     name = "User Logout"
@@ -16,9 +24,7 @@ use crate::application::server::cookies;
 )]
 pub async fn logout(cookies: Cookies) -> Result<impl IntoResponse, Error> {
     cookies::remove_token_cookie(&cookies);
-    Ok::<_, Error>(Json(serde_json::json!({
-        "status": "success"
-    })))
+    Ok::<_, Error>(Json(StatusResp::success()))
 }
 
 #[cfg(test)]
@@ -31,22 +37,21 @@ mod tests {
     };
     use hyper::header::SET_COOKIE;
     use mockall::predicate::*;
-    use secrecy::Secret;
-    use std::sync::Arc;
     use tower::ServiceExt;
     use tower_cookies::{cookie::time::Duration, Cookie, CookieManagerLayer};
 
     use crate::{
+        application::server::AppState,
         application::server::{
             middleware::resolve_context::resolve_context, middleware::response_map::error,
         },
-        application::server::{AppState, ApplicationBaseUrl},
         domain::ports::secondary::{
             MockAuthenticationStorage, MockEmailService, MockSubscriptionStorage,
         },
     };
 
     use super::*;
+    use crate::application::server::TestStateBuilder;
 
     fn logout_route(state: AppState) -> Router {
         Router::new()
@@ -72,13 +77,11 @@ mod tests {
         let authentication_mock = MockAuthenticationStorage::new();
         let subscription_mock = MockSubscriptionStorage::new();
         let email_mock = MockEmailService::new();
-        let state = AppState {
-            authentication: Arc::new(authentication_mock),
-            subscription: Arc::new(subscription_mock),
-            email: Arc::new(email_mock),
-            base_url: ApplicationBaseUrl("http://127.0.0.1".to_string()),
-            secret: Secret::new("secret".to_string()),
-        };
+        let state = TestStateBuilder::new()
+            .authentication(authentication_mock)
+            .subscription(subscription_mock)
+            .email(email_mock)
+            .build();
 
         let app = logout_route(state);
 
@@ -1,21 +1,60 @@
+pub mod archive;
 mod error;
 pub mod health;
+pub mod issues;
+pub mod lists;
 pub mod login;
 pub mod logout;
 pub mod newsletter;
+pub mod openapi;
+pub mod preferences;
 pub mod register;
 pub mod static_dir;
+mod status;
+pub mod subscriber_data;
+pub mod subscribers;
 pub mod subscription_confirmation;
 pub mod subscriptions;
+pub mod suppressions;
+pub mod tracking;
+pub mod unsubscribe;
+pub mod webhooks;
 
+use super::context::{Context, Error as ContextError};
+use super::middleware::resolve_context::Error as ContextResolutionError;
 use super::AppState;
-use axum::routing::{get, post, Router};
+use crate::authentication::jwt::{validate_subscriber_token, SubscriberScope};
+use axum::routing::{delete, get, post, Router};
+use common::err_context::ErrorContextExt;
+use uuid::Uuid;
 
-pub use self::error::Error;
+pub use self::error::{Error, ErrorCode, Problem};
+pub use self::status::StatusResp;
 use self::{
-    health::health, login::login, logout::logout, newsletter::publish_newsletter,
-    register::register, subscription_confirmation::subscriptions_confirmation,
+    archive::{get_archived_issue, list_archived_issues},
+    health::health,
+    issues::{
+        cancel_issue, create_issue, delete_issue, get_delivery_summary, get_issue, get_issue_stats,
+        list_deliveries, list_issues, preview_issue, test_issue, update_issue,
+    },
+    lists::{create_list, list_lists},
+    login::login,
+    logout::logout,
+    newsletter::{count_segment, publish_newsletter},
+    openapi::openapi,
+    preferences::{get_preferences, update_preferences},
+    register::register,
+    subscriber_data::{erase_subscriber_data, export_subscriber_data, request_subscriber_data},
+    subscribers::{
+        delete_subscriber, export_subscribers, get_subscriber, import_subscribers,
+        list_subscribers, update_subscriber,
+    },
+    subscription_confirmation::subscriptions_confirmation,
     subscriptions::subscriptions,
+    suppressions::{create_suppression, delete_suppression, list_suppressions},
+    tracking::{track_click, track_open},
+    unsubscribe::unsubscribe,
+    webhooks::email_webhook,
 };
 
 pub fn routes(state: AppState) -> Router {
@@ -29,6 +68,84 @@ pub fn routes(state: AppState) -> Router {
             "/subscription_confirmation",
             post(subscriptions_confirmation),
         )
+        .route("/subscriptions/data_request", post(request_subscriber_data))
+        .route(
+            "/subscriptions/data",
+            get(export_subscriber_data).delete(erase_subscriber_data),
+        )
+        .route(
+            "/subscriptions/unsubscribe",
+            get(unsubscribe).post(unsubscribe),
+        )
+        .route(
+            "/subscriptions/preferences",
+            get(get_preferences).put(update_preferences),
+        )
+        .route("/lists", post(create_list).get(list_lists))
         .route("/newsletter/publish", post(publish_newsletter))
+        .route("/newsletter/segment/count", post(count_segment))
+        .route("/newsletter/issues", post(create_issue).get(list_issues))
+        .route(
+            "/newsletter/issues/:id",
+            get(get_issue).put(update_issue).delete(delete_issue),
+        )
+        .route("/newsletter/issues/:id/cancel", post(cancel_issue))
+        .route("/newsletter/issues/:id/preview", get(preview_issue))
+        .route("/newsletter/issues/:id/test", post(test_issue))
+        .route("/newsletter/issues/:id/stats", get(get_issue_stats))
+        .route("/newsletter/issues/:id/deliveries", get(list_deliveries))
+        .route(
+            "/newsletter/issues/:id/deliveries/summary",
+            get(get_delivery_summary),
+        )
+        .route("/issues", get(list_archived_issues))
+        .route("/issues/:slug", get(get_archived_issue))
+        .route("/openapi.json", get(openapi))
+        .route("/subscribers", get(list_subscribers))
+        .route("/subscribers/import", post(import_subscribers))
+        .route("/subscribers/export", get(export_subscribers))
+        .route(
+            "/subscribers/:id",
+            get(get_subscriber)
+                .patch(update_subscriber)
+                .delete(delete_subscriber),
+        )
+        .route(
+            "/suppressions",
+            get(list_suppressions).post(create_suppression),
+        )
+        .route("/suppressions/:email", delete(delete_suppression))
+        .route("/tracking/open", get(track_open))
+        .route("/tracking/click", get(track_click))
+        .route("/webhooks/email", post(email_webhook))
         .with_state(state)
 }
+
+/// Returns the id of the authenticated user, for the handlers only users can
+/// call.
+#[allow(clippy::result_large_err)]
+pub(crate) fn authorize(context: Result<Context, ContextResolutionError>) -> Result<Uuid, Error> {
+    let context = context.context("Could not resolve context")?;
+
+    let id = context.user_id().ok_or(Error::Context {
+        context: "Missing User Id".to_string(),
+        source: ContextError::InvalidUserId {
+            context: "User Id is None".to_string(),
+        },
+    })?;
+
+    Ok(id)
+}
+
+/// Returns the id of the subscriber the token was built for, if it is valid
+/// for the scope, for the handlers reached from the links of the emails.
+#[allow(clippy::result_large_err)]
+pub(crate) fn authorize_subscriber(
+    token: &str,
+    scope: SubscriberScope,
+    state: &AppState,
+) -> Result<Uuid, Error> {
+    validate_subscriber_token(token, scope, &state.secret).map_err(|err| Error::InvalidToken {
+        context: format!("Could not validate subscriber token: {err}"),
+    })
+}
@@ -1,22 +1,48 @@
 use axum::extract::{Json, State};
 use axum::response::IntoResponse;
 use axum::Extension;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
 use tower_cookies::Cookies;
+use utoipa::ToSchema;
 use uuid::Uuid;
 
-use super::Error;
+use super::lists::resolve_lists;
+use super::tracking::{ISSUE_METADATA, METADATA_HEADER_PREFIX, SUBSCRIBER_METADATA};
+use super::{authorize, Error, Problem};
 
 use crate::application::server::middleware::resolve_context::Error as ContextResolutionError;
-use crate::application::server::{
-    context::{Context, Error as ContextError},
-    AppState,
+use crate::application::server::{context::Context, AppState};
+use crate::authentication::jwt::{
+    build_subscriber_token, build_tracking_token, build_unsubscribe_token, SubscriberScope,
 };
-use crate::domain::ports::secondary::Email;
-use crate::domain::BodyData;
-use crate::domain::SubscriberEmail;
+use crate::domain::ports::secondary::{Email, EmailError, EmailHeader};
+use crate::domain::{
+    archive, ArchivedIssue, BodyData, ConfirmedSubscriber, Content, Delivery, DeliveryFrequency,
+    DeliveryStatus, Digest, FieldError, Issue, Segment, SubscriberEmail, TrackingEvent,
+    TrackingEventKind,
+};
+use crate::domain::{DigestContext, DigestIssue, EmailTemplate, NewsletterContext};
+use crate::utils::links::rewrite_links;
+use crate::utils::placeholders::{Placeholder, PlaceholderName, Placeholders};
 use common::err_context::ErrorContextExt;
 
 /// POST handler for newsletter publishing
+#[utoipa::path(
+    post,
+    path = "/newsletter/publish",
+    tag = "newsletter",
+    request_body = BodyData,
+    security(("jwt" = [])),
+    responses(
+        (status = 200, description = "Newsletter sent to the subscribers of the segment confirmed on the lists"),
+        (status = 400, description = "Invalid segment, or placeholders", body = Problem, content_type = "application/problem+json"),
+        (status = 401, description = "Missing or invalid JWT cookie", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Unknown list", body = Problem, content_type = "application/problem+json"),
+        (status = 422, description = "The sanitizer removed too much of the content, the segment has no subscriber, or a placeholder has no value for some subscribers", body = Problem, content_type = "application/problem+json"),
+        (status = 500, description = "Internal error", body = Problem, content_type = "application/problem+json"),
+    )
+)]
 #[allow(clippy::unused_async)]
 #[tracing::instrument(
     name = "Publishing a newsletter"
@@ -35,48 +61,569 @@ pub async fn publish_newsletter(
 ) -> Result<impl IntoResponse, Error> {
     println!("publish newsletter");
     println!("context: {:?}", context);
-    let context = context.context("Could not resolve context")?;
-
-    let id = context.user_id().ok_or(Error::Context {
-        context: "Missing User Id".to_string(),
-        source: ContextError::InvalidUserId {
-            context: "User Id is None".to_string(),
-        },
-    })?;
+    let id = authorize(context)?;
 
     tracing::Span::current().record("userid", &tracing::field::display(id));
 
+    request.segment.validate().context("Invalid segment")?;
+    let lists = resolve_lists(&state, &request.lists).await?;
+    let newsletter = Newsletter::prepare(&state, &request.title, &request.content, request.force)?;
+    send_newsletter(&state, &newsletter, id, &lists, &request.segment).await?;
+
+    Ok::<axum::Json<()>, Error>(Json(()))
+}
+
+/// POST handler for counting the subscribers a newsletter would be sent to,
+/// without sending anything.
+#[utoipa::path(
+    post,
+    path = "/newsletter/segment/count",
+    tag = "newsletter",
+    request_body = SegmentCountRequest,
+    security(("jwt" = [])),
+    responses(
+        (status = 200, description = "Number of subscribers of the segment confirmed on the lists", body = SegmentCountResp),
+        (status = 400, description = "Invalid segment", body = Problem, content_type = "application/problem+json"),
+        (status = 401, description = "Missing or invalid JWT cookie", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Unknown list", body = Problem, content_type = "application/problem+json"),
+        (status = 500, description = "Internal error", body = Problem, content_type = "application/problem+json"),
+    )
+)]
+#[allow(clippy::unused_async)]
+#[tracing::instrument(
+    name = "Counting the subscribers of a segment"
+    skip(state, context),
+    fields(
+        request_id = %Uuid::new_v4(),
+    )
+)]
+pub async fn count_segment(
+    Extension(context): Extension<Result<Context, ContextResolutionError>>,
+    State(state): State<AppState>,
+    Json(request): Json<SegmentCountRequest>,
+) -> Result<impl IntoResponse, Error> {
+    authorize(context)?;
+
+    request.segment.validate().context("Invalid segment")?;
+    let lists = resolve_lists(&state, &request.lists).await?;
+    let count = state
+        .subscription
+        .count_confirmed_subscribers(&lists, &request.segment)
+        .await
+        .context("Could not count confirmed subscribers")?;
+
+    Ok::<_, Error>(Json(SegmentCountResp { count }))
+}
+
+/// The lists and the segment of a newsletter, whose subscribers are counted.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct SegmentCountRequest {
+    /// Ids or slugs of the lists, the default list if there are none.
+    #[serde(default)]
+    pub lists: Vec<String>,
+    #[serde(default)]
+    pub segment: Segment,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SegmentCountResp {
+    pub count: i64,
+}
+
+/// A newsletter whose content is rendered and sanitized, ready to be sent to
+/// each subscriber, and to be archived under its slug. Its placeholders are
+/// filled with the values of each recipient.
+pub(crate) struct Newsletter {
+    id: Uuid,
+    slug: String,
+    title: String,
+    html_content: String,
+    text_content: String,
+    placeholders: Placeholders,
+    /// The issue the newsletter was prepared from, whose deliveries are
+    /// recorded.
+    issue_id: Option<Uuid>,
+    /// The issue whose opens and clicks are tracked, if it asks for it.
+    tracking: Option<Uuid>,
+}
+
+impl Newsletter {
+    #[allow(clippy::result_large_err)]
+    pub(crate) fn prepare(
+        state: &AppState,
+        title: &str,
+        content: &Content,
+        force: bool,
+    ) -> Result<Newsletter, Error> {
+        let mut placeholders = Placeholders::default();
+        let title = placeholders
+            .protect(title)
+            .map_err(|err| vec![FieldError::new("title", err)])
+            .context("Invalid placeholders")?;
+        let content = match content {
+            Content::Html { html, text } => placeholders.protect(html).and_then(|html| {
+                Ok(Content::Html {
+                    html,
+                    text: placeholders.protect(text)?,
+                })
+            }),
+            Content::Markdown { markdown } => placeholders
+                .protect(markdown)
+                .map(|markdown| Content::Markdown { markdown }),
+        }
+        .map_err(|err| vec![FieldError::new("content", err)])
+        .context("Invalid placeholders")?;
+        let (html_content, text_content) = content.render();
+        let html_content = state
+            .sanitizer
+            .sanitize(&html_content, force)
+            .context("Could not sanitize newsletter content")?;
+        let id = Uuid::new_v4();
+        let mut newsletter = Newsletter {
+            id,
+            slug: String::new(),
+            title,
+            html_content,
+            text_content,
+            placeholders,
+            issue_id: None,
+            tracking: None,
+        };
+        newsletter.slug = archive::slug(&newsletter.archived_title(state), &id);
+        Ok(newsletter)
+    }
+
+    /// The newsletter of the issue, whose deliveries are recorded, with the
+    /// opens and clicks of its recipients tracked if the issue asks for it.
+    pub(crate) fn for_issue(self, issue: &Issue) -> Newsletter {
+        Newsletter {
+            issue_id: Some(issue.id),
+            tracking: issue.tracking.then_some(issue.id),
+            ..self
+        }
+    }
+
+    /// Checks that every recipient has a value for each placeholder without
+    /// fallback, so that none is mailed as is.
+    #[allow(clippy::result_large_err)]
+    fn check(&self, subscribers: &[ConfirmedSubscriber]) -> Result<(), Error> {
+        let unresolved = self
+            .placeholders
+            .iter()
+            .filter(|placeholder| placeholder.fallback.is_none())
+            .filter_map(|placeholder| {
+                let count = subscribers
+                    .iter()
+                    .filter(|subscriber| personal_value(placeholder, subscriber).is_none())
+                    .count();
+                (count > 0).then(|| format!("{placeholder} for {count} subscribers"))
+            })
+            .collect::<Vec<_>>();
+        if unresolved.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::UnresolvedPlaceholder {
+                context: format!("No value for {}", unresolved.join(", ")),
+            })
+        }
+    }
+
+    /// Renders the email sent to the subscriber, tracked if the newsletter is,
+    /// and the list of the subscriber allows it.
+    #[allow(clippy::result_large_err)]
+    pub(crate) fn render(
+        &self,
+        state: &AppState,
+        subscriber: &ConfirmedSubscriber,
+        tracked_list: bool,
+    ) -> Result<Email, Error> {
+        let unsubscribe_link = unsubscribe_link(state, subscriber);
+        let preferences_link = preferences_link(state, &subscriber.id);
+        let values = self
+            .placeholders
+            .iter()
+            .map(|placeholder| match placeholder.name {
+                PlaceholderName::UnsubscribeUrl => unsubscribe_link.clone(),
+                PlaceholderName::PreferencesUrl => preferences_link.clone(),
+                _ => personal_value(placeholder, subscriber)
+                    .or_else(|| placeholder.fallback.clone())
+                    .unwrap_or_default(),
+            })
+            .collect::<Vec<_>>();
+        let tracking = self
+            .tracking
+            .filter(|_| tracked_list)
+            .map(|issue_id| (issue_id, subscriber.id));
+        self.render_with(
+            state,
+            &subscriber.email,
+            &values,
+            unsubscribe_link,
+            preferences_link,
+            tracking,
+        )
+    }
+
+    /// Renders the email sent to an administrator, to preview the newsletter.
+    /// Placeholders are filled with their fallback, or left as written.
+    #[allow(clippy::result_large_err)]
+    pub(crate) fn render_preview(
+        &self,
+        state: &AppState,
+        to: &SubscriberEmail,
+    ) -> Result<Email, Error> {
+        let values = self
+            .placeholders
+            .iter()
+            .map(|placeholder| {
+                placeholder
+                    .fallback
+                    .clone()
+                    .unwrap_or_else(|| placeholder.to_string())
+            })
+            .collect::<Vec<_>>();
+        let token = build_unsubscribe_token(Uuid::nil(), Uuid::nil(), &state.secret);
+        self.render_with(
+            state,
+            to,
+            &values,
+            format!(
+                "{}/api/v1/subscriptions/unsubscribe?token={}",
+                state.base_url, token
+            ),
+            preferences_link(state, &Uuid::nil()),
+            None,
+        )
+    }
+
+    /// Renders the email. With the ids of a tracked issue and of its
+    /// recipient, links to other sites lead through the click tracking
+    /// endpoint, a tracking pixel is added, and the ids are added to the
+    /// metadata reported with bounces.
+    #[allow(clippy::result_large_err)]
+    fn render_with(
+        &self,
+        state: &AppState,
+        to: &SubscriberEmail,
+        values: &[String],
+        unsubscribe_link: String,
+        preferences_link: String,
+        tracking: Option<(Uuid, Uuid)>,
+    ) -> Result<Email, Error> {
+        let mut html_content = self.placeholders.fill(&self.html_content, values, true);
+        if let Some((issue_id, subscriber_id)) = tracking {
+            html_content = rewrite_links(&html_content, |url| {
+                let external = (url.starts_with("http://") || url.starts_with("https://"))
+                    && !url.starts_with(&state.base_url.0);
+                external.then(|| click_link(state, issue_id, subscriber_id, url))
+            });
+            html_content.push_str(&format!(
+                r#"<img src="{}" width="1" height="1" alt="">"#,
+                open_pixel_link(state, issue_id, subscriber_id)
+            ));
+        }
+        let template = EmailTemplate::Newsletter(NewsletterContext {
+            title: self.placeholders.fill(&self.title, values, false),
+            html_content,
+            text_content: self.placeholders.fill(&self.text_content, values, false),
+            unsubscribe_link,
+            preferences_link,
+            web_link: format!("{}/issues/{}", state.base_url, self.slug),
+        });
+        let mut email = state
+            .templates
+            .render(to, &template)
+            .context("Could not render newsletter email")?;
+        if let Some((issue_id, subscriber_id)) = tracking {
+            for (key, id) in [
+                (ISSUE_METADATA, issue_id),
+                (SUBSCRIBER_METADATA, subscriber_id),
+            ] {
+                email.headers.push(EmailHeader {
+                    name: format!("{METADATA_HEADER_PREFIX}{key}"),
+                    value: id.to_string(),
+                });
+            }
+        }
+        Ok(email)
+    }
+
+    /// The values of the public, archived copy, which has no recipient:
+    /// personal placeholders get their fallback, links lead to the site.
+    fn archived_values(&self, state: &AppState) -> Vec<String> {
+        self.placeholders
+            .iter()
+            .map(|placeholder| match placeholder.name {
+                PlaceholderName::UnsubscribeUrl | PlaceholderName::PreferencesUrl => {
+                    state.base_url.to_string()
+                }
+                _ => placeholder.fallback.clone().unwrap_or_default(),
+            })
+            .collect()
+    }
+
+    fn archived_title(&self, state: &AppState) -> String {
+        self.placeholders
+            .fill(&self.title, &self.archived_values(state), false)
+    }
+
+    fn archived_issue(&self, state: &AppState, author_id: Uuid) -> ArchivedIssue {
+        let values = self.archived_values(state);
+        ArchivedIssue {
+            id: self.id,
+            slug: self.slug.clone(),
+            title: self.placeholders.fill(&self.title, &values, false),
+            html_content: self.placeholders.fill(&self.html_content, &values, true),
+            text_content: self.placeholders.fill(&self.text_content, &values, false),
+            author_id,
+            published_at: Utc::now(),
+        }
+    }
+}
+
+/// The value of a placeholder taken from the subscriber, if it has one.
+/// Attributes which are not strings, numbers, or booleans have no value.
+fn personal_value(placeholder: &Placeholder, subscriber: &ConfirmedSubscriber) -> Option<String> {
+    match &placeholder.name {
+        PlaceholderName::Username => Some(subscriber.username.as_ref().to_string()),
+        PlaceholderName::Email => Some(subscriber.email.as_ref().to_string()),
+        PlaceholderName::UnsubscribeUrl | PlaceholderName::PreferencesUrl => Some(String::new()),
+        PlaceholderName::Attribute(key) => match subscriber.attributes.get(key)? {
+            serde_json::Value::String(value) => Some(value.clone()),
+            value @ (serde_json::Value::Number(_) | serde_json::Value::Bool(_)) => {
+                Some(value.to_string())
+            }
+            _ => None,
+        },
+    }
+}
+
+/// Archives the newsletter, so that the link to its archived copy works, and
+/// sends it, once, to every subscriber of the segment confirmed on any of the
+/// lists. Subscribers who chose a weekly digest get it in their next digest.
+/// If the segment selects no subscriber, or a placeholder has no value for
+/// some subscribers, nothing is archived, nor sent.
+pub(crate) async fn send_newsletter(
+    state: &AppState,
+    newsletter: &Newsletter,
+    author_id: Uuid,
+    lists: &[Uuid],
+    segment: &Segment,
+) -> Result<(), Error> {
     let subscribers = state
         .subscription
-        .get_confirmed_subscribers_email()
+        .get_confirmed_subscribers_email(lists, segment)
         .await
         .context("Could not retrieve list of confirmed subscribers")?;
 
-    for subscriber in subscribers {
-        let email = create_newsletter_email(&subscriber.email, &request);
+    if subscribers.is_empty() && !segment.is_empty() {
+        return Err(Error::EmptySegment {
+            context: "No confirmed subscriber belongs to the segment".to_string(),
+        });
+    }
+
+    let (weekly, immediate): (Vec<_>, Vec<_>) = subscribers
+        .into_iter()
+        .partition(|subscriber| subscriber.frequency == DeliveryFrequency::Weekly);
+
+    newsletter.check(&immediate)?;
+
+    state
+        .issues
+        .archive_issue(&newsletter.archived_issue(state, author_id))
+        .await
+        .context("Could not archive newsletter")?;
+
+    if !weekly.is_empty() {
+        let ids = weekly
+            .iter()
+            .map(|subscriber| subscriber.id)
+            .collect::<Vec<_>>();
         state
-            .email
-            .send_email(email)
+            .subscription
+            .queue_digest(&newsletter.id, &ids)
             .await
-            .context("Cannot send newsletter email")?;
+            .context("Could not queue newsletter for digests")?;
+    }
+
+    let tracked_lists = match newsletter.tracking {
+        Some(_) => state
+            .subscription
+            .get_lists()
+            .await
+            .context("Could not get lists")?
+            .into_iter()
+            .filter(|list| list.tracking)
+            .map(|list| list.id)
+            .collect(),
+        None => Vec::new(),
+    };
+    let mut emails = Vec::with_capacity(immediate.len());
+    let mut tracked = Vec::with_capacity(immediate.len());
+    for subscriber in &immediate {
+        let tracked_list = tracked_lists.contains(&subscriber.list_id);
+        emails.push(newsletter.render(state, subscriber, tracked_list)?);
+        tracked.push(tracked_list);
+    }
+    let total = emails.len();
+    let delivery = |subscriber: &ConfirmedSubscriber, status| {
+        newsletter.issue_id.map(|issue_id| {
+            Delivery::new(
+                issue_id,
+                subscriber.id,
+                subscriber.email.as_ref().to_string(),
+                status,
+            )
+        })
+    };
+    let queued = immediate
+        .iter()
+        .filter_map(|subscriber| delivery(subscriber, DeliveryStatus::Queued))
+        .collect::<Vec<_>>();
+    record_deliveries(state, &queued).await;
+    let outcomes = state.email.send_batch(emails).await;
+
+    let mut failures = Vec::new();
+    let mut events = Vec::new();
+    let mut deliveries = Vec::new();
+    for ((outcome, subscriber), tracked) in outcomes.into_iter().zip(&immediate).zip(tracked) {
+        match outcome {
+            Ok(message_id) => {
+                if let Some(issue_id) = newsletter.tracking.filter(|_| tracked) {
+                    events.push(TrackingEvent::new(
+                        issue_id,
+                        subscriber.id,
+                        TrackingEventKind::Sent,
+                    ));
+                }
+                deliveries.extend(delivery(subscriber, DeliveryStatus::Sent).map(|delivery| {
+                    Delivery {
+                        message_id,
+                        ..delivery
+                    }
+                }));
+            }
+            Err(err) => {
+                deliveries.extend(
+                    delivery(subscriber, DeliveryStatus::Failed).map(|delivery| Delivery {
+                        last_error: Some(err.to_string()),
+                        ..delivery
+                    }),
+                );
+                match err {
+                    // Suppressed since the subscribers were selected: not a failure.
+                    EmailError::Suppressed { context } => {
+                        tracing::info!("Newsletter not sent to {}: {context}", subscriber.id);
+                    }
+                    err => {
+                        tracing::warn!("Could not send newsletter to {}: {err}", subscriber.id);
+                        failures.push(err);
+                    }
+                }
+            }
+        }
+    }
+    // The newsletter is sent: the stats are only missing recipients.
+    if !events.is_empty() {
+        if let Err(err) = state.issues.record_tracking_events(&events).await {
+            tracing::error!("Could not record the recipients of the newsletter: {err}");
+        }
+    }
+    record_deliveries(state, &deliveries).await;
+
+    if failures.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::Email {
+            context: format!(
+                "Cannot send newsletter email to {} of {total} subscribers",
+                failures.len()
+            ),
+            source: failures.swap_remove(0),
+        })
     }
-    Ok::<axum::Json<()>, Error>(Json(()))
 }
 
-/// This is a helper function to create an email sent to the subscriber,
-/// which contains a link he needs to use to confirm his subscription.
-/// the url argument is the URL of the zero2prod server, and will be used
-/// as the base for the confirmation link.
-fn create_newsletter_email(to: &SubscriberEmail, newsletter: &BodyData) -> Email {
-    let BodyData { title, content } = newsletter.clone();
-    Email {
-        to: to.clone(),
-        subject: title.clone(),
-        html_content: content.html.clone(),
-        text_content: content.text.clone(),
+/// Records the deliveries of an issue. A failure is only traced, as the
+/// emails are, or are about to be, sent anyway.
+async fn record_deliveries(state: &AppState, deliveries: &[Delivery]) {
+    if deliveries.is_empty() {
+        return;
+    }
+    if let Err(err) = state.issues.record_deliveries(deliveries).await {
+        tracing::error!(
+            "Could not record {} deliveries of the newsletter: {err}",
+            deliveries.len()
+        );
     }
 }
 
+/// This is a helper function to create the link, found in the footer of each
+/// newsletter, with which the subscriber can unsubscribe from the list the
+/// newsletter was sent through.
+fn unsubscribe_link(state: &AppState, subscriber: &ConfirmedSubscriber) -> String {
+    let token = build_unsubscribe_token(subscriber.id, subscriber.list_id, &state.secret);
+    format!(
+        "{}/api/v1/subscriptions/unsubscribe?token={}",
+        state.base_url, token
+    )
+}
+
+/// This is a helper function to create the link of the tracking pixel of a
+/// tracked issue.
+fn open_pixel_link(state: &AppState, issue_id: Uuid, subscriber_id: Uuid) -> String {
+    let token = build_tracking_token(subscriber_id, issue_id, None, &state.secret);
+    format!("{}/api/v1/tracking/open?token={}", state.base_url, token)
+}
+
+/// This is a helper function to create the link, leading to url, which tracks
+/// the clicks of a recipient of a tracked issue.
+fn click_link(state: &AppState, issue_id: Uuid, subscriber_id: Uuid, url: &str) -> String {
+    let token = build_tracking_token(subscriber_id, issue_id, Some(url), &state.secret);
+    format!("{}/api/v1/tracking/click?token={}", state.base_url, token)
+}
+
+/// Renders the weekly digest of a subscriber, with links to the archived
+/// copies of the queued newsletters. Its unsubscribe link removes the
+/// subscription from every list.
+#[allow(clippy::result_large_err)]
+pub(crate) fn render_digest(state: &AppState, digest: &Digest) -> Result<Email, Error> {
+    let token = build_subscriber_token(
+        digest.subscriber_id,
+        SubscriberScope::Unsubscribe,
+        &state.secret,
+    );
+    let template = EmailTemplate::Digest(DigestContext {
+        title: "Your weekly digest".to_string(),
+        issues: digest
+            .issues
+            .iter()
+            .map(|issue| DigestIssue {
+                title: issue.title.clone(),
+                link: format!("{}/issues/{}", state.base_url, issue.slug),
+            })
+            .collect(),
+        unsubscribe_link: format!(
+            "{}/api/v1/subscriptions/unsubscribe?token={}",
+            state.base_url, token
+        ),
+        preferences_link: preferences_link(state, &digest.subscriber_id),
+        web_link: format!("{}/issues", state.base_url),
+    });
+    let email = state
+        .templates
+        .render(&digest.email, &template)
+        .context("Could not render digest email")?;
+    Ok(email)
+}
+
+/// This is a helper function to create the link, found in the footer of each
+/// newsletter, to the page where the subscriber chooses topics and frequency.
+pub(crate) fn preferences_link(state: &AppState, id: &Uuid) -> String {
+    let token = build_subscriber_token(*id, SubscriberScope::Preferences, &state.secret);
+    format!("{}/preferences?token={}", state.base_url, token)
+}
+
 #[cfg(test)]
 mod tests {
     use axum::{
@@ -90,34 +637,58 @@ mod tests {
     use mockall::predicate::*;
     use secrecy::Secret;
     use speculoos::prelude::*;
-    use std::sync::Arc;
     use tower::ServiceExt;
     use tower_cookies::CookieManagerLayer;
 
     use crate::{
-        application::server::{cookies::JWT, AppState, ApplicationBaseUrl},
+        application::server::{cookies::JWT, routes::ErrorCode, AppState},
         application::server::{
             middleware::resolve_context::resolve_context, middleware::response_map::error,
         },
         authentication::jwt::build_token,
+        domain::ports::secondary::Email,
         domain::ports::secondary::MockAuthenticationStorage,
         domain::ports::secondary::MockEmailService,
         domain::ports::secondary::MockSubscriptionStorage,
-        domain::{ConfirmedSubscriber, Content, SubscriberEmail},
+        domain::ports::secondary::{MockHtmlSanitizer, MockIssueStorage, SanitizerError},
+        domain::{
+            ConfirmedSubscriber, Content, MailingList, SubscriberEmail, SubscriberName,
+            DEFAULT_LIST,
+        },
+        services::templates::repository_templates,
     };
 
     use super::*;
+    use crate::application::server::TestStateBuilder;
 
     /// This is a helper function to build an App with axum.
     fn newsletter_route(state: AppState) -> Router {
         Router::new()
             .route("/api/newsletter", post(publish_newsletter))
+            .route("/api/newsletter/segment/count", post(count_segment))
             .layer(map_response(error))
             .layer(from_fn_with_state(state.clone(), resolve_context))
             .layer(CookieManagerLayer::new())
             .with_state(state)
     }
 
+    /// This is a helper function to make the storage find the default list,
+    /// identified by list_id.
+    fn expect_default_list(subscription_mock: &mut MockSubscriptionStorage, list_id: Uuid) {
+        subscription_mock
+            .expect_get_list()
+            .with(eq(DEFAULT_LIST))
+            .returning(move |_| {
+                Ok(Some(MailingList {
+                    id: list_id,
+                    slug: DEFAULT_LIST.to_string(),
+                    name: "Newsletter".to_string(),
+                    tracking: true,
+                    created_at: chrono::Utc::now(),
+                }))
+            });
+    }
+
     /// This is a helper function to build the content of the request
     /// to our subscription endpoint. Essentially, it wraps the content
     /// of the subscription request into a html request with the proper header.
@@ -149,22 +720,15 @@ mod tests {
         // We create a fake user id, and make sure that the authentication mock believes it exists
         // in storage
         let user_id = Uuid::new_v4(); // This is the id of a user
-        let mut authentication_mock = MockAuthenticationStorage::new();
-        authentication_mock
-            .expect_id_exists()
-            .withf(move |id: &Uuid| id == &user_id)
-            .return_const(Ok(true));
-
         let subscription_mock = MockSubscriptionStorage::new();
         let email_mock = MockEmailService::new();
 
-        let state = AppState {
-            authentication: Arc::new(authentication_mock),
-            subscription: Arc::new(subscription_mock),
-            email: Arc::new(email_mock),
-            base_url: ApplicationBaseUrl("http://127.0.0.1".to_string()),
-            secret: Secret::new("secret".to_string()),
-        };
+        let state = TestStateBuilder::new()
+            .user(user_id)
+            .subscription(subscription_mock)
+            .email(email_mock)
+            .templates(repository_templates())
+            .build();
 
         // A list of <json = test content, string = test title>
         let test_cases = vec![
@@ -213,58 +777,86 @@ mod tests {
         // email address is returned.
         let email_addr = SafeEmail().fake::<String>();
 
+        let list_id = Uuid::new_v4();
         let confirmed_subscriber = ConfirmedSubscriber {
+            id: Uuid::new_v4(),
             email: SubscriberEmail::try_from(email_addr.clone()).unwrap(),
+            username: SubscriberName::parse("alice".to_string()).unwrap(),
+            list_id,
+            frequency: DeliveryFrequency::Immediate,
+            attributes: serde_json::json!({}),
         };
 
         let mut email_mock = MockEmailService::new();
         email_mock
-            .expect_send_email()
-            .withf(move |email: &Email| {
+            .expect_send_batch()
+            .withf(move |emails: &Vec<Email>| {
+                let [email] = emails.as_slice() else {
+                    return false;
+                };
                 let Email {
                     to,
                     subject: _,
-                    html_content: _,
+                    html_content,
                     text_content: _,
+                    ..
                 } = email;
 
                 if *to != SubscriberEmail::parse(email_addr.clone()).unwrap() {
                     return false;
                 }
-                true
+                // The email links to its archived copy.
+                html_content.contains("http://127.0.0.1/issues/newsletter-")
             })
-            .return_once(|_| Ok(()));
+            .return_once(|emails| emails.iter().map(|_| Ok(None)).collect());
 
         // We also need a storage mock that returns a list of confirmed subscribers
         let mut subscription_mock = MockSubscriptionStorage::new();
+        expect_default_list(&mut subscription_mock, list_id);
 
         subscription_mock
             .expect_get_confirmed_subscribers_email()
-            .return_once(move || Ok(vec![confirmed_subscriber]));
+            .withf(move |lists: &[Uuid], segment: &Segment| {
+                lists == [list_id] && segment.is_empty()
+            })
+            .return_once(move |_, _| Ok(vec![confirmed_subscriber]));
 
         let user_id = Uuid::new_v4();
-        let mut authentication_mock = MockAuthenticationStorage::new();
-        authentication_mock
-            .expect_id_exists()
-            .withf(move |id: &Uuid| id == &user_id)
-            .return_const(Ok(true));
-
-        let state = AppState {
-            authentication: Arc::new(authentication_mock),
-            subscription: Arc::new(subscription_mock),
-            email: Arc::new(email_mock),
-            base_url: ApplicationBaseUrl("http://127.0.0.1".to_string()),
-            secret: Secret::new("secret".to_string()),
-        };
+        let mut issues_mock = MockIssueStorage::new();
+        issues_mock
+            .expect_archive_issue()
+            .withf(move |issue: &ArchivedIssue| {
+                issue.author_id == user_id && issue.slug.starts_with("newsletter-")
+            })
+            .times(1)
+            .return_once(|_| Ok(()));
+
+        let mut sanitizer_mock = MockHtmlSanitizer::new();
+        sanitizer_mock
+            .expect_sanitize()
+            .withf(|_, force| !force)
+            .return_once(|html, _| Ok(html.to_string()));
+
+        let state = TestStateBuilder::new()
+            .user(user_id)
+            .subscription(subscription_mock)
+            .issues(issues_mock)
+            .email(email_mock)
+            .templates(repository_templates())
+            .sanitizer(sanitizer_mock)
+            .build();
 
         let app = newsletter_route(state.clone());
 
         let body = BodyData {
             title: "Newsletter".to_string(),
-            content: Content {
+            content: Content::Html {
                 html: "<p>Newsletter Content</p>".to_string(),
                 text: "Newsletter Content".to_string(),
             },
+            force: false,
+            lists: vec![],
+            segment: Segment::default(),
         };
         let response = app
             .oneshot(send_newsletter_request_from_json(
@@ -288,33 +880,35 @@ mod tests {
 
         let mut email_mock = MockEmailService::new();
         email_mock
-            .expect_send_email()
+            .expect_send_batch()
             .never()
-            .return_once(|_| Ok(()));
+            .return_once(|_| vec![]);
         let authentication_mock = MockAuthenticationStorage::new();
         let mut subscription_mock = MockSubscriptionStorage::new();
 
         subscription_mock
             .expect_get_confirmed_subscribers_email()
             .never()
-            .return_once(|| Ok(vec![]));
-
-        let state = AppState {
-            authentication: Arc::new(authentication_mock),
-            subscription: Arc::new(subscription_mock),
-            email: Arc::new(email_mock),
-            base_url: ApplicationBaseUrl("http://127.0.0.1".to_string()),
-            secret: Secret::new("secret".to_string()),
-        };
+            .return_once(|_, _| Ok(vec![]));
+
+        let state = TestStateBuilder::new()
+            .authentication(authentication_mock)
+            .subscription(subscription_mock)
+            .email(email_mock)
+            .templates(repository_templates())
+            .build();
 
         let app = newsletter_route(state.clone());
 
         let body = BodyData {
             title: "Newsletter".to_string(),
-            content: Content {
+            content: Content::Html {
                 html: "<p>Newsletter Content</p>".to_string(),
                 text: "Newsletter Content".to_string(),
             },
+            force: false,
+            lists: vec![],
+            segment: Segment::default(),
         };
         let response = app
             .oneshot(send_newsletter_request_from_json(
@@ -346,32 +940,34 @@ mod tests {
 
         let mut email_mock = MockEmailService::new();
         email_mock
-            .expect_send_email()
+            .expect_send_batch()
             .never()
-            .return_once(|_| Ok(()));
+            .return_once(|_| vec![]);
 
         let mut subscription_mock = MockSubscriptionStorage::new();
         subscription_mock
             .expect_get_confirmed_subscribers_email()
             .never()
-            .return_once(|| Ok(vec![]));
-
-        let state = AppState {
-            authentication: Arc::new(authentication_mock),
-            subscription: Arc::new(subscription_mock),
-            email: Arc::new(email_mock),
-            base_url: ApplicationBaseUrl("http://127.0.0.1".to_string()),
-            secret: Secret::new("secret".to_string()),
-        };
+            .return_once(|_, _| Ok(vec![]));
+
+        let state = TestStateBuilder::new()
+            .authentication(authentication_mock)
+            .subscription(subscription_mock)
+            .email(email_mock)
+            .templates(repository_templates())
+            .build();
 
         let app = newsletter_route(state.clone());
 
         let body = BodyData {
             title: "Newsletter".to_string(),
-            content: Content {
+            content: Content::Html {
                 html: "<p>Newsletter Content</p>".to_string(),
                 text: "Newsletter Content".to_string(),
             },
+            force: false,
+            lists: vec![],
+            segment: Segment::default(),
         };
         let response = app
             .oneshot(send_newsletter_request_from_json(
@@ -386,4 +982,381 @@ mod tests {
         // Check the response status code.
         assert_that(&response.status()).is_equal_to(StatusCode::UNAUTHORIZED);
     }
+
+    #[tokio::test]
+    async fn newsletter_should_be_refused_when_the_sanitizer_rejects_it() {
+        // In this test, we make sure that nothing is sent when the sanitizer
+        // removed too much of the content.
+
+        let mut email_mock = MockEmailService::new();
+        email_mock.expect_send_batch().never();
+
+        let mut subscription_mock = MockSubscriptionStorage::new();
+        expect_default_list(&mut subscription_mock, Uuid::new_v4());
+        subscription_mock
+            .expect_get_confirmed_subscribers_email()
+            .never();
+
+        let user_id = Uuid::new_v4();
+        let mut sanitizer_mock = MockHtmlSanitizer::new();
+        sanitizer_mock.expect_sanitize().return_once(|_, _| {
+            Err(SanitizerError::Rejected {
+                context: "Too much removed".to_string(),
+                removed: 0.5,
+            })
+        });
+
+        let state = TestStateBuilder::new()
+            .user(user_id)
+            .subscription(subscription_mock)
+            .email(email_mock)
+            .templates(repository_templates())
+            .sanitizer(sanitizer_mock)
+            .build();
+
+        let app = newsletter_route(state.clone());
+
+        let body = serde_json::json!({
+            "title": "Newsletter",
+            "content": {
+                "html": "<script>alert('hello')</script>",
+                "text": "Newsletter Content",
+            }
+        });
+        let response = app
+            .oneshot(send_newsletter_request_from_json(
+                "/api/newsletter",
+                body,
+                Some(user_id),
+                &state.secret,
+            ))
+            .await
+            .expect("response");
+
+        assert_that(&response.status()).is_equal_to(StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    #[tokio::test]
+    async fn newsletter_should_be_queued_for_weekly_subscribers() {
+        // In this test, we make sure that subscribers who chose a weekly
+        // digest are not sent the newsletter, which is queued for them.
+        let list_id = Uuid::new_v4();
+        let weekly_id = Uuid::new_v4();
+        let subscribers = vec![
+            ConfirmedSubscriber {
+                id: Uuid::new_v4(),
+                email: SubscriberEmail::parse("alice@acme.inc").unwrap(),
+                username: SubscriberName::parse("alice".to_string()).unwrap(),
+                list_id,
+                frequency: DeliveryFrequency::Immediate,
+                attributes: serde_json::json!({}),
+            },
+            ConfirmedSubscriber {
+                id: weekly_id,
+                email: SubscriberEmail::parse("bob@acme.inc").unwrap(),
+                username: SubscriberName::parse("bob".to_string()).unwrap(),
+                list_id,
+                frequency: DeliveryFrequency::Weekly,
+                attributes: serde_json::json!({}),
+            },
+        ];
+
+        let mut email_mock = MockEmailService::new();
+        email_mock
+            .expect_send_batch()
+            .withf(|emails: &Vec<Email>| {
+                emails.len() == 1 && emails[0].to.as_ref() == "alice@acme.inc"
+            })
+            .times(1)
+            .return_once(|emails| emails.iter().map(|_| Ok(None)).collect());
+
+        let mut subscription_mock = MockSubscriptionStorage::new();
+        expect_default_list(&mut subscription_mock, list_id);
+        subscription_mock
+            .expect_get_confirmed_subscribers_email()
+            .withf(|_, segment: &Segment| segment.topics == ["rust"])
+            .return_once(move |_, _| Ok(subscribers));
+        subscription_mock
+            .expect_queue_digest()
+            .withf(move |_, ids: &[Uuid]| ids == [weekly_id])
+            .times(1)
+            .return_once(|_, _| Ok(()));
+
+        let user_id = Uuid::new_v4();
+        let mut issues_mock = MockIssueStorage::new();
+        issues_mock.expect_archive_issue().return_once(|_| Ok(()));
+
+        let mut sanitizer_mock = MockHtmlSanitizer::new();
+        sanitizer_mock
+            .expect_sanitize()
+            .return_once(|html, _| Ok(html.to_string()));
+
+        let state = TestStateBuilder::new()
+            .user(user_id)
+            .subscription(subscription_mock)
+            .issues(issues_mock)
+            .email(email_mock)
+            .templates(repository_templates())
+            .sanitizer(sanitizer_mock)
+            .build();
+
+        let body = serde_json::json!({
+            "title": "Newsletter",
+            "content": { "markdown": "Some *news*" },
+            "segment": { "topics": ["rust"] },
+        });
+        let response = newsletter_route(state.clone())
+            .oneshot(send_newsletter_request_from_json(
+                "/api/newsletter",
+                body,
+                Some(user_id),
+                &state.secret,
+            ))
+            .await
+            .expect("response");
+
+        assert_that(&response.status()).is_equal_to(StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn newsletter_should_be_refused_when_the_segment_is_empty() {
+        // In this test, we make sure that nothing is archived, nor sent, when
+        // no subscriber belongs to the segment.
+        let mut email_mock = MockEmailService::new();
+        email_mock.expect_send_batch().never();
+
+        let mut subscription_mock = MockSubscriptionStorage::new();
+        expect_default_list(&mut subscription_mock, Uuid::new_v4());
+        subscription_mock
+            .expect_get_confirmed_subscribers_email()
+            .return_once(|_, _| Ok(vec![]));
+
+        let user_id = Uuid::new_v4();
+        let mut issues_mock = MockIssueStorage::new();
+        issues_mock.expect_archive_issue().never();
+
+        let mut sanitizer_mock = MockHtmlSanitizer::new();
+        sanitizer_mock
+            .expect_sanitize()
+            .return_once(|html, _| Ok(html.to_string()));
+
+        let state = TestStateBuilder::new()
+            .user(user_id)
+            .subscription(subscription_mock)
+            .issues(issues_mock)
+            .email(email_mock)
+            .templates(repository_templates())
+            .sanitizer(sanitizer_mock)
+            .build();
+
+        let body = serde_json::json!({
+            "title": "Newsletter",
+            "content": { "markdown": "Some *news*" },
+            "segment": { "attributes": { "plan": "pro" } },
+        });
+        let response = newsletter_route(state.clone())
+            .oneshot(send_newsletter_request_from_json(
+                "/api/newsletter",
+                body,
+                Some(user_id),
+                &state.secret,
+            ))
+            .await
+            .expect("response");
+
+        assert_that(&response.status()).is_equal_to(StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    #[tokio::test]
+    async fn newsletter_should_be_personalized_for_each_subscriber() {
+        // In this test, we make sure that placeholders are filled with the
+        // values of each subscriber, escaped in HTML, and with fallbacks in
+        // the archived copy.
+        let list_id = Uuid::new_v4();
+        let subscribers = vec![ConfirmedSubscriber {
+            id: Uuid::new_v4(),
+            email: SubscriberEmail::parse("alice@acme.inc").unwrap(),
+            username: SubscriberName::parse("alice & co".to_string()).unwrap(),
+            list_id,
+            frequency: DeliveryFrequency::Immediate,
+            attributes: serde_json::json!({ "plan": "pro" }),
+        }];
+
+        let mut email_mock = MockEmailService::new();
+        email_mock
+            .expect_send_batch()
+            .withf(|emails: &Vec<Email>| {
+                let [email] = emails.as_slice() else {
+                    return false;
+                };
+                email.subject == "News for alice & co"
+                    && email
+                        .html_content
+                        .contains("Hi alice &amp; co, on the pro plan")
+                    && email
+                        .text_content
+                        .contains("Hi alice & co, on the pro plan")
+                    && email.html_content.contains(
+                        r#"href="http://127.0.0.1/api/v1/subscriptions/unsubscribe?token="#,
+                    )
+            })
+            .times(1)
+            .return_once(|emails| emails.iter().map(|_| Ok(None)).collect());
+
+        let mut subscription_mock = MockSubscriptionStorage::new();
+        expect_default_list(&mut subscription_mock, list_id);
+        subscription_mock
+            .expect_get_confirmed_subscribers_email()
+            .return_once(move |_, _| Ok(subscribers));
+
+        let user_id = Uuid::new_v4();
+        let mut issues_mock = MockIssueStorage::new();
+        issues_mock
+            .expect_archive_issue()
+            .withf(|issue: &ArchivedIssue| {
+                issue.title == "News for you"
+                    && issue.text_content.contains("Hi there,")
+                    && issue.slug.starts_with("news-for-you-")
+            })
+            .return_once(|_| Ok(()));
+
+        let mut sanitizer_mock = MockHtmlSanitizer::new();
+        sanitizer_mock
+            .expect_sanitize()
+            .return_once(|html, _| Ok(html.to_string()));
+
+        let state = TestStateBuilder::new()
+            .user(user_id)
+            .subscription(subscription_mock)
+            .issues(issues_mock)
+            .email(email_mock)
+            .templates(repository_templates())
+            .sanitizer(sanitizer_mock)
+            .build();
+
+        let body = serde_json::json!({
+            "title": "News for {{ username | you }}",
+            "content": {
+                "markdown": "Hi {{ username | there }}, on the {{ attributes.plan }} plan. \
+                    [Unsubscribe]({{ unsubscribe_url }})"
+            },
+        });
+        let response = newsletter_route(state.clone())
+            .oneshot(send_newsletter_request_from_json(
+                "/api/newsletter",
+                body,
+                Some(user_id),
+                &state.secret,
+            ))
+            .await
+            .expect("response");
+
+        assert_that(&response.status()).is_equal_to(StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn newsletter_should_be_refused_when_a_placeholder_has_no_value() {
+        // In this test, we make sure that nothing is archived, nor sent, when
+        // a subscriber has no value for a placeholder without fallback.
+        let list_id = Uuid::new_v4();
+        let subscribers = vec![ConfirmedSubscriber {
+            id: Uuid::new_v4(),
+            email: SubscriberEmail::parse("alice@acme.inc").unwrap(),
+            username: SubscriberName::parse("alice".to_string()).unwrap(),
+            list_id,
+            frequency: DeliveryFrequency::Immediate,
+            attributes: serde_json::json!({}),
+        }];
+
+        let mut email_mock = MockEmailService::new();
+        email_mock.expect_send_batch().never();
+
+        let mut subscription_mock = MockSubscriptionStorage::new();
+        expect_default_list(&mut subscription_mock, list_id);
+        subscription_mock
+            .expect_get_confirmed_subscribers_email()
+            .return_once(move |_, _| Ok(subscribers));
+
+        let user_id = Uuid::new_v4();
+        let mut issues_mock = MockIssueStorage::new();
+        issues_mock.expect_archive_issue().never();
+
+        let mut sanitizer_mock = MockHtmlSanitizer::new();
+        sanitizer_mock
+            .expect_sanitize()
+            .return_once(|html, _| Ok(html.to_string()));
+
+        let state = TestStateBuilder::new()
+            .user(user_id)
+            .subscription(subscription_mock)
+            .issues(issues_mock)
+            .email(email_mock)
+            .templates(repository_templates())
+            .sanitizer(sanitizer_mock)
+            .build();
+
+        let body = serde_json::json!({
+            "title": "Newsletter",
+            "content": { "markdown": "On the {{ attributes.plan }} plan" },
+        });
+        let response = newsletter_route(state.clone())
+            .oneshot(send_newsletter_request_from_json(
+                "/api/newsletter",
+                body,
+                Some(user_id),
+                &state.secret,
+            ))
+            .await
+            .expect("response");
+
+        assert_that(&response.status()).is_equal_to(StatusCode::UNPROCESSABLE_ENTITY);
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let problem: Problem = serde_json::from_slice(&body).unwrap();
+        assert_that(&problem.code).is_equal_to(ErrorCode::NewsletterUnresolvedPlaceholder);
+    }
+
+    #[tokio::test]
+    async fn segment_count_should_not_send_anything() {
+        let list_id = Uuid::new_v4();
+        let mut subscription_mock = MockSubscriptionStorage::new();
+        expect_default_list(&mut subscription_mock, list_id);
+        subscription_mock
+            .expect_count_confirmed_subscribers()
+            .withf(move |lists: &[Uuid], segment: &Segment| {
+                lists == [list_id] && segment.subscribed_after.is_some()
+            })
+            .return_once(|_, _| Ok(42));
+        subscription_mock
+            .expect_get_confirmed_subscribers_email()
+            .never();
+
+        let user_id = Uuid::new_v4();
+        let mut email_mock = MockEmailService::new();
+        email_mock.expect_send_batch().never();
+
+        let state = TestStateBuilder::new()
+            .user(user_id)
+            .subscription(subscription_mock)
+            .email(email_mock)
+            .templates(repository_templates())
+            .build();
+
+        let body = serde_json::json!({
+            "segment": { "subscribed_after": "2023-01-01T00:00:00Z" },
+        });
+        let response = newsletter_route(state.clone())
+            .oneshot(send_newsletter_request_from_json(
+                "/api/newsletter/segment/count",
+                body,
+                Some(user_id),
+                &state.secret,
+            ))
+            .await
+            .expect("response");
+
+        assert_that(&response.status()).is_equal_to(StatusCode::OK);
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let resp: SegmentCountResp = serde_json::from_slice(&body).unwrap();
+        assert_that(&resp.count).is_equal_to(42);
+    }
 }
@@ -0,0 +1,254 @@
+use axum::extract::Json;
+use axum::response::IntoResponse;
+use utoipa::openapi::security::{ApiKey, ApiKeyValue, Http, HttpAuthScheme, SecurityScheme};
+use utoipa::{Modify, OpenApi};
+
+use super::{
+    archive, health, issues, lists, login, logout, newsletter, preferences, register,
+    subscriber_data, subscribers, subscription_confirmation, subscriptions, suppressions, tracking,
+    unsubscribe, webhooks,
+};
+use super::{ErrorCode, Problem, StatusResp};
+use crate::application::server::cookies;
+use crate::application::subscribers_csv::{DuplicateLine, ImportReport, RejectedLine};
+use crate::domain::{
+    ArchivedIssue, ArchivedIssueSummary, BodyData, Content, Delivery, DeliveryFrequency,
+    DeliveryStatus, DeliverySummary, FieldError, Issue, IssueRequest, IssueStats, IssueStatus,
+    LinkClicks, ListMembership, MailingList, MailingListRequest, Segment, SubscriberEmail,
+    SubscriberName, SubscriberPreferences, Subscription, SubscriptionRequest, SubscriptionStatus,
+    SubscriptionUpdateRequest, Suppression, SuppressionReason, SuppressionRequest, TrackingEvent,
+    TrackingEventKind,
+};
+
+/// The OpenAPI document of the REST API, built from the route handlers and
+/// the types they exchange. Paths are relative to the `/api/v1` server.
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "zero2prod", description = "Newsletter subscriptions and publishing"),
+    servers((url = "/api/v1")),
+    paths(
+        health::health,
+        login::login,
+        logout::logout,
+        register::register,
+        subscriptions::subscriptions,
+        subscription_confirmation::subscriptions_confirmation,
+        subscriber_data::request_subscriber_data,
+        subscriber_data::export_subscriber_data,
+        subscriber_data::erase_subscriber_data,
+        unsubscribe::unsubscribe,
+        preferences::get_preferences,
+        preferences::update_preferences,
+        lists::list_lists,
+        lists::create_list,
+        newsletter::publish_newsletter,
+        newsletter::count_segment,
+        issues::create_issue,
+        issues::list_issues,
+        issues::get_issue,
+        issues::update_issue,
+        issues::delete_issue,
+        issues::cancel_issue,
+        issues::preview_issue,
+        issues::test_issue,
+        issues::get_issue_stats,
+        issues::list_deliveries,
+        issues::get_delivery_summary,
+        archive::list_archived_issues,
+        archive::get_archived_issue,
+        subscribers::list_subscribers,
+        subscribers::get_subscriber,
+        subscribers::update_subscriber,
+        subscribers::delete_subscriber,
+        subscribers::import_subscribers,
+        subscribers::export_subscribers,
+        suppressions::list_suppressions,
+        suppressions::create_suppression,
+        suppressions::delete_suppression,
+        tracking::track_open,
+        tracking::track_click,
+        webhooks::email_webhook,
+        openapi,
+    ),
+    components(schemas(
+        health::Zero2ProdHealthResp,
+        login::LoginRequest,
+        register::RegistrationRequest,
+        register::RegistrationResp,
+        subscriptions::SubscriptionsResp,
+        subscriber_data::SubscriberDataRequest,
+        subscriber_data::SubscriberDataResp,
+        subscribers::SubscribersResp,
+        issues::IssuesResp,
+        issues::IssuePreview,
+        issues::DeliveriesResp,
+        archive::ArchiveResp,
+        lists::ListsResp,
+        newsletter::SegmentCountRequest,
+        newsletter::SegmentCountResp,
+        suppressions::SuppressionsResp,
+        webhooks::EmailEvent,
+        webhooks::BounceEvent,
+        webhooks::SpamComplaintEvent,
+        webhooks::DeliveryEvent,
+        ImportReport,
+        DuplicateLine,
+        RejectedLine,
+        SubscriptionUpdateRequest,
+        SubscriptionRequest,
+        Subscription,
+        SubscriptionStatus,
+        SubscriberPreferences,
+        DeliveryFrequency,
+        Segment,
+        SubscriberEmail,
+        SubscriberName,
+        BodyData,
+        Content,
+        Issue,
+        IssueRequest,
+        IssueStatus,
+        IssueStats,
+        LinkClicks,
+        TrackingEvent,
+        TrackingEventKind,
+        Delivery,
+        DeliveryStatus,
+        DeliverySummary,
+        ArchivedIssue,
+        ArchivedIssueSummary,
+        MailingList,
+        MailingListRequest,
+        ListMembership,
+        Suppression,
+        SuppressionReason,
+        SuppressionRequest,
+        StatusResp,
+        Problem,
+        ErrorCode,
+        FieldError,
+    )),
+    modifiers(&JwtCookie, &BasicCredentials),
+)]
+pub struct ApiDoc;
+
+/// Declares the `jwt` cookie set by login and registration as a security scheme.
+struct JwtCookie;
+
+impl Modify for JwtCookie {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "jwt",
+            SecurityScheme::ApiKey(ApiKey::Cookie(ApiKeyValue::new(cookies::JWT))),
+        );
+    }
+}
+
+/// Declares the basic authentication of the email service webhook as a
+/// security scheme.
+struct BasicCredentials;
+
+impl Modify for BasicCredentials {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "basic",
+            SecurityScheme::Http(Http::new(HttpAuthScheme::Basic)),
+        );
+    }
+}
+
+/// GET handler for the OpenAPI document
+#[utoipa::path(
+    get,
+    path = "/openapi.json",
+    tag = "documentation",
+    responses(
+        (status = 200, description = "The OpenAPI document of this API", content_type = "application/json")
+    )
+)]
+#[allow(clippy::unused_async)]
+pub async fn openapi() -> impl IntoResponse {
+    Json(ApiDoc::openapi())
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        body::Body,
+        http::{Request, StatusCode},
+        routing::{get, Router},
+    };
+    use hyper::body::to_bytes;
+    use tower::ServiceExt;
+
+    use super::*;
+
+    #[test]
+    fn openapi_should_document_every_route() {
+        let doc = serde_json::to_value(ApiDoc::openapi()).expect("openapi json");
+
+        assert_eq!(doc["openapi"], "3.1.0");
+        for path in [
+            "/health",
+            "/login",
+            "/logout",
+            "/register",
+            "/subscriptions",
+            "/subscription_confirmation",
+            "/subscriptions/data_request",
+            "/subscriptions/data",
+            "/subscriptions/unsubscribe",
+            "/subscriptions/preferences",
+            "/lists",
+            "/newsletter/publish",
+            "/newsletter/segment/count",
+            "/newsletter/issues",
+            "/newsletter/issues/{id}",
+            "/newsletter/issues/{id}/cancel",
+            "/newsletter/issues/{id}/preview",
+            "/newsletter/issues/{id}/test",
+            "/newsletter/issues/{id}/stats",
+            "/newsletter/issues/{id}/deliveries",
+            "/newsletter/issues/{id}/deliveries/summary",
+            "/issues",
+            "/issues/{slug}",
+            "/subscribers",
+            "/subscribers/{id}",
+            "/subscribers/import",
+            "/subscribers/export",
+            "/suppressions",
+            "/suppressions/{email}",
+            "/tracking/open",
+            "/tracking/click",
+            "/webhooks/email",
+            "/openapi.json",
+        ] {
+            assert!(doc["paths"].get(path).is_some(), "missing path {path}");
+        }
+        assert!(doc["components"]["schemas"].get("Problem").is_some());
+        assert!(doc["components"]["securitySchemes"].get("jwt").is_some());
+        assert!(doc["components"]["securitySchemes"].get("basic").is_some());
+    }
+
+    #[tokio::test]
+    async fn openapi_should_be_served_as_json() {
+        let app = Router::new().route("/api/openapi.json", get(openapi));
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/api/openapi.json")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .expect("response");
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = to_bytes(response.into_body()).await.expect("body");
+        let doc: serde_json::Value = serde_json::from_slice(&body).expect("json");
+        assert_eq!(doc["info"]["title"], "zero2prod");
+    }
+}
@@ -0,0 +1,220 @@
+use axum::extract::{Json, Query, State};
+use axum::response::IntoResponse;
+use serde::{Deserialize, Serialize};
+use utoipa::IntoParams;
+use uuid::Uuid;
+
+use super::{authorize_subscriber, Error, Problem};
+
+use crate::application::server::AppState;
+use crate::authentication::jwt::SubscriberScope;
+use crate::domain::SubscriberPreferences;
+use common::err_context::ErrorContextExt;
+
+/// GET handler for the preferences of a subscriber
+/// The token comes from the link found in the footer of every newsletter.
+#[utoipa::path(
+    get,
+    path = "/subscriptions/preferences",
+    tag = "subscriptions",
+    params(PreferencesQuery),
+    responses(
+        (status = 200, description = "The preferences of the subscriber", body = SubscriberPreferences),
+        (status = 401, description = "Invalid or expired token", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "The subscription no longer exists", body = Problem, content_type = "application/problem+json"),
+        (status = 500, description = "Internal error", body = Problem, content_type = "application/problem+json"),
+    )
+)]
+#[allow(clippy::unused_async)]
+#[tracing::instrument(
+    name = "Fetching subscriber preferences"
+    skip(state),
+    fields(
+        request_id = %Uuid::new_v4(),
+    )
+)]
+pub async fn get_preferences(
+    State(state): State<AppState>,
+    Query(query): Query<PreferencesQuery>,
+) -> Result<impl IntoResponse, Error> {
+    let id = authorize_subscriber(&query.token, SubscriberScope::Preferences, &state)?;
+
+    let subscription = state
+        .subscription
+        .get_subscription_by_id(&id)
+        .await
+        .context("Could not get subscription by id")?
+        .ok_or_else(|| Error::MissingSubscription {
+            context: format!("No subscription with id {id}"),
+        })?;
+
+    Ok::<_, Error>(Json(subscription.preferences))
+}
+
+/// PUT handler for the preferences of a subscriber
+/// The preferences replace the previous ones.
+#[utoipa::path(
+    put,
+    path = "/subscriptions/preferences",
+    tag = "subscriptions",
+    params(PreferencesQuery),
+    request_body = SubscriberPreferences,
+    responses(
+        (status = 200, description = "The new preferences of the subscriber", body = SubscriberPreferences),
+        (status = 400, description = "Invalid preferences", body = Problem, content_type = "application/problem+json"),
+        (status = 401, description = "Invalid or expired token", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "The subscription no longer exists", body = Problem, content_type = "application/problem+json"),
+        (status = 500, description = "Internal error", body = Problem, content_type = "application/problem+json"),
+    )
+)]
+#[allow(clippy::unused_async)]
+#[tracing::instrument(
+    name = "Updating subscriber preferences"
+    skip(state),
+    fields(
+        request_id = %Uuid::new_v4(),
+    )
+)]
+pub async fn update_preferences(
+    State(state): State<AppState>,
+    Query(query): Query<PreferencesQuery>,
+    Json(preferences): Json<SubscriberPreferences>,
+) -> Result<impl IntoResponse, Error> {
+    let id = authorize_subscriber(&query.token, SubscriberScope::Preferences, &state)?;
+    preferences.validate().context("Invalid preferences")?;
+
+    let subscription = state
+        .subscription
+        .update_preferences(&id, &preferences.normalize())
+        .await
+        .context("Could not update preferences")?
+        .ok_or_else(|| Error::MissingSubscription {
+            context: format!("No subscription with id {id}"),
+        })?;
+
+    Ok::<_, Error>(Json(subscription.preferences))
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct PreferencesQuery {
+    /// The token found in the preferences link of the newsletters.
+    pub token: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        body::Body,
+        http::{header, Request, StatusCode},
+        middleware::map_response,
+        routing::{get, Router},
+    };
+    use chrono::Utc;
+    use hyper::body::to_bytes;
+    use speculoos::prelude::*;
+    use tower::ServiceExt;
+
+    use crate::{
+        application::server::middleware::response_map::error,
+        authentication::jwt::build_subscriber_token,
+        domain::ports::secondary::MockSubscriptionStorage,
+        domain::{
+            DeliveryFrequency, SubscriberEmail, SubscriberName, Subscription, SubscriptionStatus,
+        },
+    };
+
+    use super::*;
+    use crate::application::server::TestStateBuilder;
+
+    /// This is a helper function to build an App with axum.
+    fn preferences_route(state: AppState) -> Router {
+        Router::new()
+            .route(
+                "/api/subscriptions/preferences",
+                get(get_preferences).put(update_preferences),
+            )
+            .layer(map_response(error))
+            .with_state(state)
+    }
+
+    fn state(subscription_mock: MockSubscriptionStorage) -> AppState {
+        TestStateBuilder::new()
+            .subscription(subscription_mock)
+            .build()
+    }
+
+    fn subscription(id: Uuid, preferences: SubscriberPreferences) -> Subscription {
+        Subscription {
+            id,
+            email: SubscriberEmail::parse("alice@acme.inc").unwrap(),
+            username: SubscriberName::parse("alice".to_string()).unwrap(),
+            status: SubscriptionStatus::Confirmed,
+            subscribed_at: Utc::now(),
+            preferences,
+            attributes: serde_json::json!({}),
+        }
+    }
+
+    #[tokio::test]
+    async fn preferences_should_be_updated_with_a_valid_token() {
+        let id = Uuid::new_v4();
+        let mut subscription_mock = MockSubscriptionStorage::new();
+        subscription_mock
+            .expect_update_preferences()
+            .withf(
+                move |update_id: &Uuid, preferences: &SubscriberPreferences| {
+                    update_id == &id
+                        && preferences.topics == ["async", "rust"]
+                        && preferences.frequency == DeliveryFrequency::Weekly
+                },
+            )
+            .return_once(move |_, preferences| Ok(Some(subscription(id, preferences.clone()))));
+        let state = state(subscription_mock);
+        let token = build_subscriber_token(id, SubscriberScope::Preferences, &state.secret);
+
+        let response = preferences_route(state)
+            .oneshot(
+                Request::builder()
+                    .uri(format!("/api/subscriptions/preferences?token={token}"))
+                    .method("PUT")
+                    .header(header::CONTENT_TYPE, "application/json")
+                    .body(Body::from(
+                        serde_json::json!({
+                            "topics": ["rust", "async"],
+                            "frequency": "weekly",
+                        })
+                        .to_string(),
+                    ))
+                    .unwrap(),
+            )
+            .await
+            .expect("response");
+
+        assert_that(&response.status()).is_equal_to(StatusCode::OK);
+        let body = to_bytes(response.into_body()).await.unwrap();
+        let preferences: SubscriberPreferences = serde_json::from_slice(&body).unwrap();
+        assert_that(&preferences.frequency).is_equal_to(DeliveryFrequency::Weekly);
+    }
+
+    #[tokio::test]
+    async fn preferences_should_not_accept_an_unsubscribe_token() {
+        let mut subscription_mock = MockSubscriptionStorage::new();
+        subscription_mock.expect_get_subscription_by_id().never();
+        let state = state(subscription_mock);
+        let token =
+            build_subscriber_token(Uuid::new_v4(), SubscriberScope::Unsubscribe, &state.secret);
+
+        let response = preferences_route(state)
+            .oneshot(
+                Request::builder()
+                    .uri(format!("/api/subscriptions/preferences?token={token}"))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .expect("response");
+
+        assert_that(&response.status()).is_equal_to(StatusCode::UNAUTHORIZED);
+    }
+}
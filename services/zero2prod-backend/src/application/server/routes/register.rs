@@ -5,9 +5,10 @@ use passwords::{analyzer, scorer};
 use secrecy::Secret;
 use serde::{Deserialize, Serialize};
 use tower_cookies::Cookies;
+use utoipa::ToSchema;
 use uuid::Uuid;
 
-use super::Error;
+use super::{Error, Problem};
 use crate::application::server::cookies;
 use crate::application::server::AppState;
 use crate::authentication::jwt::build_token;
@@ -20,6 +21,18 @@ use crate::domain::Credentials;
 ///     - The user is considered logged in
 ///     - { "status": "success", "id": ... } + cookie
 /// - On
+#[utoipa::path(
+    post,
+    path = "/register",
+    tag = "authentication",
+    request_body = RegistrationRequest,
+    responses(
+        (status = 200, description = "Registered and logged in, the JWT cookie is set", body = RegistrationResp),
+        (status = 400, description = "Password too weak", body = Problem, content_type = "application/problem+json"),
+        (status = 409, description = "Email or username already registered", body = Problem, content_type = "application/problem+json"),
+        (status = 500, description = "Internal error", body = Problem, content_type = "application/problem+json"),
+    )
+)]
 #[allow(clippy::unused_async)]
 #[tracing::instrument(
     name = "User Registration"
@@ -34,31 +47,6 @@ pub async fn register(
     Json(request): Json<RegistrationRequest>,
 ) -> Result<impl IntoResponse, Error> {
     println!("register");
-    // Check for duplicates
-    if state
-        .authentication
-        .email_exists(&request.email)
-        .await
-        .context("Could not check if the email exists")?
-    {
-        println!("duplicate email");
-        return Err(Error::DuplicateEmail {
-            context: "Unable to register new user".to_string(),
-        });
-    }
-
-    if state
-        .authentication
-        .username_exists(&request.username)
-        .await
-        .context("Could not check if the username exists")?
-    {
-        println!("duplicate username");
-        return Err(Error::DuplicateUsername {
-            context: "Unable to register new user".to_string(),
-        });
-    }
-
     let password_score = scorer::score(&analyzer::analyze(&request.password));
     if password_score < 90f64 {
         println!("weak password");
@@ -76,6 +64,9 @@ pub async fn register(
 
     let id = Uuid::new_v4();
 
+    // There is no check for duplicates beforehand, which could race with
+    // another registration: an email, or a username, already registered
+    // violates its unique constraint, which is reported as a duplicate.
     state
         .authentication
         .store_credentials(id, &request.email, &credentials)
@@ -97,14 +88,14 @@ pub async fn register(
 /// This is what we return to the user in response to the subscription request.
 /// Currently this is just a placeholder, and it does not return any useful
 /// information.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct RegistrationResp {
     pub status: String, // FIXME Placeholder
     pub id: String,
 }
 
 /// This is the information sent by the user to login.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct RegistrationRequest {
     pub username: String,
     pub email: String,
@@ -125,29 +116,30 @@ mod tests {
     };
     use fake::Fake;
     use hyper::body::HttpBody;
-    use mockall::predicate::*;
-    use secrecy::Secret;
-    use std::sync::Arc;
     use tower::ServiceExt;
     use tower_cookies::CookieManagerLayer;
 
     use crate::{
+        application::server::AppState,
         application::server::{
             middleware::resolve_context::resolve_context, middleware::response_map::error,
         },
-        application::server::{AppState, ApplicationBaseUrl},
         domain::ports::secondary::{
-            MockAuthenticationStorage, MockEmailService, MockSubscriptionStorage,
+            AuthenticationError, MockAuthenticationStorage, UNIQUE_EMAIL, UNIQUE_USERNAME,
         },
         domain::Credentials,
     };
 
     use super::*;
+    use crate::application::server::TestStateBuilder;
 
     #[derive(Debug, Clone, Serialize, Deserialize)]
     pub struct FailedRegistrationResp {
-        pub status: String,
-        pub message: String,
+        #[serde(rename = "type")]
+        pub problem_type: String,
+        pub title: String,
+        pub status: u16,
+        pub detail: String,
         pub code: String,
     }
 
@@ -175,6 +167,23 @@ mod tests {
             .unwrap()
     }
 
+    /// This is a helper function to build the state of the app, with the
+    /// given authentication storage.
+    fn state(authentication_mock: MockAuthenticationStorage) -> AppState {
+        TestStateBuilder::new()
+            .authentication(authentication_mock)
+            .build()
+    }
+
+    /// This is a helper function to read the problem document of a response.
+    async fn problem(mut response: axum::response::Response) -> FailedRegistrationResp {
+        let mut data = Vec::new();
+        while let Some(chunk) = response.data().await {
+            data.extend(&chunk.unwrap());
+        }
+        serde_json::from_slice(&data).expect("json")
+    }
+
     #[tokio::test]
     async fn registration_should_store_credentials() {
         let username = Name().fake::<String>();
@@ -185,49 +194,27 @@ mod tests {
         let username_clone = username.clone();
 
         let request = RegistrationRequest {
-            username: username.clone(),
-            email: email.clone(),
-            password: password.clone(),
-        };
-
-        let _credentials = Credentials {
             username,
-            password: Secret::new(password),
+            email,
+            password,
         };
 
-        let _id = Uuid::new_v4();
-
         let mut authentication_mock = MockAuthenticationStorage::new();
-        let subscription_mock = MockSubscriptionStorage::new();
         authentication_mock
             .expect_store_credentials()
-            .return_once(move |_, _, _| Ok(()));
-        authentication_mock
-            .expect_email_exists()
-            .withf(move |email: &str| email == email_clone)
-            .return_once(|_| Ok(false));
-        authentication_mock
-            .expect_username_exists()
-            .withf(move |username: &str| username == username_clone)
-            .return_once(|_| Ok(false));
-
-        let email_mock = MockEmailService::new();
-        let state = AppState {
-            authentication: Arc::new(authentication_mock),
-            subscription: Arc::new(subscription_mock),
-            email: Arc::new(email_mock),
-            base_url: ApplicationBaseUrl("http://127.0.0.1".to_string()),
-            secret: Secret::new("secret".to_string()),
-        };
+            .withf(move |_, email: &str, credentials: &Credentials| {
+                email == email_clone && credentials.username == username_clone
+            })
+            .times(1)
+            .return_once(|_, _, _| Ok(()));
 
-        let app = registration_route(state);
+        let app = registration_route(state(authentication_mock));
 
         let response = app
             .oneshot(send_registration_request("/api/register", request))
             .await
             .expect("response");
 
-        println!("response: {:?}", response);
         // Check the response status code.
         assert_eq!(response.status(), StatusCode::OK);
 
@@ -238,47 +225,25 @@ mod tests {
 
     #[tokio::test]
     async fn registration_should_fail_if_username_exists() {
-        let username = Name().fake::<String>();
-        let email = SafeEmail().fake::<String>();
-        let password = Password(12..32).fake::<String>();
-
-        let email_clone = email.clone();
-        let username_clone = username.clone();
-
         let request = RegistrationRequest {
-            username: username.clone(),
-            email: email.clone(),
-            password: password.clone(),
+            username: Name().fake::<String>(),
+            email: SafeEmail().fake::<String>(),
+            password: Password(12..32).fake::<String>(),
         };
 
         let mut authentication_mock = MockAuthenticationStorage::new();
-        let subscription_mock = MockSubscriptionStorage::new();
-
         authentication_mock
             .expect_store_credentials()
-            .never()
-            .return_once(move |_, _, _| Ok(()));
-        authentication_mock
-            .expect_email_exists()
-            .withf(move |email: &str| email == email_clone)
-            .return_once(|_| Ok(false));
-        authentication_mock
-            .expect_username_exists()
-            .withf(move |username: &str| username == username_clone)
-            .return_once(|_| Ok(true));
-
-        let email_mock = MockEmailService::new();
-        let state = AppState {
-            authentication: Arc::new(authentication_mock),
-            subscription: Arc::new(subscription_mock),
-            email: Arc::new(email_mock),
-            base_url: ApplicationBaseUrl("http://127.0.0.1".to_string()),
-            secret: Secret::new("secret".to_string()),
-        };
+            .return_once(|_, _, _| {
+                Err(AuthenticationError::UniqueViolation {
+                    context: "Could not create credentials".to_string(),
+                    constraint: UNIQUE_USERNAME.to_string(),
+                })
+            });
 
-        let app = registration_route(state);
+        let app = registration_route(state(authentication_mock));
 
-        let mut response = app
+        let response = app
             .oneshot(send_registration_request("/api/register", request))
             .await
             .expect("response");
@@ -286,60 +251,38 @@ mod tests {
         // Check the response status code.
         assert_eq!(response.status(), StatusCode::CONFLICT);
 
-        // Check the response code
-        // let mut data = Vec::with_capacity(expected_length);
-        let mut data = Vec::new();
-        while let Some(chunk) = response.data().await {
-            data.extend(&chunk.unwrap());
-        }
-        let response: FailedRegistrationResp = serde_json::from_slice(&data).expect("json");
-        assert_eq!(response.status, "fail");
+        // Check the response is a problem document
+        assert_eq!(
+            response.headers().get(header::CONTENT_TYPE).unwrap(),
+            "application/problem+json"
+        );
+
+        let response = problem(response).await;
+        assert_eq!(response.status, 409);
         assert_eq!(response.code, "auth/duplicate_username");
     }
 
     #[tokio::test]
     async fn registration_should_fail_if_email_exists() {
-        let username = Name().fake::<String>();
-        let email = SafeEmail().fake::<String>();
-        let password = Password(12..32).fake::<String>();
-
-        let email_clone = email.clone();
-        let username_clone = username.clone();
-
         let request = RegistrationRequest {
-            username: username.clone(),
-            email: email.clone(),
-            password: password.clone(),
+            username: Name().fake::<String>(),
+            email: SafeEmail().fake::<String>(),
+            password: Password(12..32).fake::<String>(),
         };
 
         let mut authentication_mock = MockAuthenticationStorage::new();
-        let subscription_mock = MockSubscriptionStorage::new();
-
         authentication_mock
             .expect_store_credentials()
-            .never()
-            .return_once(move |_, _, _| Ok(()));
-        authentication_mock
-            .expect_email_exists()
-            .withf(move |email: &str| email == email_clone)
-            .return_once(|_| Ok(true));
-        authentication_mock
-            .expect_username_exists()
-            .withf(move |username: &str| username == username_clone)
-            .return_once(|_| Ok(false));
-
-        let email_mock = MockEmailService::new();
-        let state = AppState {
-            authentication: Arc::new(authentication_mock),
-            subscription: Arc::new(subscription_mock),
-            email: Arc::new(email_mock),
-            base_url: ApplicationBaseUrl("http://127.0.0.1".to_string()),
-            secret: Secret::new("secret".to_string()),
-        };
+            .return_once(|_, _, _| {
+                Err(AuthenticationError::UniqueViolation {
+                    context: "Could not create credentials".to_string(),
+                    constraint: UNIQUE_EMAIL.to_string(),
+                })
+            });
 
-        let app = registration_route(state);
+        let app = registration_route(state(authentication_mock));
 
-        let mut response = app
+        let response = app
             .oneshot(send_registration_request("/api/register", request))
             .await
             .expect("response");
@@ -347,55 +290,25 @@ mod tests {
         // Check the response status code.
         assert_eq!(response.status(), StatusCode::CONFLICT);
 
-        // Check the response code
-        // let mut data = Vec::with_capacity(expected_length);
-        let mut data = Vec::new();
-        while let Some(chunk) = response.data().await {
-            data.extend(&chunk.unwrap());
-        }
-        let response: FailedRegistrationResp = serde_json::from_slice(&data).expect("json");
-        assert_eq!(response.status, "fail");
+        let response = problem(response).await;
+        assert_eq!(response.status, 409);
         assert_eq!(response.code, "auth/duplicate_email");
     }
 
     #[tokio::test]
     async fn registration_should_fail_if_password_is_weak() {
-        let username = Name().fake::<String>();
-        let email = SafeEmail().fake::<String>();
-        let password = "Secret123".to_string();
-
         let request = RegistrationRequest {
-            username: username.clone(),
-            email: email.clone(),
-            password: password.clone(),
+            username: Name().fake::<String>(),
+            email: SafeEmail().fake::<String>(),
+            password: "Secret123".to_string(),
         };
 
         let mut authentication_mock = MockAuthenticationStorage::new();
-        let subscription_mock = MockSubscriptionStorage::new();
+        authentication_mock.expect_store_credentials().never();
 
-        authentication_mock
-            .expect_store_credentials()
-            .never()
-            .return_once(move |_, _, _| Ok(()));
-        authentication_mock
-            .expect_email_exists()
-            .return_once(|_| Ok(false));
-        authentication_mock
-            .expect_username_exists()
-            .return_once(|_| Ok(false));
-
-        let email_mock = MockEmailService::new();
-        let state = AppState {
-            authentication: Arc::new(authentication_mock),
-            subscription: Arc::new(subscription_mock),
-            email: Arc::new(email_mock),
-            base_url: ApplicationBaseUrl("http://127.0.0.1".to_string()),
-            secret: Secret::new("secret".to_string()),
-        };
+        let app = registration_route(state(authentication_mock));
 
-        let app = registration_route(state);
-
-        let mut response = app
+        let response = app
             .oneshot(send_registration_request("/api/register", request))
             .await
             .expect("response");
@@ -403,14 +316,37 @@ mod tests {
         // Check the response status code.
         assert_eq!(response.status(), StatusCode::BAD_REQUEST);
 
-        // Check the response code
-        // let mut data = Vec::with_capacity(expected_length);
-        let mut data = Vec::new();
-        while let Some(chunk) = response.data().await {
-            data.extend(&chunk.unwrap());
-        }
-        let response: FailedRegistrationResp = serde_json::from_slice(&data).expect("json");
-        assert_eq!(response.status, "fail");
+        let response = problem(response).await;
+        assert_eq!(response.status, 400);
         assert_eq!(response.code, "auth/weak_password");
     }
+
+    #[tokio::test]
+    async fn registration_should_be_retried_if_the_storage_is_unavailable() {
+        let request = RegistrationRequest {
+            username: Name().fake::<String>(),
+            email: SafeEmail().fake::<String>(),
+            password: Password(12..32).fake::<String>(),
+        };
+
+        let mut authentication_mock = MockAuthenticationStorage::new();
+        authentication_mock
+            .expect_store_credentials()
+            .return_once(|_, _, _| {
+                Err(AuthenticationError::SerializationFailure {
+                    context: "Could not create credentials".to_string(),
+                })
+            });
+
+        let app = registration_route(state(authentication_mock));
+
+        let response = app
+            .oneshot(send_registration_request("/api/register", request))
+            .await
+            .expect("response");
+
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let response = problem(response).await;
+        assert_eq!(response.code, "storage/unavailable");
+    }
 }
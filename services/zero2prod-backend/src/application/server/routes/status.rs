@@ -0,0 +1,17 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Acknowledgement returned by endpoints which have nothing else to report.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct StatusResp {
+    #[schema(example = "success")]
+    pub status: String,
+}
+
+impl StatusResp {
+    pub fn success() -> Self {
+        StatusResp {
+            status: "success".to_string(),
+        }
+    }
+}
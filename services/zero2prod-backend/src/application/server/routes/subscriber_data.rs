@@ -0,0 +1,554 @@
+use axum::extract::{Json, Query, State};
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use super::subscriptions::send_through_outbox;
+use super::{authorize_subscriber, Error, Problem, StatusResp};
+
+use crate::application::server::{AppState, ApplicationBaseUrl};
+use crate::authentication::jwt::{build_subscriber_token, SubscriberScope};
+use crate::domain::{
+    ArchivedIssueSummary, Delivery, EmailTemplate, FieldError, ListMembership,
+    SubscriberDataContext, SubscriberEmail, Subscription, Suppression, TrackingEvent,
+};
+use common::err_context::ErrorContextExt;
+
+/// POST handler for a subscriber asking for access to their data
+/// If there is a subscription for the email, we send it a link with a token
+/// giving access to the data, through the outbox. The response is the same
+/// whether or not there is such a subscription, and whether or not the link
+/// could be sent, so that it can't be used to probe our subscribers.
+#[utoipa::path(
+    post,
+    path = "/subscriptions/data_request",
+    tag = "subscriptions",
+    request_body = SubscriberDataRequest,
+    responses(
+        (status = 200, description = "If the email is subscribed, a link to its data is sent", body = StatusResp),
+        (status = 400, description = "Invalid email", body = Problem, content_type = "application/problem+json"),
+        (status = 500, description = "Internal error", body = Problem, content_type = "application/problem+json"),
+    )
+)]
+#[allow(clippy::unused_async)]
+#[tracing::instrument(
+    name = "Requesting access to subscriber data"
+    skip(state),
+    fields(
+        request_id = %Uuid::new_v4(),
+    )
+)]
+pub async fn request_subscriber_data(
+    State(state): State<AppState>,
+    Json(request): Json<SubscriberDataRequest>,
+) -> Result<impl IntoResponse, Error> {
+    let email = SubscriberEmail::parse(request.email)
+        .map_err(|err| vec![FieldError::new("email", err)])
+        .context("Invalid subscriber data request")?;
+
+    if let Some(subscription) = state
+        .subscription
+        .get_subscription_by_email(email.as_ref())
+        .await
+        .context("Could not get subscription by email")?
+    {
+        // Only subscribed emails can fail here, so the failure is logged
+        // rather than returned, or it would tell them apart.
+        if let Err(err) = send_data_link(&state, &subscription).await {
+            tracing::error!(
+                "Could not send the data link to subscriber {}: {err}",
+                subscription.id
+            );
+        }
+    }
+
+    Ok::<_, Error>(Json(StatusResp::success()))
+}
+
+/// Writes the email with the link to the data of the subscriber to the
+/// outbox, which sends it.
+async fn send_data_link(state: &AppState, subscription: &Subscription) -> Result<(), Error> {
+    let token = build_subscriber_token(subscription.id, SubscriberScope::Data, &state.secret);
+    let template = subscriber_data_template(&state.base_url, &token);
+    let email = state
+        .templates
+        .render(&subscription.email, &template)
+        .context("Could not render subscriber data email")?;
+    send_through_outbox(state, email).await
+}
+
+/// GET handler for exporting everything we hold about a subscriber
+/// The subscription comes with its lists, queued digest, deliveries, tracking
+/// events and suppressions.
+#[utoipa::path(
+    get,
+    path = "/subscriptions/data",
+    tag = "subscriptions",
+    params(SubscriberDataQuery),
+    responses(
+        (status = 200, description = "The data held about the subscriber", body = SubscriberDataResp),
+        (status = 401, description = "Invalid or expired token", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "The subscription no longer exists", body = Problem, content_type = "application/problem+json"),
+        (status = 500, description = "Internal error", body = Problem, content_type = "application/problem+json"),
+    )
+)]
+#[allow(clippy::unused_async)]
+#[tracing::instrument(
+    name = "Exporting subscriber data"
+    skip(state),
+    fields(
+        request_id = %Uuid::new_v4(),
+    )
+)]
+pub async fn export_subscriber_data(
+    State(state): State<AppState>,
+    Query(query): Query<SubscriberDataQuery>,
+) -> Result<impl IntoResponse, Error> {
+    let id = authorize_subscriber(&query.token, SubscriberScope::Data, &state)?;
+
+    let subscription = state
+        .subscription
+        .get_subscription_by_id(&id)
+        .await
+        .context("Could not get subscription by id")?
+        .ok_or_else(|| Error::MissingSubscription {
+            context: format!("No subscription with id {id}"),
+        })?;
+
+    let lists = state
+        .subscription
+        .get_list_memberships(&id)
+        .await
+        .context("Could not get the lists of the subscriber")?;
+    let queued_digest = state
+        .subscription
+        .get_queued_digest(&id)
+        .await
+        .context("Could not get the queued digest of the subscriber")?;
+    let suppressions = state
+        .subscription
+        .get_suppressions(std::slice::from_ref(&subscription.email))
+        .await
+        .context("Could not get the suppressions of the subscriber")?;
+    let deliveries = state
+        .issues
+        .get_subscriber_deliveries(&id)
+        .await
+        .context("Could not get the deliveries to the subscriber")?;
+    let tracking_events = state
+        .issues
+        .get_subscriber_tracking_events(&id)
+        .await
+        .context("Could not get the tracking events of the subscriber")?;
+
+    Ok::<_, Error>(Json(SubscriberDataResp {
+        subscription,
+        lists,
+        queued_digest,
+        deliveries,
+        tracking_events,
+        suppressions,
+        exported_at: Utc::now(),
+    }))
+}
+
+/// DELETE handler for erasing a subscriber
+/// The subscription, its tokens, deliveries, tracking events and emails in the
+/// outbox are deleted, and the erasure is recorded in an audit log which does
+/// not keep the email.
+#[utoipa::path(
+    delete,
+    path = "/subscriptions/data",
+    tag = "subscriptions",
+    params(SubscriberDataQuery),
+    responses(
+        (status = 204, description = "Subscriber erased"),
+        (status = 401, description = "Invalid or expired token", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "The subscription no longer exists", body = Problem, content_type = "application/problem+json"),
+        (status = 500, description = "Internal error", body = Problem, content_type = "application/problem+json"),
+    )
+)]
+#[allow(clippy::unused_async)]
+#[tracing::instrument(
+    name = "Erasing subscriber data"
+    skip(state),
+    fields(
+        request_id = %Uuid::new_v4(),
+    )
+)]
+pub async fn erase_subscriber_data(
+    State(state): State<AppState>,
+    Query(query): Query<SubscriberDataQuery>,
+) -> Result<impl IntoResponse, Error> {
+    let id = authorize_subscriber(&query.token, SubscriberScope::Data, &state)?;
+
+    if state
+        .subscription
+        .erase_subscription(&id)
+        .await
+        .context("Could not erase subscription")?
+    {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(Error::MissingSubscription {
+            context: format!("No subscription with id {id}"),
+        })
+    }
+}
+
+/// This is a helper function to create the email sent to a subscriber who
+/// asked for their data. It contains a link to the export of the data.
+fn subscriber_data_template(url: &ApplicationBaseUrl, token: &str) -> EmailTemplate {
+    EmailTemplate::SubscriberData(SubscriberDataContext {
+        data_link: format!("{}/api/v1/subscriptions/data?token={}", url, token),
+    })
+}
+
+/// This is the information sent by a subscriber to get access to their data.
+#[derive(Deserialize, Serialize, Debug, Clone, ToSchema)]
+pub struct SubscriberDataRequest {
+    #[schema(example = "john.doe@example.com")]
+    pub email: String,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SubscriberDataQuery {
+    /// The token sent in the subscriber data email.
+    pub token: String,
+}
+
+/// Everything we hold about a subscriber. Tokens are left out, so that the
+/// export can't be used to act on the subscription.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SubscriberDataResp {
+    /// The subscription, with its preferences and attributes.
+    pub subscription: Subscription,
+    pub lists: Vec<ListMembership>,
+    /// The issues waiting for the next digest.
+    pub queued_digest: Vec<ArchivedIssueSummary>,
+    pub deliveries: Vec<Delivery>,
+    pub tracking_events: Vec<TrackingEvent>,
+    pub suppressions: Vec<Suppression>,
+    pub exported_at: DateTime<Utc>,
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        body::Body,
+        http::{header, Request},
+        middleware::{from_fn_with_state, map_response},
+        routing::{get, post, Router},
+    };
+    use fake::faker::{internet::en::SafeEmail, name::en::Name};
+    use fake::Fake;
+    use hyper::body::to_bytes;
+    use mockall::predicate::*;
+    use secrecy::Secret;
+    use speculoos::prelude::*;
+    use tower::ServiceExt;
+    use tower_cookies::CookieManagerLayer;
+
+    use crate::{
+        application::server::{
+            middleware::resolve_context::resolve_context, middleware::response_map::error,
+        },
+        domain::ports::secondary::Email,
+        domain::ports::secondary::MockIssueStorage,
+        domain::ports::secondary::{MockEmailService, MockSubscriptionStorage, SubscriptionError},
+        domain::{
+            OutboxEmail, SubscriberName, SubscriberPreferences, SubscriptionStatus,
+            TrackingEventKind,
+        },
+        services::templates::repository_templates,
+    };
+
+    use super::*;
+    use crate::application::server::TestStateBuilder;
+
+    /// This is a helper function to build an App with axum.
+    fn subscriber_data_route(state: AppState) -> Router {
+        Router::new()
+            .route(
+                "/api/subscriptions/data_request",
+                post(request_subscriber_data),
+            )
+            .route(
+                "/api/subscriptions/data",
+                get(export_subscriber_data).delete(erase_subscriber_data),
+            )
+            .layer(map_response(error))
+            .layer(from_fn_with_state(state.clone(), resolve_context))
+            .layer(CookieManagerLayer::new())
+            .with_state(state)
+    }
+
+    fn state(subscription_mock: MockSubscriptionStorage, email_mock: MockEmailService) -> AppState {
+        TestStateBuilder::new()
+            .subscription(subscription_mock)
+            .email(email_mock)
+            .templates(repository_templates())
+            .build()
+    }
+
+    fn fake_subscription() -> Subscription {
+        Subscription {
+            id: Uuid::new_v4(),
+            email: SubscriberEmail::parse(SafeEmail().fake::<String>()).unwrap(),
+            username: SubscriberName::parse(Name().fake::<String>()).unwrap(),
+            status: SubscriptionStatus::PendingConfirmation,
+            subscribed_at: Utc::now(),
+            preferences: SubscriberPreferences::default(),
+            attributes: serde_json::json!({}),
+        }
+    }
+
+    fn send_request(method: &str, uri: &str) -> Request<Body> {
+        Request::builder()
+            .uri(uri)
+            .method(method)
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn data_request_should_send_a_link_to_subscribers() {
+        let subscription = fake_subscription();
+        let email = subscription.email.clone();
+        let body = serde_json::json!({ "email": email.as_ref() });
+
+        let mut subscription_mock = MockSubscriptionStorage::new();
+        subscription_mock
+            .expect_get_subscription_by_email()
+            .with(eq(email.as_ref().to_string()))
+            .return_once(move |_| Ok(Some(subscription)));
+        let queued = email.clone();
+        subscription_mock
+            .expect_queue_email()
+            .withf(move |outbox: &OutboxEmail| outbox.email.to == queued)
+            .times(1)
+            .return_once(|_| Ok(()));
+        subscription_mock
+            .expect_complete_outbox_email()
+            .times(1)
+            .return_once(|_| Ok(()));
+        let mut email_mock = MockEmailService::new();
+        email_mock
+            .expect_send_email()
+            .withf(move |sent: &Email| {
+                sent.to == email && sent.text_content.contains("/subscriptions/data?token=")
+            })
+            .times(1)
+            .return_once(|_| Ok(()));
+        let state = state(subscription_mock, email_mock);
+
+        let response = subscriber_data_route(state)
+            .oneshot(
+                Request::builder()
+                    .uri("/api/subscriptions/data_request")
+                    .method("POST")
+                    .header(header::CONTENT_TYPE, "application/json")
+                    .body(Body::from(body.to_string()))
+                    .unwrap(),
+            )
+            .await
+            .expect("response");
+
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn data_request_should_not_reveal_failures_to_send_the_link() {
+        let subscription = fake_subscription();
+        let email = subscription.email.clone();
+        let body = serde_json::json!({ "email": email.as_ref() });
+
+        let mut subscription_mock = MockSubscriptionStorage::new();
+        subscription_mock
+            .expect_get_subscription_by_email()
+            .return_once(move |_| Ok(Some(subscription)));
+        subscription_mock.expect_queue_email().return_once(|_| {
+            Err(SubscriptionError::Database {
+                context: "Could not queue email".to_string(),
+                source: sqlx::Error::PoolTimedOut.to_string(),
+                retryable: true,
+            })
+        });
+        let mut email_mock = MockEmailService::new();
+        email_mock.expect_send_email().never();
+        let state = state(subscription_mock, email_mock);
+
+        let response = subscriber_data_route(state)
+            .oneshot(
+                Request::builder()
+                    .uri("/api/subscriptions/data_request")
+                    .method("POST")
+                    .header(header::CONTENT_TYPE, "application/json")
+                    .body(Body::from(body.to_string()))
+                    .unwrap(),
+            )
+            .await
+            .expect("response");
+
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn data_request_should_not_reveal_unknown_emails() {
+        let mut subscription_mock = MockSubscriptionStorage::new();
+        subscription_mock
+            .expect_get_subscription_by_email()
+            .return_once(|_| Ok(None));
+        let mut email_mock = MockEmailService::new();
+        email_mock.expect_send_email().never();
+        let state = state(subscription_mock, email_mock);
+
+        let body = serde_json::json!({ "email": SafeEmail().fake::<String>() });
+        let response = subscriber_data_route(state)
+            .oneshot(
+                Request::builder()
+                    .uri("/api/subscriptions/data_request")
+                    .method("POST")
+                    .header(header::CONTENT_TYPE, "application/json")
+                    .body(Body::from(body.to_string()))
+                    .unwrap(),
+            )
+            .await
+            .expect("response");
+
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn export_should_return_every_record_of_the_subscriber() {
+        let subscription = fake_subscription();
+        let id = subscription.id;
+        let email = subscription.email.clone();
+        let issue_id = Uuid::new_v4();
+        let expected = subscription.clone();
+
+        let mut subscription_mock = MockSubscriptionStorage::new();
+        subscription_mock
+            .expect_get_subscription_by_id()
+            .with(eq(id))
+            .return_once(move |_| Ok(Some(subscription)));
+        subscription_mock
+            .expect_get_token_by_subscriber_id()
+            .never();
+        subscription_mock
+            .expect_get_list_memberships()
+            .with(eq(id))
+            .return_once(|_| Ok(vec![]));
+        subscription_mock
+            .expect_get_queued_digest()
+            .with(eq(id))
+            .return_once(|_| Ok(vec![]));
+        subscription_mock
+            .expect_get_suppressions()
+            .withf(move |emails: &[SubscriberEmail]| emails == [email.clone()])
+            .return_once(|_| Ok(vec![]));
+        let mut issues_mock = MockIssueStorage::new();
+        issues_mock
+            .expect_get_subscriber_deliveries()
+            .with(eq(id))
+            .return_once(|_| Ok(vec![]));
+        issues_mock
+            .expect_get_subscriber_tracking_events()
+            .with(eq(id))
+            .return_once(move |_| {
+                Ok(vec![TrackingEvent::new(
+                    issue_id,
+                    id,
+                    TrackingEventKind::Open,
+                )])
+            });
+        let state = TestStateBuilder::new()
+            .subscription(subscription_mock)
+            .issues(issues_mock)
+            .build();
+        let token = build_subscriber_token(id, SubscriberScope::Data, &state.secret);
+
+        let response = subscriber_data_route(state)
+            .oneshot(send_request(
+                "GET",
+                &format!("/api/subscriptions/data?token={token}"),
+            ))
+            .await
+            .expect("response");
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = to_bytes(response.into_body()).await.expect("body");
+        assert!(!String::from_utf8_lossy(&body).contains("token"));
+        let data: SubscriberDataResp = serde_json::from_slice(&body).expect("json");
+        assert_that(&data.subscription).is_equal_to(expected);
+        assert_eq!(data.tracking_events.len(), 1);
+        assert_that(&data.tracking_events[0].issue_id).is_equal_to(issue_id);
+    }
+
+    #[tokio::test]
+    async fn export_should_reject_an_invalid_token() {
+        let state = state(MockSubscriptionStorage::new(), MockEmailService::new());
+        let token = build_subscriber_token(
+            Uuid::new_v4(),
+            SubscriberScope::Data,
+            &Secret::new("other".to_string()),
+        );
+
+        let response = subscriber_data_route(state)
+            .oneshot(send_request(
+                "GET",
+                &format!("/api/subscriptions/data?token={token}"),
+            ))
+            .await
+            .expect("response");
+
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn erasure_should_erase_the_subscription() {
+        let id = Uuid::new_v4();
+        let mut subscription_mock = MockSubscriptionStorage::new();
+        subscription_mock
+            .expect_erase_subscription()
+            .with(eq(id))
+            .times(1)
+            .return_once(|_| Ok(true));
+        let state = state(subscription_mock, MockEmailService::new());
+        let token = build_subscriber_token(id, SubscriberScope::Data, &state.secret);
+
+        let response = subscriber_data_route(state.clone())
+            .oneshot(send_request(
+                "DELETE",
+                &format!("/api/subscriptions/data?token={token}"),
+            ))
+            .await
+            .expect("response");
+
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    }
+
+    #[tokio::test]
+    async fn erasure_of_an_erased_subscription_should_be_not_found() {
+        let mut subscription_mock = MockSubscriptionStorage::new();
+        subscription_mock
+            .expect_erase_subscription()
+            .return_once(|_| Ok(false));
+        let state = state(subscription_mock, MockEmailService::new());
+        let token = build_subscriber_token(Uuid::new_v4(), SubscriberScope::Data, &state.secret);
+
+        let response = subscriber_data_route(state)
+            .oneshot(send_request(
+                "DELETE",
+                &format!("/api/subscriptions/data?token={token}"),
+            ))
+            .await
+            .expect("response");
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+}
@@ -0,0 +1,726 @@
+use axum::body::{Bytes, StreamBody};
+use axum::extract::{Json, Path, Query, State};
+use axum::http::{header, StatusCode};
+use axum::response::IntoResponse;
+use axum::Extension;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use super::{authorize, Error, Problem};
+
+use crate::application::server::middleware::resolve_context::Error as ContextResolutionError;
+use crate::application::server::{context::Context, AppState};
+use crate::application::subscribers_csv::{self, ImportReport};
+use crate::domain::{
+    FieldError, Subscription, SubscriptionCursor, SubscriptionFilter, SubscriptionStatus,
+    SubscriptionUpdate, SubscriptionUpdateRequest,
+};
+use common::err_context::ErrorContextExt;
+
+/// Number of subscriptions returned in a page, when the request does not specify it.
+const DEFAULT_LIMIT: i64 = 50;
+/// Upper bound on the number of subscriptions returned in a page.
+const MAX_LIMIT: i64 = 200;
+
+/// GET handler for listing subscriptions
+/// The response contains a page of subscriptions, and, if there are more, a
+/// cursor to use in the next request.
+#[utoipa::path(
+    get,
+    path = "/subscribers",
+    tag = "subscribers",
+    params(SubscribersQuery),
+    security(("jwt" = [])),
+    responses(
+        (status = 200, description = "A page of subscriptions", body = SubscribersResp),
+        (status = 400, description = "Invalid cursor or limit", body = Problem, content_type = "application/problem+json"),
+        (status = 401, description = "Missing or invalid JWT cookie", body = Problem, content_type = "application/problem+json"),
+        (status = 500, description = "Internal error", body = Problem, content_type = "application/problem+json"),
+    )
+)]
+#[allow(clippy::unused_async)]
+#[tracing::instrument(
+    name = "Listing subscriptions"
+    skip(state, context),
+    fields(
+        request_id = %Uuid::new_v4(),
+    )
+)]
+pub async fn list_subscribers(
+    Extension(context): Extension<Result<Context, ContextResolutionError>>,
+    State(state): State<AppState>,
+    Query(query): Query<SubscribersQuery>,
+) -> Result<impl IntoResponse, Error> {
+    authorize(context)?;
+
+    let mut filter = SubscriptionFilter::try_from(query).context("Invalid subscribers query")?;
+    let limit = filter.limit;
+    // Fetch one more subscription than requested, to know if there is a next page.
+    filter.limit += 1;
+
+    let mut subscribers = state
+        .subscription
+        .list_subscriptions(&filter)
+        .await
+        .context("Could not list subscriptions")?;
+
+    let next_cursor = if subscribers.len() as i64 > limit {
+        subscribers.truncate(limit as usize);
+        subscribers.last().map(|subscription| {
+            SubscriptionCursor {
+                subscribed_at: subscription.subscribed_at,
+                id: subscription.id,
+            }
+            .encode()
+        })
+    } else {
+        None
+    };
+
+    let resp = SubscribersResp {
+        subscribers,
+        next_cursor,
+    };
+    Ok::<_, Error>(Json(resp))
+}
+
+/// GET handler for a single subscription
+#[utoipa::path(
+    get,
+    path = "/subscribers/{id}",
+    tag = "subscribers",
+    params(("id" = Uuid, Path, description = "Subscription id")),
+    security(("jwt" = [])),
+    responses(
+        (status = 200, description = "The subscription", body = Subscription),
+        (status = 401, description = "Missing or invalid JWT cookie", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Unknown subscription", body = Problem, content_type = "application/problem+json"),
+        (status = 500, description = "Internal error", body = Problem, content_type = "application/problem+json"),
+    )
+)]
+#[allow(clippy::unused_async)]
+#[tracing::instrument(
+    name = "Fetching a subscription"
+    skip(state, context),
+    fields(
+        request_id = %Uuid::new_v4(),
+    )
+)]
+pub async fn get_subscriber(
+    Extension(context): Extension<Result<Context, ContextResolutionError>>,
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<impl IntoResponse, Error> {
+    authorize(context)?;
+
+    let subscription = state
+        .subscription
+        .get_subscription_by_id(&id)
+        .await
+        .context("Could not get subscription by id")?
+        .ok_or_else(|| Error::MissingSubscription {
+            context: format!("No subscription with id {id}"),
+        })?;
+
+    Ok::<_, Error>(Json(subscription))
+}
+
+/// PATCH handler for modifying a subscription's username or status
+#[utoipa::path(
+    patch,
+    path = "/subscribers/{id}",
+    tag = "subscribers",
+    params(("id" = Uuid, Path, description = "Subscription id")),
+    request_body = SubscriptionUpdateRequest,
+    security(("jwt" = [])),
+    responses(
+        (status = 200, description = "The modified subscription", body = Subscription),
+        (status = 400, description = "Invalid username", body = Problem, content_type = "application/problem+json"),
+        (status = 401, description = "Missing or invalid JWT cookie", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Unknown subscription", body = Problem, content_type = "application/problem+json"),
+        (status = 500, description = "Internal error", body = Problem, content_type = "application/problem+json"),
+    )
+)]
+#[allow(clippy::unused_async)]
+#[tracing::instrument(
+    name = "Updating a subscription"
+    skip(state, context),
+    fields(
+        request_id = %Uuid::new_v4(),
+    )
+)]
+pub async fn update_subscriber(
+    Extension(context): Extension<Result<Context, ContextResolutionError>>,
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Json(request): Json<SubscriptionUpdateRequest>,
+) -> Result<impl IntoResponse, Error> {
+    authorize(context)?;
+
+    let update = SubscriptionUpdate::try_from(request).context("Could not get valid update")?;
+
+    let subscription = state
+        .subscription
+        .update_subscription(&id, &update)
+        .await
+        .context("Could not update subscription")?
+        .ok_or_else(|| Error::MissingSubscription {
+            context: format!("No subscription with id {id}"),
+        })?;
+
+    Ok::<_, Error>(Json(subscription))
+}
+
+/// DELETE handler for removing a subscription
+#[utoipa::path(
+    delete,
+    path = "/subscribers/{id}",
+    tag = "subscribers",
+    params(("id" = Uuid, Path, description = "Subscription id")),
+    security(("jwt" = [])),
+    responses(
+        (status = 204, description = "The subscription and its tokens are deleted"),
+        (status = 401, description = "Missing or invalid JWT cookie", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Unknown subscription", body = Problem, content_type = "application/problem+json"),
+        (status = 500, description = "Internal error", body = Problem, content_type = "application/problem+json"),
+    )
+)]
+#[allow(clippy::unused_async)]
+#[tracing::instrument(
+    name = "Deleting a subscription"
+    skip(state, context),
+    fields(
+        request_id = %Uuid::new_v4(),
+    )
+)]
+pub async fn delete_subscriber(
+    Extension(context): Extension<Result<Context, ContextResolutionError>>,
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<impl IntoResponse, Error> {
+    authorize(context)?;
+
+    let deleted = state
+        .subscription
+        .delete_subscription(&id)
+        .await
+        .context("Could not delete subscription")?;
+
+    if deleted {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(Error::MissingSubscription {
+            context: format!("No subscription with id {id}"),
+        })
+    }
+}
+
+/// POST handler for importing subscriptions from CSV
+/// The body is CSV with an `email` and a `username` column, and optional `status`
+/// and `subscribed_at` columns. Valid lines are imported, and the response reports
+/// the lines which were rejected or duplicated.
+#[utoipa::path(
+    post,
+    path = "/subscribers/import",
+    tag = "subscribers",
+    params(ImportQuery),
+    request_body(content = String, content_type = "text/csv"),
+    security(("jwt" = [])),
+    responses(
+        (status = 200, description = "Import report", body = ImportReport),
+        (status = 400, description = "Unreadable CSV, or missing columns", body = Problem, content_type = "application/problem+json"),
+        (status = 401, description = "Missing or invalid JWT cookie", body = Problem, content_type = "application/problem+json"),
+        (status = 500, description = "Internal error", body = Problem, content_type = "application/problem+json"),
+    )
+)]
+#[allow(clippy::unused_async)]
+#[tracing::instrument(
+    name = "Importing subscriptions"
+    skip(state, context, body),
+    fields(
+        request_id = %Uuid::new_v4(),
+    )
+)]
+pub async fn import_subscribers(
+    Extension(context): Extension<Result<Context, ContextResolutionError>>,
+    State(state): State<AppState>,
+    Query(query): Query<ImportQuery>,
+    body: Bytes,
+) -> Result<impl IntoResponse, Error> {
+    authorize(context)?;
+
+    let report = subscribers_csv::import_subscribers(
+        &body[..],
+        query.pending,
+        &state.subscription,
+        &state.email,
+        &state.templates,
+        &state.base_url,
+    )
+    .await
+    .context("Could not import subscriptions")?;
+
+    Ok::<_, Error>(Json(report))
+}
+
+/// GET handler for exporting all subscriptions as CSV
+#[utoipa::path(
+    get,
+    path = "/subscribers/export",
+    tag = "subscribers",
+    security(("jwt" = [])),
+    responses(
+        (status = 200, description = "All subscriptions, ordered by signup date", body = String, content_type = "text/csv"),
+        (status = 401, description = "Missing or invalid JWT cookie", body = Problem, content_type = "application/problem+json"),
+    )
+)]
+#[allow(clippy::unused_async)]
+#[tracing::instrument(
+    name = "Exporting subscriptions"
+    skip(state, context),
+    fields(
+        request_id = %Uuid::new_v4(),
+    )
+)]
+pub async fn export_subscribers(
+    Extension(context): Extension<Result<Context, ContextResolutionError>>,
+    State(state): State<AppState>,
+) -> Result<impl IntoResponse, Error> {
+    authorize(context)?;
+
+    let body = StreamBody::new(subscribers_csv::export_subscribers(
+        state.subscription.clone(),
+    ));
+
+    Ok::<_, Error>((
+        [
+            (header::CONTENT_TYPE, "text/csv"),
+            (
+                header::CONTENT_DISPOSITION,
+                "attachment; filename=\"subscribers.csv\"",
+            ),
+        ],
+        body,
+    ))
+}
+
+/// Query string of the subscription listing.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SubscribersQuery {
+    /// Only return subscriptions with this status.
+    pub status: Option<SubscriptionStatus>,
+    /// Only return subscriptions made at or after this date.
+    pub subscribed_after: Option<DateTime<Utc>>,
+    /// Only return subscriptions made before this date.
+    pub subscribed_before: Option<DateTime<Utc>>,
+    /// Only return subscriptions whose email contains this string (case insensitive).
+    pub email: Option<String>,
+    /// Cursor returned with the previous page.
+    pub cursor: Option<String>,
+    /// Maximum number of subscriptions in the page (1 to 200, default 50).
+    pub limit: Option<i64>,
+}
+
+impl TryFrom<SubscribersQuery> for SubscriptionFilter {
+    type Error = Vec<FieldError>;
+
+    fn try_from(query: SubscribersQuery) -> Result<Self, Self::Error> {
+        let SubscribersQuery {
+            status,
+            subscribed_after,
+            subscribed_before,
+            email,
+            cursor,
+            limit,
+        } = query;
+
+        let mut errors = Vec::new();
+
+        let after = cursor
+            .map(SubscriptionCursor::decode)
+            .transpose()
+            .unwrap_or_else(|err| {
+                errors.push(FieldError::new("cursor", err));
+                None
+            });
+
+        let limit = limit.unwrap_or(DEFAULT_LIMIT);
+        if !(1..=MAX_LIMIT).contains(&limit) {
+            errors.push(FieldError::new(
+                "limit",
+                format!("{limit} is not between 1 and {MAX_LIMIT}."),
+            ));
+        }
+
+        if errors.is_empty() {
+            Ok(SubscriptionFilter {
+                status,
+                subscribed_after,
+                subscribed_before,
+                email,
+                after,
+                limit,
+            })
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+/// Query string of the subscription import.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ImportQuery {
+    /// Import lines without a status as pending, and send them a confirmation email.
+    #[serde(default)]
+    pub pending: bool,
+}
+
+/// A page of subscriptions. `next_cursor` is absent on the last page.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SubscribersResp {
+    pub subscribers: Vec<Subscription>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        body::Body,
+        http::{header, Request, StatusCode},
+        middleware::{from_fn_with_state, map_response},
+        routing::{get, post, Router},
+    };
+    use fake::faker::{internet::en::SafeEmail, name::en::Name};
+    use fake::Fake;
+    use hyper::body::to_bytes;
+    use mockall::predicate::*;
+    use secrecy::Secret;
+    use speculoos::prelude::*;
+    use tower::ServiceExt;
+    use tower_cookies::CookieManagerLayer;
+
+    use crate::{
+        application::server::{cookies::JWT, AppState},
+        application::server::{
+            middleware::resolve_context::resolve_context, middleware::response_map::error,
+        },
+        authentication::jwt::build_token,
+        domain::ports::secondary::MockSubscriptionStorage,
+        domain::{SubscriberEmail, SubscriberName, SubscriberPreferences},
+    };
+
+    use super::*;
+    use crate::application::server::TestStateBuilder;
+
+    /// This is a helper function to build an App with axum.
+    fn subscribers_route(state: AppState) -> Router {
+        Router::new()
+            .route("/api/subscribers", get(list_subscribers))
+            .route("/api/subscribers/import", post(import_subscribers))
+            .route("/api/subscribers/export", get(export_subscribers))
+            .route(
+                "/api/subscribers/:id",
+                get(get_subscriber)
+                    .patch(update_subscriber)
+                    .delete(delete_subscriber),
+            )
+            .layer(map_response(error))
+            .layer(from_fn_with_state(state.clone(), resolve_context))
+            .layer(CookieManagerLayer::new())
+            .with_state(state)
+    }
+
+    /// This is a helper function to build a request, authenticated with
+    /// a token for the given user id, if there is one.
+    fn send_request(
+        method: &str,
+        uri: &str,
+        body: Option<serde_json::Value>,
+        id: Option<Uuid>,
+        secret: &Secret<String>,
+    ) -> Request<Body> {
+        let builder = match id {
+            Some(id) => {
+                let token = build_token(id, secret);
+                Request::builder().header(header::COOKIE, format!("{}={}", JWT, token))
+            }
+            None => Request::builder(),
+        };
+        let builder = builder.uri(uri).method(method);
+        match body {
+            Some(body) => builder
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap(),
+            None => builder.body(Body::empty()).unwrap(),
+        }
+    }
+
+    /// This is a helper function to build an application state, with an
+    /// authentication mock that knows about the given user id.
+    fn state_with_user(user_id: Uuid, subscription_mock: MockSubscriptionStorage) -> AppState {
+        TestStateBuilder::new()
+            .user(user_id)
+            .subscription(subscription_mock)
+            .build()
+    }
+
+    fn fake_subscription() -> Subscription {
+        Subscription {
+            id: Uuid::new_v4(),
+            email: SubscriberEmail::parse(SafeEmail().fake::<String>()).unwrap(),
+            username: SubscriberName::parse(Name().fake::<String>()).unwrap(),
+            status: SubscriptionStatus::Confirmed,
+            subscribed_at: Utc::now(),
+            preferences: SubscriberPreferences::default(),
+            attributes: serde_json::json!({}),
+        }
+    }
+
+    #[tokio::test]
+    async fn subscribers_should_require_authentication() {
+        let state = TestStateBuilder::new().build();
+
+        let response = subscribers_route(state.clone())
+            .oneshot(send_request(
+                "GET",
+                "/api/subscribers",
+                None,
+                None,
+                &state.secret,
+            ))
+            .await
+            .expect("response");
+
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn subscribers_should_return_a_cursor_when_there_are_more() {
+        let user_id = Uuid::new_v4();
+        let subscriptions = vec![
+            fake_subscription(),
+            fake_subscription(),
+            fake_subscription(),
+        ];
+        let last = subscriptions[1].clone();
+
+        let mut subscription_mock = MockSubscriptionStorage::new();
+        subscription_mock
+            .expect_list_subscriptions()
+            .withf(|filter: &SubscriptionFilter| {
+                filter.limit == 3
+                    && filter.status == Some(SubscriptionStatus::Confirmed)
+                    && filter.email.as_deref() == Some("acme")
+            })
+            .return_once(move |_| Ok(subscriptions));
+        let state = state_with_user(user_id, subscription_mock);
+
+        let response = subscribers_route(state.clone())
+            .oneshot(send_request(
+                "GET",
+                "/api/subscribers?limit=2&status=confirmed&email=acme",
+                None,
+                Some(user_id),
+                &state.secret,
+            ))
+            .await
+            .expect("response");
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = to_bytes(response.into_body()).await.expect("body");
+        let resp: SubscribersResp = serde_json::from_slice(&body).expect("json");
+        assert_eq!(resp.subscribers.len(), 2);
+        let cursor = SubscriptionCursor::decode(resp.next_cursor.expect("cursor")).unwrap();
+        assert_eq!(cursor.id, last.id);
+    }
+
+    #[tokio::test]
+    async fn subscribers_should_reject_invalid_cursor_and_limit() {
+        let user_id = Uuid::new_v4();
+        let state = state_with_user(user_id, MockSubscriptionStorage::new());
+
+        let response = subscribers_route(state.clone())
+            .oneshot(send_request(
+                "GET",
+                "/api/subscribers?limit=0&cursor=garbage",
+                None,
+                Some(user_id),
+                &state.secret,
+            ))
+            .await
+            .expect("response");
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = to_bytes(response.into_body()).await.expect("body");
+        let problem: serde_json::Value = serde_json::from_slice(&body).expect("json");
+        assert_eq!(problem["code"], "request/invalid");
+        assert_eq!(problem["errors"].as_array().map(Vec::len), Some(2));
+    }
+
+    #[tokio::test]
+    async fn subscriber_should_return_not_found_for_unknown_id() {
+        let user_id = Uuid::new_v4();
+        let id = Uuid::new_v4();
+        let mut subscription_mock = MockSubscriptionStorage::new();
+        subscription_mock
+            .expect_get_subscription_by_id()
+            .with(eq(id))
+            .return_once(|_| Ok(None));
+        let state = state_with_user(user_id, subscription_mock);
+
+        let response = subscribers_route(state.clone())
+            .oneshot(send_request(
+                "GET",
+                &format!("/api/subscribers/{id}"),
+                None,
+                Some(user_id),
+                &state.secret,
+            ))
+            .await
+            .expect("response");
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let body = to_bytes(response.into_body()).await.expect("body");
+        let problem: serde_json::Value = serde_json::from_slice(&body).expect("json");
+        assert_eq!(problem["code"], "subscription/not_found");
+    }
+
+    #[tokio::test]
+    async fn subscriber_update_should_validate_username() {
+        let user_id = Uuid::new_v4();
+        let state = state_with_user(user_id, MockSubscriptionStorage::new());
+        let id = Uuid::new_v4();
+
+        let response = subscribers_route(state.clone())
+            .oneshot(send_request(
+                "PATCH",
+                &format!("/api/subscribers/{id}"),
+                Some(serde_json::json!({ "username": "<script>" })),
+                Some(user_id),
+                &state.secret,
+            ))
+            .await
+            .expect("response");
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn subscriber_update_should_return_the_modified_subscription() {
+        let user_id = Uuid::new_v4();
+        let subscription = fake_subscription();
+        let id = subscription.id;
+        let mut subscription_mock = MockSubscriptionStorage::new();
+        subscription_mock
+            .expect_update_subscription()
+            .withf(move |update_id: &Uuid, update: &SubscriptionUpdate| {
+                update_id == &id
+                    && update.username.is_none()
+                    && update.status == Some(SubscriptionStatus::Confirmed)
+            })
+            .return_once(move |_, _| Ok(Some(subscription)));
+        let state = state_with_user(user_id, subscription_mock);
+
+        let response = subscribers_route(state.clone())
+            .oneshot(send_request(
+                "PATCH",
+                &format!("/api/subscribers/{id}"),
+                Some(serde_json::json!({ "status": "confirmed" })),
+                Some(user_id),
+                &state.secret,
+            ))
+            .await
+            .expect("response");
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = to_bytes(response.into_body()).await.expect("body");
+        let resp: Subscription = serde_json::from_slice(&body).expect("json");
+        assert_that(&resp.id).is_equal_to(id);
+    }
+
+    #[tokio::test]
+    async fn subscriber_delete_should_return_no_content() {
+        let user_id = Uuid::new_v4();
+        let id = Uuid::new_v4();
+        let mut subscription_mock = MockSubscriptionStorage::new();
+        subscription_mock
+            .expect_delete_subscription()
+            .with(eq(id))
+            .return_once(|_| Ok(true));
+        let state = state_with_user(user_id, subscription_mock);
+
+        let response = subscribers_route(state.clone())
+            .oneshot(send_request(
+                "DELETE",
+                &format!("/api/subscribers/{id}"),
+                None,
+                Some(user_id),
+                &state.secret,
+            ))
+            .await
+            .expect("response");
+
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    }
+
+    #[tokio::test]
+    async fn subscribers_import_should_reject_csv_without_email_column() {
+        let user_id = Uuid::new_v4();
+        let state = state_with_user(user_id, MockSubscriptionStorage::new());
+        let token = build_token(user_id, &state.secret);
+
+        let response = subscribers_route(state.clone())
+            .oneshot(
+                Request::builder()
+                    .uri("/api/subscribers/import")
+                    .method("POST")
+                    .header(header::COOKIE, format!("{}={}", JWT, token))
+                    .header(header::CONTENT_TYPE, "text/csv")
+                    .body(Body::from("username\nalice\n"))
+                    .unwrap(),
+            )
+            .await
+            .expect("response");
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = to_bytes(response.into_body()).await.expect("body");
+        let problem: serde_json::Value = serde_json::from_slice(&body).expect("json");
+        assert_eq!(problem["errors"][0]["field"], "file");
+    }
+
+    #[tokio::test]
+    async fn subscribers_export_should_stream_csv() {
+        let user_id = Uuid::new_v4();
+        let subscription = fake_subscription();
+        let email = subscription.email.clone();
+        let mut subscription_mock = MockSubscriptionStorage::new();
+        subscription_mock
+            .expect_list_subscriptions()
+            .return_once(move |_| Ok(vec![subscription]));
+        let state = state_with_user(user_id, subscription_mock);
+
+        let response = subscribers_route(state.clone())
+            .oneshot(send_request(
+                "GET",
+                "/api/subscribers/export",
+                None,
+                Some(user_id),
+                &state.secret,
+            ))
+            .await
+            .expect("response");
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "text/csv");
+        let body = to_bytes(response.into_body()).await.expect("body");
+        let csv = String::from_utf8(body.to_vec()).unwrap();
+        assert_eq!(csv.lines().count(), 2);
+        assert!(csv.contains(email.as_ref()));
+    }
+}
@@ -1,14 +1,26 @@
 use axum::extract::{Json, Query, State};
 use axum::response::IntoResponse;
 use serde::{Deserialize, Serialize};
+use utoipa::IntoParams;
 use uuid::Uuid;
 
-use super::Error;
+use super::{Error, Problem, StatusResp};
 
 use crate::application::server::AppState;
 use common::err_context::ErrorContextExt;
 
 /// POST handler for user subscription confirmation
+#[utoipa::path(
+    post,
+    path = "/subscription_confirmation",
+    tag = "subscriptions",
+    params(SubscriptionConfirmationRequest),
+    responses(
+        (status = 200, description = "Subscription confirmed", body = StatusResp),
+        (status = 404, description = "Unknown token", body = Problem, content_type = "application/problem+json"),
+        (status = 500, description = "Internal error", body = Problem, content_type = "application/problem+json"),
+    )
+)]
 #[allow(clippy::unused_async)]
 #[tracing::instrument(
     name = "Confirming subscription with token"
@@ -31,21 +43,21 @@ pub async fn subscriptions_confirmation(
         None => Err(Error::MissingToken {
             context: "Expected token".to_string(),
         }),
-        Some(id) => {
+        Some((id, list_id)) => {
             state
                 .subscription
-                .confirm_subscriber_by_id_and_delete_token(&id)
+                .confirm_subscriber_by_id_and_delete_token(&id, &list_id)
                 .await
                 .context("Could not confirm subscriber")?;
-            Ok::<_, Error>(Json(serde_json::json!({
-                "status": "success"
-            })))
+            Ok::<_, Error>(Json(StatusResp::success()))
         }
     }
 }
 
-#[derive(Deserialize, Serialize, Debug, Clone)]
+#[derive(Deserialize, Serialize, Debug, Clone, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct SubscriptionConfirmationRequest {
+    /// The token sent in the confirmation email.
     pub token: String,
 }
 
@@ -59,22 +71,21 @@ mod tests {
     };
     use fake::Fake;
     use mockall::predicate::*;
-    use secrecy::Secret;
-    use std::sync::Arc;
     use tower::ServiceExt;
     use tower_cookies::CookieManagerLayer;
 
     use crate::{
+        application::server::AppState,
         application::server::{
             middleware::resolve_context::resolve_context, middleware::response_map::error,
         },
-        application::server::{AppState, ApplicationBaseUrl},
         domain::ports::secondary::{
             MockAuthenticationStorage, MockEmailService, MockSubscriptionStorage,
         },
     };
 
     use super::*;
+    use crate::application::server::TestStateBuilder;
 
     /// This is a helper function to build an App with axum.
     fn subscriptions_confirmation_route(state: AppState) -> Router {
@@ -110,31 +121,30 @@ mod tests {
         // In this test, we use a MockSubscriptionStorage, and we expect that
         // the subscription confirmation handler will trigger a call to
         // Storage::get_subscriber_id_by_token, and then use that id to confirm the
-        // subscriber.
+        // subscriber on the list of the token.
 
         let token = 32.fake::<String>();
         let authentication_mock = MockAuthenticationStorage::new();
         let mut subscription_mock = MockSubscriptionStorage::new();
 
         let id = Uuid::new_v4();
+        let list_id = Uuid::new_v4();
         subscription_mock
             .expect_get_subscriber_id_by_token()
             .with(eq(token.clone()))
-            .return_once(move |_| Ok(Some(id)));
+            .return_once(move |_| Ok(Some((id, list_id))));
         subscription_mock
             .expect_confirm_subscriber_by_id_and_delete_token()
-            .with(eq(id))
-            .return_once(|_| Ok(()));
+            .with(eq(id), eq(list_id))
+            .return_once(|_, _| Ok(()));
 
         let email_mock = MockEmailService::new();
 
-        let state = AppState {
-            authentication: Arc::new(authentication_mock),
-            subscription: Arc::new(subscription_mock),
-            email: Arc::new(email_mock),
-            base_url: ApplicationBaseUrl("http://127.0.0.1".to_string()),
-            secret: Secret::new("secret".to_string()),
-        };
+        let state = TestStateBuilder::new()
+            .authentication(authentication_mock)
+            .subscription(subscription_mock)
+            .email(email_mock)
+            .build();
 
         let app = subscriptions_confirmation_route(state);
 
@@ -156,7 +166,7 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn subscription_confirmation_with_invalid_token_should_return_unauthorized() {
+    async fn subscription_confirmation_with_invalid_token_should_return_not_found() {
         // In this test, we use a MockSubscriptionStorage, and we expect that:
         // - Storage::get_subscriber_id_by_token will get called (it returns None to simulate no
         //   valid token was found)
@@ -175,17 +185,15 @@ mod tests {
         subscription_mock
             .expect_confirm_subscriber_by_id_and_delete_token()
             .never()
-            .return_once(|_| Ok(()));
+            .return_once(|_, _| Ok(()));
 
         let email_mock = MockEmailService::new();
 
-        let state = AppState {
-            authentication: Arc::new(authentication_mock),
-            subscription: Arc::new(subscription_mock),
-            email: Arc::new(email_mock),
-            base_url: ApplicationBaseUrl("http://127.0.0.1".to_string()),
-            secret: Secret::new("secret".to_string()),
-        };
+        let state = TestStateBuilder::new()
+            .authentication(authentication_mock)
+            .subscription(subscription_mock)
+            .email(email_mock)
+            .build();
 
         let app = subscriptions_confirmation_route(state);
 
@@ -198,6 +206,6 @@ mod tests {
             .expect("response");
 
         // Check the response status code.
-        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
     }
 }
@@ -2,18 +2,35 @@ use axum::extract::{Json, State};
 use axum::response::IntoResponse;
 use rand::{distributions::Alphanumeric, thread_rng, Rng};
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 use uuid::Uuid;
 
-use super::Error;
+use super::lists::fetch_list;
+use super::{Error, Problem};
 
+use crate::application::outbox::deliver;
 use crate::application::server::{AppState, ApplicationBaseUrl};
-use crate::domain::ports::secondary::Email;
+use crate::domain::ports::secondary::{Email, SubscriptionError, UNIQUE_SUBSCRIPTION_EMAIL};
 use crate::domain::{
-    NewSubscription, SubscriberEmail, Subscription, SubscriptionRequest, SubscriptionStatus,
+    AlreadySubscribedContext, ConfirmationContext, EmailTemplate, MailingList, NewSubscription,
+    OutboxEmail, SubscriberEmail, SubscriberName, Subscription, SubscriptionRequest,
+    SubscriptionStatus,
 };
-use common::err_context::ErrorContextExt;
+use common::err_context::{ErrorContext, ErrorContextExt};
 
 /// POST handler for user subscriptions
+#[utoipa::path(
+    post,
+    path = "/subscriptions",
+    tag = "subscriptions",
+    request_body = SubscriptionRequest,
+    responses(
+        (status = 200, description = "Subscription created, a confirmation email is sent", body = SubscriptionsResp),
+        (status = 400, description = "Invalid username or email", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Unknown list", body = Problem, content_type = "application/problem+json"),
+        (status = 500, description = "Internal error", body = Problem, content_type = "application/problem+json"),
+    )
+)]
 #[allow(clippy::unused_async)]
 #[tracing::instrument(
     name = "Adding a new subscription"
@@ -26,124 +43,165 @@ pub async fn subscriptions(
     State(state): State<AppState>,
     Json(request): Json<SubscriptionRequest>,
 ) -> Result<impl IntoResponse, Error> {
+    let list_key = request.list.clone();
     let subscription =
         NewSubscription::try_from(request).context("Could not get valid subscription")?;
-
+    let list = fetch_list(&state, list_key.as_deref()).await?;
+
+    // There is no check for a prior subscription beforehand: the email of a
+    // prior subscription violates its unique constraint.
+    let token = generate_subscription_token();
+    let confirmation = OutboxEmail::new(confirmation_email(
+        &state,
+        &subscription.email,
+        &subscription.username,
+        &token,
+    )?);
     match state
         .subscription
-        .get_subscription_by_email(subscription.email.as_ref())
+        .create_subscription_and_store_token(&subscription, &list.id, &token, &confirmation)
         .await
-        .context("Could not get subscription by email")?
     {
-        None => {
-            tracing::info!("No prior subscription found");
-            let token = generate_subscription_token();
-
+        Ok(subscription) => {
+            deliver(&state, &confirmation).await;
+            Ok::<axum::Json<SubscriptionsResp>, Error>(Json(SubscriptionsResp {
+                subscription,
+                list,
+            }))
+        }
+        Err(SubscriptionError::UniqueViolation { constraint, .. })
+            if constraint == UNIQUE_SUBSCRIPTION_EMAIL =>
+        {
+            tracing::info!("Prior subscription found");
             let subscription = state
                 .subscription
-                .create_subscription_and_store_token(&subscription, &token)
-                .await
-                .context("Could not create new subscription")?;
-
-            let email = create_confirmation_email(&state.base_url, &subscription.email, &token);
-
-            state
-                .email
-                .send_email(email)
+                .get_subscription_by_email(subscription.email.as_ref())
                 .await
-                .context("Could not send confirmation email")?;
-
-            let resp = SubscriptionsResp { subscription };
-            Ok::<axum::Json<SubscriptionsResp>, Error>(Json(resp))
-        }
-        Some(subscription) => {
+                .context("Could not get subscription by email")?
+                .ok_or_else(|| Error::MissingSubscription {
+                    context: "The prior subscription was removed".to_string(),
+                })?;
             // FIXME The logic here is probably not very secure. It's not taking the
             // username into account, and more...
-            // Depending on the subscription's status:
+            // Depending on the subscription's status on the list:
+            // * if it is not on the list, then we add it, and send a confirmation email
             // * if it is 'pending_confirmation', then we get the token, and send another
             //   confirmation email
             // * if it is 'confirmed', then we send an email 'already subscribed'
-            match subscription.status {
-                SubscriptionStatus::PendingConfirmation => {
-                    match state
+            match state
+                .subscription
+                .get_list_status(&subscription.id, &list.id)
+                .await
+                .context("Could not get subscription status on list")?
+            {
+                None => {
+                    let token = generate_subscription_token();
+                    let confirmation = OutboxEmail::new(confirmation_email(
+                        &state,
+                        &subscription.email,
+                        &subscription.username,
+                        &token,
+                    )?);
+                    state
                         .subscription
-                        .get_token_by_subscriber_id(&subscription.id)
+                        .subscribe_to_list(&subscription.id, &list.id, &token, &confirmation)
+                        .await
+                        .context("Could not add subscription to list")?;
+                    deliver(&state, &confirmation).await;
+                }
+                Some(SubscriptionStatus::PendingConfirmation) => {
+                    let token = state
+                        .subscription
+                        .get_token_by_subscriber_and_list(&subscription.id, &list.id)
                         .await
                         .context("Could not get token by subscriber's id")?
-                    {
-                        None => Err(Error::MissingToken {
+                        .ok_or_else(|| Error::MissingToken {
                             context: "Expected token".to_string(),
-                        }),
-                        Some(token) => {
-                            let email = create_confirmation_email(
-                                &state.base_url,
-                                &subscription.email,
-                                &token,
-                            );
-
-                            state
-                                .email
-                                .send_email(email)
-                                .await
-                                .context("Could not send confirmation email")?;
-                            let resp = SubscriptionsResp { subscription };
-                            Ok::<axum::Json<SubscriptionsResp>, Error>(Json(resp))
-                        }
-                    }
+                        })?;
+                    let email = confirmation_email(
+                        &state,
+                        &subscription.email,
+                        &subscription.username,
+                        &token,
+                    )?;
+                    send_through_outbox(&state, email).await?;
                 }
-                SubscriptionStatus::Confirmed => {
-                    let email = Email {
-                        to: subscription.email.clone(),
-                        subject: "Already Subscribed".to_string(),
-                        html_content: "You are already subscribed".to_string(),
-                        text_content: "You are already subscribed".to_string(),
-                    };
-                    state
-                        .email
-                        .send_email(email)
-                        .await
-                        .context("Could not send confirmation email")?;
-                    let resp = SubscriptionsResp { subscription };
-                    Ok::<axum::Json<SubscriptionsResp>, Error>(Json(resp))
+                Some(SubscriptionStatus::Confirmed) => {
+                    let template = EmailTemplate::AlreadySubscribed(AlreadySubscribedContext {
+                        username: subscription.username.as_ref().to_string(),
+                    });
+                    let email = state
+                        .templates
+                        .render(&subscription.email, &template)
+                        .context("Could not render already subscribed email")?;
+                    send_through_outbox(&state, email).await?;
                 }
             }
+            Ok::<axum::Json<SubscriptionsResp>, Error>(Json(SubscriptionsResp {
+                subscription,
+                list,
+            }))
         }
+        Err(err) => Err(ErrorContext("Could not create new subscription".to_string(), err).into()),
     }
 }
 
-/// This is a helper function to create an email sent to the subscriber,
-/// which contains a link he needs to use to confirm his subscription.
+/// Renders the email with the link confirming the subscription to a list.
+#[allow(clippy::result_large_err)]
+fn confirmation_email(
+    state: &AppState,
+    email: &SubscriberEmail,
+    username: &SubscriberName,
+    token: &str,
+) -> Result<Email, Error> {
+    let template = confirmation_template(&state.base_url, username, token);
+    let email = state
+        .templates
+        .render(email, &template)
+        .context("Could not render confirmation email")?;
+    Ok(email)
+}
+
+/// Writes the email to the outbox, then tries to send it right away. If it
+/// could not be sent, the relay of the outbox sends it later.
+pub(super) async fn send_through_outbox(state: &AppState, email: Email) -> Result<(), Error> {
+    let email = OutboxEmail::new(email);
+    state
+        .subscription
+        .queue_email(&email)
+        .await
+        .context("Could not write email to the outbox")?;
+    deliver(state, &email).await;
+    Ok(())
+}
+
+/// This is a helper function to create the email sent to the subscriber,
+/// which contains a link they need to use to confirm their subscription.
 /// the url argument is the URL of the zero2prod server, and will be used
 /// as the base for the confirmation link.
-fn create_confirmation_email(url: &ApplicationBaseUrl, to: &SubscriberEmail, token: &str) -> Email {
-    let confirmation_link = format!("{}/api/subscriptions/confirmation?token={}", url, token);
-    let html_content = format!(
-        r#"Welcome to our newsletter!<br/> Click <a href="{}">here</a> to confirm your subscription"#,
-        confirmation_link
-    );
-    let text_content = format!(
-        r#"Welcome to our newsletter!\nVisit {} to confirm your subscription"#,
-        confirmation_link
-    );
-
-    Email {
-        to: to.clone(),
-        subject: "Welcome".to_string(),
-        html_content,
-        text_content,
-    }
+pub(crate) fn confirmation_template(
+    url: &ApplicationBaseUrl,
+    username: &SubscriberName,
+    token: &str,
+) -> EmailTemplate {
+    EmailTemplate::Confirmation(ConfirmationContext {
+        username: username.as_ref().to_string(),
+        confirmation_link: format!("{}/api/subscriptions/confirmation?token={}", url, token),
+    })
 }
 
 /// This is what we return to the user in response to the subscription request.
 /// Currently this is just a placeholder, and it does not return any useful
 /// information.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct SubscriptionsResp {
     pub subscription: Subscription,
+    /// The list the subscription is for.
+    pub list: MailingList,
 }
 
 /// Generates a token (32 Alphanumeric String)
-fn generate_subscription_token() -> String {
+pub(crate) fn generate_subscription_token() -> String {
     let mut rng = thread_rng();
     std::iter::repeat_with(|| rng.sample(Alphanumeric))
         .map(char::from)
@@ -159,14 +217,13 @@ mod tests {
         middleware::{from_fn_with_state, map_response},
         routing::{post, Router},
     };
+    use chrono::Utc;
     use fake::faker::{
         internet::en::{IPv4, SafeEmail},
         name::en::Name,
     };
     use fake::Fake;
     use mockall::predicate::*;
-    use secrecy::Secret;
-    use std::sync::Arc;
     use tower::ServiceExt;
     use tower_cookies::CookieManagerLayer;
 
@@ -175,13 +232,20 @@ mod tests {
             middleware::resolve_context::resolve_context, middleware::response_map::error,
         },
         application::server::{AppState, ApplicationBaseUrl},
+        domain::ports::secondary::Email,
         domain::ports::secondary::{
-            MockAuthenticationStorage, MockEmailService, MockSubscriptionStorage, SubscriptionError,
+            EmailError, MockAuthenticationStorage, MockEmailService, MockSubscriptionStorage,
+            SubscriptionError,
+        },
+        domain::{
+            NewSubscription, SubscriberEmail, SubscriberPreferences, Subscription,
+            SubscriptionStatus, DEFAULT_LIST,
         },
-        domain::{NewSubscription, SubscriberEmail, Subscription, SubscriptionStatus},
+        services::templates::repository_templates,
     };
 
     use super::*;
+    use crate::application::server::TestStateBuilder;
 
     /// This is a helper function to build an App with axum.
     fn subscription_route(state: AppState) -> Router {
@@ -218,6 +282,22 @@ mod tests {
         links[0].as_str().to_owned()
     }
 
+    /// This is a helper function to make the storage find the default list.
+    fn expect_default_list(subscription_mock: &mut MockSubscriptionStorage) {
+        subscription_mock
+            .expect_get_list()
+            .with(eq(DEFAULT_LIST))
+            .returning(|_| {
+                Ok(Some(MailingList {
+                    id: Uuid::new_v4(),
+                    slug: DEFAULT_LIST.to_string(),
+                    name: "Newsletter".to_string(),
+                    tracking: true,
+                    created_at: Utc::now(),
+                }))
+            });
+    }
+
     #[tokio::test]
     async fn subscription_should_store_subscriber_info() {
         // In this test, we use a MockStorage, and we expect that
@@ -228,44 +308,56 @@ mod tests {
         let username = Name().fake::<String>();
         let email = SafeEmail().fake::<String>();
 
-        let request = SubscriptionRequest { username, email };
+        let request = SubscriptionRequest {
+            username,
+            email,
+            list: None,
+        };
 
         let new_subscription = NewSubscription::try_from(request.clone()).unwrap();
 
         let username = new_subscription.username.clone();
         let email = new_subscription.email.clone();
-        let email_clone = email.clone();
         let authentication_mock = MockAuthenticationStorage::new();
         let mut subscription_mock = MockSubscriptionStorage::new();
+        expect_default_list(&mut subscription_mock);
 
         subscription_mock
             .expect_create_subscription_and_store_token()
-            .withf(move |subscription: &NewSubscription, _token: &str| {
-                subscription == &new_subscription
-            })
-            .return_once(move |_, _| {
+            .withf(
+                move |subscription: &NewSubscription,
+                      _list_id: &Uuid,
+                      _token: &str,
+                      confirmation: &OutboxEmail| {
+                    subscription == &new_subscription && confirmation.email.to == subscription.email
+                },
+            )
+            .return_once(move |_, _, _, _| {
                 Ok(Subscription {
                     id: Uuid::new_v4(),
                     username,
                     email,
                     status: SubscriptionStatus::PendingConfirmation,
+                    subscribed_at: Utc::now(),
+                    preferences: SubscriberPreferences::default(),
+                    attributes: serde_json::json!({}),
                 })
             });
+        subscription_mock.expect_get_subscription_by_email().never();
         subscription_mock
-            .expect_get_subscription_by_email()
-            .withf(move |email: &str| email == email_clone.as_ref())
-            .return_once(|_| Ok(None));
+            .expect_complete_outbox_email()
+            .times(1)
+            .return_once(|_| Ok(()));
 
         let mut email_mock = MockEmailService::new();
         email_mock.expect_send_email().return_once(|_| Ok(()));
 
-        let state = AppState {
-            authentication: Arc::new(authentication_mock),
-            subscription: Arc::new(subscription_mock),
-            email: Arc::new(email_mock),
-            base_url: ApplicationBaseUrl("http://127.0.0.1".to_string()),
-            secret: Secret::new("secret".to_string()),
-        };
+        let state = TestStateBuilder::new()
+            .authentication(authentication_mock)
+            .subscription(subscription_mock)
+            .email(email_mock)
+            .templates(repository_templates())
+            .build();
 
         let app = subscription_route(state);
 
@@ -290,10 +382,10 @@ mod tests {
         let request = SubscriptionRequest {
             username,
             email: email_addr.clone(),
+            list: None,
         };
 
         let new_subscription = NewSubscription::try_from(request.clone()).unwrap();
-        let email_clone = new_subscription.email.clone();
 
         let base_url = format!("http://{}", IPv4().fake::<String>());
         let base_url_clone = base_url.clone();
@@ -306,6 +398,7 @@ mod tests {
                     subject: _,
                     html_content,
                     text_content: _,
+                    ..
                 } = email;
 
                 if *to != SubscriberEmail::parse(email_addr.clone()).unwrap() {
@@ -325,30 +418,35 @@ mod tests {
         // We also need a storage mock that returns 'Ok(())'
         let authentication_mock = MockAuthenticationStorage::new();
         let mut subscription_mock = MockSubscriptionStorage::new();
+        expect_default_list(&mut subscription_mock);
 
         subscription_mock
             .expect_create_subscription_and_store_token()
-            .return_once(move |_, _| {
+            .return_once(move |_, _, _, _| {
                 Ok(Subscription {
                     id: Uuid::new_v4(),
                     username: new_subscription.username,
                     email: new_subscription.email,
                     status: SubscriptionStatus::PendingConfirmation,
+                    subscribed_at: Utc::now(),
+                    preferences: SubscriberPreferences::default(),
+                    attributes: serde_json::json!({}),
                 })
             });
 
+        subscription_mock.expect_get_subscription_by_email().never();
         subscription_mock
-            .expect_get_subscription_by_email()
-            .withf(move |email: &str| email == email_clone.as_ref())
-            .return_once(|_| Ok(None));
-
-        let state = AppState {
-            authentication: Arc::new(authentication_mock),
-            subscription: Arc::new(subscription_mock),
-            email: Arc::new(email_mock),
-            base_url: ApplicationBaseUrl(base_url),
-            secret: Secret::new("secret".to_string()),
-        };
+            .expect_complete_outbox_email()
+            .times(1)
+            .return_once(|_| Ok(()));
+
+        let state = TestStateBuilder::new()
+            .authentication(authentication_mock)
+            .subscription(subscription_mock)
+            .email(email_mock)
+            .templates(repository_templates())
+            .base_url(ApplicationBaseUrl(base_url))
+            .build();
 
         let app = subscription_route(state);
 
@@ -371,7 +469,11 @@ mod tests {
         let username = Name().fake::<String>();
         let email = SafeEmail().fake::<String>();
 
-        let request = SubscriptionRequest { username, email };
+        let request = SubscriptionRequest {
+            username,
+            email,
+            list: None,
+        };
 
         let new_subscription = NewSubscription::try_from(request.clone()).unwrap();
 
@@ -379,32 +481,36 @@ mod tests {
         // sense.
         let authentication_mock = MockAuthenticationStorage::new();
         let mut subscription_mock = MockSubscriptionStorage::new();
+        expect_default_list(&mut subscription_mock);
 
         subscription_mock
             .expect_create_subscription_and_store_token()
-            .withf(move |subscription: &NewSubscription, _token: &str| {
-                subscription == &new_subscription
-            })
-            .return_once(|_, _| {
+            .withf(
+                move |subscription: &NewSubscription,
+                      _list_id: &Uuid,
+                      _token: &str,
+                      confirmation: &OutboxEmail| {
+                    subscription == &new_subscription && confirmation.email.to == subscription.email
+                },
+            )
+            .return_once(|_, _, _, _| {
                 Err(SubscriptionError::Database {
                     context: "subscription context".to_string(),
                     source: sqlx::Error::RowNotFound.to_string(),
+                    retryable: false,
                 })
             });
-        subscription_mock
-            .expect_get_subscription_by_email()
-            .return_once(|_| Ok(None));
+        subscription_mock.expect_get_subscription_by_email().never();
 
         let mut email_mock = MockEmailService::new();
         email_mock.expect_send_email().return_once(|_| Ok(()));
 
-        let state = AppState {
-            authentication: Arc::new(authentication_mock),
-            subscription: Arc::new(subscription_mock),
-            email: Arc::new(email_mock),
-            base_url: ApplicationBaseUrl("http://127.0.0.1".to_string()),
-            secret: Secret::new("secret".to_string()),
-        };
+        let state = TestStateBuilder::new()
+            .authentication(authentication_mock)
+            .subscription(subscription_mock)
+            .email(email_mock)
+            .templates(repository_templates())
+            .build();
 
         let app = subscription_route(state);
 
@@ -422,20 +528,23 @@ mod tests {
         let username = Name().fake::<String>();
         let email = username.clone();
 
-        let request = SubscriptionRequest { username, email };
+        let request = SubscriptionRequest {
+            username,
+            email,
+            list: None,
+        };
 
         let authentication_mock = MockAuthenticationStorage::new();
         let subscription_mock = MockSubscriptionStorage::new();
 
         let email_mock = MockEmailService::new();
 
-        let state = AppState {
-            authentication: Arc::new(authentication_mock),
-            subscription: Arc::new(subscription_mock),
-            email: Arc::new(email_mock),
-            base_url: ApplicationBaseUrl("http://127.0.0.1".to_string()),
-            secret: Secret::new("secret".to_string()),
-        };
+        let state = TestStateBuilder::new()
+            .authentication(authentication_mock)
+            .subscription(subscription_mock)
+            .email(email_mock)
+            .templates(repository_templates())
+            .build();
 
         let app = subscription_route(state);
 
@@ -446,5 +555,157 @@ mod tests {
 
         // Check the response status code.
         assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        // Check the response reports the invalid field.
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let problem: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(problem["code"], "request/invalid");
+        assert_eq!(problem["errors"][0]["field"], "email");
+    }
+
+    #[tokio::test]
+    async fn subscription_to_another_list_should_be_confirmed_separately() {
+        // In this test, the subscriber is already confirmed on the default list,
+        // and subscribes to another list: the subscription is added to that list,
+        // and a confirmation email is sent.
+        let subscription = Subscription {
+            id: Uuid::new_v4(),
+            username: SubscriberName::parse(Name().fake::<String>()).unwrap(),
+            email: SubscriberEmail::parse(SafeEmail().fake::<String>()).unwrap(),
+            status: SubscriptionStatus::Confirmed,
+            subscribed_at: Utc::now(),
+            preferences: SubscriberPreferences::default(),
+            attributes: serde_json::json!({}),
+        };
+        let id = subscription.id;
+        let request = SubscriptionRequest {
+            username: subscription.username.as_ref().to_string(),
+            email: subscription.email.as_ref().to_string(),
+            list: Some("rust".to_string()),
+        };
+        let list = MailingList {
+            id: Uuid::new_v4(),
+            slug: "rust".to_string(),
+            name: "Rust".to_string(),
+            tracking: true,
+            created_at: Utc::now(),
+        };
+        let list_id = list.id;
+
+        let mut subscription_mock = MockSubscriptionStorage::new();
+        subscription_mock
+            .expect_get_list()
+            .with(eq("rust"))
+            .return_once(move |_| Ok(Some(list)));
+        // The subscription is already there, which the storage reports.
+        subscription_mock
+            .expect_create_subscription_and_store_token()
+            .times(1)
+            .return_once(|_, _, _, _| {
+                Err(SubscriptionError::UniqueViolation {
+                    context: "Could not store new subscription".to_string(),
+                    constraint: UNIQUE_SUBSCRIPTION_EMAIL.to_string(),
+                })
+            });
+        subscription_mock
+            .expect_get_subscription_by_email()
+            .return_once(move |_| Ok(Some(subscription)));
+        subscription_mock
+            .expect_get_list_status()
+            .with(eq(id), eq(list_id))
+            .return_once(|_, _| Ok(None));
+        subscription_mock
+            .expect_subscribe_to_list()
+            .withf(
+                move |sid: &Uuid, lid: &Uuid, _token: &str, _: &OutboxEmail| {
+                    sid == &id && lid == &list_id
+                },
+            )
+            .times(1)
+            .return_once(|_, _, _, _| Ok(()));
+        subscription_mock
+            .expect_complete_outbox_email()
+            .times(1)
+            .return_once(|_| Ok(()));
+
+        let mut email_mock = MockEmailService::new();
+        email_mock
+            .expect_send_email()
+            .withf(|email: &Email| {
+                email
+                    .text_content
+                    .contains("/api/subscriptions/confirmation")
+            })
+            .times(1)
+            .return_once(|_| Ok(()));
+
+        let state = TestStateBuilder::new()
+            .subscription(subscription_mock)
+            .email(email_mock)
+            .templates(repository_templates())
+            .build();
+
+        let response = subscription_route(state)
+            .oneshot(send_subscription_request("/api/subscriptions", request))
+            .await
+            .expect("response");
+
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn subscription_should_succeed_and_keep_the_confirmation_if_the_email_fails() {
+        // The confirmation is in the outbox with the subscription, so the relay
+        // sends it later if it cannot be sent right away.
+        let request = SubscriptionRequest {
+            username: Name().fake::<String>(),
+            email: SafeEmail().fake::<String>(),
+            list: None,
+        };
+        let new_subscription = NewSubscription::try_from(request.clone()).unwrap();
+
+        let mut subscription_mock = MockSubscriptionStorage::new();
+        expect_default_list(&mut subscription_mock);
+        subscription_mock.expect_get_subscription_by_email().never();
+        subscription_mock
+            .expect_create_subscription_and_store_token()
+            .times(1)
+            .return_once(move |_, _, _, _| {
+                Ok(Subscription {
+                    id: Uuid::new_v4(),
+                    username: new_subscription.username,
+                    email: new_subscription.email,
+                    status: SubscriptionStatus::PendingConfirmation,
+                    subscribed_at: Utc::now(),
+                    preferences: SubscriberPreferences::default(),
+                    attributes: serde_json::json!({}),
+                })
+            });
+        subscription_mock.expect_complete_outbox_email().never();
+        subscription_mock
+            .expect_fail_outbox_email()
+            .withf(|_, _, retry_at| retry_at.is_some())
+            .times(1)
+            .return_once(|_, _, _| Ok(()));
+
+        let mut email_mock = MockEmailService::new();
+        email_mock.expect_send_email().return_once(|_| {
+            Err(EmailError::Unavailable {
+                context: "Postmark is down".to_string(),
+            })
+        });
+
+        let state = TestStateBuilder::new()
+            .subscription(subscription_mock)
+            .email(email_mock)
+            .templates(repository_templates())
+            .build();
+
+        let response = subscription_route(state)
+            .oneshot(send_subscription_request("/api/subscriptions", request))
+            .await
+            .expect("response");
+
+        assert_eq!(response.status(), StatusCode::OK);
     }
 }
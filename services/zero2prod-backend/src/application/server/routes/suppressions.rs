@@ -0,0 +1,393 @@
+use axum::extract::{Json, Path, Query, State};
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::Extension;
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use super::{authorize, Error, Problem};
+
+use crate::application::server::middleware::resolve_context::Error as ContextResolutionError;
+use crate::application::server::{context::Context, AppState};
+use crate::domain::{
+    FieldError, SubscriberEmail, Suppression, SuppressionReason, SuppressionRequest,
+};
+use common::err_context::ErrorContextExt;
+
+/// Source recorded with the suppressions made by an admin.
+const ADMIN_SOURCE: &str = "admin";
+
+/// GET handler for listing the suppressed addresses
+#[utoipa::path(
+    get,
+    path = "/suppressions",
+    tag = "suppressions",
+    params(SuppressionsQuery),
+    security(("jwt" = [])),
+    responses(
+        (status = 200, description = "The suppressions, latest first", body = SuppressionsResp),
+        (status = 401, description = "Missing or invalid JWT cookie", body = Problem, content_type = "application/problem+json"),
+        (status = 500, description = "Internal error", body = Problem, content_type = "application/problem+json"),
+    )
+)]
+#[allow(clippy::unused_async)]
+#[tracing::instrument(
+    name = "Listing suppressions"
+    skip(state, context),
+    fields(
+        request_id = %Uuid::new_v4(),
+    )
+)]
+pub async fn list_suppressions(
+    Extension(context): Extension<Result<Context, ContextResolutionError>>,
+    State(state): State<AppState>,
+    Query(query): Query<SuppressionsQuery>,
+) -> Result<impl IntoResponse, Error> {
+    authorize(context)?;
+
+    let suppressions = state
+        .subscription
+        .list_suppressions(query.reason)
+        .await
+        .context("Could not list suppressions")?;
+
+    Ok::<_, Error>(Json(SuppressionsResp { suppressions }))
+}
+
+/// POST handler for suppressing an address
+/// Suppressing an address already suppressed keeps the original suppression.
+#[utoipa::path(
+    post,
+    path = "/suppressions",
+    tag = "suppressions",
+    request_body = SuppressionRequest,
+    security(("jwt" = [])),
+    responses(
+        (status = 201, description = "The suppression", body = Suppression),
+        (status = 400, description = "Invalid email", body = Problem, content_type = "application/problem+json"),
+        (status = 401, description = "Missing or invalid JWT cookie", body = Problem, content_type = "application/problem+json"),
+        (status = 500, description = "Internal error", body = Problem, content_type = "application/problem+json"),
+    )
+)]
+#[allow(clippy::unused_async)]
+#[tracing::instrument(
+    name = "Suppressing an email"
+    skip(state, context, request),
+    fields(
+        request_id = %Uuid::new_v4(),
+    )
+)]
+pub async fn create_suppression(
+    Extension(context): Extension<Result<Context, ContextResolutionError>>,
+    State(state): State<AppState>,
+    Json(request): Json<SuppressionRequest>,
+) -> Result<impl IntoResponse, Error> {
+    authorize(context)?;
+    let email = parse_email(&request.email)?;
+
+    state
+        .subscription
+        .suppress_email(&email, SuppressionReason::Manual, ADMIN_SOURCE)
+        .await
+        .context("Could not suppress email")?;
+    let suppression = state
+        .subscription
+        .get_suppressions(std::slice::from_ref(&email))
+        .await
+        .context("Could not get suppression")?
+        .pop()
+        .ok_or_else(|| Error::MissingSuppression {
+            context: "No suppression for the email".to_string(),
+        })?;
+
+    Ok::<_, Error>((StatusCode::CREATED, Json(suppression)))
+}
+
+/// DELETE handler for lifting the suppression of an address
+#[utoipa::path(
+    delete,
+    path = "/suppressions/{email}",
+    tag = "suppressions",
+    params(("email" = String, Path, description = "Suppressed email")),
+    security(("jwt" = [])),
+    responses(
+        (status = 204, description = "The address receives emails again"),
+        (status = 400, description = "Invalid email", body = Problem, content_type = "application/problem+json"),
+        (status = 401, description = "Missing or invalid JWT cookie", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "The address is not suppressed", body = Problem, content_type = "application/problem+json"),
+        (status = 500, description = "Internal error", body = Problem, content_type = "application/problem+json"),
+    )
+)]
+#[allow(clippy::unused_async)]
+#[tracing::instrument(
+    name = "Removing a suppression"
+    skip(state, context, email),
+    fields(
+        request_id = %Uuid::new_v4(),
+    )
+)]
+pub async fn delete_suppression(
+    Extension(context): Extension<Result<Context, ContextResolutionError>>,
+    State(state): State<AppState>,
+    Path(email): Path<String>,
+) -> Result<impl IntoResponse, Error> {
+    authorize(context)?;
+    let email = parse_email(&email)?;
+
+    let removed = state
+        .subscription
+        .remove_suppression(&email)
+        .await
+        .context("Could not remove suppression")?;
+
+    if removed {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(Error::MissingSuppression {
+            context: "The email is not suppressed".to_string(),
+        })
+    }
+}
+
+#[allow(clippy::result_large_err)]
+fn parse_email(email: &str) -> Result<SubscriberEmail, Error> {
+    SubscriberEmail::parse(email).map_err(|err| Error::InvalidRequest {
+        context: "Invalid email".to_string(),
+        source: vec![FieldError::new("email", err)],
+    })
+}
+
+/// Query string of the suppression listing.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SuppressionsQuery {
+    /// Only return suppressions for this reason.
+    pub reason: Option<SuppressionReason>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SuppressionsResp {
+    pub suppressions: Vec<Suppression>,
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        body::Body,
+        http::{header, Request},
+        middleware::{from_fn_with_state, map_response},
+        routing::{delete, get, Router},
+    };
+    use chrono::Utc;
+    use hyper::body::to_bytes;
+    use mockall::predicate::*;
+    use secrecy::Secret;
+    use tower::ServiceExt;
+    use tower_cookies::CookieManagerLayer;
+
+    use crate::{
+        application::server::cookies::JWT,
+        application::server::{
+            middleware::resolve_context::resolve_context, middleware::response_map::error,
+        },
+        authentication::jwt::build_token,
+        domain::ports::secondary::MockSubscriptionStorage,
+    };
+
+    use super::*;
+    use crate::application::server::TestStateBuilder;
+
+    /// This is a helper function to build an App with axum.
+    fn suppressions_route(state: AppState) -> Router {
+        Router::new()
+            .route(
+                "/api/suppressions",
+                get(list_suppressions).post(create_suppression),
+            )
+            .route("/api/suppressions/:email", delete(delete_suppression))
+            .layer(map_response(error))
+            .layer(from_fn_with_state(state.clone(), resolve_context))
+            .layer(CookieManagerLayer::new())
+            .with_state(state)
+    }
+
+    /// This is a helper function to build a request, authenticated with
+    /// a token for the given user id, if there is one.
+    fn send_request(
+        method: &str,
+        uri: &str,
+        body: Option<serde_json::Value>,
+        id: Option<Uuid>,
+        secret: &Secret<String>,
+    ) -> Request<Body> {
+        let builder = match id {
+            Some(id) => {
+                let token = build_token(id, secret);
+                Request::builder().header(header::COOKIE, format!("{}={}", JWT, token))
+            }
+            None => Request::builder(),
+        };
+        let builder = builder.uri(uri).method(method);
+        match body {
+            Some(body) => builder
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap(),
+            None => builder.body(Body::empty()).unwrap(),
+        }
+    }
+
+    /// This is a helper function to build an application state, with an
+    /// authentication mock that knows about the given user id.
+    fn state_with_user(user_id: Uuid, subscription_mock: MockSubscriptionStorage) -> AppState {
+        TestStateBuilder::new()
+            .user(user_id)
+            .subscription(subscription_mock)
+            .build()
+    }
+
+    fn suppression(email: &str, reason: SuppressionReason) -> Suppression {
+        Suppression {
+            email: SubscriberEmail::parse(email).unwrap(),
+            reason,
+            source: ADMIN_SOURCE.to_string(),
+            suppressed_at: Utc::now(),
+        }
+    }
+
+    #[tokio::test]
+    async fn list_should_filter_suppressions_by_reason() {
+        let user_id = Uuid::new_v4();
+        let mut subscription_mock = MockSubscriptionStorage::new();
+        subscription_mock
+            .expect_list_suppressions()
+            .with(eq(Some(SuppressionReason::SpamComplaint)))
+            .times(1)
+            .returning(|_| {
+                Ok(vec![suppression(
+                    "bob@acme.inc",
+                    SuppressionReason::SpamComplaint,
+                )])
+            });
+        let state = state_with_user(user_id, subscription_mock);
+
+        let request = send_request(
+            "GET",
+            "/api/suppressions?reason=spam_complaint",
+            None,
+            Some(user_id),
+            &state.secret,
+        );
+        let response = suppressions_route(state)
+            .oneshot(request)
+            .await
+            .expect("response");
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = to_bytes(response.into_body()).await.unwrap();
+        let body: SuppressionsResp = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body.suppressions.len(), 1);
+        assert_eq!(body.suppressions[0].email.as_ref(), "bob@acme.inc");
+    }
+
+    #[tokio::test]
+    async fn create_should_suppress_the_email_manually() {
+        let user_id = Uuid::new_v4();
+        let mut subscription_mock = MockSubscriptionStorage::new();
+        subscription_mock
+            .expect_suppress_email()
+            .with(
+                eq(SubscriberEmail::parse("alice@acme.inc").unwrap()),
+                eq(SuppressionReason::Manual),
+                eq(ADMIN_SOURCE),
+            )
+            .times(1)
+            .returning(|_, _, _| Ok(()));
+        subscription_mock
+            .expect_get_suppressions()
+            .times(1)
+            .returning(|_| {
+                Ok(vec![suppression(
+                    "alice@acme.inc",
+                    SuppressionReason::Manual,
+                )])
+            });
+        let state = state_with_user(user_id, subscription_mock);
+
+        let request = send_request(
+            "POST",
+            "/api/suppressions",
+            Some(serde_json::json!({ "email": "alice@acme.inc" })),
+            Some(user_id),
+            &state.secret,
+        );
+        let response = suppressions_route(state)
+            .oneshot(request)
+            .await
+            .expect("response");
+
+        assert_eq!(response.status(), StatusCode::CREATED);
+    }
+
+    #[tokio::test]
+    async fn create_should_refuse_an_invalid_email() {
+        let user_id = Uuid::new_v4();
+        let mut subscription_mock = MockSubscriptionStorage::new();
+        subscription_mock.expect_suppress_email().never();
+        let state = state_with_user(user_id, subscription_mock);
+
+        let request = send_request(
+            "POST",
+            "/api/suppressions",
+            Some(serde_json::json!({ "email": "not an email" })),
+            Some(user_id),
+            &state.secret,
+        );
+        let response = suppressions_route(state)
+            .oneshot(request)
+            .await
+            .expect("response");
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn delete_should_report_an_address_not_suppressed() {
+        let user_id = Uuid::new_v4();
+        let mut subscription_mock = MockSubscriptionStorage::new();
+        subscription_mock
+            .expect_remove_suppression()
+            .with(eq(SubscriberEmail::parse("carol@acme.inc").unwrap()))
+            .times(1)
+            .returning(|_| Ok(false));
+        let state = state_with_user(user_id, subscription_mock);
+
+        let request = send_request(
+            "DELETE",
+            "/api/suppressions/carol@acme.inc",
+            None,
+            Some(user_id),
+            &state.secret,
+        );
+        let response = suppressions_route(state)
+            .oneshot(request)
+            .await
+            .expect("response");
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn suppressions_should_require_authentication() {
+        let state = state_with_user(Uuid::new_v4(), MockSubscriptionStorage::new());
+
+        let request = send_request("GET", "/api/suppressions", None, None, &state.secret);
+        let response = suppressions_route(state)
+            .oneshot(request)
+            .await
+            .expect("response");
+
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+}
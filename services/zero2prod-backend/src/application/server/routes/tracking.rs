@@ -0,0 +1,295 @@
+use axum::extract::{Query, State};
+use axum::http::header;
+use axum::response::{IntoResponse, Redirect};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use utoipa::IntoParams;
+use uuid::Uuid;
+
+use super::{Error, Problem};
+
+use crate::application::server::AppState;
+use crate::authentication::jwt::validate_tracking_token;
+use crate::domain::{TrackingEvent, TrackingEventKind};
+
+/// Prefix of the headers whose values the email service reports with the
+/// events of the email, as its metadata.
+pub(crate) const METADATA_HEADER_PREFIX: &str = "X-PM-Metadata-";
+/// Metadata key of the id of a tracked issue.
+pub(crate) const ISSUE_METADATA: &str = "issue_id";
+/// Metadata key of the id of the recipient of a tracked issue.
+pub(crate) const SUBSCRIBER_METADATA: &str = "subscriber_id";
+
+/// A transparent, 1x1 GIF.
+const PIXEL: [u8; 43] = [
+    0x47, 0x49, 0x46, 0x38, 0x39, 0x61, 0x01, 0x00, 0x01, 0x00, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0xff, 0xff, 0xff, 0x21, 0xf9, 0x04, 0x01, 0x00, 0x00, 0x00, 0x00, 0x2c, 0x00, 0x00, 0x00, 0x00,
+    0x01, 0x00, 0x01, 0x00, 0x00, 0x02, 0x02, 0x44, 0x01, 0x00, 0x3b,
+];
+
+/// GET handler for the tracking pixel of tracked issues
+/// The open is recorded if the token is valid. The pixel is returned in any
+/// case, so that the email displays the same.
+#[utoipa::path(
+    get,
+    path = "/tracking/open",
+    tag = "tracking",
+    params(TrackingQuery),
+    responses(
+        (status = 200, description = "A transparent pixel", body = String, content_type = "image/gif"),
+    )
+)]
+#[allow(clippy::unused_async)]
+#[tracing::instrument(
+    name = "Tracking an open"
+    skip(state),
+    fields(
+        request_id = %Uuid::new_v4(),
+    )
+)]
+pub async fn track_open(
+    State(state): State<AppState>,
+    Query(query): Query<TrackingQuery>,
+) -> impl IntoResponse {
+    match validate_tracking_token(&query.token, &state.secret) {
+        Ok(token) => {
+            let event =
+                TrackingEvent::new(token.issue_id, token.subscriber_id, TrackingEventKind::Open);
+            if let Err(err) = state.issues.record_tracking_events(&[event]).await {
+                tracing::error!("Could not record open: {err}");
+            }
+        }
+        Err(err) => tracing::info!("Invalid tracking token: {err}"),
+    }
+
+    (
+        [
+            (header::CONTENT_TYPE, "image/gif"),
+            (header::CACHE_CONTROL, "no-store"),
+        ],
+        PIXEL,
+    )
+}
+
+/// GET handler for the tracked links of tracked issues
+/// The click is recorded, and the recipient redirected to the link signed in
+/// the token.
+#[utoipa::path(
+    get,
+    path = "/tracking/click",
+    tag = "tracking",
+    params(TrackingQuery),
+    responses(
+        (status = 303, description = "Redirection to the link"),
+        (status = 401, description = "Invalid token", body = Problem, content_type = "application/problem+json"),
+    )
+)]
+#[allow(clippy::unused_async)]
+#[tracing::instrument(
+    name = "Tracking a click"
+    skip(state),
+    fields(
+        request_id = %Uuid::new_v4(),
+    )
+)]
+pub async fn track_click(
+    State(state): State<AppState>,
+    Query(query): Query<TrackingQuery>,
+) -> Result<impl IntoResponse, Error> {
+    let token = validate_tracking_token(&query.token, &state.secret).map_err(|err| {
+        Error::InvalidToken {
+            context: format!("Could not validate tracking token: {err}"),
+        }
+    })?;
+    let url = token.url.ok_or_else(|| Error::InvalidToken {
+        context: "The tracking token has no link".to_string(),
+    })?;
+
+    let event = TrackingEvent {
+        url: Some(url.clone()),
+        ..TrackingEvent::new(
+            token.issue_id,
+            token.subscriber_id,
+            TrackingEventKind::Click,
+        )
+    };
+    if let Err(err) = state.issues.record_tracking_events(&[event]).await {
+        tracing::error!("Could not record click: {err}");
+    }
+
+    Ok::<_, Error>(Redirect::to(&url))
+}
+
+/// The bounce of a tracked issue, identified by the metadata of the bounced
+/// email, if it has any.
+pub(crate) fn bounce_event(metadata: &HashMap<String, String>) -> Option<TrackingEvent> {
+    let issue_id = Uuid::parse_str(metadata.get(ISSUE_METADATA)?).ok()?;
+    let subscriber_id = Uuid::parse_str(metadata.get(SUBSCRIBER_METADATA)?).ok()?;
+    Some(TrackingEvent::new(
+        issue_id,
+        subscriber_id,
+        TrackingEventKind::Bounce,
+    ))
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct TrackingQuery {
+    /// The token of the tracking pixel, or link, of a tracked issue.
+    pub token: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        body::Body,
+        http::{Request, StatusCode},
+        routing::{get, Router},
+    };
+
+    use tower::ServiceExt;
+
+    use crate::{
+        authentication::jwt::build_tracking_token,
+        domain::ports::secondary::{IssueError, MockIssueStorage},
+    };
+
+    use super::*;
+    use crate::application::server::TestStateBuilder;
+
+    fn tracking_route(state: AppState) -> Router {
+        Router::new()
+            .route("/api/tracking/open", get(track_open))
+            .route("/api/tracking/click", get(track_click))
+            .with_state(state)
+    }
+
+    fn state(issues_mock: MockIssueStorage) -> AppState {
+        TestStateBuilder::new().issues(issues_mock).build()
+    }
+
+    fn get_request(uri: &str) -> Request<Body> {
+        Request::builder()
+            .uri(uri)
+            .method("GET")
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn track_open_should_record_the_open_and_return_a_pixel() {
+        let (subscriber_id, issue_id) = (Uuid::new_v4(), Uuid::new_v4());
+        let mut issues_mock = MockIssueStorage::new();
+        issues_mock
+            .expect_record_tracking_events()
+            .withf(move |events: &[TrackingEvent]| {
+                events.len() == 1
+                    && events[0].kind == TrackingEventKind::Open
+                    && events[0].issue_id == issue_id
+                    && events[0].subscriber_id == subscriber_id
+            })
+            .times(1)
+            .returning(|_| Ok(()));
+        let state = state(issues_mock);
+        let token = build_tracking_token(subscriber_id, issue_id, None, &state.secret);
+
+        let response = tracking_route(state)
+            .oneshot(get_request(&format!("/api/tracking/open?token={token}")))
+            .await
+            .expect("response");
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "image/gif");
+    }
+
+    #[tokio::test]
+    async fn track_open_should_return_a_pixel_for_invalid_tokens() {
+        let mut issues_mock = MockIssueStorage::new();
+        issues_mock.expect_record_tracking_events().never();
+
+        let response = tracking_route(state(issues_mock))
+            .oneshot(get_request("/api/tracking/open?token=forged"))
+            .await
+            .expect("response");
+
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn track_click_should_record_the_click_and_redirect_to_the_link() {
+        let mut issues_mock = MockIssueStorage::new();
+        issues_mock
+            .expect_record_tracking_events()
+            .withf(|events: &[TrackingEvent]| {
+                events[0].kind == TrackingEventKind::Click
+                    && events[0].url.as_deref() == Some("https://acme.inc/news?a=1&b=2")
+            })
+            .times(1)
+            .returning(|_| Ok(()));
+        let state = state(issues_mock);
+        let token = build_tracking_token(
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            Some("https://acme.inc/news?a=1&b=2"),
+            &state.secret,
+        );
+
+        let response = tracking_route(state)
+            .oneshot(get_request(&format!("/api/tracking/click?token={token}")))
+            .await
+            .expect("response");
+
+        assert_eq!(response.status(), StatusCode::SEE_OTHER);
+        assert_eq!(
+            response.headers()[header::LOCATION],
+            "https://acme.inc/news?a=1&b=2"
+        );
+    }
+
+    #[tokio::test]
+    async fn track_click_should_redirect_even_if_the_click_can_not_be_recorded() {
+        let mut issues_mock = MockIssueStorage::new();
+        issues_mock
+            .expect_record_tracking_events()
+            .times(1)
+            .returning(|_| {
+                Err(IssueError::Connection {
+                    context: "Could not record tracking events".to_string(),
+                    source: "pool timed out".to_string(),
+                })
+            });
+        let state = state(issues_mock);
+        let token = build_tracking_token(
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            Some("https://acme.inc/news"),
+            &state.secret,
+        );
+
+        let response = tracking_route(state)
+            .oneshot(get_request(&format!("/api/tracking/click?token={token}")))
+            .await
+            .expect("response");
+
+        assert_eq!(response.status(), StatusCode::SEE_OTHER);
+        assert_eq!(
+            response.headers()[header::LOCATION],
+            "https://acme.inc/news"
+        );
+    }
+
+    #[tokio::test]
+    async fn track_click_should_refuse_tokens_without_link() {
+        let mut issues_mock = MockIssueStorage::new();
+        issues_mock.expect_record_tracking_events().never();
+        let state = state(issues_mock);
+        let token = build_tracking_token(Uuid::new_v4(), Uuid::new_v4(), None, &state.secret);
+
+        let response = tracking_route(state)
+            .oneshot(get_request(&format!("/api/tracking/click?token={token}")))
+            .await
+            .expect("response");
+
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+}
@@ -0,0 +1,184 @@
+use axum::extract::{Json, Query, State};
+use axum::response::IntoResponse;
+use serde::{Deserialize, Serialize};
+use utoipa::IntoParams;
+use uuid::Uuid;
+
+use super::{Error, Problem, StatusResp};
+
+use crate::application::server::AppState;
+use crate::authentication::jwt::validate_unsubscribe_token;
+use common::err_context::ErrorContextExt;
+
+/// GET and POST handler for the unsubscribe link found in every newsletter
+/// The subscription is removed from the list the newsletter was sent to, and
+/// deleted once it is on no list. Following the link again succeeds, so that
+/// a subscriber clicking twice does not see an error.
+#[utoipa::path(
+    method(get, post),
+    path = "/subscriptions/unsubscribe",
+    tag = "subscriptions",
+    params(UnsubscribeQuery),
+    responses(
+        (status = 200, description = "The subscription is no longer on the list", body = StatusResp),
+        (status = 401, description = "Invalid or expired token", body = Problem, content_type = "application/problem+json"),
+        (status = 500, description = "Internal error", body = Problem, content_type = "application/problem+json"),
+    )
+)]
+#[allow(clippy::unused_async)]
+#[tracing::instrument(
+    name = "Unsubscribing"
+    skip(state),
+    fields(
+        request_id = %Uuid::new_v4(),
+    )
+)]
+pub async fn unsubscribe(
+    State(state): State<AppState>,
+    Query(query): Query<UnsubscribeQuery>,
+) -> Result<impl IntoResponse, Error> {
+    let (id, list_id) = validate_unsubscribe_token(&query.token, &state.secret).map_err(|err| {
+        Error::InvalidToken {
+            context: format!("Could not validate unsubscribe token: {err}"),
+        }
+    })?;
+
+    match list_id {
+        Some(list_id) => state
+            .subscription
+            .unsubscribe_from_list(&id, &list_id)
+            .await
+            .context("Could not remove subscription from list")?,
+        None => state
+            .subscription
+            .delete_subscription(&id)
+            .await
+            .context("Could not delete subscription")?,
+    };
+
+    Ok::<_, Error>(Json(StatusResp::success()))
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct UnsubscribeQuery {
+    /// The token found in the unsubscribe link of a newsletter.
+    pub token: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        body::Body,
+        http::{Request, StatusCode},
+        routing::{get, Router},
+    };
+    use mockall::predicate::*;
+    use tower::ServiceExt;
+
+    use crate::{
+        authentication::jwt::{build_subscriber_token, build_unsubscribe_token, SubscriberScope},
+        domain::ports::secondary::MockSubscriptionStorage,
+    };
+
+    use super::*;
+    use crate::application::server::TestStateBuilder;
+
+    /// This is a helper function to build an App with axum.
+    fn unsubscribe_route(state: AppState) -> Router {
+        Router::new()
+            .route(
+                "/api/subscriptions/unsubscribe",
+                get(unsubscribe).post(unsubscribe),
+            )
+            .with_state(state)
+    }
+
+    fn state(subscription_mock: MockSubscriptionStorage) -> AppState {
+        TestStateBuilder::new()
+            .subscription(subscription_mock)
+            .build()
+    }
+
+    fn send_request(method: &str, token: &str) -> Request<Body> {
+        Request::builder()
+            .uri(format!("/api/subscriptions/unsubscribe?token={token}"))
+            .method(method)
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn unsubscribe_should_remove_the_subscription_from_the_list() {
+        let (id, list_id) = (Uuid::new_v4(), Uuid::new_v4());
+        let mut subscription_mock = MockSubscriptionStorage::new();
+        subscription_mock
+            .expect_unsubscribe_from_list()
+            .with(eq(id), eq(list_id))
+            .times(1)
+            .returning(|_, _| Ok(true));
+        subscription_mock.expect_delete_subscription().never();
+        let state = state(subscription_mock);
+        let token = build_unsubscribe_token(id, list_id, &state.secret);
+
+        let response = unsubscribe_route(state)
+            .oneshot(send_request("GET", &token))
+            .await
+            .expect("response");
+
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn unsubscribe_without_list_should_delete_the_subscription() {
+        let id = Uuid::new_v4();
+        let mut subscription_mock = MockSubscriptionStorage::new();
+        subscription_mock
+            .expect_delete_subscription()
+            .with(eq(id))
+            .times(2)
+            .returning(|_| Ok(true));
+        let state = state(subscription_mock);
+        let token = build_subscriber_token(id, SubscriberScope::Unsubscribe, &state.secret);
+
+        for method in ["GET", "POST"] {
+            let response = unsubscribe_route(state.clone())
+                .oneshot(send_request(method, &token))
+                .await
+                .expect("response");
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+    }
+
+    #[tokio::test]
+    async fn unsubscribe_should_succeed_when_already_unsubscribed() {
+        let mut subscription_mock = MockSubscriptionStorage::new();
+        subscription_mock
+            .expect_unsubscribe_from_list()
+            .return_once(|_, _| Ok(false));
+        let state = state(subscription_mock);
+        let token = build_unsubscribe_token(Uuid::new_v4(), Uuid::new_v4(), &state.secret);
+
+        let response = unsubscribe_route(state)
+            .oneshot(send_request("GET", &token))
+            .await
+            .expect("response");
+
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn unsubscribe_should_reject_a_data_token() {
+        let mut subscription_mock = MockSubscriptionStorage::new();
+        subscription_mock.expect_delete_subscription().never();
+        let state = state(subscription_mock);
+        let token = build_subscriber_token(Uuid::new_v4(), SubscriberScope::Data, &state.secret);
+
+        let response = unsubscribe_route(state)
+            .oneshot(send_request("GET", &token))
+            .await
+            .expect("response");
+
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+}
@@ -0,0 +1,502 @@
+use axum::body::Bytes;
+use axum::extract::{Json, State};
+use axum::http::HeaderMap;
+use axum::response::IntoResponse;
+use secrecy::ExposeSecret;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use subtle::ConstantTimeEq;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use super::tracking::bounce_event;
+use super::{Error, Problem, StatusResp};
+use crate::application::server::AppState;
+use crate::authentication::basic::{basic_authentication, Error as BasicError};
+use crate::domain::{FieldError, SubscriberEmail, SuppressionReason};
+use common::err_context::ErrorContextExt;
+
+/// Source recorded with the suppressions coming from the webhook.
+const WEBHOOK_SOURCE: &str = "webhook";
+
+/// Bounce types after which sending again to the address is pointless.
+const HARD_BOUNCES: [&str; 2] = ["HardBounce", "BadEmailAddress"];
+
+/// POST handler for the events reported by the email service
+/// The email service authenticates with the webhook secret as password, in the
+/// basic authentication scheme, whatever the username. Hard bounces and spam
+/// complaints suppress the address, which newsletters are no longer sent to.
+/// Bounces of tracked issues, identified by the metadata of the email, count in
+/// their stats. Other events are only traced, by message id, and unknown ones
+/// ignored, so that the service does not retry them.
+#[utoipa::path(
+    post,
+    path = "/webhooks/email",
+    tag = "webhooks",
+    request_body = EmailEvent,
+    responses(
+        (status = 200, description = "The event was processed", body = StatusResp),
+        (status = 400, description = "Invalid event", body = Problem, content_type = "application/problem+json"),
+        (status = 401, description = "Missing or invalid credentials", body = Problem, content_type = "application/problem+json"),
+        (status = 500, description = "Internal error", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("basic" = []))
+)]
+#[tracing::instrument(
+    name = "Receiving an email event"
+    skip(state, headers, body),
+    fields(
+        request_id = %Uuid::new_v4(),
+    )
+)]
+pub async fn email_webhook(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<impl IntoResponse, Error> {
+    let credentials =
+        basic_authentication(&headers).context("Could not read the webhook credentials")?;
+    if !constant_time_eq(
+        credentials.password.expose_secret().as_bytes(),
+        state.webhook_secret.expose_secret().as_bytes(),
+    ) {
+        return Err(Error::BasicAuthentication {
+            context: "Could not validate the webhook credentials".to_string(),
+            source: BasicError::InvalidCredentials {
+                context: "The password is not the webhook secret".to_string(),
+            },
+        });
+    }
+
+    // The event is only read once the service is authenticated.
+    let event =
+        serde_json::from_slice::<EmailEvent>(&body).map_err(|err| Error::InvalidRequest {
+            context: "Invalid email event".to_string(),
+            source: vec![FieldError::new("body", err.to_string())],
+        })?;
+
+    let suppression = match &event {
+        EmailEvent::Bounce(bounce) if HARD_BOUNCES.contains(&bounce.bounce_type.as_str()) => {
+            Some((&bounce.email, SuppressionReason::HardBounce))
+        }
+        EmailEvent::SpamComplaint(complaint) => {
+            Some((&complaint.email, SuppressionReason::SpamComplaint))
+        }
+        EmailEvent::Bounce(bounce) => {
+            tracing::info!(
+                "Soft bounce ({}) of message {}",
+                bounce.bounce_type,
+                event.message_id()
+            );
+            None
+        }
+        EmailEvent::Delivery(_) => {
+            tracing::info!("Message {} delivered", event.message_id());
+            None
+        }
+        EmailEvent::Other => None,
+    };
+    if let Some((email, reason)) = suppression {
+        let email = SubscriberEmail::parse(email).map_err(|err| Error::InvalidRequest {
+            context: "Invalid email event".to_string(),
+            source: vec![FieldError::new("Email", err)],
+        })?;
+        state
+            .subscription
+            .suppress_email(&email, reason, WEBHOOK_SOURCE)
+            .await
+            .context("Could not suppress the email")?;
+        tracing::info!(
+            "Suppressed the recipient of message {} after {reason:?}",
+            event.message_id()
+        );
+    }
+
+    if let EmailEvent::Bounce(bounce) = &event {
+        if let Some(message_id) = &bounce.message_id {
+            let error = bounce.description.as_deref().unwrap_or(&bounce.bounce_type);
+            let bounced = state
+                .issues
+                .bounce_delivery(message_id, error)
+                .await
+                .context("Could not bounce the delivery")?;
+            if !bounced {
+                tracing::info!("No delivery of an issue for message {message_id}");
+            }
+        }
+        if let Some(event) = bounce_event(&bounce.metadata) {
+            state
+                .issues
+                .record_tracking_events(&[event])
+                .await
+                .context("Could not record the bounce")?;
+        }
+    }
+
+    Ok::<_, Error>(Json(StatusResp::success()))
+}
+
+/// Compares the secrets in a time which depends neither on where they differ
+/// nor on their lengths, by comparing their digests.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    Sha256::digest(a).ct_eq(&Sha256::digest(b)).into()
+}
+
+/// An event reported by a Postmark-style email service, told apart by its
+/// `RecordType`.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "RecordType")]
+pub enum EmailEvent {
+    Bounce(BounceEvent),
+    SpamComplaint(SpamComplaintEvent),
+    Delivery(DeliveryEvent),
+    /// Events of other types, which are ignored.
+    #[serde(other)]
+    Other,
+}
+
+impl EmailEvent {
+    /// The id of the message the event is about, which is logged in place of
+    /// the address.
+    fn message_id(&self) -> &str {
+        let message_id = match self {
+            EmailEvent::Bounce(bounce) => &bounce.message_id,
+            EmailEvent::SpamComplaint(complaint) => &complaint.message_id,
+            EmailEvent::Delivery(delivery) => &delivery.message_id,
+            EmailEvent::Other => &None,
+        };
+        message_id.as_deref().unwrap_or("unknown")
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "PascalCase")]
+pub struct BounceEvent {
+    /// The kind of bounce, eg `HardBounce`, `SoftBounce`, `BadEmailAddress`.
+    #[serde(rename = "Type")]
+    pub bounce_type: String,
+    pub email: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// The id of the bounced message, given when it was sent.
+    #[serde(rename = "MessageID", default)]
+    pub message_id: Option<String>,
+    /// The metadata of the bounced email, which identify tracked issues.
+    #[serde(default)]
+    pub metadata: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "PascalCase")]
+pub struct SpamComplaintEvent {
+    pub email: String,
+    #[serde(rename = "MessageID", default)]
+    pub message_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "PascalCase")]
+pub struct DeliveryEvent {
+    pub recipient: String,
+    #[serde(rename = "MessageID", default)]
+    pub message_id: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        body::Body,
+        http::{header, Request, StatusCode},
+        routing::{post, Router},
+    };
+    use fake::faker::{internet::en::Password, name::en::Name};
+    use fake::Fake;
+    use mockall::predicate::*;
+    use secrecy::Secret;
+    use tower::ServiceExt;
+
+    use crate::{
+        authentication::password::compute_password_hash,
+        domain::ports::secondary::{
+            MockAuthenticationStorage, MockIssueStorage, MockSubscriptionStorage,
+        },
+        domain::{Credentials, TrackingEvent, TrackingEventKind},
+    };
+
+    use super::*;
+    use crate::application::server::TestStateBuilder;
+
+    fn webhook_route(state: AppState) -> Router {
+        Router::new()
+            .route("/api/webhooks/email", post(email_webhook))
+            .with_state(state)
+    }
+
+    /// A state with the given webhook secret, left to build. Users are never
+    /// looked up.
+    fn state(
+        webhook_secret: &Secret<String>,
+        subscription_mock: MockSubscriptionStorage,
+    ) -> TestStateBuilder {
+        let mut authentication_mock = MockAuthenticationStorage::new();
+        authentication_mock.expect_get_credentials().never();
+        TestStateBuilder::new()
+            .authentication(authentication_mock)
+            .subscription(subscription_mock)
+            .webhook_secret(webhook_secret.clone())
+    }
+
+    fn credentials() -> Credentials {
+        Credentials {
+            username: Name().fake::<String>(),
+            password: Secret::new(Password(12..32).fake::<String>()),
+        }
+    }
+
+    fn send_event(credentials: &Credentials, event: serde_json::Value) -> Request<Body> {
+        Request::builder()
+            .uri("/api/webhooks/email")
+            .method("POST")
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(
+                header::AUTHORIZATION,
+                format!("Basic {}", credentials.encode()),
+            )
+            .body(Body::from(event.to_string()))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn email_webhook_should_suppress_hard_bounces() {
+        let credentials = credentials();
+        let mut subscription_mock = MockSubscriptionStorage::new();
+        subscription_mock
+            .expect_suppress_email()
+            .with(
+                eq(SubscriberEmail::parse("alice@acme.inc").unwrap()),
+                eq(SuppressionReason::HardBounce),
+                eq(WEBHOOK_SOURCE),
+            )
+            .times(1)
+            .returning(|_, _, _| Ok(()));
+        let app = webhook_route(state(&credentials.password, subscription_mock).build());
+
+        let response = app
+            .oneshot(send_event(
+                &credentials,
+                serde_json::json!({
+                    "RecordType": "Bounce",
+                    "Type": "HardBounce",
+                    "TypeCode": 1,
+                    "Email": "alice@acme.inc",
+                    "Description": "The server was unable to deliver your message",
+                }),
+            ))
+            .await
+            .expect("response");
+
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn email_webhook_should_suppress_spam_complaints() {
+        let credentials = credentials();
+        let mut subscription_mock = MockSubscriptionStorage::new();
+        subscription_mock
+            .expect_suppress_email()
+            .with(
+                eq(SubscriberEmail::parse("bob@acme.inc").unwrap()),
+                eq(SuppressionReason::SpamComplaint),
+                eq(WEBHOOK_SOURCE),
+            )
+            .times(1)
+            .returning(|_, _, _| Ok(()));
+        let app = webhook_route(state(&credentials.password, subscription_mock).build());
+
+        let response = app
+            .oneshot(send_event(
+                &credentials,
+                serde_json::json!({
+                    "RecordType": "SpamComplaint",
+                    "Type": "SpamComplaint",
+                    "Email": "bob@acme.inc",
+                }),
+            ))
+            .await
+            .expect("response");
+
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn email_webhook_should_only_trace_soft_bounces_and_deliveries() {
+        let credentials = credentials();
+        let mut subscription_mock = MockSubscriptionStorage::new();
+        subscription_mock.expect_suppress_email().never();
+        let state = state(&credentials.password, subscription_mock).build();
+
+        for event in [
+            serde_json::json!({
+                "RecordType": "Bounce",
+                "Type": "SoftBounce",
+                "Email": "alice@acme.inc",
+            }),
+            serde_json::json!({
+                "RecordType": "Delivery",
+                "Recipient": "alice@acme.inc",
+            }),
+            serde_json::json!({
+                "RecordType": "Open",
+                "Recipient": "alice@acme.inc",
+            }),
+        ] {
+            let response = webhook_route(state.clone())
+                .oneshot(send_event(&credentials, event))
+                .await
+                .expect("response");
+
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+    }
+
+    #[tokio::test]
+    async fn email_webhook_should_count_bounces_of_tracked_issues() {
+        let credentials = credentials();
+        let (issue_id, subscriber_id) = (Uuid::new_v4(), Uuid::new_v4());
+        let mut issues_mock = MockIssueStorage::new();
+        issues_mock
+            .expect_record_tracking_events()
+            .withf(move |events: &[TrackingEvent]| {
+                events.len() == 1
+                    && events[0].kind == TrackingEventKind::Bounce
+                    && events[0].issue_id == issue_id
+                    && events[0].subscriber_id == subscriber_id
+            })
+            .times(1)
+            .returning(|_| Ok(()));
+        let state = state(&credentials.password, MockSubscriptionStorage::new())
+            .issues(issues_mock)
+            .build();
+
+        let response = webhook_route(state)
+            .oneshot(send_event(
+                &credentials,
+                serde_json::json!({
+                    "RecordType": "Bounce",
+                    "Type": "SoftBounce",
+                    "Email": "alice@acme.inc",
+                    "Metadata": {
+                        "issue_id": issue_id,
+                        "subscriber_id": subscriber_id,
+                    },
+                }),
+            ))
+            .await
+            .expect("response");
+
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn email_webhook_should_mark_the_delivery_of_the_message_bounced() {
+        let credentials = credentials();
+        let mut issues_mock = MockIssueStorage::new();
+        issues_mock
+            .expect_bounce_delivery()
+            .with(eq("b7bc2f4a"), eq("Mailbox full"))
+            .times(1)
+            .returning(|_, _| Ok(true));
+        let state = state(&credentials.password, MockSubscriptionStorage::new())
+            .issues(issues_mock)
+            .build();
+
+        let response = webhook_route(state)
+            .oneshot(send_event(
+                &credentials,
+                serde_json::json!({
+                    "RecordType": "Bounce",
+                    "Type": "SoftBounce",
+                    "Email": "alice@acme.inc",
+                    "Description": "Mailbox full",
+                    "MessageID": "b7bc2f4a",
+                }),
+            ))
+            .await
+            .expect("response");
+
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn email_webhook_should_refuse_invalid_credentials() {
+        let credentials = credentials();
+        let mut subscription_mock = MockSubscriptionStorage::new();
+        subscription_mock.expect_suppress_email().never();
+        let app = webhook_route(state(&credentials.password, subscription_mock).build());
+        let impostor = Credentials {
+            username: credentials.username.clone(),
+            password: Secret::new("not the password".to_string()),
+        };
+
+        let response = app
+            .oneshot(send_event(
+                &impostor,
+                serde_json::json!({
+                    "RecordType": "SpamComplaint",
+                    "Email": "bob@acme.inc",
+                }),
+            ))
+            .await
+            .expect("response");
+
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn email_webhook_should_refuse_the_credentials_of_users() {
+        let user = credentials();
+        let password_hash = compute_password_hash(user.password.clone()).expect("password hash");
+        let mut authentication_mock = MockAuthenticationStorage::new();
+        authentication_mock
+            .expect_get_credentials()
+            .returning(move |_| Ok(Some((Uuid::new_v4(), password_hash.clone()))));
+        let mut subscription_mock = MockSubscriptionStorage::new();
+        subscription_mock.expect_suppress_email().never();
+        let state = state(&credentials().password, subscription_mock)
+            .authentication(authentication_mock)
+            .build();
+
+        let response = webhook_route(state)
+            .oneshot(send_event(
+                &user,
+                serde_json::json!({
+                    "RecordType": "SpamComplaint",
+                    "Email": "bob@acme.inc",
+                }),
+            ))
+            .await
+            .expect("response");
+
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn email_webhook_should_refuse_requests_without_credentials() {
+        let app =
+            webhook_route(state(&credentials().password, MockSubscriptionStorage::new()).build());
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/api/webhooks/email")
+                    .method("POST")
+                    .body(Body::from("{}"))
+                    .unwrap(),
+            )
+            .await
+            .expect("response");
+
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+}
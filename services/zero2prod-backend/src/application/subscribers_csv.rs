@@ -0,0 +1,599 @@
+use chrono::{DateTime, Utc};
+use common::err_context::{ErrorContext, ErrorContextExt};
+use futures::stream::{self, Stream, StreamExt, TryStreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+use std::str::FromStr;
+use utoipa::ToSchema;
+
+use crate::application::server::routes::subscriptions::{
+    confirmation_template, generate_subscription_token,
+};
+use crate::application::server::{ApplicationBaseUrl, DynEmail, DynSubscription, DynTemplates};
+use crate::domain::ports::secondary::SubscriptionError;
+use crate::domain::{
+    FieldError, NewSubscription, Subscription, SubscriptionCursor, SubscriptionFilter,
+    SubscriptionRequest, SubscriptionStatus, DEFAULT_LIST,
+};
+
+/// Number of subscriptions fetched from storage for each chunk of the export.
+const EXPORT_BATCH_SIZE: i64 = 500;
+
+/// Columns of the CSV files, in the order they are exported.
+const COLUMNS: [&str; 4] = ["email", "username", "status", "subscribed_at"];
+
+/// One line of the CSV files used to import and export subscriptions, from the
+/// REST API and the command line. `status` and `subscribed_at` are optional on
+/// import.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubscriberRecord {
+    pub email: String,
+    pub username: String,
+    #[serde(default)]
+    pub status: Option<String>,
+    #[serde(default)]
+    pub subscribed_at: Option<DateTime<Utc>>,
+}
+
+impl From<&Subscription> for SubscriberRecord {
+    fn from(subscription: &Subscription) -> Self {
+        SubscriberRecord {
+            email: subscription.email.as_ref().to_string(),
+            username: subscription.username.as_ref().to_string(),
+            status: Some(subscription.status.as_str().to_string()),
+            subscribed_at: Some(subscription.subscribed_at),
+        }
+    }
+}
+
+/// Outcome of an import. Lines are numbered from the start of the file,
+/// the header being line 1.
+#[derive(Debug, Default, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ImportReport {
+    /// Number of subscriptions created.
+    pub imported: usize,
+    /// Lines whose email is already subscribed, or appears earlier in the file.
+    pub duplicates: Vec<DuplicateLine>,
+    /// Lines which could not be imported, or whose confirmation email could not be sent.
+    pub errors: Vec<RejectedLine>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct DuplicateLine {
+    pub line: u64,
+    pub email: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct RejectedLine {
+    pub line: u64,
+    pub errors: Vec<FieldError>,
+}
+
+/// Imports subscriptions from CSV with an `email` and a `username` column, and
+/// optional `status` and `subscribed_at` columns.
+/// Lines without a status are confirmed, unless `pending` is set. Pending
+/// subscriptions are given a token, and sent a confirmation email.
+/// Subscriptions are imported on the default list.
+pub async fn import_subscribers<R: io::Read + Send>(
+    reader: R,
+    pending: bool,
+    storage: &DynSubscription,
+    email: &DynEmail,
+    templates: &DynTemplates,
+    base_url: &ApplicationBaseUrl,
+) -> Result<ImportReport, Error> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .flexible(true)
+        .from_reader(reader);
+
+    let headers = reader
+        .headers()
+        .context("Could not read the CSV header")?
+        .clone();
+    let missing = ["email", "username"]
+        .into_iter()
+        .filter(|column| !headers.iter().any(|header| header == *column))
+        .collect::<Vec<_>>();
+    if !missing.is_empty() {
+        return Err(Error::Format {
+            context: format!("Missing CSV column(s): {}", missing.join(", ")),
+        });
+    }
+
+    let list = storage
+        .get_list(DEFAULT_LIST)
+        .await
+        .context("Could not get the default list")?
+        .ok_or_else(|| Error::MissingList {
+            context: format!("No list {DEFAULT_LIST}"),
+        })?;
+
+    let mut report = ImportReport::default();
+    let mut seen = HashSet::new();
+    let mut record = csv::StringRecord::new();
+    loop {
+        match reader.read_record(&mut record) {
+            Ok(false) => break,
+            Ok(true) => {}
+            Err(err) => {
+                let line = err.position().map(|p| p.line()).unwrap_or_default();
+                report.errors.push(RejectedLine {
+                    line,
+                    errors: vec![FieldError::new("line", err.to_string())],
+                });
+                continue;
+            }
+        }
+        let line = record.position().map(|p| p.line()).unwrap_or_default();
+
+        let parsed = record
+            .deserialize::<SubscriberRecord>(Some(&headers))
+            .map_err(|err| vec![FieldError::new("line", err.to_string())])
+            .and_then(|record| validate(record, pending));
+        let (subscription, status, subscribed_at) = match parsed {
+            Ok(parsed) => parsed,
+            Err(errors) => {
+                report.errors.push(RejectedLine { line, errors });
+                continue;
+            }
+        };
+
+        if !seen.insert(subscription.email.as_ref().to_string()) {
+            report.duplicates.push(DuplicateLine {
+                line,
+                email: subscription.email.as_ref().to_string(),
+            });
+            continue;
+        }
+
+        let token = match status {
+            SubscriptionStatus::PendingConfirmation => Some(generate_subscription_token()),
+            SubscriptionStatus::Confirmed => None,
+        };
+
+        let stored = storage
+            .import_subscription(
+                &subscription,
+                &list.id,
+                &status,
+                &subscribed_at,
+                token.clone(),
+            )
+            .await
+            .context(format!("Could not import subscription on line {line}"))?;
+
+        match (stored, token) {
+            (None, _) => report.duplicates.push(DuplicateLine {
+                line,
+                email: subscription.email.as_ref().to_string(),
+            }),
+            (Some(stored), token) => {
+                report.imported += 1;
+                if let Some(token) = token {
+                    let template = confirmation_template(base_url, &stored.username, &token);
+                    let sent = match templates.render(&stored.email, &template) {
+                        Ok(confirmation) => email
+                            .send_email(confirmation)
+                            .await
+                            .map_err(|err| err.to_string()),
+                        Err(err) => Err(err.to_string()),
+                    };
+                    if let Err(err) = sent {
+                        tracing::warn!("Could not send confirmation email: {err}");
+                        report.errors.push(RejectedLine {
+                            line,
+                            errors: vec![FieldError::new(
+                                "email",
+                                format!("Imported, but the confirmation email was not sent: {err}"),
+                            )],
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(report)
+}
+
+/// Validates every field of a record, and reports all the fields that failed.
+fn validate(
+    record: SubscriberRecord,
+    pending: bool,
+) -> Result<(NewSubscription, SubscriptionStatus, DateTime<Utc>), Vec<FieldError>> {
+    let SubscriberRecord {
+        email,
+        username,
+        status,
+        subscribed_at,
+    } = record;
+
+    let subscription = NewSubscription::try_from(SubscriptionRequest {
+        username,
+        email,
+        list: None,
+    });
+
+    let status = match status.filter(|status| !status.is_empty()) {
+        Some(status) => {
+            SubscriptionStatus::from_str(&status).map_err(|err| FieldError::new("status", err))
+        }
+        None if pending => Ok(SubscriptionStatus::PendingConfirmation),
+        None => Ok(SubscriptionStatus::Confirmed),
+    };
+
+    match (subscription, status) {
+        (Ok(subscription), Ok(status)) => {
+            Ok((subscription, status, subscribed_at.unwrap_or_else(Utc::now)))
+        }
+        (subscription, status) => Err(subscription
+            .err()
+            .unwrap_or_default()
+            .into_iter()
+            .chain(status.err())
+            .collect()),
+    }
+}
+
+/// Exports all the subscriptions, ordered by signup date, as a stream of CSV
+/// chunks. The first chunk is the header.
+pub fn export_subscribers(
+    storage: DynSubscription,
+) -> impl Stream<Item = Result<Vec<u8>, Error>> + Send {
+    let header = stream::once(async { to_csv(&[], true) });
+    let pages = stream::try_unfold(
+        Some(None),
+        move |after: Option<Option<SubscriptionCursor>>| {
+            let storage = storage.clone();
+            async move {
+                let Some(after) = after else {
+                    return Ok(None);
+                };
+                let filter = SubscriptionFilter {
+                    status: None,
+                    subscribed_after: None,
+                    subscribed_before: None,
+                    email: None,
+                    after,
+                    limit: EXPORT_BATCH_SIZE,
+                };
+                let page = storage
+                    .list_subscriptions(&filter)
+                    .await
+                    .context("Could not list subscriptions")?;
+                let next = if page.len() as i64 == EXPORT_BATCH_SIZE {
+                    page.last().map(|subscription| {
+                        Some(SubscriptionCursor {
+                            subscribed_at: subscription.subscribed_at,
+                            id: subscription.id,
+                        })
+                    })
+                } else {
+                    None
+                };
+                Ok(Some((to_csv(&page, false)?, next)))
+            }
+        },
+    );
+    header.chain(pages)
+}
+
+fn to_csv(subscriptions: &[Subscription], with_header: bool) -> Result<Vec<u8>, Error> {
+    let mut writer = csv::WriterBuilder::new()
+        .has_headers(false)
+        .from_writer(Vec::new());
+    if with_header {
+        writer
+            .write_record(COLUMNS)
+            .context("Could not write the CSV header")?;
+    }
+    for subscription in subscriptions {
+        writer
+            .serialize(SubscriberRecord::from(subscription))
+            .context("Could not write a CSV record")?;
+    }
+    writer
+        .into_inner()
+        .map_err(|err| csv::Error::from(err.into_error()))
+        .context("Could not flush CSV records")
+        .map_err(Error::from)
+}
+
+/// Imports subscriptions from a CSV file. See [`import_subscribers`].
+pub async fn import_file(
+    path: &Path,
+    pending: bool,
+    storage: &DynSubscription,
+    email: &DynEmail,
+    templates: &DynTemplates,
+    base_url: &ApplicationBaseUrl,
+) -> Result<ImportReport, Error> {
+    let file = File::open(path).context(format!("Could not open {}", path.display()))?;
+    import_subscribers(file, pending, storage, email, templates, base_url).await
+}
+
+/// Exports all the subscriptions to a CSV file, or to stdout if there is no path.
+pub async fn export_file(path: Option<&Path>, storage: DynSubscription) -> Result<(), Error> {
+    let mut out: Box<dyn Write> = match path {
+        Some(path) => {
+            Box::new(File::create(path).context(format!("Could not create {}", path.display()))?)
+        }
+        None => Box::new(io::stdout()),
+    };
+    let mut chunks = export_subscribers(storage).boxed();
+    while let Some(chunk) = chunks.try_next().await? {
+        out.write_all(&chunk).context("Could not write CSV")?;
+    }
+    out.flush().context("Could not write CSV")?;
+    Ok(())
+}
+
+#[derive(Debug)]
+pub enum Error {
+    /// The CSV cannot be read or written.
+    Csv {
+        context: String,
+        source: csv::Error,
+    },
+    /// The CSV does not have the expected columns.
+    Format {
+        context: String,
+    },
+    /// The list subscriptions are imported on does not exist.
+    MissingList {
+        context: String,
+    },
+    Io {
+        context: String,
+        source: io::Error,
+    },
+    Storage {
+        context: String,
+        source: SubscriptionError,
+    },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Csv { context, source } => {
+                write!(fmt, "CSV: {context} | {source}")
+            }
+            Error::Format { context } => {
+                write!(fmt, "CSV Format: {context}")
+            }
+            Error::MissingList { context } => {
+                write!(fmt, "Missing List: {context}")
+            }
+            Error::Io { context, source } => {
+                write!(fmt, "IO: {context} | {source}")
+            }
+            Error::Storage { context, source } => {
+                write!(fmt, "Storage: {context} | {source}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<ErrorContext<csv::Error>> for Error {
+    fn from(err: ErrorContext<csv::Error>) -> Self {
+        Error::Csv {
+            context: err.0,
+            source: err.1,
+        }
+    }
+}
+
+impl From<ErrorContext<io::Error>> for Error {
+    fn from(err: ErrorContext<io::Error>) -> Self {
+        Error::Io {
+            context: err.0,
+            source: err.1,
+        }
+    }
+}
+
+impl From<ErrorContext<SubscriptionError>> for Error {
+    fn from(err: ErrorContext<SubscriptionError>) -> Self {
+        Error::Storage {
+            context: err.0,
+            source: err.1,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::domain::ports::secondary::{Email, MockEmailService, MockSubscriptionStorage};
+    use crate::domain::{MailingList, SubscriberPreferences};
+    use crate::services::templates::repository_templates;
+
+    fn expect_default_list(mock: &mut MockSubscriptionStorage) {
+        mock.expect_get_list().return_once(|_| {
+            Ok(Some(MailingList {
+                id: Uuid::new_v4(),
+                slug: DEFAULT_LIST.to_string(),
+                name: "Newsletter".to_string(),
+                tracking: true,
+                created_at: Utc::now(),
+            }))
+        });
+    }
+
+    fn stored(subscription: &NewSubscription, status: &SubscriptionStatus) -> Subscription {
+        Subscription {
+            id: Uuid::new_v4(),
+            email: subscription.email.clone(),
+            username: subscription.username.clone(),
+            status: status.clone(),
+            subscribed_at: Utc::now(),
+            preferences: SubscriberPreferences::default(),
+            attributes: serde_json::json!({}),
+        }
+    }
+
+    #[tokio::test]
+    async fn import_should_report_invalid_lines_and_duplicates() {
+        let csv = "\
+email,username,status
+alice@acme.inc,alice,
+not an email,bob,
+carol@acme.inc,carol,unknown
+alice@acme.inc,alice2,
+dave@acme.inc,dave,confirmed
+";
+        let mut subscription_mock = MockSubscriptionStorage::new();
+        expect_default_list(&mut subscription_mock);
+        subscription_mock
+            .expect_import_subscription()
+            .times(2)
+            .returning(|subscription, _, status, _, token| {
+                assert!(token.is_none());
+                if subscription.email.as_ref() == "dave@acme.inc" {
+                    // Already in storage
+                    Ok(None)
+                } else {
+                    Ok(Some(stored(subscription, status)))
+                }
+            });
+        let storage: DynSubscription = Arc::new(subscription_mock);
+        let email: DynEmail = Arc::new(MockEmailService::new());
+        let templates: DynTemplates = Arc::new(repository_templates());
+        let base_url = ApplicationBaseUrl("http://127.0.0.1".to_string());
+
+        let report = import_subscribers(
+            csv.as_bytes(),
+            false,
+            &storage,
+            &email,
+            &templates,
+            &base_url,
+        )
+        .await
+        .expect("report");
+
+        assert_eq!(report.imported, 1);
+        assert_eq!(
+            report
+                .duplicates
+                .iter()
+                .map(|duplicate| duplicate.line)
+                .collect::<Vec<_>>(),
+            vec![5, 6]
+        );
+        assert_eq!(report.errors.len(), 2);
+        assert_eq!(report.errors[0].line, 3);
+        assert_eq!(report.errors[0].errors[0].field, "email");
+        assert_eq!(report.errors[1].line, 4);
+        assert_eq!(report.errors[1].errors[0].field, "status");
+    }
+
+    #[tokio::test]
+    async fn import_should_send_confirmation_to_pending_subscriptions() {
+        let csv = "username,email\nalice,alice@acme.inc\n";
+        let mut subscription_mock = MockSubscriptionStorage::new();
+        expect_default_list(&mut subscription_mock);
+        subscription_mock
+            .expect_import_subscription()
+            .withf(|_, _, status, _, token| {
+                *status == SubscriptionStatus::PendingConfirmation && token.is_some()
+            })
+            .return_once(|subscription, _, status, _, _| Ok(Some(stored(subscription, status))));
+        let mut email_mock = MockEmailService::new();
+        email_mock
+            .expect_send_email()
+            .withf(|email: &Email| email.to.as_ref() == "alice@acme.inc")
+            .return_once(|_| Ok(()));
+        let storage: DynSubscription = Arc::new(subscription_mock);
+        let email: DynEmail = Arc::new(email_mock);
+        let templates: DynTemplates = Arc::new(repository_templates());
+        let base_url = ApplicationBaseUrl("http://127.0.0.1".to_string());
+
+        let report = import_subscribers(
+            csv.as_bytes(),
+            true,
+            &storage,
+            &email,
+            &templates,
+            &base_url,
+        )
+        .await
+        .expect("report");
+
+        assert_eq!(report.imported, 1);
+        assert!(report.errors.is_empty());
+    }
+
+    #[tokio::test]
+    async fn import_should_require_email_and_username_columns() {
+        let csv = "email\nalice@acme.inc\n";
+        let storage: DynSubscription = Arc::new(MockSubscriptionStorage::new());
+        let email: DynEmail = Arc::new(MockEmailService::new());
+        let templates: DynTemplates = Arc::new(repository_templates());
+        let base_url = ApplicationBaseUrl("http://127.0.0.1".to_string());
+
+        let report = import_subscribers(
+            csv.as_bytes(),
+            false,
+            &storage,
+            &email,
+            &templates,
+            &base_url,
+        )
+        .await;
+
+        assert!(matches!(report, Err(Error::Format { .. })));
+    }
+
+    #[tokio::test]
+    async fn export_should_page_through_storage() {
+        let first = (0..EXPORT_BATCH_SIZE)
+            .map(|i| {
+                let subscription = NewSubscription::try_from(SubscriptionRequest {
+                    username: format!("user{i}"),
+                    email: format!("user{i}@acme.inc"),
+                    list: None,
+                })
+                .unwrap();
+                stored(&subscription, &SubscriptionStatus::Confirmed)
+            })
+            .collect::<Vec<_>>();
+        let last_id = first.last().unwrap().id;
+        let mut subscription_mock = MockSubscriptionStorage::new();
+        subscription_mock
+            .expect_list_subscriptions()
+            .withf(|filter: &SubscriptionFilter| filter.after.is_none())
+            .return_once(move |_| Ok(first));
+        subscription_mock
+            .expect_list_subscriptions()
+            .withf(move |filter: &SubscriptionFilter| {
+                filter.after.as_ref().map(|cursor| cursor.id) == Some(last_id)
+            })
+            .return_once(|_| Ok(Vec::new()));
+
+        let chunks = export_subscribers(Arc::new(subscription_mock))
+            .try_concat()
+            .await
+            .expect("csv");
+        let csv = String::from_utf8(chunks).unwrap();
+        let mut lines = csv.lines();
+        assert_eq!(lines.next(), Some("email,username,status,subscribed_at"));
+        assert!(lines
+            .next()
+            .unwrap()
+            .starts_with("user0@acme.inc,user0,confirmed,"));
+        assert_eq!(lines.count() as i64, EXPORT_BATCH_SIZE - 1);
+    }
+}
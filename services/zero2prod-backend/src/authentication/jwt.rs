@@ -37,6 +37,214 @@ pub fn build_token(id: Uuid, secret: &Secret<String>) -> String {
     token
 }
 
+/// Claims of the tokens sent to subscribers. The scope keeps these tokens apart
+/// from the users' session tokens, and from each other.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SubscriberTokenClaims {
+    pub sub: String,
+    pub scope: String,
+    /// The list the token is restricted to, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub list: Option<String>,
+    pub iat: usize,
+    pub exp: usize,
+}
+
+/// What a token sent to a subscriber gives access to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubscriberScope {
+    /// Export and erasure of the data we hold about the subscriber.
+    Data,
+    /// Removal of the subscription, from the link found in every newsletter.
+    Unsubscribe,
+    /// Choice of topics and frequency, from the link found in every newsletter.
+    Preferences,
+}
+
+impl SubscriberScope {
+    fn as_str(&self) -> &'static str {
+        match self {
+            SubscriberScope::Data => "subscriber_data",
+            SubscriberScope::Unsubscribe => "unsubscribe",
+            SubscriberScope::Preferences => "preferences",
+        }
+    }
+
+    fn validity(&self) -> Duration {
+        match self {
+            SubscriberScope::Data => Duration::hours(24),
+            // Newsletters are kept, and their link should keep working.
+            SubscriberScope::Unsubscribe | SubscriberScope::Preferences => Duration::days(365),
+        }
+    }
+}
+
+/// Builds a token giving access, within the scope, to the subscription identified by id.
+pub fn build_subscriber_token(id: Uuid, scope: SubscriberScope, secret: &Secret<String>) -> String {
+    encode_subscriber_token(id, scope, None, secret)
+}
+
+/// Builds the token of the unsubscribe link, which removes the subscription
+/// identified by id from the list identified by list_id.
+pub fn build_unsubscribe_token(id: Uuid, list_id: Uuid, secret: &Secret<String>) -> String {
+    encode_subscriber_token(id, SubscriberScope::Unsubscribe, Some(list_id), secret)
+}
+
+fn encode_subscriber_token(
+    id: Uuid,
+    scope: SubscriberScope,
+    list_id: Option<Uuid>,
+    secret: &Secret<String>,
+) -> String {
+    let now = Utc::now();
+    let claims = SubscriberTokenClaims {
+        sub: id.to_string(),
+        scope: scope.as_str().to_string(),
+        list: list_id.map(|id| id.to_string()),
+        iat: now.timestamp() as usize,
+        exp: (now + scope.validity()).timestamp() as usize,
+    };
+
+    encode(
+        &Header::default(),
+        &claims,
+        &EncodingKey::from_secret(secret.expose_secret().as_bytes()),
+    )
+    .unwrap()
+}
+
+/// Returns the id of the subscription the token gives access to, within the scope.
+pub fn validate_subscriber_token(
+    token: &str,
+    scope: SubscriberScope,
+    secret: &Secret<String>,
+) -> Result<Uuid, Error> {
+    let claims = decode_subscriber_token(token, scope, secret)?;
+
+    uuid::Uuid::parse_str(&claims.sub).map_err(|_| Error::InvalidToken)
+}
+
+/// Returns the id of the subscription the unsubscribe token removes, and the
+/// id of the list it removes it from. Tokens built before there were several
+/// lists have no list: they remove the subscription altogether.
+pub fn validate_unsubscribe_token(
+    token: &str,
+    secret: &Secret<String>,
+) -> Result<(Uuid, Option<Uuid>), Error> {
+    let claims = decode_subscriber_token(token, SubscriberScope::Unsubscribe, secret)?;
+
+    let id = uuid::Uuid::parse_str(&claims.sub).map_err(|_| Error::InvalidToken)?;
+    let list_id = claims
+        .list
+        .map(|list| uuid::Uuid::parse_str(&list))
+        .transpose()
+        .map_err(|_| Error::InvalidToken)?;
+    Ok((id, list_id))
+}
+
+fn decode_subscriber_token(
+    token: &str,
+    scope: SubscriberScope,
+    secret: &Secret<String>,
+) -> Result<SubscriberTokenClaims, Error> {
+    let claims = decode::<SubscriberTokenClaims>(
+        token,
+        &DecodingKey::from_secret(secret.expose_secret().as_bytes()),
+        &Validation::default(),
+    )
+    .map_err(|_| Error::InvalidToken)?
+    .claims;
+
+    if claims.scope != scope.as_str() {
+        return Err(Error::InvalidToken);
+    }
+
+    Ok(claims)
+}
+
+/// Scope of the tracking tokens, which keeps them apart from the others.
+const TRACKING_SCOPE: &str = "tracking";
+
+/// Claims of the tokens of the tracking pixel and links of an issue. Tracked
+/// links are only followed to the url signed in their token, and do not
+/// expire, so that the links of old issues keep working.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TrackingTokenClaims {
+    pub sub: String,
+    pub scope: String,
+    pub issue: String,
+    /// The link a click leads to. Open tokens have none.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    pub iat: usize,
+    /// Open tokens expire. Click tokens have no expiry.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exp: Option<usize>,
+}
+
+/// The recipient and issue a tracking token was built for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrackingToken {
+    pub subscriber_id: Uuid,
+    pub issue_id: Uuid,
+    pub url: Option<String>,
+}
+
+/// Builds the token of a tracking pixel, without url, or of a tracked link,
+/// for the subscriber identified by id, who received the issue.
+pub fn build_tracking_token(
+    id: Uuid,
+    issue_id: Uuid,
+    url: Option<&str>,
+    secret: &Secret<String>,
+) -> String {
+    let now = Utc::now();
+    let claims = TrackingTokenClaims {
+        sub: id.to_string(),
+        scope: TRACKING_SCOPE.to_string(),
+        issue: issue_id.to_string(),
+        url: url.map(String::from),
+        iat: now.timestamp() as usize,
+        // The signature is enough to keep links from being tampered with.
+        exp: url
+            .is_none()
+            .then(|| (now + Duration::days(365)).timestamp() as usize),
+    };
+
+    encode(
+        &Header::default(),
+        &claims,
+        &EncodingKey::from_secret(secret.expose_secret().as_bytes()),
+    )
+    .unwrap()
+}
+
+pub fn validate_tracking_token(
+    token: &str,
+    secret: &Secret<String>,
+) -> Result<TrackingToken, Error> {
+    // The expiry is still checked when the token has one.
+    let mut validation = Validation::default();
+    validation.required_spec_claims.remove("exp");
+    let claims = decode::<TrackingTokenClaims>(
+        token,
+        &DecodingKey::from_secret(secret.expose_secret().as_bytes()),
+        &validation,
+    )
+    .map_err(|_| Error::InvalidToken)?
+    .claims;
+
+    if claims.scope != TRACKING_SCOPE {
+        return Err(Error::InvalidToken);
+    }
+
+    Ok(TrackingToken {
+        subscriber_id: uuid::Uuid::parse_str(&claims.sub).map_err(|_| Error::InvalidToken)?,
+        issue_id: uuid::Uuid::parse_str(&claims.issue).map_err(|_| Error::InvalidToken)?,
+        url: claims.url,
+    })
+}
+
 // TODO This should really be a trait and an implementation...
 // validate_credentials could be a free function, but for mocking
 // it should be either a struct or a trait.
@@ -106,3 +314,86 @@ impl From<ErrorContext<AuthenticationError>> for Error {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use speculoos::prelude::*;
+
+    #[test]
+    fn subscriber_token_should_give_access_to_its_subscription() {
+        let secret = Secret::new("secret".to_string());
+        let id = Uuid::new_v4();
+        let token = build_subscriber_token(id, SubscriberScope::Data, &secret);
+        assert_that(&validate_subscriber_token(&token, SubscriberScope::Data, &secret).ok())
+            .is_equal_to(Some(id));
+    }
+
+    #[test]
+    fn subscriber_token_should_only_be_valid_in_its_scope() {
+        let secret = Secret::new("secret".to_string());
+        let token = build_subscriber_token(Uuid::new_v4(), SubscriberScope::Unsubscribe, &secret);
+        assert_that(&validate_subscriber_token(&token, SubscriberScope::Data, &secret).ok())
+            .is_none();
+    }
+
+    #[test]
+    fn unsubscribe_token_should_name_its_list() {
+        let secret = Secret::new("secret".to_string());
+        let (id, list_id) = (Uuid::new_v4(), Uuid::new_v4());
+        let token = build_unsubscribe_token(id, list_id, &secret);
+        assert_that(&validate_unsubscribe_token(&token, &secret).ok())
+            .is_equal_to(Some((id, Some(list_id))));
+        let token = build_subscriber_token(id, SubscriberScope::Unsubscribe, &secret);
+        assert_that(&validate_unsubscribe_token(&token, &secret).ok())
+            .is_equal_to(Some((id, None)));
+    }
+
+    #[test]
+    fn session_token_should_not_be_a_subscriber_token() {
+        let secret = Secret::new("secret".to_string());
+        let token = build_token(Uuid::new_v4(), &secret);
+        assert_that(&validate_subscriber_token(&token, SubscriberScope::Data, &secret).ok())
+            .is_none();
+        let token = build_subscriber_token(
+            Uuid::new_v4(),
+            SubscriberScope::Data,
+            &Secret::new("other".to_string()),
+        );
+        assert_that(&validate_subscriber_token(&token, SubscriberScope::Data, &secret).ok())
+            .is_none();
+    }
+
+    #[test]
+    fn tracking_token_should_sign_its_issue_and_link() {
+        let secret = Secret::new("secret".to_string());
+        let (id, issue_id) = (Uuid::new_v4(), Uuid::new_v4());
+        let token = build_tracking_token(id, issue_id, Some("https://acme.inc/news"), &secret);
+        assert_that(&validate_tracking_token(&token, &secret).ok()).is_equal_to(Some(
+            TrackingToken {
+                subscriber_id: id,
+                issue_id,
+                url: Some("https://acme.inc/news".to_string()),
+            },
+        ));
+        let token = build_subscriber_token(id, SubscriberScope::Unsubscribe, &secret);
+        assert_that(&validate_tracking_token(&token, &secret).ok()).is_none();
+    }
+
+    #[test]
+    fn tracking_token_should_only_expire_without_link() {
+        let secret = Secret::new("secret".to_string());
+        let mut validation = Validation::default();
+        validation.required_spec_claims.clear();
+        let claims = |token: &str| {
+            decode::<TrackingTokenClaims>(token, &DecodingKey::from_secret(b"secret"), &validation)
+                .unwrap()
+                .claims
+        };
+        let (id, issue_id) = (Uuid::new_v4(), Uuid::new_v4());
+        let click = build_tracking_token(id, issue_id, Some("https://acme.inc/news"), &secret);
+        let open = build_tracking_token(id, issue_id, None, &secret);
+        assert_that(&claims(&click).exp).is_none();
+        assert_that(&claims(&open).exp).is_some();
+    }
+}
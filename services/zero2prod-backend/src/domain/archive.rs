@@ -0,0 +1,115 @@
+use base64::engine::general_purpose;
+use base64::Engine;
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// A published newsletter, as it was sent to the subscribers.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ArchivedIssue {
+    pub id: Uuid,
+    /// Identifies the issue in the archive urls.
+    pub slug: String,
+    pub title: String,
+    pub html_content: String,
+    pub text_content: String,
+    /// The admin who published the issue.
+    pub author_id: Uuid,
+    pub published_at: DateTime<Utc>,
+}
+
+/// What the archive listing shows of a published newsletter.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ArchivedIssueSummary {
+    pub id: Uuid,
+    pub slug: String,
+    pub title: String,
+    pub published_at: DateTime<Utc>,
+}
+
+/// Builds the slug of an issue from its title. The start of the id keeps the
+/// slugs of issues with the same title apart.
+pub fn slug(title: &str, id: &Uuid) -> String {
+    let words = title
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+    let suffix = &id.simple().to_string()[..8];
+    if words.is_empty() {
+        suffix.to_string()
+    } else {
+        format!("{words}-{suffix}")
+    }
+}
+
+/// Criteria used to select a page of the archive. Issues are ordered by
+/// publication date, the most recent first, and the page starts right after
+/// the cursor.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArchiveFilter {
+    pub after: Option<ArchiveCursor>,
+    pub limit: i64,
+}
+
+/// Position of an issue in the archive order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArchiveCursor {
+    pub published_at: DateTime<Utc>,
+    pub id: Uuid,
+}
+
+impl ArchiveCursor {
+    /// Encodes the cursor as an opaque, url safe, string.
+    pub fn encode(&self) -> String {
+        let raw = format!(
+            "{}|{}",
+            self.published_at
+                .to_rfc3339_opts(SecondsFormat::Micros, true),
+            self.id
+        );
+        general_purpose::URL_SAFE_NO_PAD.encode(raw)
+    }
+
+    pub fn decode<S: AsRef<str>>(s: S) -> Result<ArchiveCursor, String> {
+        let invalid = || format!("{} is not a valid cursor.", s.as_ref());
+        let raw = general_purpose::URL_SAFE_NO_PAD
+            .decode(s.as_ref())
+            .map_err(|_| invalid())?;
+        let raw = String::from_utf8(raw).map_err(|_| invalid())?;
+        let (published_at, id) = raw.split_once('|').ok_or_else(invalid)?;
+        let published_at = DateTime::parse_from_rfc3339(published_at)
+            .map_err(|_| invalid())?
+            .with_timezone(&Utc);
+        let id = Uuid::parse_str(id).map_err(|_| invalid())?;
+        Ok(ArchiveCursor { published_at, id })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use speculoos::prelude::*;
+
+    #[test]
+    fn slug_should_only_keep_words_of_the_title() {
+        let id = Uuid::parse_str("0f1e2d3c-4b5a-6978-8796-a5b4c3d2e1f0").unwrap();
+
+        assert_that(&slug("Hello, World! Issue #3", &id))
+            .is_equal_to("hello-world-issue-3-0f1e2d3c".to_string());
+        assert_that(&slug("???", &id)).is_equal_to("0f1e2d3c".to_string());
+    }
+
+    #[test]
+    fn cursor_should_survive_a_round_trip() {
+        let cursor = ArchiveCursor {
+            published_at: DateTime::parse_from_rfc3339("2023-09-01T10:11:12.123456Z")
+                .unwrap()
+                .with_timezone(&Utc),
+            id: Uuid::new_v4(),
+        };
+        assert_that(&ArchiveCursor::decode(cursor.encode())).is_equal_to(Ok(cursor));
+    }
+}
@@ -1,6 +1,16 @@
-use crate::domain::SubscriberEmail;
+use uuid::Uuid;
+
+use crate::domain::{DeliveryFrequency, SubscriberEmail, SubscriberName};
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct ConfirmedSubscriber {
+    pub id: Uuid,
     pub email: SubscriberEmail,
+    pub username: SubscriberName,
+    /// The list through which the subscriber receives the newsletter.
+    pub list_id: Uuid,
+    /// Weekly subscribers get the newsletter in their next digest.
+    pub frequency: DeliveryFrequency,
+    /// The custom attributes, which newsletter placeholders can refer to.
+    pub attributes: serde_json::Value,
 }
@@ -0,0 +1,84 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// Where the email of an issue to one of its recipients stands.
+#[derive(sqlx::Type, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[sqlx(type_name = "delivery_status")]
+#[sqlx(rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryStatus {
+    /// The email is about to be handed over to the email service.
+    Queued,
+    /// The email service accepted the email.
+    Sent,
+    /// The email could not be sent.
+    Failed,
+    /// The email service reported a bounce.
+    Bounced,
+}
+
+impl DeliveryStatus {
+    pub fn as_str(&self) -> &str {
+        match self {
+            DeliveryStatus::Queued => "queued",
+            DeliveryStatus::Sent => "sent",
+            DeliveryStatus::Failed => "failed",
+            DeliveryStatus::Bounced => "bounced",
+        }
+    }
+}
+
+/// The delivery of an issue to one of its recipients.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct Delivery {
+    pub issue_id: Uuid,
+    pub subscriber_id: Uuid,
+    #[schema(example = "alice@acme.inc")]
+    pub email: String,
+    pub status: DeliveryStatus,
+    /// The id the email service gave to the message, once sent.
+    pub message_id: Option<String>,
+    /// Why the email could not be sent, or bounced.
+    pub last_error: Option<String>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Delivery {
+    pub fn new(
+        issue_id: Uuid,
+        subscriber_id: Uuid,
+        email: String,
+        status: DeliveryStatus,
+    ) -> Delivery {
+        Delivery {
+            issue_id,
+            subscriber_id,
+            email,
+            status,
+            message_id: None,
+            last_error: None,
+            updated_at: Utc::now(),
+        }
+    }
+}
+
+/// Criteria used to select the deliveries of an issue.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DeliveryFilter {
+    pub status: Option<DeliveryStatus>,
+    /// Case insensitive substring of the email.
+    pub email: Option<String>,
+}
+
+/// Number of deliveries of an issue in each state.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct DeliverySummary {
+    pub issue_id: Uuid,
+    pub total: i64,
+    pub queued: i64,
+    pub sent: i64,
+    pub failed: i64,
+    pub bounced: i64,
+}
@@ -0,0 +1,12 @@
+use uuid::Uuid;
+
+use crate::domain::{ArchivedIssueSummary, SubscriberEmail};
+
+/// The newsletters queued for a subscriber receiving a weekly digest.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Digest {
+    pub subscriber_id: Uuid,
+    pub email: SubscriberEmail,
+    /// The queued issues, the oldest first.
+    pub issues: Vec<ArchivedIssueSummary>,
+}
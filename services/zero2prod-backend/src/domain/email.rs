@@ -1,13 +1,43 @@
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
-#[derive(Debug, Serialize, Deserialize)]
+use crate::domain::Segment;
+use crate::utils::markdown;
+
+/// A newsletter issue, as submitted for publication.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct BodyData {
     pub title: String,
     pub content: Content,
+    /// Publish the content even if the sanitizer removed too much of it.
+    #[serde(default)]
+    pub force: bool,
+    /// Ids or slugs of the lists to send the newsletter to, the default list
+    /// if there are none.
+    #[serde(default)]
+    pub lists: Vec<String>,
+    /// Restricts the newsletter to part of the subscribers of the lists.
+    #[serde(default)]
+    pub segment: Segment,
+}
+
+/// The body of a newsletter issue, either in both HTML and plain text, or in
+/// Markdown, from which the HTML and plain text are rendered.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(untagged)]
+pub enum Content {
+    Html { html: String, text: String },
+    Markdown { markdown: String },
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct Content {
-    pub html: String,
-    pub text: String,
+impl Content {
+    /// Returns the HTML and the plain text versions of the content.
+    pub fn render(&self) -> (String, String) {
+        match self {
+            Content::Html { html, text } => (html.clone(), text.clone()),
+            Content::Markdown { markdown } => {
+                (markdown::to_html(markdown), markdown::to_text(markdown))
+            }
+        }
+    }
 }
@@ -0,0 +1,128 @@
+use serde::Serialize;
+
+/// The emails we send. Each variant names a template, and holds the values
+/// the template is rendered with. Links are built by the server, and can be
+/// marked safe in html templates.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum EmailTemplate {
+    Confirmation(ConfirmationContext),
+    AlreadySubscribed(AlreadySubscribedContext),
+    Newsletter(NewsletterContext),
+    Digest(DigestContext),
+    PasswordReset(PasswordResetContext),
+    SubscriberData(SubscriberDataContext),
+}
+
+impl EmailTemplate {
+    /// Name of the template, which is also the name of the directory
+    /// holding its subject, html and text variants.
+    pub fn name(&self) -> &'static str {
+        match self {
+            EmailTemplate::Confirmation(_) => "confirmation",
+            EmailTemplate::AlreadySubscribed(_) => "already_subscribed",
+            EmailTemplate::Newsletter(_) => "newsletter",
+            EmailTemplate::Digest(_) => "digest",
+            EmailTemplate::PasswordReset(_) => "password_reset",
+            EmailTemplate::SubscriberData(_) => "subscriber_data",
+        }
+    }
+
+    /// One email of each kind, with placeholder values, used to check that
+    /// every template exists and renders.
+    pub fn samples() -> Vec<EmailTemplate> {
+        vec![
+            EmailTemplate::Confirmation(ConfirmationContext {
+                username: "John Doe".to_string(),
+                confirmation_link: "http://127.0.0.1/confirmation?token=abc".to_string(),
+            }),
+            EmailTemplate::AlreadySubscribed(AlreadySubscribedContext {
+                username: "John Doe".to_string(),
+            }),
+            EmailTemplate::Newsletter(NewsletterContext {
+                title: "Newsletter".to_string(),
+                html_content: "<p>News</p>".to_string(),
+                text_content: "News".to_string(),
+                unsubscribe_link: "http://127.0.0.1/unsubscribe?token=abc".to_string(),
+                preferences_link: "http://127.0.0.1/preferences?token=abc".to_string(),
+                web_link: "http://127.0.0.1/issues/news-0f1e2d3c".to_string(),
+            }),
+            EmailTemplate::Digest(DigestContext {
+                title: "Weekly digest".to_string(),
+                issues: vec![DigestIssue {
+                    title: "News".to_string(),
+                    link: "http://127.0.0.1/issues/news-0f1e2d3c".to_string(),
+                }],
+                unsubscribe_link: "http://127.0.0.1/unsubscribe?token=abc".to_string(),
+                preferences_link: "http://127.0.0.1/preferences?token=abc".to_string(),
+                web_link: "http://127.0.0.1/issues".to_string(),
+            }),
+            EmailTemplate::PasswordReset(PasswordResetContext {
+                username: "John Doe".to_string(),
+                reset_link: "http://127.0.0.1/password_reset?token=abc".to_string(),
+            }),
+            EmailTemplate::SubscriberData(SubscriberDataContext {
+                data_link: "http://127.0.0.1/data?token=abc".to_string(),
+            }),
+        ]
+    }
+}
+
+/// Sent to a new subscriber, with the link to confirm the subscription.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ConfirmationContext {
+    pub username: String,
+    pub confirmation_link: String,
+}
+
+/// Sent when someone subscribes with an email which is already confirmed.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AlreadySubscribedContext {
+    pub username: String,
+}
+
+/// Sent to confirmed subscribers, wrapped in the layout. The html content is
+/// trusted, and is not escaped.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct NewsletterContext {
+    pub title: String,
+    pub html_content: String,
+    pub text_content: String,
+    pub unsubscribe_link: String,
+    /// Link to the page where the subscriber chooses topics and frequency.
+    pub preferences_link: String,
+    /// Link to the archived copy of the newsletter.
+    pub web_link: String,
+}
+
+/// Sent weekly, wrapped in the layout, to the subscribers who chose a digest,
+/// with links to the newsletters published since their previous digest.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DigestContext {
+    pub title: String,
+    pub issues: Vec<DigestIssue>,
+    pub unsubscribe_link: String,
+    pub preferences_link: String,
+    /// Link to the archive.
+    pub web_link: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DigestIssue {
+    pub title: String,
+    /// Link to the archived copy of the newsletter.
+    pub link: String,
+}
+
+/// Sent to a user who forgot their password.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PasswordResetContext {
+    pub username: String,
+    pub reset_link: String,
+}
+
+/// Sent to a subscriber who asked for the data we hold about them.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SubscriberDataContext {
+    pub data_link: String,
+}
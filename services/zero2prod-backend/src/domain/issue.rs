@@ -0,0 +1,214 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::domain::{Content, FieldError, Segment};
+
+/// A newsletter issue, from its first draft to its publication.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct Issue {
+    pub id: Uuid,
+    pub title: String,
+    pub content: Content,
+    pub status: IssueStatus,
+    /// When the scheduler publishes the issue, if it is scheduled.
+    pub scheduled_at: Option<DateTime<Utc>>,
+    /// Publish the content even if the sanitizer removed too much of it.
+    pub force: bool,
+    /// Ids or slugs of the lists the issue is sent to, the default list if
+    /// there are none.
+    pub lists: Vec<String>,
+    /// Restricts the issue to part of the subscribers of the lists.
+    pub segment: Segment,
+    /// Track the opens and clicks of the recipients, unless their list turns
+    /// tracking off.
+    pub tracking: bool,
+    /// The admin who wrote the issue.
+    pub author_id: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Issue {
+    /// A new issue, scheduled if the request has a date.
+    pub fn new(request: IssueRequest, author_id: Uuid) -> Issue {
+        let now = Utc::now();
+        Issue {
+            id: Uuid::new_v4(),
+            title: String::new(),
+            content: request.content.clone(),
+            status: IssueStatus::Draft,
+            scheduled_at: None,
+            force: false,
+            lists: Vec::new(),
+            segment: Segment::default(),
+            tracking: false,
+            author_id,
+            created_at: now,
+            updated_at: now,
+        }
+        .update(request)
+    }
+
+    /// The issue, with its content and schedule replaced by that of the request.
+    pub fn update(self, request: IssueRequest) -> Issue {
+        let IssueRequest {
+            title,
+            content,
+            scheduled_at,
+            force,
+            lists,
+            segment,
+            tracking,
+        } = request;
+        let status = if scheduled_at.is_some() {
+            IssueStatus::Scheduled
+        } else {
+            IssueStatus::Draft
+        };
+        Issue {
+            title,
+            content,
+            status,
+            scheduled_at,
+            force,
+            lists,
+            segment,
+            tracking,
+            updated_at: Utc::now(),
+            ..self
+        }
+    }
+}
+
+#[derive(sqlx::Type, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[sqlx(type_name = "issue_status")]
+#[sqlx(rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum IssueStatus {
+    Draft,
+    Scheduled,
+    Sending,
+    Sent,
+    Failed,
+}
+
+impl FromStr for IssueStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "draft" => Ok(IssueStatus::Draft),
+            "scheduled" => Ok(IssueStatus::Scheduled),
+            "sending" => Ok(IssueStatus::Sending),
+            "sent" => Ok(IssueStatus::Sent),
+            "failed" => Ok(IssueStatus::Failed),
+            _ => Err(format!("Invalid Issue Status: {s}")),
+        }
+    }
+}
+
+impl IssueStatus {
+    pub fn as_str(&self) -> &str {
+        match self {
+            IssueStatus::Draft => "draft",
+            IssueStatus::Scheduled => "scheduled",
+            IssueStatus::Sending => "sending",
+            IssueStatus::Sent => "sent",
+            IssueStatus::Failed => "failed",
+        }
+    }
+
+    /// Issues can be modified, or deleted, until their sending starts.
+    pub fn is_editable(&self) -> bool {
+        matches!(self, IssueStatus::Draft | IssueStatus::Scheduled)
+    }
+}
+
+/// This is the information sent by an admin to write, or rewrite, an issue.
+#[derive(Deserialize, Serialize, Debug, Clone, ToSchema)]
+pub struct IssueRequest {
+    pub title: String,
+    pub content: Content,
+    /// When to publish the issue. Without a date, the issue stays a draft.
+    #[serde(default)]
+    pub scheduled_at: Option<DateTime<Utc>>,
+    /// Publish the content even if the sanitizer removed too much of it.
+    #[serde(default)]
+    pub force: bool,
+    /// Ids or slugs of the lists to send the issue to, the default list if
+    /// there are none.
+    #[serde(default)]
+    pub lists: Vec<String>,
+    /// Restricts the issue to part of the subscribers of the lists.
+    #[serde(default)]
+    pub segment: Segment,
+    /// Track the opens and clicks of the recipients, unless their list turns
+    /// tracking off.
+    #[serde(default)]
+    pub tracking: bool,
+}
+
+impl IssueRequest {
+    pub fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut errors = Vec::new();
+        if self.title.trim().is_empty() {
+            errors.push(FieldError::new("title", "Empty title"));
+        }
+        if matches!(self.scheduled_at, Some(at) if at <= Utc::now()) {
+            errors.push(FieldError::new("scheduled_at", "Not in the future"));
+        }
+        if let Err(segment_errors) = self.segment.validate() {
+            errors.extend(segment_errors);
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use speculoos::prelude::*;
+
+    fn request(scheduled_at: Option<DateTime<Utc>>) -> IssueRequest {
+        IssueRequest {
+            title: "News".to_string(),
+            content: Content::Markdown {
+                markdown: "# News".to_string(),
+            },
+            scheduled_at,
+            force: false,
+            lists: Vec::new(),
+            segment: Segment::default(),
+            tracking: false,
+        }
+    }
+
+    #[test]
+    fn issue_should_be_scheduled_only_with_a_date() {
+        let draft = Issue::new(request(None), Uuid::new_v4());
+        let scheduled = draft
+            .clone()
+            .update(request(Some(Utc::now() + Duration::hours(1))));
+
+        assert_that(&draft.status).is_equal_to(IssueStatus::Draft);
+        assert_that(&scheduled.status).is_equal_to(IssueStatus::Scheduled);
+        assert_that(&scheduled.id).is_equal_to(draft.id);
+    }
+
+    #[test]
+    fn request_should_not_be_scheduled_in_the_past() {
+        let errors = request(Some(Utc::now() - Duration::hours(1)))
+            .validate()
+            .unwrap_err();
+
+        assert_that(&errors[0].field).is_equal_to("scheduled_at".to_string());
+    }
+}
@@ -0,0 +1,105 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::domain::{FieldError, SubscriptionStatus};
+
+/// Slug of the list used when a subscription, or a newsletter, does not name one.
+/// It is created with the database.
+pub const DEFAULT_LIST: &str = "newsletter";
+
+/// A mailing list. Subscribers confirm, and unsubscribe, on each list
+/// separately.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct MailingList {
+    pub id: Uuid,
+    /// Identifies the list in requests, along with its id.
+    pub slug: String,
+    pub name: String,
+    /// Whether the opens and clicks of its subscribers may be tracked. Lists
+    /// of privacy-sensitive subscribers turn it off.
+    pub tracking: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+impl MailingList {
+    pub fn new(request: MailingListRequest) -> MailingList {
+        MailingList {
+            id: Uuid::new_v4(),
+            slug: request.slug,
+            name: request.name,
+            tracking: request.tracking,
+            created_at: Utc::now(),
+        }
+    }
+}
+
+/// A list a subscriber is on, with the status of the subscription to it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ListMembership {
+    pub list_id: Uuid,
+    pub slug: String,
+    pub status: SubscriptionStatus,
+    pub subscribed_at: DateTime<Utc>,
+}
+
+/// This is the information sent by an admin to create a list.
+#[derive(Deserialize, Serialize, Debug, Clone, ToSchema)]
+pub struct MailingListRequest {
+    /// Lowercase letters, digits, and dashes.
+    pub slug: String,
+    pub name: String,
+    /// Whether the opens and clicks of its subscribers may be tracked, the
+    /// default.
+    #[serde(default = "tracking_default")]
+    pub tracking: bool,
+}
+
+fn tracking_default() -> bool {
+    true
+}
+
+impl MailingListRequest {
+    pub fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut errors = Vec::new();
+        let valid_slug = !self.slug.is_empty()
+            && self
+                .slug
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+        if !valid_slug {
+            errors.push(FieldError::new(
+                "slug",
+                "Only lowercase letters, digits, and dashes",
+            ));
+        }
+        if self.name.trim().is_empty() {
+            errors.push(FieldError::new("name", "Empty name"));
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use speculoos::prelude::*;
+
+    #[test]
+    fn request_should_have_a_url_safe_slug() {
+        let request = MailingListRequest {
+            slug: "Rust News".to_string(),
+            name: "Rust News".to_string(),
+            tracking: true,
+        };
+
+        let errors = request.validate().unwrap_err();
+
+        assert_that(&errors[0].field).is_equal_to("slug".to_string());
+    }
+}
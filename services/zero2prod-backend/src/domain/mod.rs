@@ -1,16 +1,47 @@
+pub mod archive;
 pub mod confirmed_subscriber;
+pub mod delivery;
+pub mod digest;
 pub mod email;
+pub mod email_template;
+pub mod field_error;
+pub mod issue;
+pub mod mailing_list;
 pub mod new_subscription;
+pub mod outbox;
 pub mod ports;
+pub mod preferences;
+pub mod segment;
 pub mod subscriber_email;
 pub mod subscriber_name;
 pub mod subscription;
+pub mod subscription_filter;
+pub mod subscription_update;
+pub mod suppression;
+pub mod tracking;
 pub mod user_credentials;
 
+pub use archive::{ArchiveCursor, ArchiveFilter, ArchivedIssue, ArchivedIssueSummary};
 pub use confirmed_subscriber::ConfirmedSubscriber;
+pub use delivery::{Delivery, DeliveryFilter, DeliveryStatus, DeliverySummary};
+pub use digest::Digest;
 pub use email::{BodyData, Content};
+pub use email_template::{
+    AlreadySubscribedContext, ConfirmationContext, DigestContext, DigestIssue, EmailTemplate,
+    NewsletterContext, PasswordResetContext, SubscriberDataContext,
+};
+pub use field_error::FieldError;
+pub use issue::{Issue, IssueRequest, IssueStatus};
+pub use mailing_list::{ListMembership, MailingList, MailingListRequest, DEFAULT_LIST};
 pub use new_subscription::{NewSubscription, SubscriptionRequest};
+pub use outbox::{OutboxEmail, OUTBOX_LEASE};
+pub use preferences::{DeliveryFrequency, SubscriberPreferences};
+pub use segment::Segment;
 pub use subscriber_email::SubscriberEmail;
 pub use subscriber_name::SubscriberName;
 pub use subscription::{Subscription, SubscriptionStatus};
+pub use subscription_filter::{SubscriptionCursor, SubscriptionFilter};
+pub use subscription_update::{SubscriptionUpdate, SubscriptionUpdateRequest};
+pub use suppression::{Suppression, SuppressionReason, SuppressionRequest};
+pub use tracking::{IssueStats, LinkClicks, TrackingEvent, TrackingEventKind};
 pub use user_credentials::{Credentials, CredentialsGenerator};
@@ -1,6 +1,8 @@
+use crate::domain::FieldError;
 use crate::domain::SubscriberEmail;
 use crate::domain::SubscriberName;
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct NewSubscription {
@@ -9,22 +11,37 @@ pub struct NewSubscription {
 }
 
 impl TryFrom<SubscriptionRequest> for NewSubscription {
-    type Error = String;
+    type Error = Vec<FieldError>;
 
+    /// Validates every field of the request, and reports all the fields
+    /// that failed, not just the first one.
     fn try_from(request: SubscriptionRequest) -> Result<Self, Self::Error> {
-        let SubscriptionRequest { username, email } = request;
+        let SubscriptionRequest {
+            username, email, ..
+        } = request;
 
-        let username = SubscriberName::try_from(username)?;
+        let username = SubscriberName::try_from(username);
 
-        let email = SubscriberEmail::try_from(email)?;
+        let email = SubscriberEmail::try_from(email);
 
-        Ok(NewSubscription { username, email })
+        match (username, email) {
+            (Ok(username), Ok(email)) => Ok(NewSubscription { username, email }),
+            (username, email) => Err(username
+                .err()
+                .map(|err| FieldError::new("username", err))
+                .into_iter()
+                .chain(email.err().map(|err| FieldError::new("email", err)))
+                .collect()),
+        }
     }
 }
 
 /// This is the information sent by the user to request a subscription.
-#[derive(Deserialize, Serialize, Debug, Clone)]
+#[derive(Deserialize, Serialize, Debug, Clone, ToSchema)]
 pub struct SubscriptionRequest {
     pub username: String,
     pub email: String,
+    /// Id or slug of the list, the default list if there is none.
+    #[serde(default)]
+    pub list: Option<String>,
 }
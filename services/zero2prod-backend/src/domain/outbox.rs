@@ -0,0 +1,33 @@
+use chrono::{DateTime, Duration, Utc};
+use uuid::Uuid;
+
+use crate::domain::ports::secondary::Email;
+
+/// How long an email is left to the sender which wrote, or claimed, it before
+/// the relay tries to send it again.
+pub const OUTBOX_LEASE: Duration = Duration::minutes(5);
+
+/// A transactional email in the outbox, written with the change it is about,
+/// and sent at least once.
+#[derive(Debug, Clone)]
+pub struct OutboxEmail {
+    pub id: Uuid,
+    pub email: Email,
+    /// Number of failed attempts to send the email.
+    pub attempts: i32,
+    /// When the relay may try to send the email.
+    pub available_at: DateTime<Utc>,
+}
+
+impl OutboxEmail {
+    /// A new email, left to its sender for the lease before the relay picks
+    /// it up.
+    pub fn new(email: Email) -> OutboxEmail {
+        OutboxEmail {
+            id: Uuid::new_v4(),
+            email,
+            attempts: 0,
+            available_at: Utc::now() + OUTBOX_LEASE,
+        }
+    }
+}
@@ -5,8 +5,14 @@ use serde::Serialize;
 use std::fmt;
 use uuid::Uuid;
 
+use super::database_failure::DatabaseFailure;
 use crate::domain::Credentials;
 
+/// Name of the constraint on the unique emails of users.
+pub const UNIQUE_EMAIL: &str = "users_email_key";
+/// Name of the constraint on the unique usernames of users.
+pub const UNIQUE_USERNAME: &str = "users_username_key";
+
 #[cfg_attr(test, mockall::automock)]
 #[async_trait]
 pub trait AuthenticationStorage {
@@ -17,7 +23,12 @@ pub trait AuthenticationStorage {
 
     async fn id_exists(&self, id: &Uuid) -> Result<bool, Error>;
 
+    /// Return the email of the user identified by id, if there is such a user.
+    async fn get_email(&self, id: &Uuid) -> Result<Option<String>, Error>;
+
     // Store credentials (register new user)
+    // Return a UniqueViolation error, on UNIQUE_EMAIL or UNIQUE_USERNAME, if
+    // the email or the username is already registered.
     // TODO Maybe should return the id
     async fn store_credentials(
         &self,
@@ -34,10 +45,27 @@ pub trait AuthenticationStorage {
 #[derive(Clone, Debug, Serialize)]
 pub enum Error {
     /// Error returned by sqlx
-    // *
     Database {
         context: String,
         source: String,
+        /// True if the same statement may succeed if it is tried again.
+        retryable: bool,
+    },
+    /// The statement would have duplicated the key of the named constraint.
+    UniqueViolation {
+        context: String,
+        constraint: String,
+    },
+    /// The statement referred to a row which does not exist, or removed a
+    /// row still referred to, through the named constraint.
+    ForeignKeyViolation {
+        context: String,
+        constraint: String,
+    },
+    /// The transaction could not be serialized with concurrent transactions,
+    /// and may succeed if it is tried again.
+    SerializationFailure {
+        context: String,
     },
     Connection {
         context: String,
@@ -58,9 +86,26 @@ pub enum Error {
 impl fmt::Display for Error {
     fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Error::Database { context, source } => {
+            Error::Database {
+                context, source, ..
+            } => {
                 write!(fmt, "Database: {context} | {source}")
             }
+            Error::UniqueViolation {
+                context,
+                constraint,
+            } => {
+                write!(fmt, "Unique Violation: {context} | {constraint}")
+            }
+            Error::ForeignKeyViolation {
+                context,
+                constraint,
+            } => {
+                write!(fmt, "Foreign Key Violation: {context} | {constraint}")
+            }
+            Error::SerializationFailure { context } => {
+                write!(fmt, "Serialization Failure: {context}")
+            }
             Error::Connection { context, source } => {
                 write!(fmt, "Database Connection: {context} | {source}")
             }
@@ -76,21 +121,56 @@ impl fmt::Display for Error {
 
 impl std::error::Error for Error {}
 
-impl From<ErrorContext<sqlx::Error>> for Error {
-    fn from(err: ErrorContext<sqlx::Error>) -> Self {
-        match err.1 {
-            sqlx::Error::PoolTimedOut => Error::Connection {
-                context: format!("PostgreSQL Storage: Connection Timeout: {}", err.0),
-                source: err.1.to_string(),
+impl Error {
+    /// True if the same call may succeed if it is tried again, because the
+    /// database was unreachable, or the transaction conflicted with another.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Error::Database { retryable, .. } => *retryable,
+            Error::Connection { .. } | Error::SerializationFailure { .. } => true,
+            _ => false,
+        }
+    }
+}
+
+impl From<DatabaseFailure> for Error {
+    fn from(failure: DatabaseFailure) -> Self {
+        match failure {
+            DatabaseFailure::UniqueViolation {
+                context,
+                constraint,
+            } => Error::UniqueViolation {
+                context,
+                constraint,
             },
-            sqlx::Error::Database(_) => Error::Database {
-                context: format!("PostgreSQL Storage: Database: {}", err.0),
-                source: err.1.to_string(),
+            DatabaseFailure::ForeignKeyViolation {
+                context,
+                constraint,
+            } => Error::ForeignKeyViolation {
+                context,
+                constraint,
             },
-            _ => Error::Database {
-                context: format!("PostgreSQL Storage: Miscellaneous: {}", err.0),
-                source: err.1.to_string(),
+            DatabaseFailure::SerializationFailure { context } => {
+                Error::SerializationFailure { context }
+            }
+            DatabaseFailure::Connection { context, source } => {
+                Error::Connection { context, source }
+            }
+            DatabaseFailure::Other {
+                context,
+                source,
+                retryable,
+            } => Error::Database {
+                context,
+                source,
+                retryable,
             },
         }
     }
 }
+
+impl From<ErrorContext<sqlx::Error>> for Error {
+    fn from(err: ErrorContext<sqlx::Error>) -> Self {
+        DatabaseFailure::from(err).into()
+    }
+}
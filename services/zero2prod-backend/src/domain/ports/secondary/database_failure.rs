@@ -0,0 +1,87 @@
+use common::err_context::ErrorContext;
+use sqlx::error::ErrorKind;
+
+/// SQLSTATE of a transaction which could not be serialized with concurrent
+/// transactions.
+const SERIALIZATION_FAILURE: &str = "40001";
+/// SQLSTATE of a transaction aborted to break a deadlock.
+const DEADLOCK_DETECTED: &str = "40P01";
+
+/// What went wrong with a statement, according to sqlx and the database. The
+/// errors of the storage ports are built from it, so that they classify the
+/// failures the same way.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum DatabaseFailure {
+    UniqueViolation {
+        context: String,
+        constraint: String,
+    },
+    ForeignKeyViolation {
+        context: String,
+        constraint: String,
+    },
+    SerializationFailure {
+        context: String,
+    },
+    /// The database could not be reached.
+    Connection {
+        context: String,
+        source: String,
+    },
+    /// Any other failure, which may, or not, succeed if it is tried again.
+    Other {
+        context: String,
+        source: String,
+        retryable: bool,
+    },
+}
+
+impl From<ErrorContext<sqlx::Error>> for DatabaseFailure {
+    fn from(err: ErrorContext<sqlx::Error>) -> Self {
+        let source = err.1.to_string();
+        match err.1 {
+            sqlx::Error::PoolTimedOut => DatabaseFailure::Connection {
+                context: format!("PostgreSQL Storage: Connection Timeout: {}", err.0),
+                source,
+            },
+            sqlx::Error::PoolClosed | sqlx::Error::Io(_) | sqlx::Error::Tls(_) => {
+                DatabaseFailure::Connection {
+                    context: format!(
+                        "PostgreSQL Storage: Could not establish a connection: {}",
+                        err.0
+                    ),
+                    source,
+                }
+            }
+            sqlx::Error::Database(ref db) => {
+                let context = format!("PostgreSQL Storage: Database: {}", err.0);
+                let constraint = db.constraint().unwrap_or_default().to_string();
+                match db.kind() {
+                    ErrorKind::UniqueViolation => DatabaseFailure::UniqueViolation {
+                        context,
+                        constraint,
+                    },
+                    ErrorKind::ForeignKeyViolation => DatabaseFailure::ForeignKeyViolation {
+                        context,
+                        constraint,
+                    },
+                    _ => match db.code().as_deref() {
+                        Some(SERIALIZATION_FAILURE) => {
+                            DatabaseFailure::SerializationFailure { context }
+                        }
+                        code => DatabaseFailure::Other {
+                            context,
+                            source,
+                            retryable: code == Some(DEADLOCK_DETECTED),
+                        },
+                    },
+                }
+            }
+            _ => DatabaseFailure::Other {
+                context: format!("PostgreSQL Storage: Miscellaneous: {}", err.0),
+                source,
+                retryable: false,
+            },
+        }
+    }
+}
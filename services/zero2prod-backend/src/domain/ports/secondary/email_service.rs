@@ -2,6 +2,7 @@
 use crate::domain::SubscriberEmail;
 use async_trait::async_trait;
 use common::err_context::ErrorContext;
+use futures::stream::{self, StreamExt};
 use serde::{Deserialize, Serialize};
 use serde_with::{serde_as, DisplayFromStr};
 use std::fmt;
@@ -9,10 +10,29 @@ use std::fmt;
 #[cfg(test)]
 use mockall::predicate::*;
 
+/// Number of emails sent at the same time by the default `send_batch`.
+const MAX_CONCURRENT_SENDS: usize = 8;
+
 #[cfg_attr(test, mockall::automock)]
 #[async_trait]
-pub trait EmailService {
+pub trait EmailService: Send + Sync {
     async fn send_email(&self, email: Email) -> Result<(), Error>;
+
+    /// Sends the emails, and returns the outcome of each, in the same order,
+    /// with the id the service gave to the message, if it reports one.
+    /// The failure of an email does not prevent the others from being sent.
+    async fn send_batch(&self, emails: Vec<Email>) -> Vec<Result<Option<String>, Error>> {
+        stream::iter(emails)
+            .map(|email| async { self.send_email(email).await.map(|()| None) })
+            .buffered(MAX_CONCURRENT_SENDS)
+            .collect()
+            .await
+    }
+
+    /// Checks that the service is reachable, without sending anything.
+    async fn probe(&self) -> Result<(), Error> {
+        Ok(())
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -22,6 +42,91 @@ pub struct Email {
     pub subject: String,
     pub html_content: String,
     pub text_content: String,
+    #[serde(default)]
+    pub cc: Vec<SubscriberEmail>,
+    #[serde(default)]
+    pub bcc: Vec<SubscriberEmail>,
+    #[serde(default)]
+    pub reply_to: Option<SubscriberEmail>,
+    /// Custom headers, added to those set by the EmailService implementation.
+    #[serde(default)]
+    pub headers: Vec<EmailHeader>,
+    #[serde(default)]
+    pub attachments: Vec<Attachment>,
+}
+
+impl Email {
+    /// An email without copies, custom headers, nor attachments.
+    pub fn new(
+        to: SubscriberEmail,
+        subject: String,
+        html_content: String,
+        text_content: String,
+    ) -> Email {
+        Email {
+            to,
+            subject,
+            html_content,
+            text_content,
+            cc: Vec::new(),
+            bcc: Vec::new(),
+            reply_to: None,
+            headers: Vec::new(),
+            attachments: Vec::new(),
+        }
+    }
+
+    /// Approximate size of the message in bytes, with the attachments
+    /// base64 encoded, as they are sent.
+    pub fn size(&self) -> usize {
+        let headers = self
+            .headers
+            .iter()
+            .map(|header| header.name.len() + header.value.len())
+            .sum::<usize>();
+        let attachments = self
+            .attachments
+            .iter()
+            .map(|attachment| attachment.content.len().div_ceil(3) * 4)
+            .sum::<usize>();
+        self.subject.len()
+            + self.html_content.len()
+            + self.text_content.len()
+            + headers
+            + attachments
+    }
+
+    /// Refuses the email if it is larger than `max_size` bytes.
+    pub fn check_size(&self, max_size: usize) -> Result<(), Error> {
+        let size = self.size();
+        if size > max_size {
+            Err(Error::TooLarge {
+                context: format!(
+                    "Email of {size} bytes, larger than the limit of {max_size} bytes"
+                ),
+            })
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EmailHeader {
+    pub name: String,
+    pub value: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Attachment {
+    pub filename: String,
+    /// The MIME type of the content, eg `image/png`.
+    pub content_type: String,
+    pub content: Vec<u8>,
+    /// Set for inline images, which the HTML content refers to as
+    /// `cid:<content_id>`.
+    #[serde(default)]
+    pub content_id: Option<String>,
 }
 
 /// This is the error used by the email service
@@ -29,32 +134,104 @@ pub struct Email {
 #[serde_as]
 #[derive(Debug, Serialize)]
 pub enum Error {
-    /// Connection issue with the database
+    /// Connection issue with the email service
     Connection {
         context: String,
         #[serde_as(as = "DisplayFromStr")]
         source: reqwest::Error,
     },
+    /// Failure of the exchange with an SMTP relay
+    Smtp {
+        context: String,
+        #[serde_as(as = "DisplayFromStr")]
+        source: lettre::transport::smtp::Error,
+    },
     Configuration {
         context: String,
     },
+    /// The email could not be turned into a message for the service.
+    Invalid {
+        context: String,
+    },
     Missing {
         context: String,
     },
+    /// The email is larger than the service accepts.
+    TooLarge {
+        context: String,
+    },
+    /// The service refused the email, which sending again would not change.
+    Rejected {
+        context: String,
+    },
+    /// The batch the email belonged to could not be sent.
+    Batch {
+        context: String,
+    },
+    /// The service kept failing, through every retry.
+    Unavailable {
+        context: String,
+    },
+    /// The service failed too often lately, and is not called for a while.
+    CircuitOpen {
+        context: String,
+    },
+    /// The recipient is on the suppression list, the email was not sent.
+    Suppressed {
+        context: String,
+    },
+}
+
+impl Error {
+    /// True if sending the email again, later, would fail the same way.
+    pub fn is_permanent(&self) -> bool {
+        matches!(
+            self,
+            Error::Rejected { .. }
+                | Error::TooLarge { .. }
+                | Error::Invalid { .. }
+                | Error::Configuration { .. }
+                | Error::Suppressed { .. }
+        )
+    }
 }
 
 impl fmt::Display for Error {
     fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Error::Connection { context, source } => {
-                write!(fmt, "Database Connection: {context} | {source}")
+                write!(fmt, "Connection: {context} | {source}")
+            }
+            Error::Smtp { context, source } => {
+                write!(fmt, "SMTP: {context} | {source}")
             }
             Error::Configuration { context } => {
-                write!(fmt, "Database Configuration: {context}")
+                write!(fmt, "Configuration: {context}")
+            }
+            Error::Invalid { context } => {
+                write!(fmt, "Invalid Email: {context}")
             }
             Error::Missing { context } => {
                 write!(fmt, "Missing: {context}")
             }
+            Error::TooLarge { context } => {
+                write!(fmt, "Too Large: {context}")
+            }
+            Error::Rejected { context } => {
+                write!(fmt, "Rejected: {context}")
+            }
+            Error::Batch { context } => {
+                write!(fmt, "Batch: {context}")
+            }
+            Error::Unavailable { context } => {
+                write!(fmt, "Unavailable: {context}")
+            }
+            Error::CircuitOpen { context } => {
+                write!(fmt, "Circuit Open: {context}")
+            }
+            Error::Suppressed { context } => {
+                write!(fmt, "Suppressed: {context}")
+            }
         }
     }
 }
@@ -64,7 +241,16 @@ impl std::error::Error for Error {}
 impl From<ErrorContext<reqwest::Error>> for Error {
     fn from(err: ErrorContext<reqwest::Error>) -> Self {
         Error::Connection {
-            context: format!("FIXME: {}", err.0),
+            context: err.0,
+            source: err.1,
+        }
+    }
+}
+
+impl From<ErrorContext<lettre::transport::smtp::Error>> for Error {
+    fn from(err: ErrorContext<lettre::transport::smtp::Error>) -> Self {
+        Error::Smtp {
+            context: err.0,
             source: err.1,
         }
     }
@@ -0,0 +1,39 @@
+/// Interface to a service cleaning the HTML content of newsletters before it
+/// is sent to subscribers.
+use serde::Serialize;
+use std::fmt;
+
+#[cfg_attr(test, mockall::automock)]
+pub trait HtmlSanitizer {
+    /// Returns the html without the tags, attributes and URLs the policy does
+    /// not allow. Unless forced, the html is rejected if the policy removed too
+    /// much of it.
+    fn sanitize(&self, html: &str, force: bool) -> Result<String, Error>;
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub enum Error {
+    /// The sanitizer policy is not valid.
+    Policy { context: String, source: String },
+    /// The sanitizer removed more of the content than the policy allows.
+    Rejected { context: String, removed: f64 },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Policy { context, source } => {
+                write!(fmt, "Sanitizer Policy: {context} | {source}")
+            }
+            Error::Rejected { context, removed } => {
+                write!(
+                    fmt,
+                    "Rejected Content: {context} | {:.0}% removed",
+                    removed * 100.0
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
@@ -0,0 +1,141 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use common::err_context::ErrorContext;
+use serde::Serialize;
+use std::fmt;
+use uuid::Uuid;
+
+use crate::domain::{
+    ArchiveFilter, ArchivedIssue, ArchivedIssueSummary, Delivery, DeliveryFilter, DeliverySummary,
+    Issue, IssueStats, IssueStatus, TrackingEvent,
+};
+
+#[cfg_attr(test, mockall::automock)]
+#[async_trait]
+pub trait IssueStorage {
+    /// Store a new issue
+    async fn create_issue(&self, issue: &Issue) -> Result<(), Error>;
+
+    async fn get_issue(&self, id: &Uuid) -> Result<Option<Issue>, Error>;
+
+    /// List all the issues, the most recent first.
+    async fn list_issues(&self) -> Result<Vec<Issue>, Error>;
+
+    /// Replace the issue, unless its sending has started.
+    /// Return false if there was no such editable issue.
+    async fn update_issue(&self, issue: &Issue) -> Result<bool, Error>;
+
+    /// Delete the issue, unless its sending has started.
+    /// Return false if there was no such editable issue.
+    async fn delete_issue(&self, id: &Uuid) -> Result<bool, Error>;
+
+    /// Turn the scheduled issue back into a draft, unless its sending has started.
+    /// Return false if there was no such scheduled issue.
+    async fn cancel_issue(&self, id: &Uuid) -> Result<bool, Error>;
+
+    /// Mark the scheduled issues which are due as being sent, and return them.
+    /// An issue is only returned once, even with several schedulers.
+    async fn claim_due_issues(&self, now: &DateTime<Utc>) -> Result<Vec<Issue>, Error>;
+
+    /// Mark as failed the issues claimed before the date, and still being
+    /// sent, and return their ids.
+    async fn fail_stalled_issues(&self, before: &DateTime<Utc>) -> Result<Vec<Uuid>, Error>;
+
+    async fn set_issue_status(&self, id: &Uuid, status: &IssueStatus) -> Result<(), Error>;
+
+    /// Keep a published newsletter in the archive.
+    async fn archive_issue(&self, issue: &ArchivedIssue) -> Result<(), Error>;
+
+    /// List a page of the archive, the most recent issues first.
+    async fn list_archived_issues(
+        &self,
+        filter: &ArchiveFilter,
+    ) -> Result<Vec<ArchivedIssueSummary>, Error>;
+
+    async fn get_archived_issue(&self, slug: &str) -> Result<Option<ArchivedIssue>, Error>;
+
+    /// Store the opens, clicks, and other events of the recipients of tracked issues.
+    async fn record_tracking_events(&self, events: &[TrackingEvent]) -> Result<(), Error>;
+
+    /// Compute the engagement with the issue from its tracking events.
+    async fn get_issue_stats(&self, id: &Uuid) -> Result<IssueStats, Error>;
+
+    /// Store the state of the deliveries, replacing their previous one. The
+    /// email of a delivery is not stored, it is that of the subscriber.
+    async fn record_deliveries(&self, deliveries: &[Delivery]) -> Result<(), Error>;
+
+    /// The deliveries of every issue to the subscriber, the latest first.
+    async fn get_subscriber_deliveries(&self, subscriber_id: &Uuid)
+        -> Result<Vec<Delivery>, Error>;
+
+    /// The tracking events of the subscriber, for every tracked issue, the
+    /// latest first.
+    async fn get_subscriber_tracking_events(
+        &self,
+        subscriber_id: &Uuid,
+    ) -> Result<Vec<TrackingEvent>, Error>;
+
+    /// Mark the delivery of the message as bounced.
+    /// Return false if no delivery has this message id.
+    async fn bounce_delivery(&self, message_id: &str, error: &str) -> Result<bool, Error>;
+
+    /// List the deliveries of the issue which match the filter, by email.
+    async fn list_deliveries(
+        &self,
+        id: &Uuid,
+        filter: &DeliveryFilter,
+    ) -> Result<Vec<Delivery>, Error>;
+
+    /// Count the deliveries of the issue in each state.
+    async fn get_delivery_summary(&self, id: &Uuid) -> Result<DeliverySummary, Error>;
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub enum Error {
+    /// Error returned by sqlx
+    Database { context: String, source: String },
+    /// Data store cannot be validated
+    Validation { context: String },
+    /// Connection issue with the database
+    Connection { context: String, source: String },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Database { context, source } => {
+                write!(fmt, "Database: {context} | {source}")
+            }
+            Error::Validation { context } => {
+                write!(fmt, "Data: {context}")
+            }
+            Error::Connection { context, source } => {
+                write!(fmt, "Database Connection: {context} | {source}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<ErrorContext<sqlx::Error>> for Error {
+    fn from(err: ErrorContext<sqlx::Error>) -> Self {
+        match err.1 {
+            sqlx::Error::PoolTimedOut => Error::Connection {
+                context: format!("PostgreSQL Storage: Connection Timeout: {}", err.0),
+                source: err.1.to_string(),
+            },
+            sqlx::Error::Database(_) => Error::Database {
+                context: format!("PostgreSQL Storage: Database: {}", err.0),
+                source: err.1.to_string(),
+            },
+            _ => Error::Connection {
+                context: format!(
+                    "PostgreSQL Storage: Could not establish a connection: {}",
+                    err.0
+                ),
+                source: err.1.to_string(),
+            },
+        }
+    }
+}
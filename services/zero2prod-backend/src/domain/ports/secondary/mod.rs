@@ -1,10 +1,21 @@
 pub mod authentication_storage;
+mod database_failure;
 pub mod email_service;
+pub mod html_sanitizer;
+pub mod issue_storage;
 pub mod subscription_storage;
+pub mod template_engine;
 
-pub use authentication_storage::{AuthenticationStorage, Error as AuthenticationError};
-pub use email_service::{Email, EmailService, Error as EmailError};
-pub use subscription_storage::{Error as SubscriptionError, SubscriptionStorage};
+pub use authentication_storage::{
+    AuthenticationStorage, Error as AuthenticationError, UNIQUE_EMAIL, UNIQUE_USERNAME,
+};
+pub use email_service::{Attachment, Email, EmailHeader, EmailService, Error as EmailError};
+pub use html_sanitizer::{Error as SanitizerError, HtmlSanitizer};
+pub use issue_storage::{Error as IssueError, IssueStorage};
+pub use subscription_storage::{
+    Error as SubscriptionError, SubscriptionStorage, UNIQUE_SUBSCRIPTION_EMAIL,
+};
+pub use template_engine::{Error as TemplateError, TemplateEngine};
 
 #[cfg(test)]
 pub use authentication_storage::MockAuthenticationStorage;
@@ -14,3 +25,12 @@ pub use subscription_storage::MockSubscriptionStorage;
 
 #[cfg(test)]
 pub use email_service::MockEmailService;
+
+#[cfg(test)]
+pub use html_sanitizer::MockHtmlSanitizer;
+
+#[cfg(test)]
+pub use issue_storage::MockIssueStorage;
+
+#[cfg(test)]
+pub use template_engine::MockTemplateEngine;
@@ -1,34 +1,232 @@
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 use common::err_context::ErrorContext;
 use serde::Serialize;
 use std::fmt;
 use uuid::Uuid;
 
-use crate::domain::{ConfirmedSubscriber, NewSubscription, Subscription};
+use super::database_failure::DatabaseFailure;
+use crate::domain::{
+    ArchivedIssueSummary, ConfirmedSubscriber, Digest, ListMembership, MailingList,
+    NewSubscription, OutboxEmail, Segment, SubscriberEmail, SubscriberPreferences, Subscription,
+    SubscriptionFilter, SubscriptionStatus, SubscriptionUpdate, Suppression, SuppressionReason,
+};
+
+/// Name of the constraint on the unique emails of subscriptions.
+pub const UNIQUE_SUBSCRIPTION_EMAIL: &str = "subscriptions_email_key";
 
 #[cfg_attr(test, mockall::automock)]
 #[async_trait]
 pub trait SubscriptionStorage {
-    /// Store a new subscription, and a token, and return the subscription
+    /// Store a new subscription, pending on the list identified by list_id, a
+    /// token to confirm it, and its confirmation email in the outbox, in one
+    /// transaction, and return the subscription.
+    /// Return a UniqueViolation error, on UNIQUE_SUBSCRIPTION_EMAIL, if there is
+    /// already a subscription with the same email.
+    /// The status of the subscription itself tells if its email was confirmed,
+    /// on any list.
     async fn create_subscription_and_store_token(
         &self,
         subscription: &NewSubscription,
+        list_id: &Uuid,
         token: &str,
+        confirmation: &OutboxEmail,
     ) -> Result<Subscription, Error>;
 
+    /// Add the existing subscription, pending, to the list identified by
+    /// list_id, and store a token to confirm it, and its confirmation email in
+    /// the outbox, in one transaction.
+    async fn subscribe_to_list(
+        &self,
+        id: &Uuid,
+        list_id: &Uuid,
+        token: &str,
+        confirmation: &OutboxEmail,
+    ) -> Result<(), Error>;
+
+    /// The status of the subscription on the list, None if it is not on it.
+    async fn get_list_status(
+        &self,
+        id: &Uuid,
+        list_id: &Uuid,
+    ) -> Result<Option<SubscriptionStatus>, Error>;
+
+    /// The lists the subscription identified by id is on, with its status on
+    /// each.
+    async fn get_list_memberships(&self, id: &Uuid) -> Result<Vec<ListMembership>, Error>;
+
     async fn get_subscription_by_email(&self, email: &str) -> Result<Option<Subscription>, Error>;
 
-    async fn get_subscriber_id_by_token(&self, token: &str) -> Result<Option<Uuid>, Error>;
+    /// The ids of the subscriber, and of the list, the token confirms.
+    async fn get_subscriber_id_by_token(&self, token: &str) -> Result<Option<(Uuid, Uuid)>, Error>;
 
     async fn get_token_by_subscriber_id(&self, id: &Uuid) -> Result<Option<String>, Error>;
 
-    /// Modify the status of the subscriber identified by id to 'confirmed'
-    async fn confirm_subscriber_by_id_and_delete_token(&self, id: &Uuid) -> Result<(), Error>;
+    async fn get_token_by_subscriber_and_list(
+        &self,
+        id: &Uuid,
+        list_id: &Uuid,
+    ) -> Result<Option<String>, Error>;
+
+    /// Modify the status of the subscriber identified by id to 'confirmed', on
+    /// the list identified by list_id, and delete the token for that list.
+    /// An erasure of its email is lifted.
+    async fn confirm_subscriber_by_id_and_delete_token(
+        &self,
+        id: &Uuid,
+        list_id: &Uuid,
+    ) -> Result<(), Error>;
 
     /// Delete a previously stored token identified by a subscriber_id
     async fn delete_confirmation_token(&self, id: &Uuid) -> Result<(), Error>;
 
-    async fn get_confirmed_subscribers_email(&self) -> Result<Vec<ConfirmedSubscriber>, Error>;
+    /// The subscribers of the segment confirmed on any of the lists, each
+    /// only once, with the first of the lists they are on. Suppressed and
+    /// erased addresses are left out.
+    async fn get_confirmed_subscribers_email(
+        &self,
+        lists: &[Uuid],
+        segment: &Segment,
+    ) -> Result<Vec<ConfirmedSubscriber>, Error>;
+
+    /// The number of subscribers get_confirmed_subscribers_email returns.
+    async fn count_confirmed_subscribers(
+        &self,
+        lists: &[Uuid],
+        segment: &Segment,
+    ) -> Result<i64, Error>;
+
+    /// Return at most `filter.limit` subscriptions matching the filter, ordered by
+    /// signup date and id, and starting after the filter's cursor.
+    async fn list_subscriptions(
+        &self,
+        filter: &SubscriptionFilter,
+    ) -> Result<Vec<Subscription>, Error>;
+
+    async fn get_subscription_by_id(&self, id: &Uuid) -> Result<Option<Subscription>, Error>;
+
+    /// Apply the update to the subscription identified by id, and return the
+    /// modified subscription, or None if there is no such subscription.
+    /// Confirming a subscription confirms it on all its lists, and deletes its
+    /// pending tokens.
+    async fn update_subscription(
+        &self,
+        id: &Uuid,
+        update: &SubscriptionUpdate,
+    ) -> Result<Option<Subscription>, Error>;
+
+    /// Replace the preferences of the subscription identified by id, and return
+    /// the modified subscription, or None if there is no such subscription.
+    async fn update_preferences(
+        &self,
+        id: &Uuid,
+        preferences: &SubscriberPreferences,
+    ) -> Result<Option<Subscription>, Error>;
+
+    /// Queue the archived issue identified by issue_id for the next digest of
+    /// each of the subscribers.
+    async fn queue_digest(&self, issue_id: &Uuid, subscribers: &[Uuid]) -> Result<(), Error>;
+
+    /// The digests of the subscribers whose oldest queued issue was queued
+    /// before the date.
+    async fn get_due_digests(&self, before: &DateTime<Utc>) -> Result<Vec<Digest>, Error>;
+
+    /// Remove the issues from the queue of the subscriber, once they were sent.
+    async fn complete_digest(&self, subscriber_id: &Uuid, issues: &[Uuid]) -> Result<(), Error>;
+
+    /// The issues queued for the next digest of the subscriber, the oldest
+    /// first.
+    async fn get_queued_digest(
+        &self,
+        subscriber_id: &Uuid,
+    ) -> Result<Vec<ArchivedIssueSummary>, Error>;
+
+    /// Store a subscription with the given status and signup date, on the list
+    /// identified by list_id, and, if there is one, its confirmation token.
+    /// Return None if there is already a subscription for that email, or if
+    /// the email was erased.
+    async fn import_subscription(
+        &self,
+        subscription: &NewSubscription,
+        list_id: &Uuid,
+        status: &SubscriptionStatus,
+        subscribed_at: &DateTime<Utc>,
+        token: Option<String>,
+    ) -> Result<Option<Subscription>, Error>;
+
+    /// Delete the subscription identified by id, along with its tokens.
+    /// Return false if there was no such subscription.
+    async fn delete_subscription(&self, id: &Uuid) -> Result<bool, Error>;
+
+    /// Erase the subscription identified by id, along with its tokens, its
+    /// deliveries and tracking events, and the emails of the outbox sent to
+    /// its address, and record the erasure in the audit log, without the
+    /// email. Only a keyed hash of the email is kept, so that newsletters and
+    /// imports skip it until a new subscription is confirmed.
+    /// Return false if there was no such subscription.
+    async fn erase_subscription(&self, id: &Uuid) -> Result<bool, Error>;
+
+    /// Remove the subscription from the list identified by list_id, and delete
+    /// the subscription once it is on no list.
+    /// Return false if the subscription was not on the list.
+    async fn unsubscribe_from_list(&self, id: &Uuid, list_id: &Uuid) -> Result<bool, Error>;
+
+    /// Store a new list. Return false if there is already a list with that slug.
+    async fn create_list(&self, list: &MailingList) -> Result<bool, Error>;
+
+    /// List all the lists, in the order they were created.
+    async fn get_lists(&self) -> Result<Vec<MailingList>, Error>;
+
+    /// The list identified by key, which is either its id or its slug.
+    async fn get_list(&self, key: &str) -> Result<Option<MailingList>, Error>;
+
+    /// Suppress the email address, which no email is sent to anymore.
+    /// An address already suppressed keeps its first reason and source.
+    async fn suppress_email(
+        &self,
+        email: &SubscriberEmail,
+        reason: SuppressionReason,
+        source: &str,
+    ) -> Result<(), Error>;
+
+    /// The suppressions of the addresses, whatever their case, among emails.
+    async fn get_suppressions(&self, emails: &[SubscriberEmail])
+        -> Result<Vec<Suppression>, Error>;
+
+    /// List the suppressions, with the given reason if any, the latest first.
+    async fn list_suppressions(
+        &self,
+        reason: Option<SuppressionReason>,
+    ) -> Result<Vec<Suppression>, Error>;
+
+    /// Lift the suppression of the address.
+    /// Return false if the address was not suppressed.
+    async fn remove_suppression(&self, email: &SubscriberEmail) -> Result<bool, Error>;
+
+    /// Write an email to the outbox, for an email which comes with no change.
+    async fn queue_email(&self, email: &OutboxEmail) -> Result<(), Error>;
+
+    /// Return at most limit emails of the outbox which are available at now,
+    /// and make them unavailable until lease_until, so that a single relay
+    /// sends them.
+    async fn claim_outbox_emails(
+        &self,
+        now: &DateTime<Utc>,
+        lease_until: &DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<OutboxEmail>, Error>;
+
+    /// Mark the email of the outbox as sent.
+    async fn complete_outbox_email(&self, id: &Uuid) -> Result<(), Error>;
+
+    /// Record a failed attempt to send the email of the outbox, to be tried
+    /// again at retry_at, or given up without one.
+    async fn fail_outbox_email(
+        &self,
+        id: &Uuid,
+        error: &str,
+        retry_at: Option<DateTime<Utc>>,
+    ) -> Result<(), Error>;
 }
 
 #[derive(Clone, Debug, Serialize)]
@@ -37,6 +235,24 @@ pub enum Error {
     Database {
         context: String,
         source: String,
+        /// True if the same statement may succeed if it is tried again.
+        retryable: bool,
+    },
+    /// The statement would have duplicated the key of the named constraint.
+    UniqueViolation {
+        context: String,
+        constraint: String,
+    },
+    /// The statement referred to a row which does not exist, or removed a
+    /// row still referred to, through the named constraint.
+    ForeignKeyViolation {
+        context: String,
+        constraint: String,
+    },
+    /// The transaction could not be serialized with concurrent transactions,
+    /// and may succeed if it is tried again.
+    SerializationFailure {
+        context: String,
     },
     /// Data store cannot be validated
     Validation {
@@ -58,9 +274,26 @@ pub enum Error {
 impl fmt::Display for Error {
     fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Error::Database { context, source } => {
+            Error::Database {
+                context, source, ..
+            } => {
                 write!(fmt, "Database: {context} | {source}")
             }
+            Error::UniqueViolation {
+                context,
+                constraint,
+            } => {
+                write!(fmt, "Unique Violation: {context} | {constraint}")
+            }
+            Error::ForeignKeyViolation {
+                context,
+                constraint,
+            } => {
+                write!(fmt, "Foreign Key Violation: {context} | {constraint}")
+            }
+            Error::SerializationFailure { context } => {
+                write!(fmt, "Serialization Failure: {context}")
+            }
             Error::Validation { context } => {
                 write!(fmt, "Data: {context}")
             }
@@ -79,24 +312,56 @@ impl fmt::Display for Error {
 
 impl std::error::Error for Error {}
 
-impl From<ErrorContext<sqlx::Error>> for Error {
-    fn from(err: ErrorContext<sqlx::Error>) -> Self {
-        match err.1 {
-            sqlx::Error::PoolTimedOut => Error::Connection {
-                context: format!("PostgreSQL Storage: Connection Timeout: {}", err.0),
-                source: err.1.to_string(),
+impl Error {
+    /// True if the same call may succeed if it is tried again, because the
+    /// database was unreachable, or the transaction conflicted with another.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Error::Database { retryable, .. } => *retryable,
+            Error::Connection { .. } | Error::SerializationFailure { .. } => true,
+            _ => false,
+        }
+    }
+}
+
+impl From<DatabaseFailure> for Error {
+    fn from(failure: DatabaseFailure) -> Self {
+        match failure {
+            DatabaseFailure::UniqueViolation {
+                context,
+                constraint,
+            } => Error::UniqueViolation {
+                context,
+                constraint,
             },
-            sqlx::Error::Database(_) => Error::Database {
-                context: format!("PostgreSQL Storage: Database: {}", err.0),
-                source: err.1.to_string(),
+            DatabaseFailure::ForeignKeyViolation {
+                context,
+                constraint,
+            } => Error::ForeignKeyViolation {
+                context,
+                constraint,
             },
-            _ => Error::Connection {
-                context: format!(
-                    "PostgreSQL Storage: Could not establish a connection: {}",
-                    err.0
-                ),
-                source: err.1.to_string(),
+            DatabaseFailure::SerializationFailure { context } => {
+                Error::SerializationFailure { context }
+            }
+            DatabaseFailure::Connection { context, source } => {
+                Error::Connection { context, source }
+            }
+            DatabaseFailure::Other {
+                context,
+                source,
+                retryable,
+            } => Error::Database {
+                context,
+                source,
+                retryable,
             },
         }
     }
 }
+
+impl From<ErrorContext<sqlx::Error>> for Error {
+    fn from(err: ErrorContext<sqlx::Error>) -> Self {
+        DatabaseFailure::from(err).into()
+    }
+}
@@ -0,0 +1,52 @@
+/// Interface to a service rendering the emails we send from templates.
+use common::err_context::ErrorContext;
+use serde::Serialize;
+use std::fmt;
+
+use crate::domain::ports::secondary::Email;
+use crate::domain::{EmailTemplate, SubscriberEmail};
+
+#[cfg_attr(test, mockall::automock)]
+pub trait TemplateEngine {
+    /// Render the subject, html and text variants of the template, as an
+    /// email sent to `to`.
+    fn render(&self, to: &SubscriberEmail, template: &EmailTemplate) -> Result<Email, Error>;
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub enum Error {
+    /// The template, or one of its variants, could not be found.
+    Missing { context: String, source: String },
+    /// The template could not be parsed, or rendered with its context.
+    Render { context: String, source: String },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Missing { context, source } => {
+                write!(fmt, "Missing Template: {context} | {source}")
+            }
+            Error::Render { context, source } => {
+                write!(fmt, "Template Rendering: {context} | {source}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<ErrorContext<minijinja::Error>> for Error {
+    fn from(err: ErrorContext<minijinja::Error>) -> Self {
+        match err.1.kind() {
+            minijinja::ErrorKind::TemplateNotFound => Error::Missing {
+                context: err.0,
+                source: err.1.to_string(),
+            },
+            _ => Error::Render {
+                context: err.0,
+                source: format!("{:#}", err.1),
+            },
+        }
+    }
+}
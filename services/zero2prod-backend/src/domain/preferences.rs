@@ -0,0 +1,129 @@
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use utoipa::ToSchema;
+
+use crate::domain::FieldError;
+
+/// Upper bound on the number of topics a subscriber follows.
+const MAX_TOPICS: usize = 32;
+
+/// How often a subscriber receives the newsletters.
+#[derive(
+    sqlx::Type, Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema,
+)]
+#[sqlx(type_name = "delivery_frequency")]
+#[sqlx(rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryFrequency {
+    /// Each newsletter as soon as it is published.
+    #[default]
+    Immediate,
+    /// A digest of the newsletters published during the week.
+    Weekly,
+}
+
+impl FromStr for DeliveryFrequency {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "immediate" => Ok(DeliveryFrequency::Immediate),
+            "weekly" => Ok(DeliveryFrequency::Weekly),
+            _ => Err(format!("Invalid Delivery Frequency: {s}")),
+        }
+    }
+}
+
+impl DeliveryFrequency {
+    pub fn as_str(&self) -> &str {
+        match self {
+            DeliveryFrequency::Immediate => "immediate",
+            DeliveryFrequency::Weekly => "weekly",
+        }
+    }
+}
+
+/// What a subscriber chooses to receive, from the preferences page.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct SubscriberPreferences {
+    /// The topics the subscriber is interested in, which newsletters can be
+    /// segmented on. Lowercase letters, digits, and dashes.
+    #[serde(default)]
+    pub topics: Vec<String>,
+    #[serde(default)]
+    pub frequency: DeliveryFrequency,
+}
+
+impl SubscriberPreferences {
+    pub fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut errors = Vec::new();
+        if self.topics.len() > MAX_TOPICS {
+            errors.push(FieldError::new(
+                "topics",
+                format!("No more than {MAX_TOPICS} topics"),
+            ));
+        }
+        errors.extend(validate_topics(&self.topics));
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// The preferences, with their topics sorted, and without duplicates.
+    pub fn normalize(mut self) -> SubscriberPreferences {
+        self.topics.sort();
+        self.topics.dedup();
+        self
+    }
+}
+
+/// Reports the topics which are not made of lowercase letters, digits, and dashes.
+pub fn validate_topics(topics: &[String]) -> Vec<FieldError> {
+    topics
+        .iter()
+        .filter(|topic| {
+            topic.is_empty()
+                || !topic
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+        })
+        .map(|topic| {
+            FieldError::new(
+                "topics",
+                format!("{topic:?} is not made of lowercase letters, digits, and dashes"),
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use speculoos::prelude::*;
+
+    #[test]
+    fn preferences_should_reject_invalid_topics() {
+        let preferences = SubscriberPreferences {
+            topics: vec!["rust".to_string(), "Web Assembly".to_string()],
+            frequency: DeliveryFrequency::Weekly,
+        };
+
+        let errors = preferences.validate().unwrap_err();
+
+        assert_eq!(errors.len(), 1);
+        assert_that(&errors[0].field).is_equal_to("topics".to_string());
+    }
+
+    #[test]
+    fn preferences_should_default_to_immediate_delivery() {
+        let preferences: SubscriberPreferences =
+            serde_json::from_str(r#"{"topics": ["rust", "async", "rust"]}"#).unwrap();
+
+        let preferences = preferences.normalize();
+
+        assert_that(&preferences.frequency).is_equal_to(DeliveryFrequency::Immediate);
+        assert_that(&preferences.topics).is_equal_to(vec!["async".to_string(), "rust".to_string()]);
+    }
+}
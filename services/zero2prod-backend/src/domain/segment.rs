@@ -0,0 +1,78 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::domain::preferences::validate_topics;
+use crate::domain::FieldError;
+
+/// Narrows the confirmed subscribers of the lists a newsletter is sent to.
+/// Criteria left empty select every subscriber.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct Segment {
+    /// Subscribers following at least one of these topics.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub topics: Vec<String>,
+    /// Subscribers who subscribed at, or after, this date.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subscribed_after: Option<DateTime<Utc>>,
+    /// Subscribers who subscribed before this date.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subscribed_before: Option<DateTime<Utc>>,
+    /// Subscribers whose custom attributes contain this JSON object, as with
+    /// the postgres `@>` operator.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<Object>)]
+    pub attributes: Option<serde_json::Value>,
+}
+
+impl Segment {
+    /// True if the segment has no criteria, and selects every subscriber.
+    pub fn is_empty(&self) -> bool {
+        self == &Segment::default()
+    }
+
+    pub fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut errors = validate_topics(&self.topics);
+        if let (Some(after), Some(before)) = (self.subscribed_after, self.subscribed_before) {
+            if after >= before {
+                errors.push(FieldError::new(
+                    "subscribed_before",
+                    "subscribed_before is not after subscribed_after",
+                ));
+            }
+        }
+        if matches!(&self.attributes, Some(attributes) if !attributes.is_object()) {
+            errors.push(FieldError::new("attributes", "Not a JSON object"));
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn segment_should_reject_inconsistent_criteria() {
+        let now = Utc::now();
+        let segment = Segment {
+            topics: vec!["rust".to_string()],
+            subscribed_after: Some(now),
+            subscribed_before: Some(now - Duration::days(1)),
+            attributes: Some(serde_json::json!(["plan", "pro"])),
+        };
+
+        let errors = segment.validate().unwrap_err();
+
+        let fields = errors
+            .iter()
+            .map(|err| err.field.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(fields, vec!["subscribed_before", "attributes"]);
+    }
+}
@@ -1,19 +1,28 @@
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::str::FromStr;
+use utoipa::ToSchema;
 use uuid::Uuid;
 
 use crate::domain::SubscriberEmail;
 use crate::domain::SubscriberName;
+use crate::domain::SubscriberPreferences;
 
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
 pub struct Subscription {
     pub id: Uuid,
     pub email: SubscriberEmail,
     pub username: SubscriberName,
     pub status: SubscriptionStatus,
+    pub subscribed_at: DateTime<Utc>,
+    pub preferences: SubscriberPreferences,
+    /// Custom attributes, set by the admins, which newsletters can be
+    /// segmented on.
+    #[schema(value_type = Object)]
+    pub attributes: serde_json::Value,
 }
 
-#[derive(sqlx::Type, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(sqlx::Type, Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
 #[sqlx(type_name = "subscription_status")]
 #[sqlx(rename_all = "snake_case")]
 #[serde(rename_all = "snake_case")]
@@ -0,0 +1,77 @@
+use base64::engine::general_purpose;
+use base64::Engine;
+use chrono::{DateTime, SecondsFormat, Utc};
+use uuid::Uuid;
+
+use crate::domain::SubscriptionStatus;
+
+/// Criteria used to select a page of subscriptions. Subscriptions are ordered
+/// by signup date, then by id, and the page starts right after the cursor.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SubscriptionFilter {
+    pub status: Option<SubscriptionStatus>,
+    pub subscribed_after: Option<DateTime<Utc>>,
+    pub subscribed_before: Option<DateTime<Utc>>,
+    /// Case insensitive substring of the email.
+    pub email: Option<String>,
+    pub after: Option<SubscriptionCursor>,
+    pub limit: i64,
+}
+
+/// Position of a subscription in the listing order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SubscriptionCursor {
+    pub subscribed_at: DateTime<Utc>,
+    pub id: Uuid,
+}
+
+impl SubscriptionCursor {
+    /// Encodes the cursor as an opaque, url safe, string.
+    pub fn encode(&self) -> String {
+        let raw = format!(
+            "{}|{}",
+            self.subscribed_at
+                .to_rfc3339_opts(SecondsFormat::Micros, true),
+            self.id
+        );
+        general_purpose::URL_SAFE_NO_PAD.encode(raw)
+    }
+
+    pub fn decode<S: AsRef<str>>(s: S) -> Result<SubscriptionCursor, String> {
+        let invalid = || format!("{} is not a valid cursor.", s.as_ref());
+        let raw = general_purpose::URL_SAFE_NO_PAD
+            .decode(s.as_ref())
+            .map_err(|_| invalid())?;
+        let raw = String::from_utf8(raw).map_err(|_| invalid())?;
+        let (subscribed_at, id) = raw.split_once('|').ok_or_else(invalid)?;
+        let subscribed_at = DateTime::parse_from_rfc3339(subscribed_at)
+            .map_err(|_| invalid())?
+            .with_timezone(&Utc);
+        let id = Uuid::parse_str(id).map_err(|_| invalid())?;
+        Ok(SubscriptionCursor { subscribed_at, id })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use speculoos::prelude::*;
+
+    #[test]
+    fn cursor_should_survive_a_round_trip() {
+        let cursor = SubscriptionCursor {
+            subscribed_at: DateTime::parse_from_rfc3339("2023-09-01T10:11:12.123456Z")
+                .unwrap()
+                .with_timezone(&Utc),
+            id: Uuid::new_v4(),
+        };
+        assert_that(&SubscriptionCursor::decode(cursor.encode())).is_equal_to(Ok(cursor));
+    }
+
+    #[test]
+    fn garbage_cursor_is_rejected() {
+        assert_that(&SubscriptionCursor::decode("not a cursor")).is_err();
+        let raw = general_purpose::URL_SAFE_NO_PAD.encode("yesterday|42");
+        assert_that(&SubscriptionCursor::decode(raw)).is_err();
+    }
+}
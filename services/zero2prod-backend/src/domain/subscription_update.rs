@@ -0,0 +1,58 @@
+use crate::domain::FieldError;
+use crate::domain::SubscriberName;
+use crate::domain::SubscriptionStatus;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// A partial modification of a subscription. Fields left to `None` are unchanged.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SubscriptionUpdate {
+    pub username: Option<SubscriberName>,
+    pub status: Option<SubscriptionStatus>,
+    pub attributes: Option<serde_json::Value>,
+}
+
+impl TryFrom<SubscriptionUpdateRequest> for SubscriptionUpdate {
+    type Error = Vec<FieldError>;
+
+    fn try_from(request: SubscriptionUpdateRequest) -> Result<Self, Self::Error> {
+        let SubscriptionUpdateRequest {
+            username,
+            status,
+            attributes,
+        } = request;
+
+        let mut errors = Vec::new();
+        let username = username
+            .map(SubscriberName::parse)
+            .transpose()
+            .unwrap_or_else(|err| {
+                errors.push(FieldError::new("username", err));
+                None
+            });
+        if matches!(&attributes, Some(attributes) if !attributes.is_object()) {
+            errors.push(FieldError::new("attributes", "Not a JSON object"));
+        }
+
+        if errors.is_empty() {
+            Ok(SubscriptionUpdate {
+                username,
+                status,
+                attributes,
+            })
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+/// This is the information sent by an admin to modify a subscription.
+/// The email identifies the subscriber, and cannot be modified.
+#[derive(Deserialize, Serialize, Debug, Clone, ToSchema)]
+pub struct SubscriptionUpdateRequest {
+    pub username: Option<String>,
+    pub status: Option<SubscriptionStatus>,
+    /// Replaces the custom attributes, which must be a JSON object.
+    #[schema(value_type = Option<Object>)]
+    pub attributes: Option<serde_json::Value>,
+}
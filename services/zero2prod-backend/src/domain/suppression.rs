@@ -0,0 +1,36 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::domain::SubscriberEmail;
+
+/// Why an address no longer receives emails.
+#[derive(sqlx::Type, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[sqlx(type_name = "suppression_reason")]
+#[sqlx(rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum SuppressionReason {
+    /// The address does not exist, or its server refuses emails for good.
+    HardBounce,
+    /// The recipient reported an email as spam.
+    SpamComplaint,
+    /// An admin suppressed the address.
+    Manual,
+}
+
+/// An address no email is sent to.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct Suppression {
+    /// The address, lowercased.
+    pub email: SubscriberEmail,
+    pub reason: SuppressionReason,
+    /// Where the suppression comes from, eg `webhook` or `admin`.
+    pub source: String,
+    pub suppressed_at: DateTime<Utc>,
+}
+
+/// The address an admin suppresses.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SuppressionRequest {
+    pub email: String,
+}
@@ -0,0 +1,77 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// What happened to a tracked issue for one of its recipients.
+#[derive(sqlx::Type, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[sqlx(type_name = "tracking_event_kind")]
+#[sqlx(rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum TrackingEventKind {
+    /// The email was handed over to the email service.
+    Sent,
+    /// The tracking pixel of the email was loaded.
+    Open,
+    /// A link of the email was followed.
+    Click,
+    /// The email service reported a bounce.
+    Bounce,
+}
+
+impl TrackingEventKind {
+    pub fn as_str(&self) -> &str {
+        match self {
+            TrackingEventKind::Sent => "sent",
+            TrackingEventKind::Open => "open",
+            TrackingEventKind::Click => "click",
+            TrackingEventKind::Bounce => "bounce",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct TrackingEvent {
+    pub issue_id: Uuid,
+    pub subscriber_id: Uuid,
+    pub kind: TrackingEventKind,
+    /// The link followed, for clicks.
+    pub url: Option<String>,
+    pub occurred_at: DateTime<Utc>,
+}
+
+impl TrackingEvent {
+    pub fn new(issue_id: Uuid, subscriber_id: Uuid, kind: TrackingEventKind) -> TrackingEvent {
+        TrackingEvent {
+            issue_id,
+            subscriber_id,
+            kind,
+            url: None,
+            occurred_at: Utc::now(),
+        }
+    }
+}
+
+/// Engagement with a tracked issue.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct IssueStats {
+    pub issue_id: Uuid,
+    /// Number of tracked recipients the issue was sent to.
+    pub recipients: i64,
+    /// Number of recipients who opened the issue at least once.
+    pub unique_opens: i64,
+    /// The links followed, the most clicked first.
+    pub clicks: Vec<LinkClicks>,
+    /// Number of recipients whose email bounced.
+    pub bounces: i64,
+    /// Share of the recipients whose email bounced, between 0 and 1.
+    pub bounce_rate: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct LinkClicks {
+    pub url: String,
+    pub clicks: i64,
+    /// Number of recipients who followed the link.
+    pub unique_clicks: i64,
+}
@@ -4,7 +4,11 @@ use std::fmt;
 use common::err_context::{ErrorContext, ErrorContextExt};
 use common::settings::Settings;
 use common::tracing;
-use zero2prod::application::opts::{Command, Error as OptsError, Opts};
+use utoipa::OpenApi;
+use zero2prod::application::opts::{Command, Error as OptsError, Opts, SubscribersCommand};
+use zero2prod::application::server::routes::openapi::ApiDoc;
+use zero2prod::application::server::ApplicationBaseUrl;
+use zero2prod::application::subscribers_csv::{self, Error as SubscribersCsvError};
 use zero2prod::application::{ApplicationBuilder, Error as ApplicationError};
 
 #[derive(Debug)]
@@ -17,6 +21,10 @@ pub enum Error {
         context: String,
         source: ApplicationError,
     },
+    Subscribers {
+        context: String,
+        source: SubscribersCsvError,
+    },
 }
 
 impl fmt::Display for Error {
@@ -28,6 +36,9 @@ impl fmt::Display for Error {
             Error::Options { context, source } => {
                 write!(fmt, "Options Error: {context} | {source}")
             }
+            Error::Subscribers { context, source } => {
+                write!(fmt, "Subscribers Error: {context} | {source}")
+            }
         }
     }
 }
@@ -52,6 +63,15 @@ impl From<ErrorContext<OptsError>> for Error {
     }
 }
 
+impl From<ErrorContext<SubscribersCsvError>> for Error {
+    fn from(err: ErrorContext<SubscribersCsvError>) -> Self {
+        Error::Subscribers {
+            context: err.0,
+            source: err.1,
+        }
+    }
+}
+
 #[allow(clippy::result_large_err)]
 #[tokio::main]
 async fn main() -> Result<(), Error> {
@@ -67,6 +87,40 @@ async fn main() -> Result<(), Error> {
         Command::Config => {
             println!("{}", serde_json::to_string_pretty(&settings).unwrap());
         }
+        Command::Openapi => {
+            println!("{}", ApiDoc::openapi().to_pretty_json().unwrap());
+        }
+        Command::Subscribers { cmd } => {
+            let builder = ApplicationBuilder::default()
+                .subscription(settings.database)
+                .await
+                .context("could not connect to subscription storage")?;
+            let storage = builder.subscription.clone().expect("subscription");
+            match cmd {
+                SubscribersCommand::Import { file, pending } => {
+                    let builder = builder
+                        .email(settings.email_client)
+                        .await
+                        .context("could not build email service")?
+                        .templates(settings.templates)
+                        .context("could not load email templates")?;
+                    let email = builder.email.expect("email");
+                    let templates = builder.templates.expect("templates");
+                    let base_url = ApplicationBaseUrl(settings.application.base_url);
+                    let report = subscribers_csv::import_file(
+                        &file, pending, &storage, &email, &templates, &base_url,
+                    )
+                    .await
+                    .context("could not import subscriptions")?;
+                    println!("{}", serde_json::to_string_pretty(&report).unwrap());
+                }
+                SubscribersCommand::Export { file } => {
+                    subscribers_csv::export_file(file.as_deref(), storage)
+                        .await
+                        .context("could not export subscriptions")?;
+                }
+            }
+        }
         Command::Run => {
             let app = ApplicationBuilder::new(settings)
                 .await
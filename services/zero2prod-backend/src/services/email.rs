@@ -1,13 +1,21 @@
 use async_trait::async_trait;
-use common::err_context::ErrorContextExt;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use chrono::{DateTime, Utc};
+use common::err_context::{ErrorContext, ErrorContextExt};
 use common::settings::EmailClientSettings;
-use reqwest::Client;
-use serde::Serialize;
-
-use crate::domain::ports::secondary::{Email, EmailError as Error, EmailService};
+use reqwest::header::RETRY_AFTER;
+use reqwest::{Client, Response, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::domain::ports::secondary::{
+    Attachment, Email, EmailError as Error, EmailHeader, EmailService,
+};
 use crate::domain::SubscriberEmail;
+use crate::services::resilience::{Failure, Resilience};
 
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct EmailClient {
     // This is the client end of a connection to an email service API.
     http_client: Client,
@@ -17,6 +25,12 @@ pub struct EmailClient {
     // This is the sender of the email sent to the end user.
     sender: SubscriberEmail,
     authorization_token: String,
+    // Emails larger than this, in bytes, are refused before being sent.
+    max_size: usize,
+    // This is the maximum number of emails in a batch request.
+    batch_size: usize,
+    // Retries and circuit breaker, shared by the clones of the client.
+    resilience: Arc<Resilience>,
 }
 
 impl EmailClient {
@@ -33,58 +47,278 @@ impl EmailClient {
             server_url: settings.server_url,
             sender,
             authorization_token: settings.authorization_token,
+            max_size: settings.max_size,
+            batch_size: settings.batch_size.max(1),
+            resilience: Arc::new(Resilience::new(&settings.retry, &settings.circuit_breaker)),
         })
     }
+
+    /// Sends a chunk of emails in one batch request. Emails too large are
+    /// left out of the request.
+    async fn send_chunk(&self, url: &str, emails: &[Email]) -> Vec<Result<Option<String>, Error>> {
+        let mut outcomes = emails
+            .iter()
+            .map(|email| email.check_size(self.max_size).err().map(Err))
+            .collect::<Vec<Option<Result<Option<String>, Error>>>>();
+        let requests = emails
+            .iter()
+            .zip(&outcomes)
+            .filter(|(_, outcome)| outcome.is_none())
+            .map(|(email, _)| SendEmailRequest::new(self.sender.as_ref(), email))
+            .collect::<Vec<_>>();
+        if !requests.is_empty() {
+            let (mut responses, failure) = match self.post_batch(url, &requests).await {
+                Ok(responses) => (responses.into_iter(), None),
+                Err(err) => (Vec::new().into_iter(), Some(err.to_string())),
+            };
+            for outcome in outcomes.iter_mut().filter(|outcome| outcome.is_none()) {
+                *outcome = Some(match (responses.next(), &failure) {
+                    (Some(response), _) if response.error_code == 0 => Ok(response.message_id),
+                    (Some(response), _) => Err(Error::Rejected {
+                        context: format!(
+                            "Email to {} refused with error code {}: {}",
+                            response.to.unwrap_or_default(),
+                            response.error_code,
+                            response.message
+                        ),
+                    }),
+                    (None, Some(failure)) => Err(Error::Batch {
+                        context: format!("Could not send the batch request: {failure}"),
+                    }),
+                    (None, None) => Err(Error::Batch {
+                        context: "No outcome for the email in the batch response".to_string(),
+                    }),
+                });
+            }
+        }
+        outcomes.into_iter().flatten().collect()
+    }
+
+    async fn post_batch(
+        &self,
+        url: &str,
+        requests: &[SendEmailRequest<'_>],
+    ) -> Result<Vec<SendBatchResponse>, Error> {
+        let responses = self
+            .resilience
+            .run(|| self.post(url, requests))
+            .await?
+            .json()
+            .await
+            .context("http client batch response")?;
+        Ok(responses)
+    }
+
+    /// Posts the request once. Connection failures, throttling and server
+    /// errors are transient, other client errors are permanent.
+    async fn post<B: Serialize + ?Sized>(&self, url: &str, body: &B) -> Result<Response, Failure> {
+        let response = self
+            .http_client
+            .post(url)
+            .header("X-Postmark-Server-Token", &self.authorization_token)
+            .json(body)
+            .send()
+            .await
+            .map_err(|err| {
+                let transient = err.is_connect() || err.is_timeout();
+                let error = Error::from(ErrorContext(
+                    "http client request to email service".to_string(),
+                    err,
+                ));
+                if transient {
+                    Failure::Transient {
+                        error,
+                        retry_after: None,
+                    }
+                } else {
+                    Failure::Permanent(error)
+                }
+            })?;
+        let status = response.status();
+        let retry_after = retry_after(&response);
+        match response.error_for_status_ref() {
+            Ok(_) => Ok(response),
+            Err(err) if status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error() => {
+                Err(Failure::Transient {
+                    error: ErrorContext("http client response".to_string(), err).into(),
+                    retry_after,
+                })
+            }
+            Err(_) => {
+                let message = response.text().await.unwrap_or_default();
+                Err(Failure::Permanent(Error::Rejected {
+                    context: format!(
+                        "Email service refused the request with status {status}: {message}"
+                    ),
+                }))
+            }
+        }
+    }
+}
+
+/// The delay asked for by the `Retry-After` header, given in seconds or as
+/// an HTTP date.
+fn retry_after(response: &Response) -> Option<Duration> {
+    let value = response.headers().get(RETRY_AFTER)?.to_str().ok()?.trim();
+    match value.parse::<u64>() {
+        Ok(seconds) => Some(Duration::from_secs(seconds)),
+        Err(_) => {
+            let date = DateTime::parse_from_rfc2822(value).ok()?;
+            (date.with_timezone(&Utc) - Utc::now()).to_std().ok()
+        }
+    }
 }
 
 #[async_trait]
 impl EmailService for EmailClient {
     async fn send_email(&self, email: Email) -> Result<(), Error> {
-        let Email {
-            to,
-            subject,
-            html_content,
-            text_content,
-        } = email;
+        email.check_size(self.max_size)?;
 
         //TODO: Replace this with Url::join() eventually
         let url = format!("{}/email", self.server_url);
 
-        let request_body = SendEmailRequest {
-            to: to.as_ref(),
-            from: self.sender.as_ref(),
-            subject: &subject,
-            html_content: &html_content,
-            text_content: &text_content,
-        };
+        let request_body = SendEmailRequest::new(self.sender.as_ref(), &email);
 
-        self.http_client
-            .post(&url)
-            .header("X-Postmark-Server-Token", &self.authorization_token)
-            .json(&request_body)
+        self.resilience
+            .run(|| self.post(&url, &request_body))
+            .await?;
+
+        Ok(())
+    }
+
+    async fn send_batch(&self, emails: Vec<Email>) -> Vec<Result<Option<String>, Error>> {
+        let url = format!("{}/email/batch", self.server_url);
+        let mut outcomes = Vec::with_capacity(emails.len());
+        for chunk in emails.chunks(self.batch_size) {
+            outcomes.extend(self.send_chunk(&url, chunk).await);
+        }
+        outcomes
+    }
+
+    /// The service is reachable if it answers, even with a client error.
+    async fn probe(&self) -> Result<(), Error> {
+        let response = self
+            .http_client
+            .get(&self.server_url)
             .send()
             .await
-            .context("http client request to email service")?
-            .error_for_status()
-            .context("http client response")?;
-
+            .context("http client probe of email service")?;
+        if response.status().is_server_error() {
+            response
+                .error_for_status()
+                .context("http client probe response")?;
+        }
         Ok(())
     }
 }
 
+/// The outcome of one email of a batch, in the order of the request.
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct SendBatchResponse {
+    error_code: i64,
+    message: String,
+    #[serde(default)]
+    to: Option<String>,
+    #[serde(rename = "MessageID", default)]
+    message_id: Option<String>,
+}
+
 #[derive(Serialize)]
 #[serde(rename_all = "PascalCase")]
 struct SendEmailRequest<'a> {
     from: &'a str,
     to: &'a str,
+    // Copies and reply to are comma separated lists of addresses.
+    #[serde(skip_serializing_if = "String::is_empty")]
+    cc: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    bcc: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reply_to: Option<&'a str>,
     subject: &'a str,
     html_content: &'a str,
     text_content: &'a str,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    headers: Vec<HeaderRequest<'a>>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    attachments: Vec<AttachmentRequest<'a>>,
+}
+
+impl<'a> SendEmailRequest<'a> {
+    fn new(from: &'a str, email: &'a Email) -> Self {
+        let join = |addresses: &[SubscriberEmail]| {
+            addresses
+                .iter()
+                .map(AsRef::as_ref)
+                .collect::<Vec<_>>()
+                .join(",")
+        };
+        SendEmailRequest {
+            from,
+            to: email.to.as_ref(),
+            cc: join(&email.cc),
+            bcc: join(&email.bcc),
+            reply_to: email.reply_to.as_ref().map(AsRef::as_ref),
+            subject: &email.subject,
+            html_content: &email.html_content,
+            text_content: &email.text_content,
+            headers: email.headers.iter().map(HeaderRequest::from).collect(),
+            attachments: email
+                .attachments
+                .iter()
+                .map(AttachmentRequest::from)
+                .collect(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct HeaderRequest<'a> {
+    name: &'a str,
+    value: &'a str,
+}
+
+impl<'a> From<&'a EmailHeader> for HeaderRequest<'a> {
+    fn from(header: &'a EmailHeader) -> Self {
+        HeaderRequest {
+            name: &header.name,
+            value: &header.value,
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct AttachmentRequest<'a> {
+    name: &'a str,
+    /// The content, base64 encoded.
+    content: String,
+    content_type: &'a str,
+    #[serde(rename = "ContentID", skip_serializing_if = "Option::is_none")]
+    content_id: Option<String>,
+}
+
+impl<'a> From<&'a Attachment> for AttachmentRequest<'a> {
+    fn from(attachment: &'a Attachment) -> Self {
+        AttachmentRequest {
+            name: &attachment.filename,
+            content: BASE64.encode(&attachment.content),
+            content_type: &attachment.content_type,
+            content_id: attachment
+                .content_id
+                .as_ref()
+                .map(|content_id| format!("cid:{content_id}")),
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::domain::ports::secondary::{Email, EmailService};
+    use crate::domain::ports::secondary::{
+        Attachment, Email, EmailError, EmailHeader, EmailService,
+    };
     use crate::domain::SubscriberEmail;
     use crate::services::email::EmailClient;
     use fake::faker::internet::en::SafeEmail;
@@ -92,10 +326,12 @@ mod tests {
     use fake::{Fake, Faker};
     use speculoos::prelude::*;
     // use crate::email::MockEmail;
-    use wiremock::matchers::{any, header, header_exists, method, path};
+    use wiremock::matchers::{any, body_partial_json, header, header_exists, method, path};
     use wiremock::{Mock, MockServer, ResponseTemplate};
 
-    use common::settings::EmailClientSettings;
+    use common::settings::{
+        CircuitBreakerSettings, EmailClientSettings, EmailProvider, RetrySettings,
+    };
 
     // Used by wiremock to ensure that our request sent
     // to the email service has all the fields required.
@@ -117,6 +353,42 @@ mod tests {
     //     }
     // }
 
+    /// Settings for a client of the server, which does not retry.
+    fn email_settings(server_url: String) -> EmailClientSettings {
+        EmailClientSettings {
+            provider: EmailProvider::Http,
+            server_url,
+            sender_email: SafeEmail().fake(),
+            authorization_token: Faker.fake::<String>(),
+            timeout: 10, // sec
+            max_size: 10_485_760,
+            batch_size: 500,
+            retry: RetrySettings {
+                max_attempts: 1,
+                initial_backoff: 10, // ms
+                max_backoff: 100,    // ms
+            },
+            circuit_breaker: CircuitBreakerSettings {
+                failure_threshold: 5,
+                reset_timeout: 30, // sec
+            },
+            smtp: None,
+            failover: None,
+        }
+    }
+
+    /// Settings for a client of the server, which tries each call three times.
+    fn retrying_settings(server_url: String) -> EmailClientSettings {
+        EmailClientSettings {
+            retry: RetrySettings {
+                max_attempts: 3,
+                initial_backoff: 10, // ms
+                max_backoff: 100,    // ms
+            },
+            ..email_settings(server_url)
+        }
+    }
+
     /// Generate a random email subject
     fn subject() -> String {
         Sentence(1..2).fake()
@@ -137,12 +409,7 @@ mod tests {
         // Arrange
         let mock_server = MockServer::start().await;
         let server_url = mock_server.uri();
-        let email_settings = EmailClientSettings {
-            server_url,
-            sender_email: SafeEmail().fake(),
-            authorization_token: Faker.fake::<String>(),
-            timeout: 10, // sec
-        };
+        let email_settings = email_settings(server_url);
         let email_client = EmailClient::new(email_settings)
             .await
             .expect("email client");
@@ -156,12 +423,7 @@ mod tests {
             .mount(&mock_server)
             .await;
 
-        let email = Email {
-            to: email_addr(),
-            subject: subject(),
-            html_content: content(),
-            text_content: content(),
-        };
+        let email = Email::new(email_addr(), subject(), content(), content());
 
         // Act
         let _ = email_client.send_email(email).await;
@@ -175,12 +437,7 @@ mod tests {
 
         let mock_server = MockServer::start().await;
         let server_url = mock_server.uri();
-        let email_settings = EmailClientSettings {
-            server_url,
-            sender_email: SafeEmail().fake(),
-            authorization_token: Faker.fake::<String>(),
-            timeout: 10, // sec
-        };
+        let email_settings = email_settings(server_url);
         let email_client = EmailClient::new(email_settings)
             .await
             .expect("email client");
@@ -191,12 +448,7 @@ mod tests {
             .mount(&mock_server)
             .await;
 
-        let email = Email {
-            to: email_addr(),
-            subject: subject(),
-            html_content: content(),
-            text_content: content(),
-        };
+        let email = Email::new(email_addr(), subject(), content(), content());
 
         // Act
         let outcome = email_client.send_email(email).await;
@@ -210,12 +462,7 @@ mod tests {
         // Arrange
         let mock_server = MockServer::start().await;
         let server_url = mock_server.uri();
-        let email_settings = EmailClientSettings {
-            server_url,
-            sender_email: SafeEmail().fake(),
-            authorization_token: Faker.fake::<String>(),
-            timeout: 10, // sec
-        };
+        let email_settings = email_settings(server_url);
         let email_client = EmailClient::new(email_settings)
             .await
             .expect("email client");
@@ -226,12 +473,7 @@ mod tests {
             .mount(&mock_server)
             .await;
 
-        let email = Email {
-            to: email_addr(),
-            subject: subject(),
-            html_content: content(),
-            text_content: content(),
-        };
+        let email = Email::new(email_addr(), subject(), content(), content());
 
         // Act
         let outcome = email_client.send_email(email).await;
@@ -249,10 +491,8 @@ mod tests {
         let mock_server = MockServer::start().await;
         let server_url = mock_server.uri();
         let email_settings = EmailClientSettings {
-            server_url,
-            sender_email: SafeEmail().fake(),
-            authorization_token: Faker.fake::<String>(),
             timeout: 3, // sec
+            ..email_settings(server_url)
         };
         let email_client = EmailClient::new(email_settings)
             .await
@@ -267,16 +507,290 @@ mod tests {
             .mount(&mock_server)
             .await;
 
-        let email = Email {
-            to: email_addr(),
-            subject: subject(),
-            html_content: content(),
-            text_content: content(),
-        };
+        let email = Email::new(email_addr(), subject(), content(), content());
 
         // Act
         let outcome = email_client.send_email(email).await;
 
         assert_that(&outcome).is_err();
     }
+
+    #[tokio::test]
+    async fn send_email_should_serialize_copies_headers_and_attachments() {
+        // Arrange
+        let mock_server = MockServer::start().await;
+        let server_url = mock_server.uri();
+        let email_settings = email_settings(server_url);
+        let email_client = EmailClient::new(email_settings)
+            .await
+            .expect("email client");
+
+        Mock::given(path("/email"))
+            .and(body_partial_json(serde_json::json!({
+                "Cc": "alice@acme.inc,bob@acme.inc",
+                "ReplyTo": "support@acme.inc",
+                "Headers": [{ "Name": "X-Campaign", "Value": "spring" }],
+                "Attachments": [{
+                    "Name": "logo.png",
+                    "Content": "iVBORw==",
+                    "ContentType": "image/png",
+                    "ContentID": "cid:logo",
+                }],
+            })))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let mut email = Email::new(email_addr(), subject(), content(), content());
+        email.cc = vec![
+            SubscriberEmail::parse("alice@acme.inc").unwrap(),
+            SubscriberEmail::parse("bob@acme.inc").unwrap(),
+        ];
+        email.reply_to = Some(SubscriberEmail::parse("support@acme.inc").unwrap());
+        email.headers = vec![EmailHeader {
+            name: "X-Campaign".to_string(),
+            value: "spring".to_string(),
+        }];
+        email.attachments = vec![Attachment {
+            filename: "logo.png".to_string(),
+            content_type: "image/png".to_string(),
+            content: vec![0x89, 0x50, 0x4e, 0x47],
+            content_id: Some("logo".to_string()),
+        }];
+
+        // Act
+        let outcome = email_client.send_email(email).await;
+
+        // Assert
+        assert_that(&outcome).is_ok();
+    }
+
+    #[tokio::test]
+    async fn send_email_fails_if_the_email_is_too_large() {
+        // Arrange
+        let mock_server = MockServer::start().await;
+        let server_url = mock_server.uri();
+        let email_settings = EmailClientSettings {
+            max_size: 1024,
+            ..email_settings(server_url)
+        };
+        let email_client = EmailClient::new(email_settings)
+            .await
+            .expect("email client");
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(200))
+            .expect(0)
+            .mount(&mock_server)
+            .await;
+
+        let mut email = Email::new(email_addr(), subject(), content(), content());
+        email.attachments = vec![Attachment {
+            filename: "report.pdf".to_string(),
+            content_type: "application/pdf".to_string(),
+            content: vec![0; 1024],
+            content_id: None,
+        }];
+
+        // Act
+        let outcome = email_client.send_email(email).await;
+
+        // Assert
+        assert!(matches!(outcome, Err(EmailError::TooLarge { .. })));
+    }
+
+    #[tokio::test]
+    async fn send_batch_should_chunk_and_report_each_outcome() {
+        // In this test, the service accepts the first email of each batch,
+        // and refuses the second. Three emails make two batches.
+        // Arrange
+        let mock_server = MockServer::start().await;
+        let server_url = mock_server.uri();
+        let email_settings = EmailClientSettings {
+            batch_size: 2,
+            ..email_settings(server_url)
+        };
+        let email_client = EmailClient::new(email_settings)
+            .await
+            .expect("email client");
+
+        Mock::given(path("/email/batch"))
+            .and(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([
+                { "ErrorCode": 0, "Message": "OK", "To": "alice@acme.inc", "MessageID": "b7bc2f4a" },
+                { "ErrorCode": 406, "Message": "Inactive recipient", "To": "bob@acme.inc" },
+            ])))
+            .expect(2)
+            .mount(&mock_server)
+            .await;
+
+        let emails = (0..3)
+            .map(|_| Email::new(email_addr(), subject(), content(), content()))
+            .collect();
+
+        // Act
+        let outcomes = email_client.send_batch(emails).await;
+
+        // Assert
+        assert_eq!(outcomes.len(), 3);
+        assert!(matches!(&outcomes[0], Ok(Some(id)) if id == "b7bc2f4a"));
+        assert!(matches!(outcomes[1], Err(EmailError::Rejected { .. })));
+        assert_that(&outcomes[2]).is_ok();
+    }
+
+    #[tokio::test]
+    async fn send_batch_should_fail_every_email_of_a_failed_batch() {
+        // Arrange
+        let mock_server = MockServer::start().await;
+        let server_url = mock_server.uri();
+        let email_settings = email_settings(server_url);
+        let email_client = EmailClient::new(email_settings)
+            .await
+            .expect("email client");
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(500))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let emails = (0..2)
+            .map(|_| Email::new(email_addr(), subject(), content(), content()))
+            .collect();
+
+        // Act
+        let outcomes = email_client.send_batch(emails).await;
+
+        // Assert
+        assert!(outcomes
+            .iter()
+            .all(|outcome| matches!(outcome, Err(EmailError::Batch { .. }))));
+    }
+
+    #[tokio::test]
+    async fn send_email_retries_if_the_server_is_unavailable() {
+        // Arrange
+        let mock_server = MockServer::start().await;
+        let email_client = EmailClient::new(retrying_settings(mock_server.uri()))
+            .await
+            .expect("email client");
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(503))
+            .up_to_n_times(2)
+            .expect(2)
+            .mount(&mock_server)
+            .await;
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let email = Email::new(email_addr(), subject(), content(), content());
+
+        // Act
+        let outcome = email_client.send_email(email).await;
+
+        // Assert
+        assert_that(&outcome).is_ok();
+    }
+
+    #[tokio::test]
+    async fn send_email_waits_as_long_as_asked_by_retry_after() {
+        // The retry after is above the maximum backoff, which bounds it.
+        // Arrange
+        let mock_server = MockServer::start().await;
+        let email_settings = EmailClientSettings {
+            retry: RetrySettings {
+                max_attempts: 2,
+                initial_backoff: 1, // ms
+                max_backoff: 1000,  // ms
+            },
+            ..email_settings(mock_server.uri())
+        };
+        let email_client = EmailClient::new(email_settings)
+            .await
+            .expect("email client");
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(429).insert_header("Retry-After", "5"))
+            .up_to_n_times(1)
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let email = Email::new(email_addr(), subject(), content(), content());
+
+        // Act
+        let start = std::time::Instant::now();
+        let outcome = email_client.send_email(email).await;
+
+        // Assert
+        assert_that(&outcome).is_ok();
+        assert!(start.elapsed() >= std::time::Duration::from_millis(1000));
+    }
+
+    #[tokio::test]
+    async fn send_email_does_not_retry_if_the_server_refuses_the_request() {
+        // Arrange
+        let mock_server = MockServer::start().await;
+        let email_client = EmailClient::new(retrying_settings(mock_server.uri()))
+            .await
+            .expect("email client");
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(422))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let email = Email::new(email_addr(), subject(), content(), content());
+
+        // Act
+        let outcome = email_client.send_email(email).await;
+
+        // Assert
+        assert!(matches!(outcome, Err(EmailError::Rejected { .. })));
+    }
+
+    #[tokio::test]
+    async fn send_email_fails_fast_once_the_server_failed_too_often() {
+        // Arrange
+        let mock_server = MockServer::start().await;
+        let email_settings = EmailClientSettings {
+            circuit_breaker: CircuitBreakerSettings {
+                failure_threshold: 2,
+                reset_timeout: 30, // sec
+            },
+            ..email_settings(mock_server.uri())
+        };
+        let email_client = EmailClient::new(email_settings)
+            .await
+            .expect("email client");
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(500))
+            .expect(2)
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let mut outcomes = Vec::new();
+        for _ in 0..3 {
+            let email = Email::new(email_addr(), subject(), content(), content());
+            outcomes.push(email_client.send_email(email).await);
+        }
+
+        // Assert
+        assert!(matches!(outcomes[0], Err(EmailError::Unavailable { .. })));
+        assert!(matches!(outcomes[1], Err(EmailError::Unavailable { .. })));
+        assert!(matches!(outcomes[2], Err(EmailError::CircuitOpen { .. })));
+    }
 }
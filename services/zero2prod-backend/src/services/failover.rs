@@ -0,0 +1,307 @@
+use async_trait::async_trait;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::domain::ports::secondary::{Email, EmailError as Error, EmailService};
+
+pub struct Provider {
+    pub name: String,
+    pub service: Arc<dyn EmailService + Send + Sync>,
+}
+
+/// Failover across several email services. Emails go through the first
+/// provider which does not fail. When a provider fails in a way another one may
+/// not (everything but permanent failures), the emails move to the next
+/// provider of the list, which stays in use. Providers preferred to the one in
+/// use are probed periodically, and the first healthy one is used again.
+pub struct FailoverEmailService {
+    // The providers, by order of preference.
+    providers: Vec<Provider>,
+    // Index of the provider in use.
+    active: AtomicUsize,
+    probe_interval: Duration,
+    // When the preferred providers were last probed.
+    last_probe: Mutex<Instant>,
+}
+
+impl FailoverEmailService {
+    /// Panics without providers.
+    pub fn new(providers: Vec<Provider>, probe_interval: Duration) -> Self {
+        assert!(!providers.is_empty(), "Failover without email providers");
+        FailoverEmailService {
+            providers,
+            active: AtomicUsize::new(0),
+            probe_interval,
+            last_probe: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Returns the index of the provider to use, after probing the preferred
+    /// ones, if it is time to.
+    async fn active(&self) -> usize {
+        let active = self.active.load(Ordering::SeqCst);
+        if active == 0 || !self.probe_due() {
+            return active;
+        }
+        for (index, provider) in self.providers[..active].iter().enumerate() {
+            match provider.service.probe().await {
+                Ok(()) => {
+                    tracing::info!(provider = %provider.name, "Email provider back in use");
+                    self.active.store(index, Ordering::SeqCst);
+                    return index;
+                }
+                Err(err) => tracing::debug!(
+                    provider = %provider.name,
+                    "Email provider still failing: {err}"
+                ),
+            }
+        }
+        active
+    }
+
+    /// True if the preferred providers should be probed, in which case the
+    /// next probe is due after the interval.
+    fn probe_due(&self) -> bool {
+        let mut last_probe = self.last_probe.lock().unwrap();
+        if last_probe.elapsed() < self.probe_interval {
+            return false;
+        }
+        *last_probe = Instant::now();
+        true
+    }
+
+    /// Moves on from the provider at index, unless another call did already.
+    fn fail_over(&self, index: usize, err: &Error) {
+        let next = index + 1;
+        if next < self.providers.len()
+            && self
+                .active
+                .compare_exchange(index, next, Ordering::SeqCst, Ordering::SeqCst)
+                .is_ok()
+        {
+            *self.last_probe.lock().unwrap() = Instant::now();
+            tracing::warn!(
+                provider = %self.providers[index].name,
+                next = %self.providers[next].name,
+                "Email provider failing, failing over: {err}"
+            );
+        }
+    }
+
+    /// Logs the outcome of an email with the provider which handled it. Nothing
+    /// exports metrics, so the emails sent by each provider are counted from
+    /// these events of the logs.
+    fn record<T>(&self, index: usize, outcome: &Result<T, Error>) {
+        let provider = &self.providers[index].name;
+        match outcome {
+            Ok(_) => tracing::info!(provider = %provider, outcome = "sent", "Email sent"),
+            Err(err) => tracing::info!(
+                provider = %provider,
+                outcome = "failed",
+                "Email not sent: {err}"
+            ),
+        }
+    }
+}
+
+#[async_trait]
+impl EmailService for FailoverEmailService {
+    async fn send_email(&self, email: Email) -> Result<(), Error> {
+        let last = self.providers.len() - 1;
+        let mut index = self.active().await;
+        loop {
+            let outcome = self.providers[index]
+                .service
+                .send_email(email.clone())
+                .await;
+            match outcome {
+                Err(err) if index < last && !err.is_permanent() => {
+                    self.fail_over(index, &err);
+                    index += 1;
+                }
+                outcome => {
+                    self.record(index, &outcome);
+                    return outcome;
+                }
+            }
+        }
+    }
+
+    async fn send_batch(&self, emails: Vec<Email>) -> Vec<Result<Option<String>, Error>> {
+        let last = self.providers.len() - 1;
+        let mut index = self.active().await;
+        let mut outcomes = emails.iter().map(|_| None).collect::<Vec<_>>();
+        // Positions of the emails still to send.
+        let mut pending = (0..emails.len()).collect::<Vec<_>>();
+        while !pending.is_empty() {
+            let batch = pending.iter().map(|&i| emails[i].clone()).collect();
+            let results = self.providers[index].service.send_batch(batch).await;
+            let mut failed = Vec::new();
+            for (position, outcome) in pending.into_iter().zip(results) {
+                match outcome {
+                    Err(err) if index < last && !err.is_permanent() => {
+                        self.fail_over(index, &err);
+                        failed.push(position);
+                    }
+                    outcome => {
+                        self.record(index, &outcome);
+                        outcomes[position] = Some(outcome);
+                    }
+                }
+            }
+            pending = failed;
+            index += 1;
+        }
+        outcomes
+            .into_iter()
+            .map(|outcome| {
+                outcome.unwrap_or_else(|| {
+                    Err(Error::Batch {
+                        context: "No outcome for the email from the email service".to_string(),
+                    })
+                })
+            })
+            .collect()
+    }
+
+    async fn probe(&self) -> Result<(), Error> {
+        let index = self.active().await;
+        self.providers[index].service.probe().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::ports::secondary::MockEmailService;
+    use crate::domain::SubscriberEmail;
+
+    fn email(to: &str) -> Email {
+        Email::new(
+            SubscriberEmail::parse(to).unwrap(),
+            "Spring news".to_string(),
+            "<p>Hello</p>".to_string(),
+            "Hello".to_string(),
+        )
+    }
+
+    fn unavailable() -> Error {
+        Error::Unavailable {
+            context: "down".to_string(),
+        }
+    }
+
+    fn failover(primary: MockEmailService, secondary: MockEmailService) -> FailoverEmailService {
+        FailoverEmailService::new(
+            vec![
+                Provider {
+                    name: "primary".to_string(),
+                    service: Arc::new(primary),
+                },
+                Provider {
+                    name: "secondary".to_string(),
+                    service: Arc::new(secondary),
+                },
+            ],
+            Duration::ZERO,
+        )
+    }
+
+    #[tokio::test]
+    async fn send_email_should_fail_over_and_stay_on_the_secondary() {
+        let mut primary = MockEmailService::new();
+        primary
+            .expect_send_email()
+            .times(1)
+            .returning(|_| Err(unavailable()));
+        primary
+            .expect_probe()
+            .times(1)
+            .returning(|| Err(unavailable()));
+        let mut secondary = MockEmailService::new();
+        secondary.expect_send_email().times(2).returning(|_| Ok(()));
+        let service = failover(primary, secondary);
+
+        let first = service.send_email(email("alice@acme.inc")).await;
+        let second = service.send_email(email("bob@acme.inc")).await;
+
+        assert!(first.is_ok());
+        assert!(second.is_ok());
+    }
+
+    #[tokio::test]
+    async fn send_email_should_return_to_the_primary_once_healthy() {
+        let mut primary = MockEmailService::new();
+        let mut calls = 0;
+        primary.expect_send_email().times(2).returning(move |_| {
+            calls += 1;
+            if calls == 1 {
+                Err(unavailable())
+            } else {
+                Ok(())
+            }
+        });
+        primary.expect_probe().times(1).returning(|| Ok(()));
+        let mut secondary = MockEmailService::new();
+        secondary.expect_send_email().times(1).returning(|_| Ok(()));
+        let service = failover(primary, secondary);
+
+        let first = service.send_email(email("alice@acme.inc")).await;
+        let second = service.send_email(email("bob@acme.inc")).await;
+
+        assert!(first.is_ok());
+        assert!(second.is_ok());
+    }
+
+    #[tokio::test]
+    async fn send_email_should_not_fail_over_permanent_failures() {
+        let mut primary = MockEmailService::new();
+        primary.expect_send_email().times(1).returning(|_| {
+            Err(Error::Rejected {
+                context: "Inactive recipient".to_string(),
+            })
+        });
+        let mut secondary = MockEmailService::new();
+        secondary.expect_send_email().never();
+        let service = failover(primary, secondary);
+
+        let outcome = service.send_email(email("alice@acme.inc")).await;
+
+        assert!(matches!(outcome, Err(Error::Rejected { .. })));
+    }
+
+    #[tokio::test]
+    async fn send_batch_should_resend_failed_emails_through_the_secondary() {
+        let mut primary = MockEmailService::new();
+        primary.expect_send_batch().times(1).returning(|emails| {
+            assert_eq!(emails.len(), 3);
+            vec![
+                Ok(None),
+                Err(unavailable()),
+                Err(Error::Rejected {
+                    context: "Inactive recipient".to_string(),
+                }),
+            ]
+        });
+        let mut secondary = MockEmailService::new();
+        secondary.expect_send_batch().times(1).returning(|emails| {
+            assert_eq!(emails.len(), 1);
+            assert_eq!(emails[0].to.as_ref(), "bob@acme.inc");
+            vec![Ok(None)]
+        });
+        let service = failover(primary, secondary);
+
+        let outcomes = service
+            .send_batch(vec![
+                email("alice@acme.inc"),
+                email("bob@acme.inc"),
+                email("carol@acme.inc"),
+            ])
+            .await;
+
+        assert!(outcomes[0].is_ok());
+        assert!(outcomes[1].is_ok());
+        assert!(matches!(outcomes[2], Err(Error::Rejected { .. })));
+    }
+}
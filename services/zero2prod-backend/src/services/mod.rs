@@ -1,2 +1,8 @@
 pub mod email;
+pub mod failover;
 pub mod postgres;
+pub mod resilience;
+pub mod sanitizer;
+pub mod smtp;
+pub mod suppression;
+pub mod templates;
@@ -76,6 +76,16 @@ impl AuthenticationStorage for PostgresStorage {
         Ok(exist)
     }
 
+    #[tracing::instrument(name = "Getting user email from postgres")]
+    async fn get_email(&self, id: &Uuid) -> Result<Option<String>, AuthenticationError> {
+        let email = sqlx::query_scalar!(r#"SELECT email FROM users WHERE id = $1"#, id)
+            .fetch_optional(&self.pool)
+            .await
+            .context("Could not get user email")?;
+
+        Ok(email)
+    }
+
     #[tracing::instrument(name = "Checking email exists")]
     async fn email_exists(&self, email: &str) -> Result<bool, AuthenticationError> {
         let exist = sqlx::query_scalar!(
@@ -0,0 +1,592 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use common::err_context::ErrorContextExt;
+use std::str::FromStr;
+use uuid::Uuid;
+
+use super::PostgresStorage;
+use crate::domain::{
+    ports::secondary::IssueError, ports::secondary::IssueStorage, ArchiveFilter, ArchivedIssue,
+    ArchivedIssueSummary, Content, Delivery, DeliveryFilter, DeliveryStatus, DeliverySummary,
+    Issue, IssueStats, IssueStatus, LinkClicks, Segment, TrackingEvent, TrackingEventKind,
+};
+
+#[async_trait]
+impl IssueStorage for PostgresStorage {
+    #[tracing::instrument(name = "Storing a new issue in postgres")]
+    async fn create_issue(&self, issue: &Issue) -> Result<(), IssueError> {
+        sqlx::query!(
+            r#"INSERT INTO issues (id, title, content, status, scheduled_at, force, lists, segment, tracking, author_id, created_at, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)"#,
+            issue.id,
+            issue.title,
+            to_json(&issue.content)?,
+            issue.status as IssueStatus,
+            issue.scheduled_at,
+            issue.force,
+            &issue.lists,
+            segment_to_json(&issue.segment)?,
+            issue.tracking,
+            issue.author_id,
+            issue.created_at,
+            issue.updated_at,
+        )
+        .execute(&self.pool)
+        .await
+        .context(format!("Could not store issue {}", issue.id))?;
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Fetching an issue in postgres")]
+    async fn get_issue(&self, id: &Uuid) -> Result<Option<Issue>, IssueError> {
+        let saved = sqlx::query!(
+            r#"SELECT id, title, content, status::text, scheduled_at, force, lists, segment, tracking, author_id, created_at, updated_at
+            FROM issues WHERE id = $1"#,
+            id
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .context(format!("Could not get issue {id}"))?;
+        saved
+            .map(|rec| {
+                to_issue(
+                    rec.id,
+                    rec.title,
+                    rec.content,
+                    rec.status,
+                    rec.scheduled_at,
+                    rec.force,
+                    rec.lists,
+                    rec.segment,
+                    rec.tracking,
+                    rec.author_id,
+                    rec.created_at,
+                    rec.updated_at,
+                )
+            })
+            .transpose()
+    }
+
+    #[tracing::instrument(name = "Listing issues in postgres")]
+    async fn list_issues(&self) -> Result<Vec<Issue>, IssueError> {
+        let saved = sqlx::query!(
+            r#"SELECT id, title, content, status::text, scheduled_at, force, lists, segment, tracking, author_id, created_at, updated_at
+            FROM issues ORDER BY created_at DESC, id"#,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("Could not list issues")?;
+        saved
+            .into_iter()
+            .map(|rec| {
+                to_issue(
+                    rec.id,
+                    rec.title,
+                    rec.content,
+                    rec.status,
+                    rec.scheduled_at,
+                    rec.force,
+                    rec.lists,
+                    rec.segment,
+                    rec.tracking,
+                    rec.author_id,
+                    rec.created_at,
+                    rec.updated_at,
+                )
+            })
+            .collect()
+    }
+
+    #[tracing::instrument(name = "Updating an issue in postgres")]
+    async fn update_issue(&self, issue: &Issue) -> Result<bool, IssueError> {
+        let result = sqlx::query!(
+            r#"UPDATE issues SET title = $2, content = $3, status = $4, scheduled_at = $5, force = $6, lists = $7, segment = $8, tracking = $9, updated_at = $10
+            WHERE id = $1 AND status IN ('draft', 'scheduled')"#,
+            issue.id,
+            issue.title,
+            to_json(&issue.content)?,
+            issue.status as IssueStatus,
+            issue.scheduled_at,
+            issue.force,
+            &issue.lists,
+            segment_to_json(&issue.segment)?,
+            issue.tracking,
+            issue.updated_at,
+        )
+        .execute(&self.pool)
+        .await
+        .context(format!("Could not update issue {}", issue.id))?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    #[tracing::instrument(name = "Deleting an issue in postgres")]
+    async fn delete_issue(&self, id: &Uuid) -> Result<bool, IssueError> {
+        let result = sqlx::query!(
+            r#"DELETE FROM issues WHERE id = $1 AND status IN ('draft', 'scheduled')"#,
+            id
+        )
+        .execute(&self.pool)
+        .await
+        .context(format!("Could not delete issue {id}"))?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    #[tracing::instrument(name = "Cancelling an issue in postgres")]
+    async fn cancel_issue(&self, id: &Uuid) -> Result<bool, IssueError> {
+        let result = sqlx::query!(
+            r#"UPDATE issues SET status = 'draft', scheduled_at = NULL, updated_at = $2
+            WHERE id = $1 AND status = 'scheduled'"#,
+            id,
+            Utc::now(),
+        )
+        .execute(&self.pool)
+        .await
+        .context(format!("Could not cancel issue {id}"))?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    #[tracing::instrument(name = "Claiming due issues in postgres")]
+    async fn claim_due_issues(&self, now: &DateTime<Utc>) -> Result<Vec<Issue>, IssueError> {
+        // The status is checked and changed in a single statement, so that a
+        // cancellation, or another scheduler, can not get in between.
+        let saved = sqlx::query!(
+            r#"UPDATE issues SET status = 'sending', updated_at = $1
+            WHERE status = 'scheduled' AND scheduled_at <= $1
+            RETURNING id, title, content, status::text, scheduled_at, force, lists, segment, tracking, author_id, created_at, updated_at"#,
+            now,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("Could not claim due issues")?;
+        saved
+            .into_iter()
+            .map(|rec| {
+                to_issue(
+                    rec.id,
+                    rec.title,
+                    rec.content,
+                    rec.status,
+                    rec.scheduled_at,
+                    rec.force,
+                    rec.lists,
+                    rec.segment,
+                    rec.tracking,
+                    rec.author_id,
+                    rec.created_at,
+                    rec.updated_at,
+                )
+            })
+            .collect()
+    }
+
+    #[tracing::instrument(name = "Failing stalled issues in postgres")]
+    async fn fail_stalled_issues(&self, before: &DateTime<Utc>) -> Result<Vec<Uuid>, IssueError> {
+        let failed = sqlx::query!(
+            r#"UPDATE issues SET status = 'failed', updated_at = $2
+            WHERE status = 'sending' AND updated_at <= $1
+            RETURNING id"#,
+            before,
+            Utc::now(),
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("Could not fail stalled issues")?;
+        Ok(failed.into_iter().map(|rec| rec.id).collect())
+    }
+
+    #[tracing::instrument(name = "Setting the status of an issue in postgres")]
+    async fn set_issue_status(&self, id: &Uuid, status: &IssueStatus) -> Result<(), IssueError> {
+        sqlx::query!(
+            r#"UPDATE issues SET status = $2, updated_at = $3 WHERE id = $1"#,
+            id,
+            *status as IssueStatus,
+            Utc::now(),
+        )
+        .execute(&self.pool)
+        .await
+        .context(format!("Could not set the status of issue {id}"))?;
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Archiving an issue in postgres")]
+    async fn archive_issue(&self, issue: &ArchivedIssue) -> Result<(), IssueError> {
+        sqlx::query!(
+            r#"INSERT INTO archived_issues (id, slug, title, html_content, text_content, author_id, published_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)"#,
+            issue.id,
+            issue.slug,
+            issue.title,
+            issue.html_content,
+            issue.text_content,
+            issue.author_id,
+            issue.published_at,
+        )
+        .execute(&self.pool)
+        .await
+        .context(format!("Could not archive issue {}", issue.slug))?;
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Listing archived issues in postgres")]
+    async fn list_archived_issues(
+        &self,
+        filter: &ArchiveFilter,
+    ) -> Result<Vec<ArchivedIssueSummary>, IssueError> {
+        let (before_published_at, before_id) = match &filter.after {
+            Some(cursor) => (Some(cursor.published_at), Some(cursor.id)),
+            None => (None, None),
+        };
+        let saved = sqlx::query!(
+            r#"SELECT id, slug, title, published_at FROM archived_issues
+            WHERE ($1::timestamptz IS NULL OR (published_at, id) < ($1, $2::uuid))
+            ORDER BY published_at DESC, id DESC
+            LIMIT $3"#,
+            before_published_at,
+            before_id,
+            filter.limit,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("Could not list archived issues")?;
+        Ok(saved
+            .into_iter()
+            .map(|rec| ArchivedIssueSummary {
+                id: rec.id,
+                slug: rec.slug,
+                title: rec.title,
+                published_at: rec.published_at,
+            })
+            .collect())
+    }
+
+    #[tracing::instrument(name = "Fetching an archived issue in postgres")]
+    async fn get_archived_issue(&self, slug: &str) -> Result<Option<ArchivedIssue>, IssueError> {
+        let saved = sqlx::query!(
+            r#"SELECT id, slug, title, html_content, text_content, author_id, published_at
+            FROM archived_issues WHERE slug = $1"#,
+            slug
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .context(format!("Could not get archived issue {slug}"))?;
+        Ok(saved.map(|rec| ArchivedIssue {
+            id: rec.id,
+            slug: rec.slug,
+            title: rec.title,
+            html_content: rec.html_content,
+            text_content: rec.text_content,
+            author_id: rec.author_id,
+            published_at: rec.published_at,
+        }))
+    }
+
+    #[tracing::instrument(name = "Recording tracking events in postgres", skip(events))]
+    async fn record_tracking_events(&self, events: &[TrackingEvent]) -> Result<(), IssueError> {
+        let mut issue_ids = Vec::with_capacity(events.len());
+        let mut subscriber_ids = Vec::with_capacity(events.len());
+        let mut kinds = Vec::with_capacity(events.len());
+        let mut urls = Vec::with_capacity(events.len());
+        let mut occurred_at = Vec::with_capacity(events.len());
+        for event in events {
+            issue_ids.push(event.issue_id);
+            subscriber_ids.push(event.subscriber_id);
+            kinds.push(event.kind.as_str().to_string());
+            urls.push(event.url.clone());
+            occurred_at.push(event.occurred_at);
+        }
+        // Events of subscriptions deleted since the issue was sent are dropped.
+        sqlx::query!(
+            r#"INSERT INTO tracking_events (issue_id, subscription_id, kind, url, occurred_at)
+            SELECT e.issue_id, e.subscription_id, e.kind::tracking_event_kind, e.url, e.occurred_at
+            FROM UNNEST($1::uuid[], $2::uuid[], $3::text[], $4::text[], $5::timestamptz[])
+                AS e(issue_id, subscription_id, kind, url, occurred_at)
+            WHERE EXISTS (SELECT 1 FROM subscriptions s WHERE s.id = e.subscription_id)
+            AND EXISTS (SELECT 1 FROM issues i WHERE i.id = e.issue_id)"#,
+            &issue_ids,
+            &subscriber_ids,
+            &kinds,
+            &urls as &[Option<String>],
+            &occurred_at,
+        )
+        .execute(&self.pool)
+        .await
+        .context(format!("Could not record {} tracking events", events.len()))?;
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Computing the stats of an issue in postgres")]
+    async fn get_issue_stats(&self, id: &Uuid) -> Result<IssueStats, IssueError> {
+        let counts = sqlx::query!(
+            r#"SELECT
+                COUNT(DISTINCT subscription_id) FILTER (WHERE kind = 'sent') AS "recipients!",
+                COUNT(DISTINCT subscription_id) FILTER (WHERE kind = 'open') AS "unique_opens!",
+                COUNT(DISTINCT subscription_id) FILTER (WHERE kind = 'bounce') AS "bounces!"
+            FROM tracking_events WHERE issue_id = $1"#,
+            id
+        )
+        .fetch_one(&self.pool)
+        .await
+        .context(format!("Could not count the tracking events of issue {id}"))?;
+        let clicks = sqlx::query!(
+            r#"SELECT url AS "url!", COUNT(*) AS "clicks!", COUNT(DISTINCT subscription_id) AS "unique_clicks!"
+            FROM tracking_events WHERE issue_id = $1 AND kind = 'click' AND url IS NOT NULL
+            GROUP BY url ORDER BY 2 DESC, url"#,
+            id
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context(format!("Could not count the clicks of issue {id}"))?;
+        let bounce_rate = if counts.recipients > 0 {
+            counts.bounces as f64 / counts.recipients as f64
+        } else {
+            0.0
+        };
+        Ok(IssueStats {
+            issue_id: *id,
+            recipients: counts.recipients,
+            unique_opens: counts.unique_opens,
+            clicks: clicks
+                .into_iter()
+                .map(|rec| LinkClicks {
+                    url: rec.url,
+                    clicks: rec.clicks,
+                    unique_clicks: rec.unique_clicks,
+                })
+                .collect(),
+            bounces: counts.bounces,
+            bounce_rate,
+        })
+    }
+
+    #[tracing::instrument(name = "Recording deliveries in postgres", skip(deliveries))]
+    async fn record_deliveries(&self, deliveries: &[Delivery]) -> Result<(), IssueError> {
+        let mut issue_ids = Vec::with_capacity(deliveries.len());
+        let mut subscriber_ids = Vec::with_capacity(deliveries.len());
+        let mut statuses = Vec::with_capacity(deliveries.len());
+        let mut message_ids = Vec::with_capacity(deliveries.len());
+        let mut last_errors = Vec::with_capacity(deliveries.len());
+        let mut updated_at = Vec::with_capacity(deliveries.len());
+        for delivery in deliveries {
+            issue_ids.push(delivery.issue_id);
+            subscriber_ids.push(delivery.subscriber_id);
+            statuses.push(delivery.status.as_str().to_string());
+            message_ids.push(delivery.message_id.clone());
+            last_errors.push(delivery.last_error.clone());
+            updated_at.push(delivery.updated_at);
+        }
+        // Deliveries to subscriptions deleted in the meantime are dropped.
+        sqlx::query!(
+            r#"INSERT INTO deliveries (issue_id, subscription_id, status, message_id, last_error, updated_at)
+            SELECT d.issue_id, d.subscription_id, d.status::delivery_status, d.message_id, d.last_error, d.updated_at
+            FROM UNNEST($1::uuid[], $2::uuid[], $3::text[], $4::text[], $5::text[], $6::timestamptz[])
+                AS d(issue_id, subscription_id, status, message_id, last_error, updated_at)
+            WHERE EXISTS (SELECT 1 FROM subscriptions s WHERE s.id = d.subscription_id)
+            AND EXISTS (SELECT 1 FROM issues i WHERE i.id = d.issue_id)
+            ON CONFLICT (issue_id, subscription_id) DO UPDATE SET
+                status = EXCLUDED.status,
+                message_id = COALESCE(EXCLUDED.message_id, deliveries.message_id),
+                last_error = EXCLUDED.last_error,
+                updated_at = EXCLUDED.updated_at"#,
+            &issue_ids,
+            &subscriber_ids,
+            &statuses,
+            &message_ids as &[Option<String>],
+            &last_errors as &[Option<String>],
+            &updated_at,
+        )
+        .execute(&self.pool)
+        .await
+        .context(format!("Could not record {} deliveries", deliveries.len()))?;
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Bouncing a delivery in postgres")]
+    async fn bounce_delivery(&self, message_id: &str, error: &str) -> Result<bool, IssueError> {
+        let bounced = sqlx::query!(
+            r#"UPDATE deliveries SET status = 'bounced', last_error = $2, updated_at = NOW()
+            WHERE message_id = $1"#,
+            message_id,
+            error
+        )
+        .execute(&self.pool)
+        .await
+        .context(format!(
+            "Could not bounce the delivery of message {message_id}"
+        ))?;
+        Ok(bounced.rows_affected() > 0)
+    }
+
+    #[tracing::instrument(name = "Listing the deliveries of an issue in postgres")]
+    async fn list_deliveries(
+        &self,
+        id: &Uuid,
+        filter: &DeliveryFilter,
+    ) -> Result<Vec<Delivery>, IssueError> {
+        let saved = sqlx::query!(
+            r#"SELECT d.issue_id, d.subscription_id, s.email, d.status AS "status: DeliveryStatus",
+                d.message_id, d.last_error, d.updated_at
+            FROM deliveries d JOIN subscriptions s ON s.id = d.subscription_id
+            WHERE d.issue_id = $1
+            AND ($2::delivery_status IS NULL OR d.status = $2)
+            AND ($3::text IS NULL OR strpos(lower(s.email), lower($3)) > 0)
+            ORDER BY s.email"#,
+            id,
+            filter.status as Option<DeliveryStatus>,
+            filter.email,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context(format!("Could not list the deliveries of issue {id}"))?;
+        Ok(saved
+            .into_iter()
+            .map(|rec| Delivery {
+                issue_id: rec.issue_id,
+                subscriber_id: rec.subscription_id,
+                email: rec.email,
+                status: rec.status,
+                message_id: rec.message_id,
+                last_error: rec.last_error,
+                updated_at: rec.updated_at,
+            })
+            .collect())
+    }
+
+    #[tracing::instrument(name = "Listing the deliveries to a subscriber in postgres")]
+    async fn get_subscriber_deliveries(
+        &self,
+        subscriber_id: &Uuid,
+    ) -> Result<Vec<Delivery>, IssueError> {
+        let saved = sqlx::query!(
+            r#"SELECT d.issue_id, d.subscription_id, s.email, d.status AS "status: DeliveryStatus",
+                d.message_id, d.last_error, d.updated_at
+            FROM deliveries d JOIN subscriptions s ON s.id = d.subscription_id
+            WHERE d.subscription_id = $1
+            ORDER BY d.updated_at DESC"#,
+            subscriber_id,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context(format!(
+            "Could not list the deliveries to subscriber {subscriber_id}"
+        ))?;
+        Ok(saved
+            .into_iter()
+            .map(|rec| Delivery {
+                issue_id: rec.issue_id,
+                subscriber_id: rec.subscription_id,
+                email: rec.email,
+                status: rec.status,
+                message_id: rec.message_id,
+                last_error: rec.last_error,
+                updated_at: rec.updated_at,
+            })
+            .collect())
+    }
+
+    #[tracing::instrument(name = "Listing the tracking events of a subscriber in postgres")]
+    async fn get_subscriber_tracking_events(
+        &self,
+        subscriber_id: &Uuid,
+    ) -> Result<Vec<TrackingEvent>, IssueError> {
+        let saved = sqlx::query!(
+            r#"SELECT issue_id, subscription_id, kind AS "kind: TrackingEventKind", url, occurred_at
+            FROM tracking_events WHERE subscription_id = $1
+            ORDER BY occurred_at DESC"#,
+            subscriber_id,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context(format!(
+            "Could not list the tracking events of subscriber {subscriber_id}"
+        ))?;
+        Ok(saved
+            .into_iter()
+            .map(|rec| TrackingEvent {
+                issue_id: rec.issue_id,
+                subscriber_id: rec.subscription_id,
+                kind: rec.kind,
+                url: rec.url,
+                occurred_at: rec.occurred_at,
+            })
+            .collect())
+    }
+
+    #[tracing::instrument(name = "Counting the deliveries of an issue in postgres")]
+    async fn get_delivery_summary(&self, id: &Uuid) -> Result<DeliverySummary, IssueError> {
+        let counts = sqlx::query!(
+            r#"SELECT
+                COUNT(*) AS "total!",
+                COUNT(*) FILTER (WHERE status = 'queued') AS "queued!",
+                COUNT(*) FILTER (WHERE status = 'sent') AS "sent!",
+                COUNT(*) FILTER (WHERE status = 'failed') AS "failed!",
+                COUNT(*) FILTER (WHERE status = 'bounced') AS "bounced!"
+            FROM deliveries WHERE issue_id = $1"#,
+            id
+        )
+        .fetch_one(&self.pool)
+        .await
+        .context(format!("Could not count the deliveries of issue {id}"))?;
+        Ok(DeliverySummary {
+            issue_id: *id,
+            total: counts.total,
+            queued: counts.queued,
+            sent: counts.sent,
+            failed: counts.failed,
+            bounced: counts.bounced,
+        })
+    }
+}
+
+fn to_json(content: &Content) -> Result<serde_json::Value, IssueError> {
+    serde_json::to_value(content).map_err(|err| IssueError::Validation {
+        context: format!("Could not serialize issue content: {err}"),
+    })
+}
+
+fn segment_to_json(segment: &Segment) -> Result<serde_json::Value, IssueError> {
+    serde_json::to_value(segment).map_err(|err| IssueError::Validation {
+        context: format!("Could not serialize issue segment: {err}"),
+    })
+}
+
+#[allow(clippy::too_many_arguments)]
+fn to_issue(
+    id: Uuid,
+    title: String,
+    content: serde_json::Value,
+    status: Option<String>,
+    scheduled_at: Option<DateTime<Utc>>,
+    force: bool,
+    lists: Vec<String>,
+    segment: serde_json::Value,
+    tracking: bool,
+    author_id: Uuid,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+) -> Result<Issue, IssueError> {
+    let content = serde_json::from_value(content).map_err(|err| IssueError::Validation {
+        context: format!("Invalid content stored in the database: {err}"),
+    })?;
+    let status = IssueStatus::from_str(&status.unwrap_or_default()).map_err(|err| {
+        IssueError::Validation {
+            context: format!("Invalid status stored in the database: {err}"),
+        }
+    })?;
+    let segment = serde_json::from_value(segment).map_err(|err| IssueError::Validation {
+        context: format!("Invalid segment stored in the database: {err}"),
+    })?;
+    Ok(Issue {
+        id,
+        title,
+        content,
+        status,
+        scheduled_at,
+        force,
+        lists,
+        segment,
+        tracking,
+        author_id,
+        created_at,
+        updated_at,
+    })
+}
@@ -1,6 +1,7 @@
-/// Implementation of authentication_store and subscriptions_store using postgres
+/// Implementation of authentication_store, subscriptions_store and issue_store using postgres
 mod authentication;
 mod error;
+mod issue;
 mod subscription;
 
 pub use self::error::Error;
@@ -58,6 +59,7 @@ pub async fn connect_with_options(config: &DatabaseSettings) -> Result<PgPool, E
 
 #[cfg(test)]
 mod tests {
+    use chrono::{Duration, Utc};
     use common::postgres::init_dev_db;
     use common::settings::database_dev_settings;
     use fake::faker::internet::en::SafeEmail;
@@ -66,16 +68,40 @@ mod tests {
     use serial_test::serial;
     use speculoos::prelude::*;
     use std::sync::Arc;
+    use uuid::Uuid;
 
     use crate::{
-        domain::ports::secondary::SubscriptionStorage,
+        domain::ports::secondary::{
+            AuthenticationError, AuthenticationStorage, Email, IssueStorage, SubscriptionError,
+            SubscriptionStorage, UNIQUE_EMAIL, UNIQUE_SUBSCRIPTION_EMAIL, UNIQUE_USERNAME,
+        },
         //domain::ports::secondary::AuthenticationStorage,
         domain::NewSubscription,
-        domain::{SubscriptionRequest, SubscriptionStatus},
+        domain::{
+            Content, Credentials, Delivery, DeliveryFilter, DeliveryStatus, Issue, IssueRequest,
+            IssueStatus, OutboxEmail, TrackingEvent, TrackingEventKind, OUTBOX_LEASE,
+        },
+        domain::{
+            DeliveryFrequency, Segment, SubscriberEmail, SubscriberPreferences,
+            SubscriptionRequest, SubscriptionStatus, SubscriptionUpdate, SuppressionReason,
+        },
+        domain::{MailingList, MailingListRequest, DEFAULT_LIST},
     };
+    use secrecy::Secret;
 
     use super::*;
 
+    /// This is a helper function to create the confirmation email written to
+    /// the outbox with a subscription.
+    fn confirmation() -> OutboxEmail {
+        OutboxEmail::new(Email::new(
+            SubscriberEmail::parse("alice@acme.inc").unwrap(),
+            "Confirm".to_string(),
+            "<p>Confirm</p>".to_string(),
+            "Confirm".to_string(),
+        ))
+    }
+
     #[serial]
     #[tokio::test]
     async fn storage_should_store_and_retrieve_subscription() {
@@ -93,14 +119,24 @@ mod tests {
 
         let username = Name().fake::<String>();
         let email = SafeEmail().fake::<String>();
-        let request = SubscriptionRequest { username, email };
+        let request = SubscriptionRequest {
+            username,
+            email,
+            list: None,
+        };
         let new_subscription = NewSubscription::try_from(request.clone()).unwrap();
 
         let email = new_subscription.email.clone();
 
         let token = 32.fake::<String>();
+        let list = default_list(&storage).await;
         let lhs = storage
-            .create_subscription_and_store_token(&new_subscription, &token)
+            .create_subscription_and_store_token(
+                &new_subscription,
+                &list.id,
+                &token,
+                &confirmation(),
+            )
             .await
             .expect("storing subscription");
 
@@ -130,14 +166,25 @@ mod tests {
 
         let username = Name().fake::<String>();
         let email = SafeEmail().fake::<String>();
-        let request = SubscriptionRequest { username, email };
+        let request = SubscriptionRequest {
+            username,
+            email,
+            list: None,
+        };
         let new_subscription = NewSubscription::try_from(request).unwrap();
 
         let token = 32.fake::<String>();
 
+        let list = default_list(&storage).await;
+
         // Exec
         let subscription = storage
-            .create_subscription_and_store_token(&new_subscription, &token)
+            .create_subscription_and_store_token(
+                &new_subscription,
+                &list.id,
+                &token,
+                &confirmation(),
+            )
             .await
             .expect("storing subscription");
 
@@ -147,7 +194,7 @@ mod tests {
             .expect("getting subscriber id");
 
         // Check
-        assert_that(&id.unwrap()).is_equal_to(subscription.id);
+        assert_that(&id.unwrap()).is_equal_to((subscription.id, list.id));
     }
 
     #[serial]
@@ -174,20 +221,30 @@ mod tests {
 
         let username = Name().fake::<String>();
         let email = SafeEmail().fake::<String>();
-        let request = SubscriptionRequest { username, email };
+        let request = SubscriptionRequest {
+            username,
+            email,
+            list: None,
+        };
         let new_subscription = NewSubscription::try_from(request).unwrap();
 
         let email = new_subscription.email.clone();
         let token = 32.fake::<String>();
+        let list = default_list(&storage).await;
 
         // Exec
         let subscription = storage
-            .create_subscription_and_store_token(&new_subscription, &token)
+            .create_subscription_and_store_token(
+                &new_subscription,
+                &list.id,
+                &token,
+                &confirmation(),
+            )
             .await
             .expect("storing subscription");
 
         storage
-            .confirm_subscriber_by_id_and_delete_token(&subscription.id)
+            .confirm_subscriber_by_id_and_delete_token(&subscription.id, &list.id)
             .await
             .expect("confirming subscriber id");
 
@@ -210,4 +267,840 @@ mod tests {
 
         assert_that(&id).is_none();
     }
+
+    #[serial]
+    #[tokio::test]
+    async fn storage_should_keep_a_status_per_list() {
+        // In this test we subscribe to the default list, and to a new one, and
+        // only confirm the subscription to the new list. The subscriber only
+        // gets the newsletters of that list, and the subscription is deleted
+        // once it is removed from both lists.
+        init_dev_db()
+            .await
+            .expect("Could not reinitialization development database");
+        let settings = database_dev_settings()
+            .await
+            .expect("Could not retrieve development database settings");
+        let storage = Arc::new(
+            PostgresStorage::new(settings)
+                .await
+                .expect("Could not get pool for development database"),
+        );
+
+        let request = SubscriptionRequest {
+            username: Name().fake::<String>(),
+            email: SafeEmail().fake::<String>(),
+            list: None,
+        };
+        let new_subscription = NewSubscription::try_from(request).unwrap();
+        let default = default_list(&storage).await;
+        let rust = MailingList::new(MailingListRequest {
+            slug: "rust".to_string(),
+            name: "Rust".to_string(),
+            tracking: true,
+        });
+        assert!(storage.create_list(&rust).await.expect("creating list"));
+        assert!(!storage
+            .create_list(&rust)
+            .await
+            .expect("creating list again"));
+
+        // Exec
+        let subscription = storage
+            .create_subscription_and_store_token(
+                &new_subscription,
+                &default.id,
+                "token1",
+                &confirmation(),
+            )
+            .await
+            .expect("storing subscription");
+        storage
+            .subscribe_to_list(&subscription.id, &rust.id, "token2", &confirmation())
+            .await
+            .expect("subscribing to list");
+        storage
+            .confirm_subscriber_by_id_and_delete_token(&subscription.id, &rust.id)
+            .await
+            .expect("confirming subscriber id");
+
+        // Check
+        let status = storage
+            .get_list_status(&subscription.id, &default.id)
+            .await
+            .expect("getting status");
+        assert_that(&status).is_equal_to(Some(SubscriptionStatus::PendingConfirmation));
+
+        let subscribers = storage
+            .get_confirmed_subscribers_email(&[default.id, rust.id], &Segment::default())
+            .await
+            .expect("getting confirmed subscribers");
+        assert_eq!(subscribers.len(), 1);
+        assert_that(&subscribers[0].list_id).is_equal_to(rust.id);
+
+        let removed = storage
+            .unsubscribe_from_list(&subscription.id, &rust.id)
+            .await
+            .expect("unsubscribing");
+        assert!(removed);
+        let kept = storage
+            .get_subscription_by_id(&subscription.id)
+            .await
+            .expect("getting subscription");
+        assert_that(&kept).is_some();
+        storage
+            .unsubscribe_from_list(&subscription.id, &default.id)
+            .await
+            .expect("unsubscribing");
+        let deleted = storage
+            .get_subscription_by_id(&subscription.id)
+            .await
+            .expect("getting subscription");
+        assert_that(&deleted).is_none();
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn storage_should_select_the_subscribers_of_a_segment() {
+        init_dev_db()
+            .await
+            .expect("Could not reinitialization development database");
+        let settings = database_dev_settings()
+            .await
+            .expect("Could not retrieve development database settings");
+        let storage = Arc::new(
+            PostgresStorage::new(settings)
+                .await
+                .expect("Could not get pool for development database"),
+        );
+        let default = default_list(&storage).await;
+
+        let mut ids = Vec::new();
+        let mut emails = Vec::new();
+        for (topics, plan) in [
+            (vec!["rust"], "pro"),
+            (vec!["go"], "pro"),
+            (vec!["rust"], "free"),
+        ] {
+            let request = SubscriptionRequest {
+                username: Name().fake::<String>(),
+                email: SafeEmail().fake::<String>(),
+                list: None,
+            };
+            let subscription = storage
+                .create_subscription_and_store_token(
+                    &NewSubscription::try_from(request).unwrap(),
+                    &default.id,
+                    &Uuid::new_v4().to_string(),
+                    &confirmation(),
+                )
+                .await
+                .expect("storing subscription");
+            storage
+                .confirm_subscriber_by_id_and_delete_token(&subscription.id, &default.id)
+                .await
+                .expect("confirming subscriber id");
+            let preferences = SubscriberPreferences {
+                topics: topics.into_iter().map(String::from).collect(),
+                frequency: DeliveryFrequency::Weekly,
+            };
+            storage
+                .update_preferences(&subscription.id, &preferences)
+                .await
+                .expect("updating preferences");
+            let update = SubscriptionUpdate {
+                username: None,
+                status: None,
+                attributes: Some(serde_json::json!({ "plan": plan, "country": "fr" })),
+            };
+            storage
+                .update_subscription(&subscription.id, &update)
+                .await
+                .expect("updating attributes");
+            ids.push(subscription.id);
+            emails.push(subscription.email);
+        }
+
+        // Exec
+        let segment = Segment {
+            topics: vec!["rust".to_string(), "wasm".to_string()],
+            attributes: Some(serde_json::json!({ "plan": "pro" })),
+            ..Segment::default()
+        };
+        let subscribers = storage
+            .get_confirmed_subscribers_email(&[default.id], &segment)
+            .await
+            .expect("getting confirmed subscribers");
+        let count = storage
+            .count_confirmed_subscribers(&[default.id], &Segment::default())
+            .await
+            .expect("counting confirmed subscribers");
+
+        // Check
+        assert_eq!(subscribers.len(), 1);
+        assert_that(&subscribers[0].id).is_equal_to(ids[0]);
+        assert_that(&subscribers[0].frequency).is_equal_to(DeliveryFrequency::Weekly);
+        assert_that(&count).is_equal_to(3);
+
+        // A suppressed address is left out, whatever its case.
+        let email = SubscriberEmail::parse(emails[0].as_ref().to_uppercase()).unwrap();
+        storage
+            .suppress_email(&email, SuppressionReason::HardBounce, "test")
+            .await
+            .expect("suppressing email");
+        let subscribers = storage
+            .get_confirmed_subscribers_email(&[default.id], &segment)
+            .await
+            .expect("getting confirmed subscribers");
+        let count = storage
+            .count_confirmed_subscribers(&[default.id], &Segment::default())
+            .await
+            .expect("counting confirmed subscribers");
+        assert!(subscribers.is_empty());
+        assert_that(&count).is_equal_to(2);
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn storage_should_skip_erased_emails_until_they_confirm_again() {
+        init_dev_db()
+            .await
+            .expect("Could not reinitialization development database");
+        let settings = database_dev_settings()
+            .await
+            .expect("Could not retrieve development database settings");
+        let storage = Arc::new(
+            PostgresStorage::new(settings)
+                .await
+                .expect("Could not get pool for development database"),
+        );
+        let default = default_list(&storage).await;
+        let request = SubscriptionRequest {
+            username: Name().fake::<String>(),
+            email: SafeEmail().fake::<String>(),
+            list: None,
+        };
+        let new_subscription = NewSubscription::try_from(request).unwrap();
+        let subscription = storage
+            .create_subscription_and_store_token(
+                &new_subscription,
+                &default.id,
+                &Uuid::new_v4().to_string(),
+                &confirmation(),
+            )
+            .await
+            .expect("storing subscription");
+        let manual = SubscriberEmail::parse(SafeEmail().fake::<String>()).unwrap();
+        storage
+            .suppress_email(&manual, SuppressionReason::Manual, "admin")
+            .await
+            .expect("suppressing email");
+
+        // Exec
+        storage
+            .erase_subscription(&subscription.id)
+            .await
+            .expect("erasing subscription");
+
+        // Check: only a hash of the address is kept, and imports skip it.
+        let hashes = sqlx::query_scalar!(r#"SELECT email_hash FROM erased_emails"#)
+            .fetch_all(&storage.pool)
+            .await
+            .expect("listing erased emails");
+        assert_eq!(hashes.len(), 1);
+        assert!(!hashes[0].contains(subscription.email.as_ref()));
+        let all = storage
+            .list_suppressions(None)
+            .await
+            .expect("listing suppressions");
+        assert_eq!(all.len(), 1);
+        let imported = storage
+            .import_subscription(
+                &new_subscription,
+                &default.id,
+                &SubscriptionStatus::Confirmed,
+                &Utc::now(),
+                None,
+            )
+            .await
+            .expect("importing subscription");
+        assert!(imported.is_none());
+
+        // Subscribing again does not lift the erasure, confirming does.
+        let subscription = storage
+            .create_subscription_and_store_token(
+                &new_subscription,
+                &default.id,
+                &Uuid::new_v4().to_string(),
+                &confirmation(),
+            )
+            .await
+            .expect("storing subscription again");
+        storage
+            .update_subscription(
+                &subscription.id,
+                &SubscriptionUpdate {
+                    username: None,
+                    status: Some(SubscriptionStatus::Confirmed),
+                    attributes: None,
+                },
+            )
+            .await
+            .expect("confirming subscription by an admin");
+        let subscribers = storage
+            .get_confirmed_subscribers_email(&[default.id], &Segment::default())
+            .await
+            .expect("getting confirmed subscribers");
+        assert!(subscribers.is_empty());
+        storage
+            .confirm_subscriber_by_id_and_delete_token(&subscription.id, &default.id)
+            .await
+            .expect("confirming subscription");
+        let subscribers = storage
+            .get_confirmed_subscribers_email(&[default.id], &Segment::default())
+            .await
+            .expect("getting confirmed subscribers");
+        assert_eq!(subscribers.len(), 1);
+
+        let removed = storage
+            .remove_suppression(&manual)
+            .await
+            .expect("removing suppression");
+        let removed_again = storage
+            .remove_suppression(&manual)
+            .await
+            .expect("removing suppression");
+        assert!(removed);
+        assert!(!removed_again);
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn storage_should_export_then_erase_every_record_of_a_subscriber() {
+        init_dev_db()
+            .await
+            .expect("Could not reinitialization development database");
+        let settings = database_dev_settings()
+            .await
+            .expect("Could not retrieve development database settings");
+        let storage = Arc::new(
+            PostgresStorage::new(settings)
+                .await
+                .expect("Could not get pool for development database"),
+        );
+        let (issue, subscribers) = issue_and_subscribers(&storage, 1).await;
+        let id = subscribers[0];
+        let subscription = storage
+            .get_subscription_by_id(&id)
+            .await
+            .expect("getting subscription")
+            .expect("subscription");
+        let address = subscription.email.as_ref().to_uppercase();
+        storage
+            .queue_email(&OutboxEmail::new(Email::new(
+                SubscriberEmail::parse(address).unwrap(),
+                "Your data".to_string(),
+                "<p>Your data</p>".to_string(),
+                "Your data".to_string(),
+            )))
+            .await
+            .expect("queuing email");
+        storage
+            .record_deliveries(&[Delivery::new(
+                issue.id,
+                id,
+                String::new(),
+                DeliveryStatus::Sent,
+            )])
+            .await
+            .expect("recording delivery");
+        storage
+            .record_tracking_events(&[TrackingEvent::new(issue.id, id, TrackingEventKind::Open)])
+            .await
+            .expect("recording event");
+
+        // The records of the subscriber are exported.
+        let lists = storage
+            .get_list_memberships(&id)
+            .await
+            .expect("getting lists");
+        assert_eq!(lists.len(), 1);
+        assert_that(&lists[0].slug.as_str()).is_equal_to(DEFAULT_LIST);
+        assert_that(&lists[0].status).is_equal_to(SubscriptionStatus::PendingConfirmation);
+        let digest = storage
+            .get_queued_digest(&id)
+            .await
+            .expect("getting digest");
+        assert_that(&digest).is_empty();
+        let deliveries = storage
+            .get_subscriber_deliveries(&id)
+            .await
+            .expect("getting deliveries");
+        assert_eq!(deliveries.len(), 1);
+        assert_that(&deliveries[0].email).is_equal_to(subscription.email.as_ref().to_string());
+        let events = storage
+            .get_subscriber_tracking_events(&id)
+            .await
+            .expect("getting events");
+        assert_eq!(events.len(), 1);
+        assert_that(&events[0].kind).is_equal_to(TrackingEventKind::Open);
+
+        // Exec
+        storage
+            .erase_subscription(&id)
+            .await
+            .expect("erasing subscription");
+
+        // Check: only the outbox emails of others are left.
+        let recipients = sqlx::query_scalar!(r#"SELECT email->>'to' FROM outbox"#)
+            .fetch_all(&storage.pool)
+            .await
+            .expect("listing outbox");
+        assert_that(&recipients).is_equal_to(vec![Some("alice@acme.inc".to_string())]);
+        let deliveries = sqlx::query_scalar!(
+            r#"SELECT COUNT(*) FROM deliveries WHERE subscription_id = $1"#,
+            id
+        )
+        .fetch_one(&storage.pool)
+        .await
+        .expect("counting deliveries");
+        assert_that(&deliveries).is_equal_to(Some(0));
+        let events = sqlx::query_scalar!(
+            r#"SELECT COUNT(*) FROM tracking_events WHERE subscription_id = $1"#,
+            id
+        )
+        .fetch_one(&storage.pool)
+        .await
+        .expect("counting events");
+        assert_that(&events).is_equal_to(Some(0));
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn storage_should_report_the_engagement_with_a_tracked_issue() {
+        init_dev_db()
+            .await
+            .expect("Could not reinitialization development database");
+        let settings = database_dev_settings()
+            .await
+            .expect("Could not retrieve development database settings");
+        let storage = Arc::new(
+            PostgresStorage::new(settings)
+                .await
+                .expect("Could not get pool for development database"),
+        );
+        let (issue, subscribers) = issue_and_subscribers(&storage, 4).await;
+        let event =
+            |subscriber: usize, kind| TrackingEvent::new(issue.id, subscribers[subscriber], kind);
+        let click = |subscriber: usize, url: &str| TrackingEvent {
+            url: Some(url.to_string()),
+            ..event(subscriber, TrackingEventKind::Click)
+        };
+
+        // Exec
+        let mut events: Vec<TrackingEvent> = (0..4)
+            .map(|subscriber| event(subscriber, TrackingEventKind::Sent))
+            .collect();
+        events.extend([
+            event(0, TrackingEventKind::Open),
+            event(0, TrackingEventKind::Open),
+            event(1, TrackingEventKind::Open),
+            click(0, "https://acme.inc/news"),
+            click(0, "https://acme.inc/news"),
+            click(1, "https://acme.inc/news"),
+            click(1, "https://acme.inc/about"),
+            event(3, TrackingEventKind::Bounce),
+        ]);
+        storage
+            .record_tracking_events(&events)
+            .await
+            .expect("recording events");
+
+        // Check
+        let stats = storage
+            .get_issue_stats(&issue.id)
+            .await
+            .expect("getting stats");
+        assert_eq!(stats.recipients, 4);
+        assert_eq!(stats.unique_opens, 2);
+        assert_eq!(stats.bounces, 1);
+        assert_eq!(stats.bounce_rate, 0.25);
+        assert_eq!(stats.clicks.len(), 2);
+        assert_that(&stats.clicks[0].url.as_str()).is_equal_to("https://acme.inc/news");
+        assert_eq!(stats.clicks[0].clicks, 3);
+        assert_eq!(stats.clicks[0].unique_clicks, 2);
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn storage_should_record_the_deliveries_of_an_issue() {
+        init_dev_db()
+            .await
+            .expect("Could not reinitialization development database");
+        let settings = database_dev_settings()
+            .await
+            .expect("Could not retrieve development database settings");
+        let storage = Arc::new(
+            PostgresStorage::new(settings)
+                .await
+                .expect("Could not get pool for development database"),
+        );
+        let (issue, subscribers) = issue_and_subscribers(&storage, 3).await;
+        let delivery = |subscriber: usize, status| {
+            Delivery::new(issue.id, subscribers[subscriber], String::new(), status)
+        };
+        let queued = (0..3)
+            .map(|subscriber| delivery(subscriber, DeliveryStatus::Queued))
+            .collect::<Vec<_>>();
+        storage
+            .record_deliveries(&queued)
+            .await
+            .expect("recording queued deliveries");
+
+        // Exec
+        let sent = |subscriber: usize, message_id: &str| Delivery {
+            message_id: Some(message_id.to_string()),
+            ..delivery(subscriber, DeliveryStatus::Sent)
+        };
+        let failed = Delivery {
+            last_error: Some("Unavailable: down".to_string()),
+            ..delivery(2, DeliveryStatus::Failed)
+        };
+        storage
+            .record_deliveries(&[sent(0, "m-0"), sent(1, "m-1"), failed])
+            .await
+            .expect("recording deliveries");
+        let bounced = storage
+            .bounce_delivery("m-1", "Mailbox full")
+            .await
+            .expect("bouncing delivery");
+        let unknown = storage
+            .bounce_delivery("m-9", "Mailbox full")
+            .await
+            .expect("bouncing delivery");
+
+        // Check
+        assert!(bounced);
+        assert!(!unknown);
+        let summary = storage
+            .get_delivery_summary(&issue.id)
+            .await
+            .expect("summarizing deliveries");
+        assert_eq!(summary.total, 3);
+        assert_eq!(summary.queued, 0);
+        assert_eq!(summary.sent, 1);
+        assert_eq!(summary.failed, 1);
+        assert_eq!(summary.bounced, 1);
+        let filter = DeliveryFilter {
+            status: Some(DeliveryStatus::Bounced),
+            email: None,
+        };
+        let deliveries = storage
+            .list_deliveries(&issue.id, &filter)
+            .await
+            .expect("listing deliveries");
+        assert_eq!(deliveries.len(), 1);
+        assert_eq!(deliveries[0].subscriber_id, subscribers[1]);
+        assert_that(&deliveries[0].message_id.as_deref()).is_equal_to(Some("m-1"));
+        assert_that(&deliveries[0].last_error.as_deref()).is_equal_to(Some("Mailbox full"));
+        assert!(!deliveries[0].email.is_empty());
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn storage_should_fail_issues_stalled_while_being_sent() {
+        init_dev_db()
+            .await
+            .expect("Could not reinitialization development database");
+        let settings = database_dev_settings()
+            .await
+            .expect("Could not retrieve development database settings");
+        let storage = Arc::new(
+            PostgresStorage::new(settings)
+                .await
+                .expect("Could not get pool for development database"),
+        );
+        let (issue, _) = issue_and_subscribers(&storage, 0).await;
+        let issue = Issue {
+            status: IssueStatus::Scheduled,
+            scheduled_at: Some(Utc::now() - Duration::minutes(1)),
+            ..issue
+        };
+        storage
+            .update_issue(&issue)
+            .await
+            .expect("scheduling issue");
+        let claimed = storage
+            .claim_due_issues(&Utc::now())
+            .await
+            .expect("claiming due issues");
+        assert_eq!(claimed.len(), 1);
+
+        // Exec
+        let recent = storage
+            .fail_stalled_issues(&(Utc::now() - Duration::hours(1)))
+            .await
+            .expect("failing stalled issues");
+        let stalled = storage
+            .fail_stalled_issues(&Utc::now())
+            .await
+            .expect("failing stalled issues");
+
+        // Check
+        assert!(recent.is_empty());
+        assert_eq!(stalled, vec![issue.id]);
+        let failed = storage
+            .get_issue(&issue.id)
+            .await
+            .expect("getting issue")
+            .expect("issue");
+        assert_that(&failed.status).is_equal_to(IssueStatus::Failed);
+    }
+
+    /// Stores an issue, and subscribes `count` subscribers to the default list.
+    async fn issue_and_subscribers(storage: &PostgresStorage, count: usize) -> (Issue, Vec<Uuid>) {
+        let author_id = Uuid::new_v4();
+        let credentials = Credentials {
+            username: Name().fake::<String>(),
+            password: Secret::new("password".to_string()),
+        };
+        storage
+            .store_credentials(author_id, &SafeEmail().fake::<String>(), &credentials)
+            .await
+            .expect("storing credentials");
+        let request = IssueRequest {
+            title: "News".to_string(),
+            content: Content::Markdown {
+                markdown: "[News](https://acme.inc/news)".to_string(),
+            },
+            scheduled_at: None,
+            force: false,
+            lists: Vec::new(),
+            segment: Segment::default(),
+            tracking: true,
+        };
+        let issue = Issue::new(request, author_id);
+        storage.create_issue(&issue).await.expect("creating issue");
+        let default = default_list(storage).await;
+        let mut subscribers = Vec::new();
+        for _ in 0..count {
+            let request = SubscriptionRequest {
+                username: Name().fake::<String>(),
+                email: SafeEmail().fake::<String>(),
+                list: None,
+            };
+            let subscription = storage
+                .create_subscription_and_store_token(
+                    &NewSubscription::try_from(request).unwrap(),
+                    &default.id,
+                    &Uuid::new_v4().to_string(),
+                    &confirmation(),
+                )
+                .await
+                .expect("storing subscription");
+            subscribers.push(subscription.id);
+        }
+        (issue, subscribers)
+    }
+
+    async fn default_list(storage: &PostgresStorage) -> MailingList {
+        storage
+            .get_list(DEFAULT_LIST)
+            .await
+            .expect("getting default list")
+            .expect("default list")
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn storage_should_relay_the_emails_of_the_outbox() {
+        init_dev_db()
+            .await
+            .expect("Could not reinitialization development database");
+        let settings = database_dev_settings()
+            .await
+            .expect("Could not retrieve development database settings");
+        let storage = Arc::new(
+            PostgresStorage::new(settings)
+                .await
+                .expect("Could not get pool for development database"),
+        );
+
+        let request = SubscriptionRequest {
+            username: Name().fake::<String>(),
+            email: SafeEmail().fake::<String>(),
+            list: None,
+        };
+        let new_subscription = NewSubscription::try_from(request).unwrap();
+        let list = default_list(&storage).await;
+        let email = confirmation();
+        storage
+            .create_subscription_and_store_token(&new_subscription, &list.id, "token", &email)
+            .await
+            .expect("storing subscription");
+
+        // The email is left to its sender until the lease expires.
+        let now = Utc::now();
+        let claimed = storage
+            .claim_outbox_emails(&now, &(now + OUTBOX_LEASE), 10)
+            .await
+            .expect("claiming emails");
+        assert_that(&claimed).is_empty();
+
+        let later = email.available_at + Duration::seconds(1);
+        let claimed = storage
+            .claim_outbox_emails(&later, &(later + OUTBOX_LEASE), 10)
+            .await
+            .expect("claiming emails");
+        assert_eq!(claimed.len(), 1);
+        assert_that(&claimed[0].id).is_equal_to(email.id);
+        assert_that(&claimed[0].email.to).is_equal_to(&email.email.to);
+
+        // Once claimed, the email is not claimed again until the new lease expires.
+        let claimed = storage
+            .claim_outbox_emails(&later, &(later + OUTBOX_LEASE), 10)
+            .await
+            .expect("claiming emails");
+        assert_that(&claimed).is_empty();
+
+        let retry_at = later + Duration::minutes(1);
+        storage
+            .fail_outbox_email(&email.id, "Unavailable", Some(retry_at))
+            .await
+            .expect("failing email");
+        let claimed = storage
+            .claim_outbox_emails(&retry_at, &(retry_at + OUTBOX_LEASE), 10)
+            .await
+            .expect("claiming emails");
+        assert_eq!(claimed.len(), 1);
+        assert_that(&claimed[0].attempts).is_equal_to(1);
+
+        storage
+            .complete_outbox_email(&email.id)
+            .await
+            .expect("completing email");
+        let much_later = retry_at + Duration::days(1);
+        let claimed = storage
+            .claim_outbox_emails(&much_later, &(much_later + OUTBOX_LEASE), 10)
+            .await
+            .expect("claiming emails");
+        assert_that(&claimed).is_empty();
+
+        // The content of a sent email is not kept.
+        let content = sqlx::query_scalar!(r#"SELECT email FROM outbox WHERE id = $1"#, email.id)
+            .fetch_one(&storage.pool)
+            .await
+            .expect("fetching email");
+        assert_that(&content).is_none();
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn storage_should_report_the_duplicates_of_credentials() {
+        init_dev_db()
+            .await
+            .expect("Could not reinitialization development database");
+        let settings = database_dev_settings()
+            .await
+            .expect("Could not retrieve development database settings");
+        let storage = PostgresStorage::new(settings)
+            .await
+            .expect("Could not get pool for development database");
+
+        let email = SafeEmail().fake::<String>();
+        let credentials = Credentials {
+            username: Name().fake::<String>(),
+            password: Secret::new("password".to_string()),
+        };
+
+        storage
+            .store_credentials(Uuid::new_v4(), &email, &credentials)
+            .await
+            .expect("storing credentials");
+        assert!(storage.email_exists(&email).await.expect("checking email"));
+
+        // The same username is reported as a duplicate.
+        let duplicate = storage
+            .store_credentials(Uuid::new_v4(), &SafeEmail().fake::<String>(), &credentials)
+            .await;
+        assert!(matches!(
+            duplicate,
+            Err(AuthenticationError::UniqueViolation { constraint, .. }) if constraint == UNIQUE_USERNAME
+        ));
+
+        // And so is the same email.
+        let duplicate = storage
+            .store_credentials(
+                Uuid::new_v4(),
+                &email,
+                &Credentials {
+                    username: Name().fake::<String>(),
+                    password: Secret::new("password".to_string()),
+                },
+            )
+            .await;
+        assert!(matches!(
+            duplicate,
+            Err(AuthenticationError::UniqueViolation { constraint, .. }) if constraint == UNIQUE_EMAIL
+        ));
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn storage_should_report_the_constraint_violated_by_a_subscription() {
+        init_dev_db()
+            .await
+            .expect("Could not reinitialization development database");
+        let settings = database_dev_settings()
+            .await
+            .expect("Could not retrieve development database settings");
+        let storage = PostgresStorage::new(settings)
+            .await
+            .expect("Could not get pool for development database");
+
+        let request = SubscriptionRequest {
+            username: Name().fake::<String>(),
+            email: SafeEmail().fake::<String>(),
+            list: None,
+        };
+        let new_subscription = NewSubscription::try_from(request).unwrap();
+        let list = default_list(&storage).await;
+        storage
+            .create_subscription_and_store_token(
+                &new_subscription,
+                &list.id,
+                "token1",
+                &confirmation(),
+            )
+            .await
+            .expect("storing subscription");
+
+        let duplicate = storage
+            .create_subscription_and_store_token(
+                &new_subscription,
+                &list.id,
+                "token2",
+                &confirmation(),
+            )
+            .await;
+        assert!(matches!(
+            duplicate,
+            Err(SubscriptionError::UniqueViolation { ref constraint, .. }) if constraint == UNIQUE_SUBSCRIPTION_EMAIL
+        ));
+        assert!(!duplicate.unwrap_err().is_retryable());
+
+        // Subscribing to a list which does not exist violates a foreign key.
+        let subscription = storage
+            .get_subscription_by_email(new_subscription.email.as_ref())
+            .await
+            .expect("getting subscription")
+            .expect("subscription");
+        let missing = storage
+            .subscribe_to_list(&subscription.id, &Uuid::new_v4(), "token3", &confirmation())
+            .await;
+        assert!(matches!(
+            missing,
+            Err(SubscriptionError::ForeignKeyViolation { .. })
+        ));
+    }
 }
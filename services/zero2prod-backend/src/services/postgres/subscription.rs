@@ -1,134 +1,227 @@
 use async_trait::async_trait;
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use common::err_context::ErrorContextExt;
+use sqlx::PgConnection;
 use std::str::FromStr;
 use uuid::Uuid;
 
 use super::PostgresStorage;
 use crate::domain::{
     ports::secondary::SubscriptionError, ports::secondary::SubscriptionStorage,
-    ConfirmedSubscriber, NewSubscription, SubscriberEmail, SubscriberName, Subscription,
-    SubscriptionStatus,
+    ArchivedIssueSummary, ConfirmedSubscriber, DeliveryFrequency, Digest, ListMembership,
+    MailingList, NewSubscription, OutboxEmail, Segment, SubscriberEmail, SubscriberName,
+    SubscriberPreferences, Subscription, SubscriptionFilter, SubscriptionStatus,
+    SubscriptionUpdate, Suppression, SuppressionReason,
 };
 
 #[async_trait]
 impl SubscriptionStorage for PostgresStorage {
-    #[tracing::instrument(name = "Storing a new subscription in postgres")]
+    // We skip the subscription, token and confirmation in the log for privacy.
+    #[tracing::instrument(
+        name = "Storing a new subscription in postgres",
+        skip(new_subscription, token, confirmation)
+    )]
     async fn create_subscription_and_store_token(
         &self,
         new_subscription: &NewSubscription,
+        list_id: &Uuid,
         token: &str,
+        confirmation: &OutboxEmail,
     ) -> Result<Subscription, SubscriptionError> {
-        // FIXME The following two statements should be part of a transaction.
-        // But I'm not sure how to bring in a Transaction from my Exec?
-        // Side stepping this issue for now, as maybe sqlx will bring a solution
-        // soon.
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .context("Could not start a transaction")?;
         let id = Uuid::new_v4();
-        // FIXME Use a RETURNING clause instead of using a subsequent SELECT
-        sqlx::query!(
-        r#"INSERT INTO subscriptions (id, email, username, subscribed_at, status) VALUES ($1, $2, $3, $4, $5)"#,
-        id,
-        new_subscription.email.as_ref(),
-        new_subscription.username.as_ref(),
-        Utc::now(),
-        SubscriptionStatus::PendingConfirmation as SubscriptionStatus,
+        let now = Utc::now();
+        let saved = sqlx::query!(
+            r#"INSERT INTO subscriptions (id, email, username, subscribed_at, status) VALUES ($1, $2, $3, $4, $5)
+            RETURNING id, email, username, status::text, subscribed_at, topics, frequency::text, attributes"#,
+            id,
+            new_subscription.email.as_ref(),
+            new_subscription.username.as_ref(),
+            now,
+            SubscriptionStatus::PendingConfirmation as SubscriptionStatus,
         )
-        .execute(&self.pool)
+        .fetch_one(&mut *transaction)
         .await
         .context(format!(
-                "Could not store new subscription for {}", new_subscription.username.as_ref()
-                ))?;
+            "Could not store new subscription for {}",
+            new_subscription.username.as_ref()
+        ))?;
+        sqlx::query!(
+            r#"INSERT INTO list_subscriptions (list_id, subscription_id, status, subscribed_at) VALUES ($1, $2, $3, $4)"#,
+            list_id,
+            id,
+            SubscriptionStatus::PendingConfirmation as SubscriptionStatus,
+            now,
+        )
+        .execute(&mut *transaction)
+        .await
+        .context(format!("Could not add subscriber id {id} to list {list_id}"))?;
+        sqlx::query!(
+            r#"INSERT INTO subscription_tokens (subscription_token, subscriber_id, list_id) VALUES ($1, $2, $3)"#,
+            token,
+            id,
+            list_id
+        )
+        .execute(&mut *transaction)
+        .await
+        .context(format!("Could not store subscription token for subscriber id {id}"))?;
+        insert_outbox_email(&mut transaction, confirmation).await?;
+        transaction
+            .commit()
+            .await
+            .context(format!("Could not commit new subscription {id}"))?;
+        to_subscription(
+            saved.id,
+            saved.email,
+            saved.username,
+            saved.status,
+            saved.subscribed_at,
+            saved.topics,
+            saved.frequency,
+            saved.attributes,
+        )
+    }
 
+    #[tracing::instrument(
+        name = "Adding a subscription to a list in postgres",
+        skip(token, confirmation)
+    )]
+    async fn subscribe_to_list(
+        &self,
+        id: &Uuid,
+        list_id: &Uuid,
+        token: &str,
+        confirmation: &OutboxEmail,
+    ) -> Result<(), SubscriptionError> {
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .context("Could not start a transaction")?;
         sqlx::query!(
-            r#"INSERT INTO subscription_tokens (subscription_token, subscriber_id) VALUES ($1, $2)"#,
-            token, id
+            r#"INSERT INTO list_subscriptions (list_id, subscription_id, status, subscribed_at) VALUES ($1, $2, $3, $4)"#,
+            list_id,
+            id,
+            SubscriptionStatus::PendingConfirmation as SubscriptionStatus,
+            Utc::now(),
         )
-        .execute(&self.pool)
+        .execute(&mut *transaction)
+        .await
+        .context(format!("Could not add subscriber id {id} to list {list_id}"))?;
+        sqlx::query!(
+            r#"INSERT INTO subscription_tokens (subscription_token, subscriber_id, list_id) VALUES ($1, $2, $3)"#,
+            token,
+            id,
+            list_id
+        )
+        .execute(&mut *transaction)
         .await
         .context(format!("Could not store subscription token for subscriber id {id}"))?;
+        insert_outbox_email(&mut transaction, confirmation).await?;
+        transaction.commit().await.context(format!(
+            "Could not commit subscription of {id} to list {list_id}"
+        ))?;
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Fetching the status of a subscription on a list in postgres")]
+    async fn get_list_status(
+        &self,
+        id: &Uuid,
+        list_id: &Uuid,
+    ) -> Result<Option<SubscriptionStatus>, SubscriptionError> {
+        let saved = sqlx::query!(
+            r#"SELECT status::text FROM list_subscriptions WHERE subscription_id = $1 AND list_id = $2"#,
+            id,
+            list_id
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .context(format!("Could not get status of subscriber id {id} on list {list_id}"))?;
+        saved
+            .map(|rec| {
+                SubscriptionStatus::from_str(&rec.status.unwrap_or_default()).map_err(|err| {
+                    SubscriptionError::Validation {
+                        context: format!("Invalid status stored in the database: {err}"),
+                    }
+                })
+            })
+            .transpose()
+    }
+
+    #[tracing::instrument(name = "Listing the lists of a subscription in postgres")]
+    async fn get_list_memberships(
+        &self,
+        id: &Uuid,
+    ) -> Result<Vec<ListMembership>, SubscriptionError> {
         let saved = sqlx::query!(
-            r#"SELECT id, email, username, status::text FROM subscriptions WHERE id = $1"#,
+            r#"SELECT l.id, l.slug, ls.status AS "status: SubscriptionStatus", ls.subscribed_at
+            FROM list_subscriptions ls JOIN lists l ON l.id = ls.list_id
+            WHERE ls.subscription_id = $1
+            ORDER BY ls.subscribed_at"#,
             id
         )
-        .fetch_one(&self.pool)
+        .fetch_all(&self.pool)
         .await
-        .context(format!("Could not get subscription for {id}"))?;
-        let username =
-            SubscriberName::parse(saved.username).map_err(|err| SubscriptionError::Validation {
-                context: format!("Invalid username stored in the database: {err}"),
-            })?;
-        let email =
-            SubscriberEmail::parse(saved.email).map_err(|err| SubscriptionError::Validation {
-                context: format!("Invalid email stored in the database: {err}"),
-            })?;
-        let status =
-            SubscriptionStatus::from_str(&saved.status.unwrap_or_default()).map_err(|err| {
-                SubscriptionError::Validation {
-                    context: format!("Invalid status stored in the database: {err}"),
-                }
-            })?;
-        Ok(Subscription {
-            id: saved.id,
-            username,
-            email,
-            status,
-        })
+        .context(format!("Could not get the lists of subscriber id {id}"))?;
+        Ok(saved
+            .into_iter()
+            .map(|rec| ListMembership {
+                list_id: rec.id,
+                slug: rec.slug,
+                status: rec.status,
+                subscribed_at: rec.subscribed_at,
+            })
+            .collect())
     }
 
-    #[tracing::instrument(name = "Fetching a subscription by email in postgres")]
+    #[tracing::instrument(name = "Fetching a subscription by email in postgres", skip(email))]
     async fn get_subscription_by_email(
         &self,
         email: &str,
     ) -> Result<Option<Subscription>, SubscriptionError> {
         let saved = sqlx::query!(
-            r#"SELECT id, email, username, status::text FROM subscriptions WHERE email = $1"#,
+            r#"SELECT id, email, username, status::text, subscribed_at, topics, frequency::text, attributes FROM subscriptions WHERE email = $1"#,
             email
         )
         .fetch_optional(&self.pool)
         .await
         .context(format!("Could not get subscription for {email}"))?;
         tracing::info!("saved: {saved:?}");
-        match saved {
-            None => Ok(None),
-            Some(rec) => {
-                let username = SubscriberName::parse(rec.username).map_err(|err| {
-                    SubscriptionError::Validation {
-                        context: format!("Invalid username stored in the database: {err}"),
-                    }
-                })?;
-                let email = SubscriberEmail::parse(rec.email).map_err(|err| {
-                    SubscriptionError::Validation {
-                        context: format!("Invalid email stored in the database: {err}"),
-                    }
-                })?;
-                let status = SubscriptionStatus::from_str(&rec.status.unwrap_or_default())
-                    .map_err(|err| SubscriptionError::Validation {
-                        context: format!("Invalid status stored in the database: {err}"),
-                    })?;
-                Ok(Some(Subscription {
-                    id: rec.id,
-                    username,
-                    email,
-                    status,
-                }))
-            }
-        }
+        saved
+            .map(|rec| {
+                to_subscription(
+                    rec.id,
+                    rec.email,
+                    rec.username,
+                    rec.status,
+                    rec.subscribed_at,
+                    rec.topics,
+                    rec.frequency,
+                    rec.attributes,
+                )
+            })
+            .transpose()
     }
 
-    #[tracing::instrument(name = "Fetching a subscriber id by token in postgres")]
+    #[tracing::instrument(name = "Fetching a subscriber id by token in postgres", skip(token))]
     async fn get_subscriber_id_by_token(
         &self,
         token: &str,
-    ) -> Result<Option<Uuid>, SubscriptionError> {
+    ) -> Result<Option<(Uuid, Uuid)>, SubscriptionError> {
         let saved = sqlx::query!(
-            r#"SELECT subscriber_id FROM subscription_tokens WHERE subscription_token = $1"#,
+            r#"SELECT subscriber_id, list_id FROM subscription_tokens WHERE subscription_token = $1"#,
             token
         )
         .fetch_optional(&self.pool)
         .await
         .context(format!("Could not get subscriber id for {token}"))?;
         tracing::info!("saved: {saved:?}");
-        Ok(saved.map(|r| r.subscriber_id))
+        Ok(saved.map(|r| (r.subscriber_id, r.list_id)))
     }
 
     #[tracing::instrument(name = "Fetching a token using the subscriber's id in postgres")]
@@ -146,6 +239,25 @@ impl SubscriptionStorage for PostgresStorage {
         Ok(saved.map(|r| r.subscription_token))
     }
 
+    #[tracing::instrument(
+        name = "Fetching a token using the subscriber's and the list's ids in postgres"
+    )]
+    async fn get_token_by_subscriber_and_list(
+        &self,
+        id: &Uuid,
+        list_id: &Uuid,
+    ) -> Result<Option<String>, SubscriptionError> {
+        let saved = sqlx::query!(
+            r#"SELECT subscription_token FROM subscription_tokens WHERE subscriber_id = $1 AND list_id = $2"#,
+            id,
+            list_id
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .context(format!("Could not get token from subscriber id {id} for list {list_id}"))?;
+        Ok(saved.map(|r| r.subscription_token))
+    }
+
     #[tracing::instrument(name = "Deleting subscription token")]
     async fn delete_confirmation_token(&self, id: &Uuid) -> Result<(), SubscriptionError> {
         sqlx::query!(
@@ -164,46 +276,951 @@ impl SubscriptionStorage for PostgresStorage {
     async fn confirm_subscriber_by_id_and_delete_token(
         &self,
         id: &Uuid,
+        list_id: &Uuid,
     ) -> Result<(), SubscriptionError> {
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .context("Could not start a transaction")?;
         sqlx::query!(
             r#"UPDATE subscriptions SET status = $1 WHERE id = $2"#,
             SubscriptionStatus::Confirmed as SubscriptionStatus,
             id
         )
-        .execute(&self.pool)
+        .execute(&mut *transaction)
         .await
         .context(format!("Could not confirm subscriber by id {id}"))?;
         sqlx::query!(
-            r#"DELETE FROM subscription_tokens WHERE subscriber_id = $1"#,
-            id
+            r#"UPDATE list_subscriptions SET status = $1 WHERE subscription_id = $2 AND list_id = $3"#,
+            SubscriptionStatus::Confirmed as SubscriptionStatus,
+            id,
+            list_id
         )
-        .execute(&self.pool)
+        .execute(&mut *transaction)
+        .await
+        .context(format!("Could not confirm subscriber by id {id} on list {list_id}"))?;
+        // Confirming a subscription again after an erasure is a new consent.
+        sqlx::query!(
+            r#"DELETE FROM erased_emails WHERE email_hash = (
+                SELECT encode(hmac(lower(email), $2, 'sha256'), 'hex') FROM subscriptions WHERE id = $1
+            )"#,
+            id,
+            self.config.erasure_key,
+        )
+        .execute(&mut *transaction)
+        .await
+        .context(format!("Could not lift the erasure of subscriber id {id}"))?;
+        sqlx::query!(
+            r#"DELETE FROM subscription_tokens WHERE subscriber_id = $1 AND list_id = $2"#,
+            id,
+            list_id
+        )
+        .execute(&mut *transaction)
         .await
         .context(format!(
             "Could not delete subscription token for subscriber id {id}"
         ))?;
+        transaction.commit().await.context(format!(
+            "Could not commit confirmation of subscriber id {id}"
+        ))?;
         Ok(())
     }
 
-    #[tracing::instrument(name = "Confirming subscriber")]
+    #[tracing::instrument(name = "Fetching confirmed subscribers")]
     async fn get_confirmed_subscribers_email(
         &self,
+        lists: &[Uuid],
+        segment: &Segment,
     ) -> Result<Vec<ConfirmedSubscriber>, SubscriptionError> {
-        //Create a fallback password hash to enforce doing the same amount
-        //of work whether we have a user account in the db or not.
         let saved = sqlx::query!(
-            r#"SELECT email FROM subscriptions WHERE status = $1"#,
+            r#"SELECT DISTINCT ON (s.id) s.id, s.email, s.username, m.list_id,
+            s.frequency::text, s.attributes
+            FROM subscriptions s JOIN list_subscriptions m ON m.subscription_id = s.id
+            WHERE m.status = $1 AND m.list_id = ANY($2)
+            AND (cardinality($3::text[]) = 0 OR s.topics && $3)
+            AND ($4::timestamptz IS NULL OR s.subscribed_at >= $4)
+            AND ($5::timestamptz IS NULL OR s.subscribed_at < $5)
+            AND ($6::jsonb IS NULL OR s.attributes @> $6)
+            AND NOT EXISTS (SELECT 1 FROM suppressions x WHERE x.email = lower(s.email))
+            AND NOT EXISTS (SELECT 1 FROM erased_emails e
+                WHERE e.email_hash = encode(hmac(lower(s.email), $7, 'sha256'), 'hex'))
+            ORDER BY s.id, array_position($2, m.list_id)"#,
             SubscriptionStatus::Confirmed as SubscriptionStatus,
+            lists,
+            &segment.topics,
+            segment.subscribed_after,
+            segment.subscribed_before,
+            segment.attributes,
+            self.config.erasure_key,
         )
         .fetch_all(&self.pool)
         .await
         .context("Could not get a list of confirmed subscribers")?;
         saved
             .into_iter()
-            .map(|r| match SubscriberEmail::try_from(r.email) {
-                Ok(email) => Ok(ConfirmedSubscriber { email }),
-                Err(err) => Err(SubscriptionError::Validation { context: err }),
+            .map(|r| {
+                let email = SubscriberEmail::try_from(r.email)
+                    .map_err(|err| SubscriptionError::Validation { context: err })?;
+                let username = SubscriberName::parse(r.username).map_err(|err| {
+                    SubscriptionError::Validation {
+                        context: format!("Invalid username stored in the database: {err}"),
+                    }
+                })?;
+                let frequency = DeliveryFrequency::from_str(&r.frequency.unwrap_or_default())
+                    .map_err(|err| SubscriptionError::Validation {
+                        context: format!("Invalid frequency stored in the database: {err}"),
+                    })?;
+                Ok(ConfirmedSubscriber {
+                    id: r.id,
+                    email,
+                    username,
+                    list_id: r.list_id,
+                    frequency,
+                    attributes: r.attributes,
+                })
+            })
+            .collect()
+    }
+
+    #[tracing::instrument(name = "Counting confirmed subscribers")]
+    async fn count_confirmed_subscribers(
+        &self,
+        lists: &[Uuid],
+        segment: &Segment,
+    ) -> Result<i64, SubscriptionError> {
+        let saved = sqlx::query!(
+            r#"SELECT count(DISTINCT s.id) AS "count!"
+            FROM subscriptions s JOIN list_subscriptions m ON m.subscription_id = s.id
+            WHERE m.status = $1 AND m.list_id = ANY($2)
+            AND (cardinality($3::text[]) = 0 OR s.topics && $3)
+            AND ($4::timestamptz IS NULL OR s.subscribed_at >= $4)
+            AND ($5::timestamptz IS NULL OR s.subscribed_at < $5)
+            AND ($6::jsonb IS NULL OR s.attributes @> $6)
+            AND NOT EXISTS (SELECT 1 FROM suppressions x WHERE x.email = lower(s.email))
+            AND NOT EXISTS (SELECT 1 FROM erased_emails e
+                WHERE e.email_hash = encode(hmac(lower(s.email), $7, 'sha256'), 'hex'))"#,
+            SubscriptionStatus::Confirmed as SubscriptionStatus,
+            lists,
+            &segment.topics,
+            segment.subscribed_after,
+            segment.subscribed_before,
+            segment.attributes,
+            self.config.erasure_key,
+        )
+        .fetch_one(&self.pool)
+        .await
+        .context("Could not count confirmed subscribers")?;
+        Ok(saved.count)
+    }
+
+    #[tracing::instrument(name = "Listing subscriptions in postgres", skip(filter))]
+    async fn list_subscriptions(
+        &self,
+        filter: &SubscriptionFilter,
+    ) -> Result<Vec<Subscription>, SubscriptionError> {
+        let (after_subscribed_at, after_id) = match &filter.after {
+            Some(cursor) => (Some(cursor.subscribed_at), Some(cursor.id)),
+            None => (None, None),
+        };
+        let saved = sqlx::query!(
+            r#"SELECT id, email, username, status::text, subscribed_at, topics, frequency::text, attributes FROM subscriptions
+            WHERE ($1::subscription_status IS NULL OR status = $1)
+            AND ($2::timestamptz IS NULL OR subscribed_at >= $2)
+            AND ($3::timestamptz IS NULL OR subscribed_at < $3)
+            AND ($4::text IS NULL OR strpos(lower(email), lower($4)) > 0)
+            AND ($5::timestamptz IS NULL OR (subscribed_at, id) > ($5, $6::uuid))
+            ORDER BY subscribed_at, id
+            LIMIT $7"#,
+            filter.status.clone() as Option<SubscriptionStatus>,
+            filter.subscribed_after,
+            filter.subscribed_before,
+            filter.email,
+            after_subscribed_at,
+            after_id,
+            filter.limit,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("Could not list subscriptions")?;
+        saved
+            .into_iter()
+            .map(|rec| {
+                to_subscription(
+                    rec.id,
+                    rec.email,
+                    rec.username,
+                    rec.status,
+                    rec.subscribed_at,
+                    rec.topics,
+                    rec.frequency,
+                    rec.attributes,
+                )
+            })
+            .collect()
+    }
+
+    #[tracing::instrument(name = "Fetching a subscription by id in postgres")]
+    async fn get_subscription_by_id(
+        &self,
+        id: &Uuid,
+    ) -> Result<Option<Subscription>, SubscriptionError> {
+        let saved = sqlx::query!(
+            r#"SELECT id, email, username, status::text, subscribed_at, topics, frequency::text, attributes FROM subscriptions WHERE id = $1"#,
+            id
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .context(format!("Could not get subscription for {id}"))?;
+        saved
+            .map(|rec| {
+                to_subscription(
+                    rec.id,
+                    rec.email,
+                    rec.username,
+                    rec.status,
+                    rec.subscribed_at,
+                    rec.topics,
+                    rec.frequency,
+                    rec.attributes,
+                )
+            })
+            .transpose()
+    }
+
+    #[tracing::instrument(name = "Updating a subscription in postgres", skip(update))]
+    async fn update_subscription(
+        &self,
+        id: &Uuid,
+        update: &SubscriptionUpdate,
+    ) -> Result<Option<Subscription>, SubscriptionError> {
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .context("Could not start a transaction")?;
+        let saved = sqlx::query!(
+            r#"UPDATE subscriptions
+            SET username = COALESCE($2, username), status = COALESCE($3, status),
+            attributes = COALESCE($4, attributes)
+            WHERE id = $1
+            RETURNING id, email, username, status::text, subscribed_at, topics, frequency::text, attributes"#,
+            id,
+            update.username.as_ref().map(|username| username.as_ref()),
+            update.status.clone() as Option<SubscriptionStatus>,
+            update.attributes,
+        )
+        .fetch_optional(&mut *transaction)
+        .await
+        .context(format!("Could not update subscription {id}"))?;
+        if let Some(status) = &update.status {
+            sqlx::query!(
+                r#"UPDATE list_subscriptions SET status = $2 WHERE subscription_id = $1"#,
+                id,
+                status.clone() as SubscriptionStatus,
+            )
+            .execute(&mut *transaction)
+            .await
+            .context(format!("Could not update the lists of subscription {id}"))?;
+        }
+        if update.status == Some(SubscriptionStatus::Confirmed) {
+            sqlx::query!(
+                r#"DELETE FROM subscription_tokens WHERE subscriber_id = $1"#,
+                id
+            )
+            .execute(&mut *transaction)
+            .await
+            .context(format!(
+                "Could not delete subscription token for subscriber id {id}"
+            ))?;
+        }
+        transaction
+            .commit()
+            .await
+            .context(format!("Could not commit update of subscription {id}"))?;
+        saved
+            .map(|rec| {
+                to_subscription(
+                    rec.id,
+                    rec.email,
+                    rec.username,
+                    rec.status,
+                    rec.subscribed_at,
+                    rec.topics,
+                    rec.frequency,
+                    rec.attributes,
+                )
+            })
+            .transpose()
+    }
+
+    #[tracing::instrument(name = "Updating the preferences of a subscription in postgres")]
+    async fn update_preferences(
+        &self,
+        id: &Uuid,
+        preferences: &SubscriberPreferences,
+    ) -> Result<Option<Subscription>, SubscriptionError> {
+        let saved = sqlx::query!(
+            r#"UPDATE subscriptions SET topics = $2, frequency = $3
+            WHERE id = $1
+            RETURNING id, email, username, status::text, subscribed_at, topics, frequency::text, attributes"#,
+            id,
+            &preferences.topics,
+            preferences.frequency as DeliveryFrequency,
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .context(format!("Could not update the preferences of subscription {id}"))?;
+        saved
+            .map(|rec| {
+                to_subscription(
+                    rec.id,
+                    rec.email,
+                    rec.username,
+                    rec.status,
+                    rec.subscribed_at,
+                    rec.topics,
+                    rec.frequency,
+                    rec.attributes,
+                )
+            })
+            .transpose()
+    }
+
+    #[tracing::instrument(name = "Queuing an issue for digests in postgres")]
+    async fn queue_digest(
+        &self,
+        issue_id: &Uuid,
+        subscribers: &[Uuid],
+    ) -> Result<(), SubscriptionError> {
+        sqlx::query!(
+            r#"INSERT INTO digest_entries (subscription_id, issue_id, queued_at)
+            SELECT subscription_id, $2, $3 FROM UNNEST($1::uuid[]) AS subscription_id
+            ON CONFLICT DO NOTHING"#,
+            subscribers,
+            issue_id,
+            Utc::now(),
+        )
+        .execute(&self.pool)
+        .await
+        .context(format!("Could not queue issue {issue_id} for digests"))?;
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Fetching due digests in postgres")]
+    async fn get_due_digests(
+        &self,
+        before: &DateTime<Utc>,
+    ) -> Result<Vec<Digest>, SubscriptionError> {
+        let saved = sqlx::query!(
+            r#"SELECT s.id AS subscription_id, s.email, a.id, a.slug, a.title, a.published_at
+            FROM digest_entries d
+            JOIN subscriptions s ON s.id = d.subscription_id
+            JOIN archived_issues a ON a.id = d.issue_id
+            WHERE d.subscription_id IN (
+                SELECT subscription_id FROM digest_entries
+                GROUP BY subscription_id HAVING min(queued_at) <= $1
+            )
+            ORDER BY s.id, a.published_at, a.id"#,
+            before,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("Could not get due digests")?;
+        let mut digests: Vec<Digest> = Vec::new();
+        for rec in saved {
+            let issue = ArchivedIssueSummary {
+                id: rec.id,
+                slug: rec.slug,
+                title: rec.title,
+                published_at: rec.published_at,
+            };
+            match digests.last_mut() {
+                Some(digest) if digest.subscriber_id == rec.subscription_id => {
+                    digest.issues.push(issue)
+                }
+                _ => {
+                    let email = SubscriberEmail::parse(rec.email).map_err(|err| {
+                        SubscriptionError::Validation {
+                            context: format!("Invalid email stored in the database: {err}"),
+                        }
+                    })?;
+                    digests.push(Digest {
+                        subscriber_id: rec.subscription_id,
+                        email,
+                        issues: vec![issue],
+                    })
+                }
+            }
+        }
+        Ok(digests)
+    }
+
+    #[tracing::instrument(name = "Completing a digest in postgres")]
+    async fn complete_digest(
+        &self,
+        subscriber_id: &Uuid,
+        issues: &[Uuid],
+    ) -> Result<(), SubscriptionError> {
+        sqlx::query!(
+            r#"DELETE FROM digest_entries WHERE subscription_id = $1 AND issue_id = ANY($2)"#,
+            subscriber_id,
+            issues,
+        )
+        .execute(&self.pool)
+        .await
+        .context(format!(
+            "Could not remove the digest of subscriber id {subscriber_id}"
+        ))?;
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Fetching the queued digest of a subscriber in postgres")]
+    async fn get_queued_digest(
+        &self,
+        subscriber_id: &Uuid,
+    ) -> Result<Vec<ArchivedIssueSummary>, SubscriptionError> {
+        let saved = sqlx::query!(
+            r#"SELECT a.id, a.slug, a.title, a.published_at
+            FROM digest_entries d JOIN archived_issues a ON a.id = d.issue_id
+            WHERE d.subscription_id = $1
+            ORDER BY a.published_at, a.id"#,
+            subscriber_id,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context(format!(
+            "Could not get the digest of subscriber id {subscriber_id}"
+        ))?;
+        Ok(saved
+            .into_iter()
+            .map(|rec| ArchivedIssueSummary {
+                id: rec.id,
+                slug: rec.slug,
+                title: rec.title,
+                published_at: rec.published_at,
+            })
+            .collect())
+    }
+
+    #[tracing::instrument(
+        name = "Importing a subscription in postgres",
+        skip(subscription, token)
+    )]
+    async fn import_subscription(
+        &self,
+        subscription: &NewSubscription,
+        list_id: &Uuid,
+        status: &SubscriptionStatus,
+        subscribed_at: &DateTime<Utc>,
+        token: Option<String>,
+    ) -> Result<Option<Subscription>, SubscriptionError> {
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .context("Could not start a transaction")?;
+        let id = Uuid::new_v4();
+        let saved = sqlx::query!(
+            r#"INSERT INTO subscriptions (id, email, username, subscribed_at, status)
+            SELECT $1, $2, $3, $4, $5
+            WHERE NOT EXISTS (SELECT 1 FROM erased_emails
+                WHERE email_hash = encode(hmac(lower($2), $6, 'sha256'), 'hex'))
+            ON CONFLICT (email) DO NOTHING
+            RETURNING id, email, username, status::text, subscribed_at, topics, frequency::text, attributes"#,
+            id,
+            subscription.email.as_ref(),
+            subscription.username.as_ref(),
+            subscribed_at,
+            status.clone() as SubscriptionStatus,
+            self.config.erasure_key,
+        )
+        .fetch_optional(&mut *transaction)
+        .await
+        .context(format!(
+            "Could not import subscription for {}",
+            subscription.username.as_ref()
+        ))?;
+        if saved.is_some() {
+            sqlx::query!(
+                r#"INSERT INTO list_subscriptions (list_id, subscription_id, status, subscribed_at) VALUES ($1, $2, $3, $4)"#,
+                list_id,
+                id,
+                status.clone() as SubscriptionStatus,
+                subscribed_at,
+            )
+            .execute(&mut *transaction)
+            .await
+            .context(format!("Could not add subscriber id {id} to list {list_id}"))?;
+        }
+        if let (Some(_), Some(token)) = (&saved, token) {
+            sqlx::query!(
+                r#"INSERT INTO subscription_tokens (subscription_token, subscriber_id, list_id) VALUES ($1, $2, $3)"#,
+                token,
+                id,
+                list_id
+            )
+            .execute(&mut *transaction)
+            .await
+            .context(format!(
+                "Could not store subscription token for subscriber id {id}"
+            ))?;
+        }
+        transaction
+            .commit()
+            .await
+            .context(format!("Could not commit import of subscription {id}"))?;
+        saved
+            .map(|rec| {
+                to_subscription(
+                    rec.id,
+                    rec.email,
+                    rec.username,
+                    rec.status,
+                    rec.subscribed_at,
+                    rec.topics,
+                    rec.frequency,
+                    rec.attributes,
+                )
+            })
+            .transpose()
+    }
+
+    #[tracing::instrument(name = "Deleting a subscription in postgres")]
+    async fn delete_subscription(&self, id: &Uuid) -> Result<bool, SubscriptionError> {
+        // Tokens are deleted by the database, with the cascade on subscriber_id.
+        let deleted = sqlx::query!(r#"DELETE FROM subscriptions WHERE id = $1"#, id)
+            .execute(&self.pool)
+            .await
+            .context(format!("Could not delete subscription {id}"))?;
+        Ok(deleted.rows_affected() > 0)
+    }
+
+    #[tracing::instrument(name = "Erasing a subscription in postgres")]
+    async fn erase_subscription(&self, id: &Uuid) -> Result<bool, SubscriptionError> {
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .context("Could not start a transaction")?;
+        sqlx::query!(r#"DELETE FROM deliveries WHERE subscription_id = $1"#, id)
+            .execute(&mut *transaction)
+            .await
+            .context(format!(
+                "Could not erase the deliveries of subscription {id}"
+            ))?;
+        sqlx::query!(
+            r#"DELETE FROM tracking_events WHERE subscription_id = $1"#,
+            id
+        )
+        .execute(&mut *transaction)
+        .await
+        .context(format!(
+            "Could not erase the tracking events of subscription {id}"
+        ))?;
+        let erased = sqlx::query!(
+            r#"DELETE FROM subscriptions WHERE id = $1 RETURNING email, subscribed_at"#,
+            id
+        )
+        .fetch_optional(&mut *transaction)
+        .await
+        .context(format!("Could not erase subscription {id}"))?;
+        let Some(erased) = erased else {
+            return Ok(false);
+        };
+        // The outbox only knows the address of the emails it holds.
+        sqlx::query!(
+            r#"DELETE FROM outbox WHERE lower(email->>'to') = lower($1)"#,
+            erased.email
+        )
+        .execute(&mut *transaction)
+        .await
+        .context(format!("Could not erase the emails of subscription {id}"))?;
+        sqlx::query!(
+            r#"INSERT INTO subscription_erasures (id, subscription_id, subscribed_at, erased_at)
+            VALUES ($1, $2, $3, $4)"#,
+            Uuid::new_v4(),
+            id,
+            erased.subscribed_at,
+            Utc::now()
+        )
+        .execute(&mut *transaction)
+        .await
+        .context(format!("Could not record erasure of subscription {id}"))?;
+        sqlx::query!(
+            r#"INSERT INTO erased_emails (email_hash, erased_at)
+            VALUES (encode(hmac(lower($1), $2, 'sha256'), 'hex'), $3)
+            ON CONFLICT (email_hash) DO NOTHING"#,
+            erased.email,
+            self.config.erasure_key,
+            Utc::now()
+        )
+        .execute(&mut *transaction)
+        .await
+        .context(format!(
+            "Could not record the erased email of subscription {id}"
+        ))?;
+        transaction
+            .commit()
+            .await
+            .context(format!("Could not commit erasure of subscription {id}"))?;
+        Ok(true)
+    }
+
+    #[tracing::instrument(name = "Removing a subscription from a list in postgres")]
+    async fn unsubscribe_from_list(
+        &self,
+        id: &Uuid,
+        list_id: &Uuid,
+    ) -> Result<bool, SubscriptionError> {
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .context("Could not start a transaction")?;
+        let removed = sqlx::query!(
+            r#"DELETE FROM list_subscriptions WHERE subscription_id = $1 AND list_id = $2"#,
+            id,
+            list_id
+        )
+        .execute(&mut *transaction)
+        .await
+        .context(format!(
+            "Could not remove subscription {id} from list {list_id}"
+        ))?;
+        sqlx::query!(
+            r#"DELETE FROM subscription_tokens WHERE subscriber_id = $1 AND list_id = $2"#,
+            id,
+            list_id
+        )
+        .execute(&mut *transaction)
+        .await
+        .context(format!(
+            "Could not delete subscription token for subscriber id {id}"
+        ))?;
+        sqlx::query!(
+            r#"DELETE FROM subscriptions WHERE id = $1
+            AND NOT EXISTS (SELECT 1 FROM list_subscriptions WHERE subscription_id = $1)"#,
+            id
+        )
+        .execute(&mut *transaction)
+        .await
+        .context(format!("Could not delete subscription {id}"))?;
+        transaction
+            .commit()
+            .await
+            .context(format!("Could not commit removal of subscription {id}"))?;
+        Ok(removed.rows_affected() > 0)
+    }
+
+    #[tracing::instrument(name = "Storing a new list in postgres")]
+    async fn create_list(&self, list: &MailingList) -> Result<bool, SubscriptionError> {
+        let created = sqlx::query!(
+            r#"INSERT INTO lists (id, slug, name, tracking, created_at) VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (slug) DO NOTHING"#,
+            list.id,
+            list.slug,
+            list.name,
+            list.tracking,
+            list.created_at,
+        )
+        .execute(&self.pool)
+        .await
+        .context(format!("Could not store list {}", list.slug))?;
+        Ok(created.rows_affected() > 0)
+    }
+
+    #[tracing::instrument(name = "Listing lists in postgres")]
+    async fn get_lists(&self) -> Result<Vec<MailingList>, SubscriptionError> {
+        let saved = sqlx::query!(
+            r#"SELECT id, slug, name, tracking, created_at FROM lists ORDER BY created_at, id"#
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("Could not list lists")?;
+        Ok(saved
+            .into_iter()
+            .map(|rec| MailingList {
+                id: rec.id,
+                slug: rec.slug,
+                name: rec.name,
+                tracking: rec.tracking,
+                created_at: rec.created_at,
+            })
+            .collect())
+    }
+
+    #[tracing::instrument(name = "Fetching a list in postgres")]
+    async fn get_list(&self, key: &str) -> Result<Option<MailingList>, SubscriptionError> {
+        let saved = sqlx::query!(
+            r#"SELECT id, slug, name, tracking, created_at FROM lists WHERE id::text = $1 OR slug = $1"#,
+            key
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .context(format!("Could not get list {key}"))?;
+        Ok(saved.map(|rec| MailingList {
+            id: rec.id,
+            slug: rec.slug,
+            name: rec.name,
+            tracking: rec.tracking,
+            created_at: rec.created_at,
+        }))
+    }
+
+    #[tracing::instrument(name = "Suppressing an email in postgres", skip(email))]
+    async fn suppress_email(
+        &self,
+        email: &SubscriberEmail,
+        reason: SuppressionReason,
+        source: &str,
+    ) -> Result<(), SubscriptionError> {
+        sqlx::query!(
+            r#"INSERT INTO suppressions (email, reason, source, suppressed_at)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (email) DO NOTHING"#,
+            email.as_ref().to_lowercase(),
+            reason as SuppressionReason,
+            source,
+            Utc::now()
+        )
+        .execute(&self.pool)
+        .await
+        .context("Could not suppress email")?;
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Fetching suppressions in postgres", skip(emails))]
+    async fn get_suppressions(
+        &self,
+        emails: &[SubscriberEmail],
+    ) -> Result<Vec<Suppression>, SubscriptionError> {
+        let emails = emails
+            .iter()
+            .map(|email| email.as_ref().to_lowercase())
+            .collect::<Vec<_>>();
+        let saved = sqlx::query!(
+            r#"SELECT email, reason AS "reason: SuppressionReason", source, suppressed_at
+            FROM suppressions WHERE email = ANY($1)"#,
+            &emails
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("Could not get suppressions")?;
+        saved
+            .into_iter()
+            .map(|r| to_suppression(r.email, r.reason, r.source, r.suppressed_at))
+            .collect()
+    }
+
+    #[tracing::instrument(name = "Listing suppressions in postgres")]
+    async fn list_suppressions(
+        &self,
+        reason: Option<SuppressionReason>,
+    ) -> Result<Vec<Suppression>, SubscriptionError> {
+        let saved = sqlx::query!(
+            r#"SELECT email, reason AS "reason: SuppressionReason", source, suppressed_at
+            FROM suppressions WHERE ($1::suppression_reason IS NULL OR reason = $1)
+            ORDER BY suppressed_at DESC, email"#,
+            reason as Option<SuppressionReason>
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("Could not list suppressions")?;
+        saved
+            .into_iter()
+            .map(|r| to_suppression(r.email, r.reason, r.source, r.suppressed_at))
+            .collect()
+    }
+
+    #[tracing::instrument(name = "Removing a suppression in postgres", skip(email))]
+    async fn remove_suppression(&self, email: &SubscriberEmail) -> Result<bool, SubscriptionError> {
+        let removed = sqlx::query!(
+            r#"DELETE FROM suppressions WHERE email = lower($1)"#,
+            email.as_ref()
+        )
+        .execute(&self.pool)
+        .await
+        .context("Could not remove suppression")?;
+        Ok(removed.rows_affected() > 0)
+    }
+
+    #[tracing::instrument(name = "Writing an email to the outbox in postgres", skip(email))]
+    async fn queue_email(&self, email: &OutboxEmail) -> Result<(), SubscriptionError> {
+        let mut conn = self
+            .pool
+            .acquire()
+            .await
+            .context("Could not get a connection")?;
+        insert_outbox_email(&mut conn, email).await
+    }
+
+    #[tracing::instrument(name = "Claiming the emails of the outbox in postgres")]
+    async fn claim_outbox_emails(
+        &self,
+        now: &DateTime<Utc>,
+        lease_until: &DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<OutboxEmail>, SubscriptionError> {
+        // Emails claimed by another relay are skipped rather than waited for.
+        let claimed = sqlx::query!(
+            r#"UPDATE outbox SET available_at = $2
+            WHERE id IN (
+                SELECT id FROM outbox
+                WHERE sent_at IS NULL AND failed_at IS NULL AND available_at <= $1
+                ORDER BY available_at
+                LIMIT $3
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING id, email, attempts, available_at"#,
+            now,
+            lease_until,
+            limit,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("Could not claim the emails of the outbox")?;
+        claimed
+            .into_iter()
+            .map(|rec| {
+                let content = rec.email.ok_or_else(|| SubscriptionError::Validation {
+                    context: format!("Email {} of the outbox has no content", rec.id),
+                })?;
+                let email = serde_json::from_value(content).map_err(|err| {
+                    SubscriptionError::Validation {
+                        context: format!("Invalid email {} in the outbox: {err}", rec.id),
+                    }
+                })?;
+                Ok(OutboxEmail {
+                    id: rec.id,
+                    email,
+                    attempts: rec.attempts,
+                    available_at: rec.available_at,
+                })
             })
             .collect()
     }
+
+    // The content of the email is dropped once it is sent, or given up, so that
+    // the outbox does not keep the address and the links of the recipient.
+    #[tracing::instrument(name = "Completing an email of the outbox in postgres")]
+    async fn complete_outbox_email(&self, id: &Uuid) -> Result<(), SubscriptionError> {
+        sqlx::query!(
+            r#"UPDATE outbox SET sent_at = NOW(), email = NULL WHERE id = $1"#,
+            id
+        )
+        .execute(&self.pool)
+        .await
+        .context(format!("Could not complete email {id} of the outbox"))?;
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Failing an email of the outbox in postgres")]
+    async fn fail_outbox_email(
+        &self,
+        id: &Uuid,
+        error: &str,
+        retry_at: Option<DateTime<Utc>>,
+    ) -> Result<(), SubscriptionError> {
+        sqlx::query!(
+            r#"UPDATE outbox SET attempts = attempts + 1, last_error = $2,
+                available_at = COALESCE($3, available_at),
+                failed_at = CASE WHEN $3::timestamptz IS NULL THEN NOW() END,
+                email = CASE WHEN $3::timestamptz IS NULL THEN NULL ELSE email END
+            WHERE id = $1"#,
+            id,
+            error,
+            retry_at,
+        )
+        .execute(&self.pool)
+        .await
+        .context(format!("Could not fail email {id} of the outbox"))?;
+        Ok(())
+    }
+}
+
+/// Writes the email to the outbox, within the transaction of the change it is
+/// about, if there is one.
+async fn insert_outbox_email(
+    conn: &mut PgConnection,
+    email: &OutboxEmail,
+) -> Result<(), SubscriptionError> {
+    let content =
+        serde_json::to_value(&email.email).map_err(|err| SubscriptionError::Validation {
+            context: format!(
+                "Could not serialize email {} of the outbox: {err}",
+                email.id
+            ),
+        })?;
+    sqlx::query!(
+        r#"INSERT INTO outbox (id, email, attempts, created_at, available_at) VALUES ($1, $2, $3, $4, $5)"#,
+        email.id,
+        content,
+        email.attempts,
+        Utc::now(),
+        email.available_at,
+    )
+    .execute(conn)
+    .await
+    .context(format!("Could not write email {} to the outbox", email.id))?;
+    Ok(())
+}
+
+/// Builds a subscription from the values stored in the database, validating them
+/// along the way.
+#[allow(clippy::too_many_arguments)]
+fn to_subscription(
+    id: Uuid,
+    email: String,
+    username: String,
+    status: Option<String>,
+    subscribed_at: DateTime<Utc>,
+    topics: Vec<String>,
+    frequency: Option<String>,
+    attributes: serde_json::Value,
+) -> Result<Subscription, SubscriptionError> {
+    let username =
+        SubscriberName::parse(username).map_err(|err| SubscriptionError::Validation {
+            context: format!("Invalid username stored in the database: {err}"),
+        })?;
+    let email = SubscriberEmail::parse(email).map_err(|err| SubscriptionError::Validation {
+        context: format!("Invalid email stored in the database: {err}"),
+    })?;
+    let status = SubscriptionStatus::from_str(&status.unwrap_or_default()).map_err(|err| {
+        SubscriptionError::Validation {
+            context: format!("Invalid status stored in the database: {err}"),
+        }
+    })?;
+    let frequency = DeliveryFrequency::from_str(&frequency.unwrap_or_default()).map_err(|err| {
+        SubscriptionError::Validation {
+            context: format!("Invalid frequency stored in the database: {err}"),
+        }
+    })?;
+    Ok(Subscription {
+        id,
+        email,
+        username,
+        status,
+        subscribed_at,
+        preferences: SubscriberPreferences { topics, frequency },
+        attributes,
+    })
+}
+
+/// Builds a suppression from the values stored in the database.
+fn to_suppression(
+    email: String,
+    reason: SuppressionReason,
+    source: String,
+    suppressed_at: DateTime<Utc>,
+) -> Result<Suppression, SubscriptionError> {
+    let email = SubscriberEmail::parse(email).map_err(|err| SubscriptionError::Validation {
+        context: format!("Invalid email stored in the database: {err}"),
+    })?;
+    Ok(Suppression {
+        email,
+        reason,
+        source,
+        suppressed_at,
+    })
 }
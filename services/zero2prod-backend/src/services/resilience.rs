@@ -0,0 +1,266 @@
+use common::settings::{CircuitBreakerSettings, RetrySettings};
+use rand::Rng;
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::domain::ports::secondary::EmailError as Error;
+
+/// The failure of one attempt.
+pub enum Failure {
+    /// The attempt may succeed if tried again, after `retry_after` if the
+    /// service asked for a delay.
+    Transient {
+        error: Error,
+        retry_after: Option<Duration>,
+    },
+    /// Trying again would fail the same way.
+    Permanent(Error),
+}
+
+/// Retries and circuit breaking for the calls to the email services. A call is
+/// attempted until it succeeds, fails permanently, or runs out of attempts,
+/// waiting between attempts for a jittered exponential backoff, or for the
+/// delay the service asked for. Failed attempts are counted by a circuit
+/// breaker which, once enough of them follow each other, fails calls fast for a
+/// while, and then lets a single trial call through.
+pub struct Resilience {
+    max_attempts: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    breaker: CircuitBreaker,
+}
+
+impl Resilience {
+    pub fn new(retry: &RetrySettings, circuit_breaker: &CircuitBreakerSettings) -> Self {
+        Resilience {
+            max_attempts: retry.max_attempts.max(1),
+            initial_backoff: Duration::from_millis(retry.initial_backoff),
+            max_backoff: Duration::from_millis(retry.max_backoff),
+            breaker: CircuitBreaker::new(
+                circuit_breaker.failure_threshold.max(1),
+                Duration::from_secs(circuit_breaker.reset_timeout),
+            ),
+        }
+    }
+
+    /// Runs the call, retrying transient failures. Fails fast while the
+    /// circuit is open.
+    pub async fn run<T, F, Fut>(&self, mut call: F) -> Result<T, Error>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, Failure>>,
+    {
+        let mut attempt = 1;
+        loop {
+            self.breaker.acquire()?;
+            let (error, retry_after) = match call().await {
+                Ok(value) => {
+                    self.breaker.record_success();
+                    return Ok(value);
+                }
+                Err(Failure::Permanent(error)) => {
+                    // The service answered, so it is up.
+                    self.breaker.record_success();
+                    return Err(error);
+                }
+                Err(Failure::Transient { error, retry_after }) => {
+                    self.breaker.record_failure();
+                    (error, retry_after)
+                }
+            };
+            if attempt >= self.max_attempts {
+                return Err(Error::Unavailable {
+                    context: format!(
+                        "Email service still failing after {attempt} attempts: {error}"
+                    ),
+                });
+            }
+            let delay = retry_after
+                .unwrap_or_else(|| self.backoff(attempt))
+                .min(self.max_backoff);
+            tracing::warn!("Attempt {attempt} failed, retrying in {delay:?}: {error}");
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    /// A random delay, up to the initial backoff doubled for each attempt.
+    fn backoff(&self, attempt: u32) -> Duration {
+        let ceiling = self
+            .initial_backoff
+            .saturating_mul(2u32.saturating_pow(attempt - 1))
+            .min(self.max_backoff);
+        ceiling.mul_f64(rand::thread_rng().gen_range(0.0..=1.0))
+    }
+}
+
+enum State {
+    Closed {
+        failures: u32,
+    },
+    Open {
+        until: Instant,
+    },
+    /// The trial call is in flight. It is deemed lost after `until`, if its
+    /// future was dropped before its outcome was recorded, and another trial
+    /// call is let through.
+    HalfOpen {
+        until: Instant,
+    },
+}
+
+struct CircuitBreaker {
+    failure_threshold: u32,
+    reset_timeout: Duration,
+    state: Mutex<State>,
+}
+
+impl CircuitBreaker {
+    fn new(failure_threshold: u32, reset_timeout: Duration) -> Self {
+        CircuitBreaker {
+            failure_threshold,
+            reset_timeout,
+            state: Mutex::new(State::Closed { failures: 0 }),
+        }
+    }
+
+    /// Lets the call through, unless the circuit is open, or its trial call
+    /// is in flight.
+    #[allow(clippy::result_large_err)]
+    fn acquire(&self) -> Result<(), Error> {
+        let mut state = self.state.lock().unwrap();
+        let now = Instant::now();
+        match *state {
+            State::Closed { .. } => Ok(()),
+            State::Open { until } | State::HalfOpen { until } if now >= until => {
+                *state = State::HalfOpen {
+                    until: now + self.reset_timeout,
+                };
+                Ok(())
+            }
+            State::Open { .. } | State::HalfOpen { .. } => Err(Error::CircuitOpen {
+                context: "Email service failing, calls suspended".to_string(),
+            }),
+        }
+    }
+
+    fn record_success(&self) {
+        *self.state.lock().unwrap() = State::Closed { failures: 0 };
+    }
+
+    fn record_failure(&self) {
+        let mut state = self.state.lock().unwrap();
+        let failures = match *state {
+            State::Closed { failures } => failures + 1,
+            State::Open { .. } | State::HalfOpen { .. } => self.failure_threshold,
+        };
+        *state = if failures >= self.failure_threshold {
+            State::Open {
+                until: Instant::now() + self.reset_timeout,
+            }
+        } else {
+            State::Closed { failures }
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    fn resilience(max_attempts: u32, failure_threshold: u32) -> Resilience {
+        Resilience::new(
+            &RetrySettings {
+                max_attempts,
+                initial_backoff: 1,
+                max_backoff: 5,
+            },
+            &CircuitBreakerSettings {
+                failure_threshold,
+                reset_timeout: 60,
+            },
+        )
+    }
+
+    fn transient() -> Failure {
+        Failure::Transient {
+            error: Error::Missing {
+                context: "down".to_string(),
+            },
+            retry_after: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn run_should_retry_transient_failures() {
+        let resilience = resilience(3, 10);
+        let attempts = AtomicU32::new(0);
+
+        let outcome = resilience
+            .run(|| async {
+                match attempts.fetch_add(1, Ordering::SeqCst) {
+                    0 | 1 => Err(transient()),
+                    _ => Ok(()),
+                }
+            })
+            .await;
+
+        assert!(outcome.is_ok());
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn run_should_not_retry_permanent_failures() {
+        let resilience = resilience(3, 10);
+        let attempts = AtomicU32::new(0);
+
+        let outcome: Result<(), Error> = resilience
+            .run(|| async {
+                attempts.fetch_add(1, Ordering::SeqCst);
+                Err(Failure::Permanent(Error::Rejected {
+                    context: "refused".to_string(),
+                }))
+            })
+            .await;
+
+        assert!(matches!(outcome, Err(Error::Rejected { .. })));
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn run_should_fail_fast_once_the_circuit_is_open() {
+        let resilience = resilience(2, 2);
+        let attempts = AtomicU32::new(0);
+        let call = || async {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            Err::<(), _>(transient())
+        };
+
+        let first = resilience.run(call).await;
+        let second = resilience.run(call).await;
+
+        assert!(matches!(first, Err(Error::Unavailable { .. })));
+        assert!(matches!(second, Err(Error::CircuitOpen { .. })));
+        assert_eq!(attempts.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn breaker_should_let_another_trial_through_once_a_trial_is_lost() {
+        let breaker = CircuitBreaker::new(1, Duration::from_millis(20));
+        breaker.record_failure();
+        tokio::time::sleep(Duration::from_millis(30)).await;
+
+        // The trial call is let through, but its outcome is never recorded,
+        // as if its future was dropped.
+        let trial = breaker.acquire();
+        let during_trial = breaker.acquire();
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        let after_trial = breaker.acquire();
+
+        assert!(trial.is_ok());
+        assert!(matches!(during_trial, Err(Error::CircuitOpen { .. })));
+        assert!(after_trial.is_ok());
+    }
+}
@@ -0,0 +1,210 @@
+use ammonia::{Builder, Url, UrlRelative};
+use common::settings::SanitizerSettings;
+use html5ever::tendril::StrTendril;
+use html5ever::tokenizer::{
+    BufferQueue, TagKind, Token, TokenSink, TokenSinkResult, Tokenizer, TokenizerOpts,
+};
+use std::cell::Cell;
+use std::collections::{HashMap, HashSet};
+
+use crate::domain::ports::secondary::{HtmlSanitizer, SanitizerError as Error};
+
+/// Tags removed with their content, instead of just their markup.
+const CLEAN_CONTENT_TAGS: &[&str] = &["script", "style"];
+
+/// Sanitizes the newsletters with ammonia, following the policy found in the
+/// settings.
+pub struct AmmoniaSanitizer {
+    settings: SanitizerSettings,
+    base_url: Url,
+}
+
+impl AmmoniaSanitizer {
+    /// Checks the policy, and the base url against which relative URLs are
+    /// rewritten.
+    pub fn new(settings: SanitizerSettings, base_url: &str) -> Result<AmmoniaSanitizer, Error> {
+        if let Some(tag) = settings
+            .tags
+            .iter()
+            .find(|tag| CLEAN_CONTENT_TAGS.contains(&tag.as_str()))
+        {
+            return Err(Error::Policy {
+                context: "Invalid sanitizer tags".to_string(),
+                source: format!("{tag} can not be allowed"),
+            });
+        }
+        if !(0.0..=1.0).contains(&settings.max_removed) {
+            return Err(Error::Policy {
+                context: "Invalid sanitizer max_removed".to_string(),
+                source: format!("{} is not between 0 and 1", settings.max_removed),
+            });
+        }
+        let base_url = Url::parse(base_url).map_err(|err| Error::Policy {
+            context: format!("Invalid base url {base_url}"),
+            source: err.to_string(),
+        })?;
+        Ok(AmmoniaSanitizer { settings, base_url })
+    }
+
+    fn builder(&self) -> Builder<'_> {
+        let SanitizerSettings {
+            tags,
+            generic_attributes,
+            tag_attributes,
+            url_schemes,
+            max_removed: _,
+        } = &self.settings;
+        let mut builder = Builder::empty();
+        builder
+            .tags(tags.iter().map(String::as_str).collect())
+            .clean_content_tags(CLEAN_CONTENT_TAGS.iter().copied().collect())
+            .generic_attributes(generic_attributes.iter().map(String::as_str).collect())
+            .tag_attributes(
+                tag_attributes
+                    .iter()
+                    .map(|(tag, attributes)| {
+                        (
+                            tag.as_str(),
+                            attributes.iter().map(String::as_str).collect(),
+                        )
+                    })
+                    .collect::<HashMap<&str, HashSet<&str>>>(),
+            )
+            .url_schemes(url_schemes.iter().map(String::as_str).collect())
+            .url_relative(UrlRelative::RewriteWithBase(self.base_url.clone()))
+            // Emails are not browsed, and the rel attribute only gets in the
+            // way of measuring what the policy removed.
+            .link_rel(None);
+        builder
+    }
+}
+
+impl HtmlSanitizer for AmmoniaSanitizer {
+    fn sanitize(&self, html: &str, force: bool) -> Result<String, Error> {
+        let clean = self.builder().clean(html).to_string();
+        let size = content_size(html);
+        let removed = if size == 0 {
+            0.0
+        } else {
+            size.saturating_sub(content_size(&clean)) as f64 / size as f64
+        };
+        if removed > self.settings.max_removed && !force {
+            return Err(Error::Rejected {
+                context: format!(
+                    "The sanitizer removed more than {:.0}% of the content",
+                    self.settings.max_removed * 100.0
+                ),
+                removed,
+            });
+        }
+        if removed > 0.0 {
+            tracing::info!(
+                "The sanitizer removed {:.0}% of the content",
+                removed * 100.0
+            );
+        }
+        Ok(clean)
+    }
+}
+
+/// Counts the tags, attributes and characters of text of the HTML. Entities
+/// are decoded and URLs are not looked at, so that what the sanitizer escaped
+/// or rewrote is the same size as before, and only what it dropped counts.
+fn content_size(html: &str) -> usize {
+    let tokenizer = Tokenizer::new(ContentSize::default(), TokenizerOpts::default());
+    let input = BufferQueue::default();
+    input.push_back(StrTendril::from_slice(html));
+    let _ = tokenizer.feed(&input);
+    tokenizer.end();
+    tokenizer.sink.0.get()
+}
+
+#[derive(Default)]
+struct ContentSize(Cell<usize>);
+
+impl TokenSink for ContentSize {
+    type Handle = ();
+
+    fn process_token(&self, token: Token, _line_number: u64) -> TokenSinkResult<()> {
+        let size = match token {
+            Token::TagToken(tag) if tag.kind == TagKind::StartTag => 1 + tag.attrs.len(),
+            Token::CharacterTokens(text) => text.chars().filter(|c| !c.is_whitespace()).count(),
+            _ => 0,
+        };
+        self.0.set(self.0.get() + size);
+        TokenSinkResult::Continue
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use speculoos::prelude::*;
+
+    fn settings(max_removed: f64) -> SanitizerSettings {
+        SanitizerSettings {
+            tags: ["p", "a", "em"].iter().map(|t| t.to_string()).collect(),
+            generic_attributes: vec!["style".to_string()],
+            tag_attributes: HashMap::from([("a".to_string(), vec!["href".to_string()])]),
+            url_schemes: vec!["https".to_string()],
+            max_removed,
+        }
+    }
+
+    #[test]
+    fn sanitizer_should_remove_what_the_policy_does_not_allow() {
+        let sanitizer = AmmoniaSanitizer::new(settings(1.0), "http://127.0.0.1").unwrap();
+
+        let html = sanitizer
+            .sanitize(
+                "<p style=\"color: red;\" onclick=\"steal()\">Hi <em>you</em></p>\
+                 <script>alert(1)</script><form><input name=\"x\"></form>\
+                 <a href=\"javascript:alert(1)\">bad</a><a href=\"/archive\">archive</a>",
+                false,
+            )
+            .expect("sanitized html");
+
+        assert_eq!(
+            html,
+            "<p style=\"color: red;\">Hi <em>you</em></p>\
+             <a>bad</a><a href=\"http://127.0.0.1/archive\">archive</a>"
+        );
+    }
+
+    #[test]
+    fn sanitizer_should_reject_content_changed_too_much_unless_forced() {
+        let sanitizer = AmmoniaSanitizer::new(settings(0.1), "http://127.0.0.1").unwrap();
+        let html = "<p>Hello</p><script>document.location = 'https://evil.com'</script>";
+
+        let rejected = sanitizer.sanitize(html, false);
+        let forced = sanitizer.sanitize(html, true);
+
+        assert!(matches!(rejected, Err(Error::Rejected { .. })));
+        assert_that(&forced.ok()).is_equal_to(Some("<p>Hello</p>".to_string()));
+    }
+
+    #[test]
+    fn sanitizer_should_not_count_escaped_entities_and_rewritten_urls() {
+        let sanitizer = AmmoniaSanitizer::new(settings(0.1), "http://127.0.0.1").unwrap();
+        let padding = "<a href=\"/archive\">Tom &amp; Jerry</a> & friends ".repeat(10);
+
+        let kept = sanitizer.sanitize(&padding, false);
+        let rejected = sanitizer.sanitize(
+            &format!("<p>{padding}</p><script>alert(document.cookie)</script>"),
+            false,
+        );
+
+        assert!(kept.is_ok());
+        assert!(matches!(rejected, Err(Error::Rejected { .. })));
+    }
+
+    #[test]
+    fn sanitizer_should_refuse_to_allow_scripts() {
+        let mut settings = settings(0.1);
+        settings.tags.push("script".to_string());
+
+        let sanitizer = AmmoniaSanitizer::new(settings, "http://127.0.0.1");
+
+        assert!(matches!(sanitizer, Err(Error::Policy { .. })));
+    }
+}
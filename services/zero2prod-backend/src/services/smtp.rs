@@ -0,0 +1,416 @@
+use async_trait::async_trait;
+use common::err_context::{ErrorContext, ErrorContextExt};
+use common::settings::{EmailClientSettings, SmtpTls};
+use lettre::message::header::{ContentType, HeaderName, HeaderValue};
+use lettre::message::{Attachment as MimeAttachment, Mailbox, MultiPart};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::transport::smtp::client::{Tls, TlsParameters};
+use lettre::transport::smtp::PoolConfig;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use std::sync::Arc;
+
+use crate::domain::ports::secondary::{Email, EmailError as Error, EmailService};
+use crate::domain::SubscriberEmail;
+use crate::services::resilience::{Failure, Resilience};
+
+/// Sends emails through an SMTP relay, over a pool of connections.
+#[derive(Clone)]
+pub struct SmtpEmailClient {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    // This is the sender of the email sent to the end user.
+    sender: Mailbox,
+    // Emails larger than this, in bytes, are refused before being sent.
+    max_size: usize,
+    // Retries and circuit breaker, shared by the clones of the client.
+    resilience: Arc<Resilience>,
+}
+
+impl SmtpEmailClient {
+    pub async fn new(settings: EmailClientSettings) -> Result<SmtpEmailClient, Error> {
+        let smtp = settings.smtp.ok_or_else(|| Error::Configuration {
+            context: "Missing SMTP settings for the smtp email provider".to_string(),
+        })?;
+        let sender =
+            settings
+                .sender_email
+                .parse::<Mailbox>()
+                .map_err(|err| Error::Configuration {
+                    context: format!("Could not parse Email Client Service Sender: {err}"),
+                })?;
+        let tls = match smtp.tls {
+            SmtpTls::None => Tls::None,
+            SmtpTls::Starttls => Tls::Required(
+                TlsParameters::new(smtp.host.clone())
+                    .context("Could not set up STARTTLS with the SMTP relay")?,
+            ),
+            SmtpTls::Tls => Tls::Wrapper(
+                TlsParameters::new(smtp.host.clone())
+                    .context("Could not set up TLS with the SMTP relay")?,
+            ),
+        };
+        let mut builder = AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&smtp.host)
+            .port(smtp.port)
+            .tls(tls)
+            .timeout(Some(std::time::Duration::from_secs(settings.timeout)))
+            .pool_config(PoolConfig::new().max_size(smtp.pool_size));
+        match (smtp.username, smtp.password) {
+            (Some(username), Some(password)) => {
+                builder = builder.credentials(Credentials::new(username, password));
+            }
+            (None, None) => {}
+            _ => {
+                return Err(Error::Configuration {
+                    context: "SMTP username and password go together".to_string(),
+                })
+            }
+        }
+        Ok(SmtpEmailClient {
+            transport: builder.build(),
+            sender,
+            max_size: settings.max_size,
+            resilience: Arc::new(Resilience::new(&settings.retry, &settings.circuit_breaker)),
+        })
+    }
+
+    /// Assembles the MIME message: the text and HTML alternatives, related
+    /// to the inline images, mixed with the attachments.
+    fn message(&self, email: &Email) -> Result<Message, Error> {
+        let mut builder = Message::builder()
+            .from(self.sender.clone())
+            .to(mailbox(&email.to)?)
+            .subject(&email.subject);
+        for cc in &email.cc {
+            builder = builder.cc(mailbox(cc)?);
+        }
+        for bcc in &email.bcc {
+            builder = builder.bcc(mailbox(bcc)?);
+        }
+        if let Some(reply_to) = &email.reply_to {
+            builder = builder.reply_to(mailbox(reply_to)?);
+        }
+        for header in &email.headers {
+            let name =
+                HeaderName::new_from_ascii(header.name.clone()).map_err(|err| Error::Invalid {
+                    context: format!("Invalid header name {}: {err}", header.name),
+                })?;
+            builder = builder.raw_header(HeaderValue::new(name, header.value.clone()));
+        }
+
+        let mut body = MultiPart::alternative_plain_html(
+            email.text_content.clone(),
+            email.html_content.clone(),
+        );
+        let (inline, attached): (Vec<_>, Vec<_>) = email
+            .attachments
+            .iter()
+            .partition(|attachment| attachment.content_id.is_some());
+        if !inline.is_empty() {
+            body = inline.into_iter().try_fold(
+                MultiPart::related().multipart(body),
+                |related, image| {
+                    let content_id = image.content_id.clone().unwrap_or_default();
+                    Ok::<_, Error>(
+                        related.singlepart(
+                            MimeAttachment::new_inline_with_name(
+                                content_id,
+                                image.filename.clone(),
+                            )
+                            .body(image.content.clone(), content_type(&image.content_type)?),
+                        ),
+                    )
+                },
+            )?;
+        }
+        if !attached.is_empty() {
+            body = attached.into_iter().try_fold(
+                MultiPart::mixed().multipart(body),
+                |mixed, attachment| {
+                    Ok::<_, Error>(mixed.singlepart(
+                        MimeAttachment::new(attachment.filename.clone()).body(
+                            attachment.content.clone(),
+                            content_type(&attachment.content_type)?,
+                        ),
+                    ))
+                },
+            )?;
+        }
+        builder.multipart(body).map_err(|err| Error::Invalid {
+            context: format!("Could not build the email message: {err}"),
+        })
+    }
+}
+
+#[async_trait]
+impl EmailService for SmtpEmailClient {
+    async fn send_email(&self, email: Email) -> Result<(), Error> {
+        email.check_size(self.max_size)?;
+        let message = self.message(&email)?;
+        self.resilience
+            .run(|| async {
+                self.transport.send(message.clone()).await.map_err(|err| {
+                    // Permanent (5xx) replies, and errors of the client, would
+                    // come again, unlike transient (4xx) replies or I/O errors.
+                    if err.is_permanent() || err.is_client() {
+                        Failure::Permanent(Error::Rejected {
+                            context: format!("Email refused by the SMTP relay: {err}"),
+                        })
+                    } else {
+                        Failure::Transient {
+                            error: Error::from(ErrorContext(
+                                "Could not send email through the SMTP relay".to_string(),
+                                err,
+                            )),
+                            retry_after: None,
+                        }
+                    }
+                })
+            })
+            .await?;
+        Ok(())
+    }
+
+    async fn probe(&self) -> Result<(), Error> {
+        let connected = self
+            .transport
+            .test_connection()
+            .await
+            .context("Could not connect to the SMTP relay")?;
+        if connected {
+            Ok(())
+        } else {
+            Err(Error::Unavailable {
+                context: "SMTP relay not ready".to_string(),
+            })
+        }
+    }
+}
+
+#[allow(clippy::result_large_err)]
+fn mailbox(email: &SubscriberEmail) -> Result<Mailbox, Error> {
+    email.as_ref().parse().map_err(|err| Error::Invalid {
+        context: format!("Invalid address {email}: {err}"),
+    })
+}
+
+#[allow(clippy::result_large_err)]
+fn content_type(mime: &str) -> Result<ContentType, Error> {
+    ContentType::parse(mime).map_err(|err| Error::Invalid {
+        context: format!("Invalid content type {mime}: {err}"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use common::settings::{
+        CircuitBreakerSettings, EmailClientSettings, EmailProvider, RetrySettings, SmtpSettings,
+        SmtpTls,
+    };
+    use speculoos::prelude::*;
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::TcpListener;
+    use tokio::sync::mpsc;
+
+    use super::SmtpEmailClient;
+    use crate::domain::ports::secondary::{Attachment, Email, EmailError, EmailService};
+    use crate::domain::SubscriberEmail;
+
+    /// What the SMTP stand-in received for one message.
+    struct Received {
+        recipients: Vec<String>,
+        data: String,
+    }
+
+    /// Starts a local SMTP stand-in, which replies to every recipient with
+    /// `recipient_reply`, and reports the messages it accepts on the returned
+    /// channel. Returns its port.
+    async fn smtp_stand_in(
+        recipient_reply: &'static [u8],
+    ) -> (u16, mpsc::UnboundedReceiver<Received>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let (sender, receiver) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let sender = sender.clone();
+                tokio::spawn(async move {
+                    let (read, mut write) = stream.into_split();
+                    let mut lines = BufReader::new(read).lines();
+                    write.write_all(b"220 localhost ESMTP\r\n").await.unwrap();
+                    let mut recipients = Vec::new();
+                    while let Ok(Some(line)) = lines.next_line().await {
+                        let command = line.to_ascii_uppercase();
+                        let reply: &[u8] = if command.starts_with("EHLO") {
+                            b"250-localhost\r\n250 8BITMIME\r\n"
+                        } else if command.starts_with("RCPT TO:") {
+                            recipients.push(line[8..].trim_matches(['<', '>']).to_string());
+                            recipient_reply
+                        } else if command.starts_with("DATA") {
+                            write.write_all(b"354 Go ahead\r\n").await.unwrap();
+                            let mut data = String::new();
+                            while let Ok(Some(line)) = lines.next_line().await {
+                                if line == "." {
+                                    break;
+                                }
+                                data.push_str(&line);
+                                data.push('\n');
+                            }
+                            let recipients = std::mem::take(&mut recipients);
+                            sender.send(Received { recipients, data }).unwrap();
+                            b"250 Queued\r\n"
+                        } else if command.starts_with("QUIT") {
+                            write.write_all(b"221 Bye\r\n").await.unwrap();
+                            break;
+                        } else {
+                            b"250 OK\r\n"
+                        };
+                        write.write_all(reply).await.unwrap();
+                    }
+                });
+            }
+        });
+        (port, receiver)
+    }
+
+    fn settings(port: u16, max_size: usize) -> EmailClientSettings {
+        EmailClientSettings {
+            provider: EmailProvider::Smtp,
+            server_url: String::new(),
+            sender_email: "newsletter@acme.inc".to_string(),
+            authorization_token: String::new(),
+            timeout: 10, // sec
+            max_size,
+            batch_size: 500,
+            retry: RetrySettings {
+                max_attempts: 1,
+                initial_backoff: 10, // ms
+                max_backoff: 100,    // ms
+            },
+            circuit_breaker: CircuitBreakerSettings {
+                failure_threshold: 5,
+                reset_timeout: 30, // sec
+            },
+            smtp: Some(SmtpSettings {
+                host: "127.0.0.1".to_string(),
+                port,
+                tls: SmtpTls::None,
+                username: None,
+                password: None,
+                pool_size: 2,
+            }),
+            failover: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn send_email_should_deliver_a_multipart_message() {
+        let (port, mut received) = smtp_stand_in(b"250 OK\r\n").await;
+        let client = SmtpEmailClient::new(settings(port, 10_485_760))
+            .await
+            .expect("smtp client");
+
+        let mut email = Email::new(
+            SubscriberEmail::parse("alice@acme.inc").unwrap(),
+            "Spring news".to_string(),
+            "<p>Hello</p>".to_string(),
+            "Hello".to_string(),
+        );
+        email.bcc = vec![SubscriberEmail::parse("archive@acme.inc").unwrap()];
+        email.attachments = vec![Attachment {
+            filename: "logo.png".to_string(),
+            content_type: "image/png".to_string(),
+            content: vec![0x89, 0x50, 0x4e, 0x47],
+            content_id: Some("logo".to_string()),
+        }];
+
+        let outcome = client.send_email(email).await;
+
+        assert_that(&outcome).is_ok();
+        let message = received.recv().await.expect("message");
+        assert_eq!(
+            message.recipients,
+            vec!["alice@acme.inc".to_string(), "archive@acme.inc".to_string()]
+        );
+        assert_that(&message.data).contains("Subject: Spring news");
+        assert_that(&message.data).contains("multipart/related");
+        assert_that(&message.data).contains("multipart/alternative");
+        assert_that(&message.data).contains("Content-ID: <logo>");
+        assert!(!message.data.contains("Bcc:"));
+    }
+
+    #[tokio::test]
+    async fn send_email_should_refuse_emails_too_large() {
+        let (port, mut received) = smtp_stand_in(b"250 OK\r\n").await;
+        let client = SmtpEmailClient::new(settings(port, 16))
+            .await
+            .expect("smtp client");
+
+        let email = Email::new(
+            SubscriberEmail::parse("alice@acme.inc").unwrap(),
+            "Spring news".to_string(),
+            "<p>Hello, and welcome</p>".to_string(),
+            "Hello, and welcome".to_string(),
+        );
+
+        let outcome = client.send_email(email).await;
+
+        assert!(matches!(outcome, Err(EmailError::TooLarge { .. })));
+        assert!(received.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn send_email_should_fail_permanently_when_the_relay_refuses_it() {
+        let (port, mut received) = smtp_stand_in(b"550 Mailbox unavailable\r\n").await;
+        let client = SmtpEmailClient::new(settings(port, 10_485_760))
+            .await
+            .expect("smtp client");
+
+        let email = Email::new(
+            SubscriberEmail::parse("nobody@acme.inc").unwrap(),
+            "Spring news".to_string(),
+            "<p>Hello</p>".to_string(),
+            "Hello".to_string(),
+        );
+
+        let outcome = client.send_email(email).await;
+
+        assert!(matches!(&outcome, Err(EmailError::Rejected { .. })));
+        assert!(outcome.unwrap_err().is_permanent());
+        assert!(received.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn send_batch_should_send_each_email() {
+        let (port, mut received) = smtp_stand_in(b"250 OK\r\n").await;
+        let client = SmtpEmailClient::new(settings(port, 10_485_760))
+            .await
+            .expect("smtp client");
+
+        let emails = ["alice@acme.inc", "bob@acme.inc"]
+            .into_iter()
+            .map(|to| {
+                Email::new(
+                    SubscriberEmail::parse(to).unwrap(),
+                    "Spring news".to_string(),
+                    "<p>Hello</p>".to_string(),
+                    "Hello".to_string(),
+                )
+            })
+            .collect();
+
+        let outcomes = client.send_batch(emails).await;
+
+        assert!(outcomes.iter().all(Result::is_ok));
+        let mut recipients = vec![
+            received.recv().await.expect("message").recipients,
+            received.recv().await.expect("message").recipients,
+        ];
+        recipients.sort();
+        assert_eq!(
+            recipients,
+            vec![
+                vec!["alice@acme.inc".to_string()],
+                vec!["bob@acme.inc".to_string()]
+            ]
+        );
+    }
+}
@@ -0,0 +1,221 @@
+use async_trait::async_trait;
+use std::sync::Arc;
+
+use crate::domain::ports::secondary::{
+    Email, EmailError as Error, EmailService, SubscriptionStorage,
+};
+use crate::domain::{SubscriberEmail, Suppression};
+
+/// Enforces the suppression list for every email sent. Emails to a suppressed
+/// address are not handed over to the email service, and fail with
+/// `EmailError::Suppressed`. Suppressed addresses in copy are left out of the
+/// email, which is still sent to its recipient.
+pub struct SuppressingEmailService {
+    email: Arc<dyn EmailService + Send + Sync>,
+    storage: Arc<dyn SubscriptionStorage + Send + Sync>,
+}
+
+impl SuppressingEmailService {
+    pub fn new(
+        email: Arc<dyn EmailService + Send + Sync>,
+        storage: Arc<dyn SubscriptionStorage + Send + Sync>,
+    ) -> Self {
+        SuppressingEmailService { email, storage }
+    }
+
+    /// The suppressions of the recipients and copies of the emails.
+    async fn suppressions(&self, emails: &[Email]) -> Result<Vec<Suppression>, Error> {
+        let addresses = emails
+            .iter()
+            .flat_map(|email| {
+                std::iter::once(&email.to)
+                    .chain(&email.cc)
+                    .chain(&email.bcc)
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        self.storage
+            .get_suppressions(&addresses)
+            .await
+            .map_err(|err| Error::Unavailable {
+                context: format!("Could not check the suppression list: {err}"),
+            })
+    }
+}
+
+/// Leaves out the suppressed copies, or fails if the recipient is suppressed.
+fn check(mut email: Email, suppressions: &[Suppression]) -> Result<Email, Error> {
+    let suppression = |address: &SubscriberEmail| {
+        suppressions.iter().find(|suppression| {
+            suppression
+                .email
+                .as_ref()
+                .eq_ignore_ascii_case(address.as_ref())
+        })
+    };
+    if let Some(suppression) = suppression(&email.to) {
+        return Err(Error::Suppressed {
+            context: format!(
+                "The recipient is suppressed since {} ({:?}, from {})",
+                suppression.suppressed_at, suppression.reason, suppression.source
+            ),
+        });
+    }
+    email.cc.retain(|address| suppression(address).is_none());
+    email.bcc.retain(|address| suppression(address).is_none());
+    Ok(email)
+}
+
+#[async_trait]
+impl EmailService for SuppressingEmailService {
+    async fn send_email(&self, email: Email) -> Result<(), Error> {
+        let suppressions = self.suppressions(std::slice::from_ref(&email)).await?;
+        self.email.send_email(check(email, &suppressions)?).await
+    }
+
+    async fn send_batch(&self, emails: Vec<Email>) -> Vec<Result<Option<String>, Error>> {
+        let suppressions = match self.suppressions(&emails).await {
+            Ok(suppressions) => suppressions,
+            Err(err) => {
+                return emails
+                    .iter()
+                    .map(|_| {
+                        Err(Error::Unavailable {
+                            context: err.to_string(),
+                        })
+                    })
+                    .collect()
+            }
+        };
+        let mut outcomes = Vec::with_capacity(emails.len());
+        let mut allowed = Vec::with_capacity(emails.len());
+        for email in emails {
+            match check(email, &suppressions) {
+                Ok(email) => {
+                    outcomes.push(None);
+                    allowed.push(email);
+                }
+                Err(err) => outcomes.push(Some(Err(err))),
+            }
+        }
+        let mut sent = self.email.send_batch(allowed).await.into_iter();
+        outcomes
+            .into_iter()
+            .map(|outcome| {
+                outcome.or_else(|| sent.next()).unwrap_or_else(|| {
+                    Err(Error::Batch {
+                        context: "No outcome for the email from the email service".to_string(),
+                    })
+                })
+            })
+            .collect()
+    }
+
+    async fn probe(&self) -> Result<(), Error> {
+        self.email.probe().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::*;
+    use crate::domain::ports::secondary::{MockEmailService, MockSubscriptionStorage};
+    use crate::domain::SuppressionReason;
+
+    fn email(to: &str) -> Email {
+        Email::new(
+            SubscriberEmail::parse(to).unwrap(),
+            "Spring news".to_string(),
+            "<p>Hello</p>".to_string(),
+            "Hello".to_string(),
+        )
+    }
+
+    /// A storage where the addresses are suppressed.
+    fn storage(suppressed: &'static [&'static str]) -> MockSubscriptionStorage {
+        let mut storage = MockSubscriptionStorage::new();
+        storage.expect_get_suppressions().returning(move |emails| {
+            Ok(emails
+                .iter()
+                .filter(|email| suppressed.contains(&email.as_ref()))
+                .map(|email| Suppression {
+                    email: email.clone(),
+                    reason: SuppressionReason::HardBounce,
+                    source: "webhook".to_string(),
+                    suppressed_at: Utc::now(),
+                })
+                .collect())
+        });
+        storage
+    }
+
+    #[tokio::test]
+    async fn send_email_should_refuse_suppressed_recipients() {
+        let mut email_mock = MockEmailService::new();
+        email_mock.expect_send_email().never();
+        let service = SuppressingEmailService::new(
+            Arc::new(email_mock),
+            Arc::new(storage(&["bob@acme.inc"])),
+        );
+
+        let outcome = service.send_email(email("bob@acme.inc")).await;
+
+        assert!(matches!(outcome, Err(Error::Suppressed { .. })));
+    }
+
+    #[tokio::test]
+    async fn send_email_should_leave_out_suppressed_copies() {
+        let mut email_mock = MockEmailService::new();
+        email_mock
+            .expect_send_email()
+            .withf(|email: &Email| {
+                email.cc.is_empty() && email.bcc.len() == 1 && email.to.as_ref() == "alice@acme.inc"
+            })
+            .times(1)
+            .returning(|_| Ok(()));
+        let service = SuppressingEmailService::new(
+            Arc::new(email_mock),
+            Arc::new(storage(&["bob@acme.inc"])),
+        );
+        let mut email = email("alice@acme.inc");
+        email.cc = vec![SubscriberEmail::parse("bob@acme.inc").unwrap()];
+        email.bcc = vec![SubscriberEmail::parse("carol@acme.inc").unwrap()];
+
+        let outcome = service.send_email(email).await;
+
+        assert!(outcome.is_ok());
+    }
+
+    #[tokio::test]
+    async fn send_batch_should_only_send_to_addresses_not_suppressed() {
+        let mut email_mock = MockEmailService::new();
+        email_mock
+            .expect_send_batch()
+            .withf(|emails: &Vec<Email>| {
+                emails
+                    .iter()
+                    .map(|email| email.to.as_ref())
+                    .eq(["alice@acme.inc", "carol@acme.inc"])
+            })
+            .times(1)
+            .returning(|_| vec![Ok(None), Ok(None)]);
+        let service = SuppressingEmailService::new(
+            Arc::new(email_mock),
+            Arc::new(storage(&["bob@acme.inc"])),
+        );
+
+        let outcomes = service
+            .send_batch(vec![
+                email("alice@acme.inc"),
+                email("bob@acme.inc"),
+                email("carol@acme.inc"),
+            ])
+            .await;
+
+        assert!(outcomes[0].is_ok());
+        assert!(matches!(outcomes[1], Err(Error::Suppressed { .. })));
+        assert!(outcomes[2].is_ok());
+    }
+}
@@ -0,0 +1,179 @@
+use common::err_context::ErrorContextExt;
+use common::settings::TemplateSettings;
+use minijinja::{path_loader, Environment, UndefinedBehavior};
+
+use crate::domain::ports::secondary::{Email, TemplateEngine, TemplateError as Error};
+use crate::domain::{EmailTemplate, SubscriberEmail};
+
+/// Renders email templates with minijinja. The html variants are escaped,
+/// the subject and text variants are not.
+pub struct MiniJinjaTemplates {
+    env: Environment<'static>,
+}
+
+impl MiniJinjaTemplates {
+    /// Loads the templates found in the directory, and checks that every email
+    /// we send has a template, which renders.
+    pub fn new(settings: TemplateSettings) -> Result<MiniJinjaTemplates, Error> {
+        let TemplateSettings { directory } = settings;
+        if !directory.is_dir() {
+            return Err(Error::Missing {
+                context: "Could not load email templates".to_string(),
+                source: format!("{} is not a directory", directory.display()),
+            });
+        }
+        let mut env = Environment::new();
+        env.set_loader(path_loader(directory));
+        // A variable missing from the context is a mistake in the template.
+        env.set_undefined_behavior(UndefinedBehavior::Strict);
+        let templates = MiniJinjaTemplates { env };
+        templates.check()?;
+        Ok(templates)
+    }
+
+    fn check(&self) -> Result<(), Error> {
+        let to = SubscriberEmail::parse("john.doe@example.com").unwrap();
+        for template in EmailTemplate::samples() {
+            self.render(&to, &template)?;
+        }
+        Ok(())
+    }
+
+    fn render_variant(&self, template: &EmailTemplate, variant: &str) -> Result<String, Error> {
+        let name = format!("{}/{}", template.name(), variant);
+        let content = self
+            .env
+            .get_template(&name)
+            .context(format!("Could not load template {name}"))?
+            .render(template)
+            .context(format!("Could not render template {name}"))?;
+        Ok(content)
+    }
+}
+
+impl TemplateEngine for MiniJinjaTemplates {
+    fn render(&self, to: &SubscriberEmail, template: &EmailTemplate) -> Result<Email, Error> {
+        Ok(Email::new(
+            to.clone(),
+            self.render_variant(template, "subject.txt")?
+                .trim()
+                .to_string(),
+            self.render_variant(template, "body.html")?,
+            self.render_variant(template, "body.txt")?,
+        ))
+    }
+}
+
+/// Loads the templates shipped with the repository, for the tests of the
+/// components sending emails.
+#[cfg(test)]
+pub(crate) fn repository_templates() -> MiniJinjaTemplates {
+    let directory = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("..")
+        .join("..")
+        .join("templates")
+        .join("email");
+    MiniJinjaTemplates::new(TemplateSettings { directory }).expect("repository templates")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{ConfirmationContext, DigestContext, DigestIssue, NewsletterContext};
+    use speculoos::prelude::*;
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn settings(directory: PathBuf) -> TemplateSettings {
+        TemplateSettings { directory }
+    }
+
+    #[test]
+    fn confirmation_email_should_contain_the_link() {
+        let templates = repository_templates();
+        let to = SubscriberEmail::parse("alice@acme.inc").unwrap();
+        let template = EmailTemplate::Confirmation(ConfirmationContext {
+            username: "Alice & Bob".to_string(),
+            confirmation_link: "http://127.0.0.1/confirm?token=abc".to_string(),
+        });
+
+        let email = templates.render(&to, &template).expect("email");
+
+        assert_eq!(email.subject, "Welcome");
+        assert_that(&email.html_content).contains("Alice &amp; Bob");
+        assert_that(&email.text_content)
+            .contains("Alice & Bob!\nVisit http://127.0.0.1/confirm?token=abc");
+    }
+
+    #[test]
+    fn newsletter_should_be_wrapped_in_the_layout() {
+        let templates = repository_templates();
+        let to = SubscriberEmail::parse("alice@acme.inc").unwrap();
+        let template = EmailTemplate::Newsletter(NewsletterContext {
+            title: "News".to_string(),
+            html_content: "<p>Hello</p>".to_string(),
+            text_content: "Hello".to_string(),
+            unsubscribe_link: "http://127.0.0.1/unsubscribe?token=abc".to_string(),
+            preferences_link: "http://127.0.0.1/preferences?token=abc".to_string(),
+            web_link: "http://127.0.0.1/issues/news-0f1e2d3c".to_string(),
+        });
+
+        let email = templates.render(&to, &template).expect("email");
+
+        assert_eq!(email.subject, "News");
+        assert_that(&email.html_content).contains("<p>Hello</p>");
+        assert_that(&email.html_content)
+            .contains("href=\"http://127.0.0.1/unsubscribe?token=abc\"");
+        assert_that(&email.text_content).contains("http://127.0.0.1/unsubscribe?token=abc");
+        assert_that(&email.html_content).contains("href=\"http://127.0.0.1/issues/news-0f1e2d3c\"");
+        assert_that(&email.text_content).contains("http://127.0.0.1/issues/news-0f1e2d3c");
+        assert_that(&email.html_content)
+            .contains("href=\"http://127.0.0.1/preferences?token=abc\"");
+    }
+
+    #[test]
+    fn digest_should_link_to_every_issue() {
+        let templates = repository_templates();
+        let to = SubscriberEmail::parse("alice@acme.inc").unwrap();
+        let template = EmailTemplate::Digest(DigestContext {
+            title: "Your weekly digest".to_string(),
+            issues: vec![
+                DigestIssue {
+                    title: "News & Views".to_string(),
+                    link: "http://127.0.0.1/issues/news-0f1e2d3c".to_string(),
+                },
+                DigestIssue {
+                    title: "More news".to_string(),
+                    link: "http://127.0.0.1/issues/more-news-1a2b3c4d".to_string(),
+                },
+            ],
+            unsubscribe_link: "http://127.0.0.1/unsubscribe?token=abc".to_string(),
+            preferences_link: "http://127.0.0.1/preferences?token=abc".to_string(),
+            web_link: "http://127.0.0.1/issues".to_string(),
+        });
+
+        let email = templates.render(&to, &template).expect("email");
+
+        assert_eq!(email.subject, "Your weekly digest");
+        assert_that(&email.html_content).contains("News &amp; Views");
+        assert_that(&email.html_content)
+            .contains("href=\"http://127.0.0.1/issues/more-news-1a2b3c4d\"");
+        assert_that(&email.text_content)
+            .contains("- News & Views: http://127.0.0.1/issues/news-0f1e2d3c");
+    }
+
+    #[test]
+    fn templates_should_be_checked_when_loaded() {
+        let directory = std::env::temp_dir().join(format!("templates-{}", uuid::Uuid::new_v4()));
+        let confirmation = directory.join("confirmation");
+        fs::create_dir_all(&confirmation).unwrap();
+        fs::write(confirmation.join("subject.txt"), "Welcome").unwrap();
+        fs::write(confirmation.join("body.html"), "{{ unknown_variable }}").unwrap();
+        fs::write(confirmation.join("body.txt"), "Welcome").unwrap();
+
+        let templates = MiniJinjaTemplates::new(settings(directory.clone()));
+        fs::remove_dir_all(&directory).unwrap();
+
+        assert!(matches!(templates, Err(Error::Render { .. })));
+    }
+}
@@ -0,0 +1,59 @@
+use super::placeholders::escape_html;
+
+const HREF: &str = "href=\"";
+
+/// Replaces the destination of each link with the one returned by `rewrite`,
+/// which is given the unescaped destination. Links for which it returns `None`
+/// are left as they are. The sanitizer serializes every attribute in double
+/// quotes, with `&` and `"` escaped, so the links of its output are found
+/// without parsing the HTML.
+pub fn rewrite_links(html: &str, mut rewrite: impl FnMut(&str) -> Option<String>) -> String {
+    let mut rewritten = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = rest.find(HREF) {
+        let (before, after) = rest.split_at(start + HREF.len());
+        rewritten.push_str(before);
+        let Some(end) = after.find('"') else {
+            rest = after;
+            break;
+        };
+        let href = &after[..end];
+        match rewrite(&unescape_html(href)) {
+            Some(url) => rewritten.push_str(&escape_html(&url)),
+            None => rewritten.push_str(href),
+        }
+        rest = &after[end..];
+    }
+    rewritten.push_str(rest);
+    rewritten
+}
+
+fn unescape_html(value: &str) -> String {
+    value
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use speculoos::prelude::*;
+
+    #[test]
+    fn rewrite_links_should_replace_the_destinations_it_is_given() {
+        let html = r#"<p><a href="https://acme.inc/?a=1&amp;b=2">Acme</a> and <a href="mailto:bob@acme.inc">Bob</a></p>"#;
+
+        let rewritten = rewrite_links(html, |url| {
+            url.starts_with("https://")
+                .then(|| format!("https://track.inc/?to={}&id=1", url.len()))
+        });
+
+        assert_that(&rewritten).is_equal_to(
+            r#"<p><a href="https://track.inc/?to=25&amp;id=1">Acme</a> and <a href="mailto:bob@acme.inc">Bob</a></p>"#
+                .to_string(),
+        );
+    }
+}
@@ -0,0 +1,172 @@
+use pulldown_cmark::{html, CowStr, Event, Options, Parser, Tag, TagEnd};
+
+/// Inline styles added to the HTML elements, since most email clients ignore
+/// style sheets.
+const STYLES: &[(&str, &str)] = &[
+    ("p", "margin: 0 0 16px; line-height: 1.5;"),
+    ("h1", "margin: 24px 0 16px; font-size: 24px;"),
+    ("h2", "margin: 24px 0 16px; font-size: 20px;"),
+    ("h3", "margin: 24px 0 16px; font-size: 18px;"),
+    ("h4", "margin: 16px 0; font-size: 16px;"),
+    ("h5", "margin: 16px 0; font-size: 16px;"),
+    ("h6", "margin: 16px 0; font-size: 16px;"),
+    ("a", "color: #1a73e8;"),
+    ("ul", "margin: 0 0 16px; padding-left: 24px;"),
+    ("ol", "margin: 0 0 16px; padding-left: 24px;"),
+    (
+        "blockquote",
+        "margin: 0 0 16px; padding-left: 16px; border-left: 4px solid #dddddd; color: #555555;",
+    ),
+    (
+        "pre",
+        "margin: 0 0 16px; padding: 12px; background: #f5f5f5; overflow: auto;",
+    ),
+    ("code", "font-family: Menlo, Consolas, monospace;"),
+    ("img", "max-width: 100%;"),
+];
+
+/// URL schemes allowed in links and images. Other URLs are dropped.
+const SCHEMES: &[&str] = &["http:", "https:", "mailto:"];
+
+/// Renders the Markdown as HTML, with inline styles. Raw HTML found in the
+/// Markdown is dropped, and so are links and images with unexpected schemes.
+pub fn to_html(markdown: &str) -> String {
+    let events =
+        Parser::new_ext(markdown, Options::ENABLE_STRIKETHROUGH).filter_map(|event| match event {
+            Event::Html(_) | Event::InlineHtml(_) => None,
+            Event::Start(Tag::HtmlBlock) | Event::End(TagEnd::HtmlBlock) => None,
+            Event::Start(Tag::Link {
+                link_type,
+                dest_url,
+                title,
+                id,
+            }) => Some(Event::Start(Tag::Link {
+                link_type,
+                dest_url: safe_url(dest_url),
+                title,
+                id,
+            })),
+            Event::Start(Tag::Image {
+                link_type,
+                dest_url,
+                title,
+                id,
+            }) => Some(Event::Start(Tag::Image {
+                link_type,
+                dest_url: safe_url(dest_url),
+                title,
+                id,
+            })),
+            event => Some(event),
+        });
+    let mut output = String::new();
+    html::push_html(&mut output, events);
+    add_styles(&output)
+}
+
+/// Renders the Markdown as plain text. Links are followed by their URL.
+pub fn to_text(markdown: &str) -> String {
+    let mut output = String::new();
+    // Numbering of the lists being rendered, None for unordered lists.
+    let mut lists: Vec<Option<u64>> = Vec::new();
+    // URLs of the links being rendered.
+    let mut links: Vec<CowStr> = Vec::new();
+    for event in Parser::new_ext(markdown, Options::ENABLE_STRIKETHROUGH) {
+        match event {
+            Event::Text(text) | Event::Code(text) => output.push_str(&text),
+            Event::SoftBreak | Event::HardBreak => output.push('\n'),
+            Event::Rule => output.push_str("----\n\n"),
+            Event::Start(Tag::List(start)) => lists.push(start),
+            Event::End(TagEnd::List(_)) => {
+                lists.pop();
+                if lists.is_empty() {
+                    output.push('\n');
+                }
+            }
+            Event::Start(Tag::Item) => {
+                if !output.is_empty() && !output.ends_with('\n') {
+                    output.push('\n');
+                }
+                output.push_str(&"  ".repeat(lists.len().saturating_sub(1)));
+                match lists.last_mut() {
+                    Some(Some(number)) => {
+                        output.push_str(&format!("{number}. "));
+                        *number += 1;
+                    }
+                    _ => output.push_str("- "),
+                }
+            }
+            Event::End(TagEnd::Item) if !output.ends_with('\n') => output.push('\n'),
+            Event::Start(Tag::Link { dest_url, .. }) => links.push(dest_url),
+            Event::End(TagEnd::Link) => {
+                if let Some(url) = links.pop() {
+                    output.push_str(&format!(" ({url})"));
+                }
+            }
+            Event::End(TagEnd::Paragraph) => {
+                output.push_str(if lists.is_empty() { "\n\n" } else { "\n" })
+            }
+            Event::End(TagEnd::Heading(_)) | Event::End(TagEnd::CodeBlock) => {
+                output.push_str("\n\n")
+            }
+            _ => {}
+        }
+    }
+    output.trim_end().to_string()
+}
+
+fn safe_url(url: CowStr) -> CowStr {
+    let lower = url.to_lowercase();
+    // Relative URLs have no scheme, and make no sense in an email.
+    if SCHEMES.iter().any(|scheme| lower.starts_with(scheme)) {
+        url
+    } else {
+        CowStr::Borrowed("")
+    }
+}
+
+/// Adds the inline style of every element. The HTML is generated from Markdown
+/// without raw HTML, so any '<' is the start of a tag.
+fn add_styles(html: &str) -> String {
+    let mut output = html.to_string();
+    for (tag, style) in STYLES {
+        output = output
+            .replace(&format!("<{tag} "), &format!("<{tag} style=\"{style}\" "))
+            .replace(&format!("<{tag}>"), &format!("<{tag} style=\"{style}\">"));
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use speculoos::prelude::*;
+
+    #[test]
+    fn html_should_have_inline_styles() {
+        let html = to_html("# Title\n\nSome *text* with a [link](https://acme.inc).");
+        assert_that(&html)
+            .contains("<h1 style=\"margin: 24px 0 16px; font-size: 24px;\">Title</h1>");
+        assert_that(&html)
+            .contains("<a style=\"color: #1a73e8;\" href=\"https://acme.inc\">link</a>");
+        assert_that(&html).contains("<em>text</em>");
+    }
+
+    #[test]
+    fn html_should_drop_raw_html_and_unsafe_urls() {
+        let html = to_html("Hello <script>alert(1)</script>\n\n[click](javascript:alert(1))");
+        assert!(!html.contains("script"));
+        assert!(!html.contains("javascript"));
+    }
+
+    #[test]
+    fn text_should_keep_structure_and_urls() {
+        let text = to_text(
+            "# Title\n\nSome *text* with a [link](https://acme.inc).\n\n- one\n- two\n\n1. first\n2. second",
+        );
+        assert_eq!(
+            text,
+            "Title\n\nSome text with a link (https://acme.inc).\n\n- one\n- two\n\n1. first\n2. second"
+        );
+    }
+}
@@ -1 +1,4 @@
+pub mod links;
+pub mod markdown;
+pub mod placeholders;
 pub mod tracing;
@@ -0,0 +1,204 @@
+use std::fmt;
+
+const MARKER: &str = "https://placeholder.invalid/";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PlaceholderName {
+    Username,
+    Email,
+    UnsubscribeUrl,
+    PreferencesUrl,
+    /// A key of the custom attributes of the subscriber.
+    Attribute(String),
+}
+
+impl PlaceholderName {
+    fn parse(s: &str) -> Result<Self, String> {
+        match s {
+            "username" => Ok(PlaceholderName::Username),
+            "email" => Ok(PlaceholderName::Email),
+            "unsubscribe_url" => Ok(PlaceholderName::UnsubscribeUrl),
+            "preferences_url" => Ok(PlaceholderName::PreferencesUrl),
+            _ => match s.strip_prefix("attributes.") {
+                Some(key)
+                    if !key.is_empty()
+                        && key
+                            .chars()
+                            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') =>
+                {
+                    Ok(PlaceholderName::Attribute(key.to_string()))
+                }
+                _ => Err(format!("Unknown placeholder: {s:?}")),
+            },
+        }
+    }
+}
+
+impl fmt::Display for PlaceholderName {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PlaceholderName::Username => write!(f, "username"),
+            PlaceholderName::Email => write!(f, "email"),
+            PlaceholderName::UnsubscribeUrl => write!(f, "unsubscribe_url"),
+            PlaceholderName::PreferencesUrl => write!(f, "preferences_url"),
+            PlaceholderName::Attribute(key) => write!(f, "attributes.{key}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Placeholder {
+    pub name: PlaceholderName,
+    pub fallback: Option<String>,
+}
+
+impl fmt::Display for Placeholder {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.fallback {
+            Some(fallback) => write!(f, "{{{{ {} | {} }}}}", self.name, fallback),
+            None => write!(f, "{{{{ {} }}}}", self.name),
+        }
+    }
+}
+
+/// The placeholders found in a newsletter, in order of appearance. They
+/// personalize the newsletter for each recipient. A placeholder is written
+/// `{{ name }}`, or `{{ name | fallback }}`, where the fallback is used when the
+/// recipient has no value for it. The names are `username`, `email`,
+/// `unsubscribe_url`, `preferences_url`, and `attributes.<key>` for the custom
+/// attributes of the subscriber.
+/// Placeholders are replaced by markers before the content is rendered and
+/// sanitized. Markers are absolute URLs, so they go through Markdown rendering
+/// and sanitizing unchanged, including when used as link destinations, and
+/// they are filled with the values of each recipient afterwards.
+#[derive(Debug, Clone, Default)]
+pub struct Placeholders {
+    placeholders: Vec<Placeholder>,
+}
+
+impl Placeholders {
+    /// Replaces the placeholders of the text with markers.
+    pub fn protect(&mut self, text: &str) -> Result<String, String> {
+        let mut protected = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(start) = rest.find("{{") {
+            protected.push_str(&rest[..start]);
+            let end = rest[start..]
+                .find("}}")
+                .ok_or_else(|| "Placeholder without closing '}}'".to_string())?;
+            let placeholder = parse(&rest[start + 2..start + end])?;
+            let index = match self.placeholders.iter().position(|p| p == &placeholder) {
+                Some(index) => index,
+                None => {
+                    self.placeholders.push(placeholder);
+                    self.placeholders.len() - 1
+                }
+            };
+            protected.push_str(&marker(index));
+            rest = &rest[start + end + 2..];
+        }
+        protected.push_str(rest);
+        Ok(protected)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Placeholder> {
+        self.placeholders.iter()
+    }
+
+    /// Replaces the markers of the text with the values, HTML escaped if
+    /// `escape` is true. Values come in the order of the placeholders.
+    pub fn fill(&self, text: &str, values: &[String], escape: bool) -> String {
+        (0..self.placeholders.len()).fold(text.to_string(), |text, index| {
+            let value = values.get(index).map(String::as_str).unwrap_or_default();
+            if escape {
+                text.replace(&marker(index), &escape_html(value))
+            } else {
+                text.replace(&marker(index), value)
+            }
+        })
+    }
+}
+
+fn parse(inner: &str) -> Result<Placeholder, String> {
+    let (name, fallback) = match inner.split_once('|') {
+        Some((name, fallback)) => (name, Some(fallback.trim().to_string())),
+        None => (inner, None),
+    };
+    Ok(Placeholder {
+        name: PlaceholderName::parse(name.trim())?,
+        fallback,
+    })
+}
+
+/// The trailing slash keeps marker 1 from matching within marker 10.
+fn marker(index: usize) -> String {
+    format!("{MARKER}{index}/")
+}
+
+pub(crate) fn escape_html(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::markdown;
+    use speculoos::prelude::*;
+
+    #[test]
+    fn placeholders_should_survive_markdown_rendering() {
+        let mut placeholders = Placeholders::default();
+        let markdown = placeholders
+            .protect("Hi {{ username | there }}, [unsubscribe]({{unsubscribe_url}})")
+            .unwrap();
+
+        let html = markdown::to_html(&markdown);
+        let html = placeholders.fill(
+            &html,
+            &[
+                "<b>bob</b>".to_string(),
+                "https://acme.inc/u?a=1&b=2".to_string(),
+            ],
+            true,
+        );
+
+        assert_that(&html).contains("Hi &lt;b&gt;bob&lt;/b&gt;,");
+        assert_that(&html).contains(r#"href="https://acme.inc/u?a=1&amp;b=2""#);
+    }
+
+    #[test]
+    fn placeholders_should_be_reused() {
+        let mut placeholders = Placeholders::default();
+        let text = placeholders
+            .protect("{{ email }} {{email}} {{ attributes.plan | free }}")
+            .unwrap();
+
+        let names = placeholders
+            .iter()
+            .map(|p| p.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["{{ email }}", "{{ attributes.plan | free }}"]);
+        let text = placeholders.fill(&text, &["a@acme.inc".to_string(), "pro".to_string()], false);
+        assert_that(&text).is_equal_to("a@acme.inc a@acme.inc pro".to_string());
+    }
+
+    #[test]
+    fn placeholders_should_reject_unknown_names() {
+        let mut placeholders = Placeholders::default();
+
+        assert_that(&placeholders.protect("Hi {{ name }}")).is_err();
+        assert_that(&placeholders.protect("Hi {{ attributes. }}")).is_err();
+        assert_that(&placeholders.protect("Hi {{ username")).is_err();
+    }
+}
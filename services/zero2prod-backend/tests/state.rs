@@ -135,6 +135,18 @@ pub async fn spawn_app() -> TestApp {
     // email service's url.
     let override_email_server_url = format!("email_client.server_url='{}'", email_server.uri());
 
+    // The templates directory in the configuration is relative to the root of the
+    // repository, so we override it with an absolute path.
+    let override_templates_directory = format!(
+        "templates.directory='{}'",
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("..")
+            .join("..")
+            .join("templates")
+            .join("email")
+            .display()
+    );
+
     // Now build the command line arguments
     let opts = Opts {
         config_dir: PathBuf::from(env!("CARGO_MANIFEST_DIR"))
@@ -142,7 +154,7 @@ pub async fn spawn_app() -> TestApp {
             .join("..")
             .join("config"),
         run_mode: Some("dev".to_string()),
-        settings: vec![override_email_server_url],
+        settings: vec![override_email_server_url, override_templates_directory],
         cmd: Command::Run,
     };
 
@@ -153,6 +165,10 @@ pub async fn spawn_app() -> TestApp {
         application,
         database,
         email_client,
+        templates,
+        sanitizer,
+        newsletter,
+        outbox,
         tracing: _,
         mode: _,
     } = settings;
@@ -167,12 +183,21 @@ pub async fn spawn_app() -> TestApp {
         .authentication(database.clone())
         .await
         .expect("authentication storage")
-        .subscription(database)
+        .subscription(database.clone())
         .await
         .expect("subscription storage")
+        .issues(database)
+        .await
+        .expect("issue storage")
         .email(email_client)
         .await
         .expect("email client service")
+        .templates(templates)
+        .expect("email templates")
+        .sanitizer(sanitizer, &base_url)
+        .expect("sanitizer")
+        .scheduler(newsletter)
+        .outbox(outbox)
         .listener(application)
         .expect("listener")
         .http(http)
@@ -6,7 +6,7 @@ use wiremock::{
 };
 
 use crate::state;
-use zero2prod::domain::{BodyData, Content};
+use zero2prod::domain::{BodyData, Content, Segment};
 
 #[when(regex = r#"the admin notifies subscribers of a new issue of the newsletter"#)]
 async fn notify_newsletter(world: &mut state::TestWorld) {
@@ -15,28 +15,33 @@ async fn notify_newsletter(world: &mut state::TestWorld) {
         // occured prior to this step).
         let _ = &app.email_server.reset().await;
 
-        let count_confirmed = u64::try_from(world.count_confirmed_subscribers()).unwrap();
+        let count_confirmed = world.count_confirmed_subscribers();
 
-        // Arrange the behaviour of the MockServer adding a Mock:
-        Mock::given(path("/email"))
+        // Arrange the behaviour of the MockServer adding a Mock: the newsletter
+        // is sent to all the confirmed subscribers in a single batch request,
+        // and the email service accepts each email of the batch.
+        let outcomes = (0..count_confirmed)
+            .map(|_| serde_json::json!({ "ErrorCode": 0, "Message": "OK" }))
+            .collect::<Vec<_>>();
+        Mock::given(path("/email/batch"))
             .and(method("POST"))
-            .respond_with(ResponseTemplate::new(200))
-            .expect(count_confirmed)
+            .respond_with(ResponseTemplate::new(200).set_body_json(outcomes))
+            .expect(u64::from(count_confirmed > 0))
             .mount(&app.email_server)
             .await;
 
-        for _ in world.subscribers.clone() {
-            let data = BodyData {
-                title: "New Issue".to_string(),
-                content: Content {
-                    html: "<p>Newsletter body as HTML</p>".to_string(),
-                    text: "Newsletter body as plain text".to_string(),
-                },
-            };
-            // FIXME Note that the world.status will only be the status of the last subscriber!
-            let resp = &app.send_newsletter(&data).await;
-            world.status_code = Some(resp.status());
-        }
+        let data = BodyData {
+            title: "New Issue".to_string(),
+            content: Content::Html {
+                html: "<p>Newsletter body as HTML</p>".to_string(),
+                text: "Newsletter body as plain text".to_string(),
+            },
+            force: false,
+            lists: vec![],
+            segment: Segment::default(),
+        };
+        let resp = &app.send_newsletter(&data).await;
+        world.status_code = Some(resp.status());
     }
 }
 
@@ -63,5 +68,8 @@ async fn one_request_to_email_server(world: &mut state::TestWorld) {
             .expect("get email server received requests");
 
         assert_that(&emails.len()).is_equal_to(1);
+        let batch: Vec<serde_json::Value> =
+            serde_json::from_slice(&emails[0].body).expect("batch of emails");
+        assert_that(&batch.len()).is_equal_to(1);
     }
 }
@@ -0,0 +1,8 @@
+-- Audit log of subscriptions erased at the request of their subscriber.
+-- The email address is deliberately not recorded.
+CREATE TABLE subscription_erasures (
+    id uuid PRIMARY KEY NOT NULL,
+    subscription_id uuid NOT NULL,
+    subscribed_at timestamp with time zone NOT NULL,
+    erased_at timestamp with time zone NOT NULL
+);
@@ -0,0 +1,23 @@
+CREATE TYPE issue_status AS ENUM (
+    'draft',
+    'scheduled',
+    'sending',
+    'sent',
+    'failed'
+);
+
+-- Newsletter issues, from their draft to their publication. The content is
+-- either html and text, or markdown.
+CREATE TABLE issues (
+    id uuid PRIMARY KEY NOT NULL,
+    title text NOT NULL,
+    content jsonb NOT NULL,
+    status issue_status NOT NULL,
+    scheduled_at timestamp with time zone,
+    force boolean NOT NULL,
+    author_id uuid NOT NULL REFERENCES users(id),
+    created_at timestamp with time zone NOT NULL,
+    updated_at timestamp with time zone NOT NULL
+);
+
+CREATE INDEX issues_scheduled_at ON issues (scheduled_at) WHERE status = 'scheduled';
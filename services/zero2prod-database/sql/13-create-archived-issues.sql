@@ -0,0 +1,12 @@
+-- Published newsletters, as they were sent, so they can be read in a browser.
+CREATE TABLE archived_issues (
+    id uuid PRIMARY KEY NOT NULL,
+    slug text NOT NULL UNIQUE,
+    title text NOT NULL,
+    html_content text NOT NULL,
+    text_content text NOT NULL,
+    author_id uuid NOT NULL REFERENCES users(id),
+    published_at timestamp with time zone NOT NULL
+);
+
+CREATE INDEX archived_issues_published_at ON archived_issues (published_at DESC, id DESC);
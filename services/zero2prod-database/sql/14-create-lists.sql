@@ -0,0 +1,31 @@
+-- Mailing lists. Subscribers confirm, and unsubscribe, on each list separately.
+CREATE TABLE lists (
+    id uuid PRIMARY KEY NOT NULL,
+    slug text UNIQUE NOT NULL,
+    name text NOT NULL,
+    created_at timestamp with time zone NOT NULL
+);
+
+-- The list used when a subscription, or a newsletter, does not name one.
+INSERT INTO lists (id, slug, name, created_at)
+VALUES ('9c2b6a3e-51d4-4c0f-8d8e-6b1f0e7a4c21', 'newsletter', 'Newsletter', now());
+
+CREATE TABLE list_subscriptions (
+    list_id uuid NOT NULL REFERENCES lists(id) ON DELETE CASCADE,
+    subscription_id uuid NOT NULL REFERENCES subscriptions(id) ON DELETE CASCADE,
+    status subscription_status NOT NULL,
+    subscribed_at timestamp with time zone NOT NULL,
+    PRIMARY KEY (list_id, subscription_id)
+);
+
+-- Existing subscriptions belong to the default list.
+INSERT INTO list_subscriptions (list_id, subscription_id, status, subscribed_at)
+SELECT '9c2b6a3e-51d4-4c0f-8d8e-6b1f0e7a4c21', id, status, subscribed_at FROM subscriptions;
+
+-- Confirmation tokens confirm the subscription to a single list.
+ALTER TABLE subscription_tokens
+ADD COLUMN list_id uuid NOT NULL DEFAULT '9c2b6a3e-51d4-4c0f-8d8e-6b1f0e7a4c21' REFERENCES lists(id) ON DELETE CASCADE;
+ALTER TABLE subscription_tokens ALTER COLUMN list_id DROP DEFAULT;
+
+-- The lists, by id or slug, an issue is sent to. The default list if empty.
+ALTER TABLE issues ADD COLUMN lists text[] NOT NULL DEFAULT '{}';
@@ -0,0 +1,24 @@
+CREATE TYPE delivery_frequency AS ENUM (
+    'immediate',
+    'weekly'
+);
+
+-- Preferences chosen by the subscribers, and custom attributes set by the
+-- admins, on which newsletters are segmented.
+ALTER TABLE subscriptions
+    ADD COLUMN topics text[] NOT NULL DEFAULT '{}',
+    ADD COLUMN frequency delivery_frequency NOT NULL DEFAULT 'immediate',
+    ADD COLUMN attributes jsonb NOT NULL DEFAULT '{}';
+
+CREATE INDEX subscriptions_topics ON subscriptions USING gin (topics);
+CREATE INDEX subscriptions_attributes ON subscriptions USING gin (attributes jsonb_path_ops);
+
+ALTER TABLE issues ADD COLUMN segment jsonb NOT NULL DEFAULT '{}';
+
+-- Newsletters waiting for the next digest of a weekly subscriber.
+CREATE TABLE digest_entries (
+    subscription_id uuid NOT NULL REFERENCES subscriptions(id) ON DELETE CASCADE,
+    issue_id uuid NOT NULL REFERENCES archived_issues(id) ON DELETE CASCADE,
+    queued_at timestamp with time zone NOT NULL,
+    PRIMARY KEY (subscription_id, issue_id)
+);
@@ -0,0 +1,13 @@
+CREATE TYPE suppression_reason AS ENUM (
+    'hard_bounce',
+    'spam_complaint'
+);
+
+-- Addresses no email is sent to anymore, lowercased. The source tells where
+-- the suppression comes from, eg the webhook of the email service.
+CREATE TABLE suppressions (
+    email text PRIMARY KEY NOT NULL,
+    reason suppression_reason NOT NULL,
+    source text NOT NULL,
+    suppressed_at timestamp with time zone NOT NULL
+);
@@ -0,0 +1,6 @@
+-- Suppressions added by the admins, and erased subscriptions, whose address is
+-- kept so that it is not mailed again, by an import for example.
+ALTER TYPE suppression_reason ADD VALUE 'manual';
+ALTER TYPE suppression_reason ADD VALUE 'erasure';
+
+CREATE INDEX suppressions_suppressed_at ON suppressions (suppressed_at);
@@ -0,0 +1,23 @@
+-- Opens and clicks are tracked for the issues which ask for it, except for the
+-- subscribers of lists which turn tracking off.
+ALTER TABLE issues ADD COLUMN tracking boolean NOT NULL DEFAULT false;
+ALTER TABLE lists ADD COLUMN tracking boolean NOT NULL DEFAULT true;
+
+CREATE TYPE tracking_event_kind AS ENUM (
+    'sent',
+    'open',
+    'click',
+    'bounce'
+);
+
+-- What happened to a tracked issue for each of its recipients. Clicks record
+-- the link which was followed.
+CREATE TABLE tracking_events (
+    issue_id uuid NOT NULL REFERENCES issues(id) ON DELETE CASCADE,
+    subscription_id uuid NOT NULL REFERENCES subscriptions(id) ON DELETE CASCADE,
+    kind tracking_event_kind NOT NULL,
+    url text,
+    occurred_at timestamp with time zone NOT NULL
+);
+
+CREATE INDEX tracking_events_issue_id ON tracking_events (issue_id, kind);
@@ -0,0 +1,20 @@
+CREATE TYPE delivery_status AS ENUM (
+    'queued',
+    'sent',
+    'failed',
+    'bounced'
+);
+
+-- The delivery of an issue to each of its recipients, with the id the email
+-- service gave to the message, and the last error, if it could not be sent.
+CREATE TABLE deliveries (
+    issue_id uuid NOT NULL REFERENCES issues(id) ON DELETE CASCADE,
+    subscription_id uuid NOT NULL REFERENCES subscriptions(id) ON DELETE CASCADE,
+    status delivery_status NOT NULL,
+    message_id text,
+    last_error text,
+    updated_at timestamp with time zone NOT NULL,
+    PRIMARY KEY (issue_id, subscription_id)
+);
+
+CREATE INDEX deliveries_message_id ON deliveries (message_id);
@@ -0,0 +1,17 @@
+-- Transactional emails, written in the same transaction as the change they are
+-- about, and sent by the relay until the email service accepts them. An email
+-- is sent, or given up, once one of sent_at or failed_at is set, and its
+-- content, with the address and links it carries, is then dropped.
+CREATE TABLE outbox (
+    id uuid PRIMARY KEY,
+    email jsonb,
+    attempts integer NOT NULL DEFAULT 0,
+    last_error text,
+    created_at timestamp with time zone NOT NULL,
+    -- When the relay may try to send the email.
+    available_at timestamp with time zone NOT NULL,
+    sent_at timestamp with time zone,
+    failed_at timestamp with time zone
+);
+
+CREATE INDEX outbox_pending ON outbox (available_at) WHERE sent_at IS NULL AND failed_at IS NULL;
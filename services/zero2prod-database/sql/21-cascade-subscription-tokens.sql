@@ -0,0 +1,5 @@
+-- Tokens are deleted with their subscription, when it is deleted or erased.
+ALTER TABLE subscription_tokens
+    DROP CONSTRAINT fk_subscription_tokens_subscriber_id,
+    ADD CONSTRAINT fk_subscription_tokens_subscriber_id
+        FOREIGN KEY (subscriber_id) REFERENCES subscriptions(id) ON DELETE CASCADE;
@@ -0,0 +1,24 @@
+-- Erased addresses are skipped by newsletters and imports, until their
+-- subscriber confirms a new subscription. Only a keyed hash of the lowercased
+-- address is kept, computed with the erasure key of the database settings.
+CREATE EXTENSION IF NOT EXISTS pgcrypto;
+
+CREATE TABLE erased_emails (
+    email_hash text PRIMARY KEY NOT NULL,
+    erased_at timestamp with time zone NOT NULL
+);
+
+-- Erasures were suppressions, which kept the address. They can't be hashed
+-- here, without the key.
+DELETE FROM suppressions WHERE reason = 'erasure';
+
+-- Without erasures, the reason of a suppression is recreated without the value.
+ALTER TYPE suppression_reason RENAME TO suppression_reason_old;
+CREATE TYPE suppression_reason AS ENUM (
+    'hard_bounce',
+    'spam_complaint',
+    'manual'
+);
+ALTER TABLE suppressions
+    ALTER COLUMN reason TYPE suppression_reason USING reason::text::suppression_reason;
+DROP TYPE suppression_reason_old;
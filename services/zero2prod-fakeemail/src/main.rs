@@ -40,6 +40,7 @@ async fn main() {
 
     let app = Router::new()
         .route("/email", post(email))
+        .route("/email/batch", post(email_batch))
         .layer(ServiceBuilder::new().layer(TraceLayer::new_for_http()));
 
     let sock_addr = SocketAddr::from((
@@ -65,6 +66,35 @@ async fn email(Json(payload): Json<SendEmailRequest>) -> impl IntoResponse {
     );
 }
 
+/// Accepts every email of the batch, and reports them sent.
+async fn email_batch(Json(payload): Json<Vec<SendEmailRequest>>) -> impl IntoResponse {
+    let outcomes = payload
+        .into_iter()
+        .map(|email| {
+            log::info!(
+                "Sending an email from {} to {}: {}",
+                email.from,
+                email.to,
+                email.subject
+            );
+            SendEmailResponse {
+                error_code: 0,
+                message: "OK".to_string(),
+                to: email.to,
+            }
+        })
+        .collect::<Vec<_>>();
+    Json(outcomes)
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "PascalCase")]
+struct SendEmailResponse {
+    error_code: i64,
+    message: String,
+    to: String,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "PascalCase")]
 struct SendEmailRequest {
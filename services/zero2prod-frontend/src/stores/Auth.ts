@@ -21,8 +21,8 @@ export const useAuthStore = defineStore('auth', {
 
       if (resp.status != 200) {
         this.isLoggedIn = false
-        if (resp?.data.status === 'fail') {
-          throw new MyError('Failure: ' + resp?.data.message)
+        if (resp?.data.code) {
+          throw new MyError('Failure: ' + resp?.data.detail)
         } else {
           throw new MyError('Failure, an unexpected error occured, please try again later.')
         }
@@ -43,8 +43,8 @@ export const useAuthStore = defineStore('auth', {
       }
       if (resp.status != 200) {
         this.isLoggedIn = false
-        if (resp?.data.status === 'fail') {
-          throw new MyError('Failure: ' + resp?.data.message)
+        if (resp?.data.code) {
+          throw new MyError('Failure: ' + resp?.data.detail)
         } else {
           throw new MyError('Failure, an unexpected error occured, please try again later.')
         }